@@ -1,9 +1,7 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use log::info;
-use rand::rngs::OsRng;
-use rand::RngCore;
+use log::{info, warn};
 use tokio::fs::try_exists;
 use tokio::task::spawn_blocking;
 
@@ -20,7 +18,10 @@ pub mod ndb;
 pub mod peaks;
 pub mod playlist;
 pub mod scanner;
+pub mod scrobble;
 pub mod thumbnail;
+pub mod transcode;
+pub mod warmup;
 
 #[cfg(test)]
 pub mod test;
@@ -57,6 +58,8 @@ pub enum Error {
 	#[error("This file format is not supported: {0}")]
 	UnsupportedFormat(&'static str),
 
+	#[error("Audio stream in `{0}` cannot be copied into {1:?} without transcoding")]
+	NotRemuxable(PathBuf, crate::utils::AudioFormat),
 	#[error("No tracks found in audio file: {0}")]
 	MediaEmpty(PathBuf),
 	#[error(transparent)]
@@ -67,6 +70,8 @@ pub enum Error {
 	MediaPacketError(symphonia::core::errors::Error),
 	#[error(transparent)]
 	MediaProbeError(symphonia::core::errors::Error),
+	#[error(transparent)]
+	MediaSeekError(symphonia::core::errors::Error),
 
 	#[error(transparent)]
 	PeaksSerialization(bitcode::Error),
@@ -82,17 +87,33 @@ pub enum Error {
 	UpdateQueryFailed(u16),
 	#[error("DDNS update query failed due to a transport error")]
 	UpdateQueryTransport,
+	#[error("Scrobble query failed with HTTP status code `{0}`")]
+	ScrobbleQueryFailed(u16),
+	#[error("Scrobble query failed due to a transport error")]
+	ScrobbleQueryTransport,
 
 	#[error("Auth secret does not have the expected format")]
 	AuthenticationSecretInvalid,
+	#[error("Auth secret file `{0}` is {1} bytes long, expected {len} bytes. Delete this file to generate a new secret (users will need to log in again)", len = auth::SECRET_LENGTH)]
+	AuthenticationSecretLengthInvalid(PathBuf, usize),
+	#[error("Auth keyring file `{0}` could not be parsed. Delete this file to generate a new keyring (users will need to log in again)")]
+	AuthenticationKeyringInvalid(PathBuf),
 	#[error("Missing auth secret")]
 	AuthenticationSecretNotFound,
 	#[error("Missing settings")]
 	MiscSettingsNotFound,
 	#[error("Index album art pattern is not a valid regex")]
 	IndexAlbumArtPatternInvalid,
+	#[error("Album art pattern of mount `{0}` is not a valid regex")]
+	MountAlbumArtPatternInvalid(String),
 	#[error("DDNS update URL is invalid")]
 	DDNSUpdateURLInvalid,
+	#[error("DDNS IP lookup URL is invalid")]
+	DDNSIPLookupURLInvalid,
+	#[error("Scrobble URL is invalid")]
+	ScrobbleURLInvalid,
+	#[error("Log level `{0}` is invalid")]
+	LogLevelInvalid(String),
 
 	#[error("Could not deserialize configuration: `{0}`")]
 	ConfigDeserialization(toml::de::Error),
@@ -102,6 +123,8 @@ pub enum Error {
 	IndexDeserializationError,
 	#[error("Could not serialize collection")]
 	IndexSerializationError,
+	#[error("Could not export collection: `{0}`")]
+	IndexExportError(std::io::Error),
 
 	#[error("Invalid Directory")]
 	InvalidDirectory(String),
@@ -121,10 +144,20 @@ pub enum Error {
 	GenreNotFound,
 	#[error("Song not found")]
 	SongNotFound,
-	#[error("Invalid search query syntax")]
-	SearchQueryParseError,
+	#[error("Invalid search query syntax at character {0}")]
+	SearchQueryParseError(usize),
+	#[error("Search terms must be at least {0} characters long")]
+	SearchQueryTooShort(usize),
+	#[error("Search query is too expensive to evaluate")]
+	SearchQueryTimeout,
 	#[error("Playlist not found")]
 	PlaylistNotFound,
+	#[error("Position {0} is out of bounds for this playlist")]
+	PlaylistPositionOutOfBounds(usize),
+	#[error("Playlist entry on line {0} is malformed")]
+	PlaylistEntryMalformed(usize),
+	#[error("A playlist with this name already exists")]
+	DuplicatePlaylist,
 	#[error("No embedded artwork was found in `{0}`")]
 	EmbeddedArtworkNotFound(PathBuf),
 
@@ -154,13 +187,17 @@ pub enum Error {
 pub struct App {
 	pub port: u16,
 	pub web_dir_path: PathBuf,
+	pub auth_keyring_file_path: PathBuf,
 	pub ddns_manager: ddns::Manager,
 	pub scanner: scanner::Scanner,
 	pub index_manager: index::Manager,
 	pub config_manager: config::Manager,
 	pub peaks_manager: peaks::Manager,
 	pub playlist_manager: playlist::Manager,
+	pub scrobble_manager: scrobble::Manager,
 	pub thumbnail_manager: thumbnail::Manager,
+	pub transcode_manager: transcode::Manager,
+	pub warmup_manager: warmup::Manager,
 }
 
 impl App {
@@ -178,29 +215,56 @@ impl App {
 		fs::create_dir_all(&thumbnails_dir_path)
 			.map_err(|e| Error::Io(thumbnails_dir_path.clone(), e))?;
 
+		let transcodes_dir_path = paths.cache_dir_path.join("transcodes");
+		fs::create_dir_all(&transcodes_dir_path)
+			.map_err(|e| Error::Io(transcodes_dir_path.clone(), e))?;
+
 		let auth_secret_file_path = paths.data_dir_path.join("auth.secret");
+		let auth_keyring_file_path = paths.data_dir_path.join("auth.keyring");
 		Self::migrate_legacy_auth_secret(&paths.db_file_path, &auth_secret_file_path).await?;
-		let auth_secret = Self::get_or_create_auth_secret(&auth_secret_file_path).await?;
+		let auth_keyring =
+			Self::get_or_create_auth_keyring(&auth_keyring_file_path, &auth_secret_file_path)
+				.await?;
 
-		let config_manager = config::Manager::new(&paths.config_file_path, auth_secret).await?;
-		let ddns_manager = ddns::Manager::new(config_manager.clone());
+		let config_manager = config::Manager::new(&paths.config_file_path, auth_keyring).await?;
 		let ndb_manager = ndb::Manager::new(&paths.data_dir_path)?;
+		let ddns_manager = ddns::Manager::new(config_manager.clone(), ndb_manager.clone());
 		let index_manager = index::Manager::new(&paths.data_dir_path).await?;
-		let scanner = scanner::Scanner::new(index_manager.clone(), config_manager.clone()).await?;
-		let peaks_manager = peaks::Manager::new(peaks_dir_path);
-		let playlist_manager = playlist::Manager::new(ndb_manager);
+		let scanner = scanner::Scanner::new(
+			index_manager.clone(),
+			config_manager.clone(),
+			paths.data_dir_path.join("skipped_files.txt"),
+		)
+		.await?;
+		let peaks_manager =
+			peaks::Manager::new(peaks_dir_path, config_manager.get_peaks_concurrency().await);
+		let playlist_manager =
+			playlist::Manager::new(ndb_manager, index_manager.clone(), config_manager.clone());
+		let scrobble_manager = scrobble::Manager::new(config_manager.clone());
 		let thumbnail_manager = thumbnail::Manager::new(thumbnails_dir_path);
+		let transcode_manager = transcode::Manager::new(transcodes_dir_path);
+		let warmup_manager = warmup::Manager::new(
+			config_manager.clone(),
+			index_manager.clone(),
+			peaks_manager.clone(),
+			scanner.clone(),
+			thumbnail_manager.clone(),
+		);
 
 		let app = Self {
 			port,
 			web_dir_path: paths.web_dir_path,
+			auth_keyring_file_path,
 			ddns_manager,
 			scanner,
 			index_manager,
 			config_manager,
 			peaks_manager,
 			playlist_manager,
+			scrobble_manager,
 			thumbnail_manager,
+			transcode_manager,
+			warmup_manager,
 		};
 
 		app.migrate_legacy_db(&paths.db_file_path).await?;
@@ -297,20 +361,65 @@ impl App {
 		Ok(())
 	}
 
+	// Signs new auth tokens with a new secret, while tokens signed with the previous
+	// secret remain valid for a grace period.
+	pub async fn rotate_auth_secret(&self) -> Result<(), Error> {
+		let auth_keyring = self.config_manager.rotate_auth_secret().await;
+		Self::write_auth_keyring(&self.auth_keyring_file_path, &auth_keyring).await
+	}
+
+	async fn get_or_create_auth_keyring(
+		keyring_path: &Path,
+		secret_path: &Path,
+	) -> Result<auth::Keyring, Error> {
+		let auth_keyring = match tokio::fs::read(&keyring_path).await {
+			Ok(k) if k.is_empty() => {
+				warn!(
+					"Auth keyring file `{}` is empty, a new one will be generated",
+					keyring_path.to_string_lossy()
+				);
+				auth::Keyring::new(auth::Secret::generate())
+			}
+			Ok(k) => {
+				let mut auth_keyring = serde_json::from_slice::<auth::Keyring>(&k)
+					.map_err(|_| Error::AuthenticationKeyringInvalid(keyring_path.to_owned()))?;
+				auth_keyring.prune();
+				auth_keyring
+			}
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				auth::Keyring::new(Self::get_or_create_auth_secret(secret_path).await?)
+			}
+			Err(e) => return Err(Error::Io(keyring_path.to_owned(), e)),
+		};
+		Self::write_auth_keyring(keyring_path, &auth_keyring).await?;
+		Ok(auth_keyring)
+	}
+
+	async fn write_auth_keyring(path: &Path, auth_keyring: &auth::Keyring) -> Result<(), Error> {
+		let serialized =
+			serde_json::to_vec(auth_keyring).map_err(|_| Error::AuthenticationSecretInvalid)?;
+		tokio::fs::write(path, serialized)
+			.await
+			.map_err(|e| Error::Io(path.to_owned(), e))
+	}
+
+	// Secret from before auth keyrings were introduced
 	async fn get_or_create_auth_secret(path: &Path) -> Result<auth::Secret, Error> {
 		match tokio::fs::read(&path).await {
-			Ok(s) => Ok(auth::Secret(
-				s.try_into()
-					.map_err(|_| Error::AuthenticationSecretInvalid)?,
-			)),
-			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-				let mut secret = auth::Secret::default();
-				OsRng.fill_bytes(secret.as_mut());
-				tokio::fs::write(&path, &secret)
-					.await
-					.map_err(|_| Error::AuthenticationSecretInvalid)?;
-				Ok(secret)
+			Ok(s) if s.is_empty() => {
+				warn!(
+					"Auth secret file `{}` is empty, a new secret will be generated",
+					path.to_string_lossy()
+				);
+				Ok(auth::Secret::generate())
+			}
+			Ok(s) => {
+				let length = s.len();
+				Ok(auth::Secret(s.try_into().map_err(|_| {
+					Error::AuthenticationSecretLengthInvalid(path.to_owned(), length)
+				})?))
 			}
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(auth::Secret::generate()),
 			Err(e) => Err(Error::Io(path.to_owned(), e)),
 		}
 	}
@@ -1,15 +1,30 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use pbkdf2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use pbkdf2::Pbkdf2;
 use rand::rngs::OsRng;
+use rand::RngCore;
 
 use serde::{Deserialize, Serialize};
 
 use crate::app::Error;
 
-#[derive(Clone, Default)]
-pub struct Secret(pub [u8; 32]);
+// How long tokens signed with a retired secret remain valid
+pub const RETIRED_SECRET_GRACE_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Size of auth secrets, in bytes
+pub const SECRET_LENGTH: usize = 32;
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Secret(pub [u8; SECRET_LENGTH]);
+
+impl Secret {
+	pub fn generate() -> Self {
+		let mut secret = Secret::default();
+		OsRng.fill_bytes(secret.as_mut());
+		secret
+	}
+}
 
 impl AsRef<[u8]> for Secret {
 	fn as_ref(&self) -> &[u8] {
@@ -23,18 +38,132 @@ impl AsMut<[u8]> for Secret {
 	}
 }
 
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RetiredSecret {
+	pub secret: Secret,
+	pub retired_at: u64, // Seconds since UNIX epoch
+}
+
+// Secrets used to sign auth tokens. New tokens are always signed with the current
+// secret, while tokens signed with retired secrets remain valid for a grace period.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Keyring {
+	pub current: Secret,
+	#[serde(default)]
+	pub retired: Vec<RetiredSecret>,
+}
+
+impl Keyring {
+	pub fn new(current: Secret) -> Self {
+		Self {
+			current,
+			retired: vec![],
+		}
+	}
+
+	pub fn rotate(&mut self) {
+		let previous = std::mem::replace(&mut self.current, Secret::generate());
+		self.retired.insert(
+			0,
+			RetiredSecret {
+				secret: previous,
+				retired_at: now(),
+			},
+		);
+		self.prune();
+	}
+
+	// Forgets retired secrets whose grace period has elapsed
+	pub fn prune(&mut self) {
+		let now = now();
+		self.retired.retain(|r| !is_expired(r, now));
+	}
+
+	pub fn accepted_secrets(&self) -> impl Iterator<Item = &Secret> {
+		let now = now();
+		std::iter::once(&self.current).chain(
+			self.retired
+				.iter()
+				.filter(move |r| !is_expired(r, now))
+				.map(|r| &r.secret),
+		)
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+fn is_expired(retired_secret: &RetiredSecret, now: u64) -> bool {
+	retired_secret.retired_at + RETIRED_SECRET_GRACE_PERIOD.as_secs() <= now
+}
+
 #[derive(Debug)]
 pub struct Token(pub String);
 
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Scope {
 	PolarisAuth,
+	PlaylistShare, // Read-only access to a single playlist, see `playlist::Manager::share_playlist`
+}
+
+// Default set of permissions of a user, ordered from least to most privileged. Roles are
+// never checked directly: every privileged operation requires a `Permission`.
+#[derive(
+	Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+pub enum Role {
+	ReadOnly, // Can browse and stream, but not modify anything
+	#[default]
+	User,
+	Admin,
+}
+
+impl Role {
+	// Permissions of users whose permissions are not configured explicitly
+	pub fn grants(self, permission: Permission) -> bool {
+		match self {
+			Role::ReadOnly => permission == Permission::Stream,
+			Role::User => matches!(permission, Permission::Stream | Permission::EditPlaylists),
+			Role::Admin => true,
+		}
+	}
+
+	pub fn permissions(self) -> impl Iterator<Item = Permission> {
+		Permission::ALL.into_iter().filter(move |p| self.grants(*p))
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Permission {
+	ManageSettings, // Mount directories, DDNS and other server settings
+	ManageUsers,
+	Scan,
+	EditPlaylists,
+	Stream,
+}
+
+impl Permission {
+	pub const ALL: [Permission; 5] = [
+		Permission::ManageSettings,
+		Permission::ManageUsers,
+		Permission::Scan,
+		Permission::EditPlaylists,
+		Permission::Stream,
+	];
 }
 
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Authorization {
 	pub username: String,
 	pub scope: Scope,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub role: Option<Role>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub share_id: Option<String>, // Only set for the `PlaylistShare` scope
 }
 
 pub fn hash_password(password: &str) -> Result<String, Error> {
@@ -66,10 +195,7 @@ pub fn generate_auth_token(
 	branca::encode(
 		serialized_authorization.as_bytes(),
 		auth_secret.as_ref(),
-		SystemTime::now()
-			.duration_since(UNIX_EPOCH)
-			.unwrap_or_default()
-			.as_secs() as u32,
+		now() as u32,
 	)
 	.or(Err(Error::BrancaTokenEncoding))
 	.map(Token)
@@ -78,14 +204,17 @@ pub fn generate_auth_token(
 pub fn decode_auth_token(
 	auth_token: &Token,
 	scope: Scope,
-	auth_secret: &Secret,
+	auth_keyring: &Keyring,
 ) -> Result<Authorization, Error> {
 	let Token(data) = auth_token;
 	let ttl = match scope {
-		Scope::PolarisAuth => 0, // permanent
+		Scope::PolarisAuth => 0,   // permanent
+		Scope::PlaylistShare => 0, // permanent until the share is revoked
 	};
-	let authorization =
-		branca::decode(data, auth_secret.as_ref(), ttl).map_err(|_| Error::InvalidAuthToken)?;
+	let authorization = auth_keyring
+		.accepted_secrets()
+		.find_map(|s| branca::decode(data, s.as_ref(), ttl).ok())
+		.ok_or(Error::InvalidAuthToken)?;
 	let authorization: Authorization =
 		serde_json::from_slice(&authorization[..]).map_err(|_| Error::InvalidAuthToken)?;
 	if authorization.scope != scope {
@@ -1,16 +1,23 @@
 use std::{
-	path::{Path, PathBuf},
+	num::NonZeroUsize,
+	path::{Component, Path, PathBuf},
 	sync::Arc,
 	time::Duration,
 };
 
-use log::{error, info};
+use log::{error, info, LevelFilter};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use notify_debouncer_full::{Debouncer, FileIdMap};
 use regex::Regex;
 use tokio::sync::{futures::Notified, Notify, RwLock};
 
-use crate::app::Error;
+use crate::{
+	app::{
+		index::{BigramThreshold, Normalization, PathIndexing, SearchLimits, TextField},
+		Error,
+	},
+	utils::AudioFormat,
+};
 
 mod mounts;
 pub mod storage;
@@ -21,12 +28,41 @@ pub use user::*;
 
 use super::auth;
 
+const DEFAULT_VARIOUS_ARTISTS_LABEL: &str = "Various Artists";
+
 #[derive(Debug, Clone, Default)]
 pub struct Config {
 	pub album_art_pattern: Option<Regex>,
+	pub album_art_patterns: Vec<Regex>,
+	pub audio_extensions: Option<Vec<String>>,
+	pub custom_fields: Vec<String>,
+	pub ddns_ip_lookup_url: Option<http::Uri>,
 	pub ddns_update_url: Option<http::Uri>,
+	pub disabled_formats: Vec<AudioFormat>,
+	pub follow_symlinks: Option<bool>,
+	pub group_by_album_artist: Option<bool>,
+	pub guest_paths: Vec<PathBuf>,
+	pub index_disabled_formats: Option<bool>,
+	pub infer_year_from_path: Option<bool>,
+	pub log_level: Option<LevelFilter>,
 	pub mount_dirs: Vec<MountDir>,
+	pub path_indexing: Option<PathIndexing>,
+	pub peaks_concurrency: Option<NonZeroUsize>,
+	pub scan_threads: Option<NonZeroUsize>,
+	pub scrobble_secret: Option<String>,
+	pub scrobble_url: Option<http::Uri>,
+	pub search_default_field: Option<TextField>,
+	pub search_min_length: Option<usize>,
+	pub search_bigram_threshold: Option<u8>,
+	pub search_timeout_ms: Option<u64>,
+	pub search_max_candidates: Option<usize>,
+	pub search_word_boundary_fields: Vec<TextField>,
+	pub text_normalization: Option<Normalization>,
 	pub users: Vec<User>,
+	pub various_artists_label: Option<String>,
+	pub warm_up_caches: Option<bool>,
+	pub watch_filesystem: Option<bool>,
+	pub write_scan_report: Option<bool>,
 }
 
 impl TryFrom<storage::Config> for Config {
@@ -43,12 +79,60 @@ impl TryFrom<storage::Config> for Config {
 			None => None,
 		};
 
+		config.album_art_patterns = c
+			.album_art_patterns
+			.iter()
+			.map(|p| Regex::new(p).map_err(|_| Error::IndexAlbumArtPatternInvalid))
+			.collect::<Result<_, _>>()?;
+
+		config.ddns_ip_lookup_url = match c.ddns_ip_lookup_url.map(http::Uri::try_from) {
+			Some(Ok(u)) => Some(u),
+			Some(Err(_)) => return Err(Error::DDNSIPLookupURLInvalid),
+			None => None,
+		};
+
 		config.ddns_update_url = match c.ddns_update_url.map(http::Uri::try_from) {
 			Some(Ok(u)) => Some(u),
 			Some(Err(_)) => return Err(Error::DDNSUpdateURLInvalid),
 			None => None,
 		};
 
+		config.scrobble_url = match c.scrobble_url.map(http::Uri::try_from) {
+			Some(Ok(u)) => Some(u),
+			Some(Err(_)) => return Err(Error::ScrobbleURLInvalid),
+			None => None,
+		};
+
+		config.log_level = match c.log_level.map(|l| l.parse().map_err(|_| l)) {
+			Some(Ok(l)) => Some(l),
+			Some(Err(l)) => return Err(Error::LogLevelInvalid(l)),
+			None => None,
+		};
+
+		config.audio_extensions = c.audio_extensions;
+		config.custom_fields = c.custom_fields;
+		config.disabled_formats = c.disabled_formats;
+		config.follow_symlinks = c.follow_symlinks;
+		config.group_by_album_artist = c.group_by_album_artist;
+		config.guest_paths = c.guest_paths;
+		config.index_disabled_formats = c.index_disabled_formats;
+		config.infer_year_from_path = c.infer_year_from_path;
+		config.path_indexing = c.path_indexing;
+		config.peaks_concurrency = c.peaks_concurrency;
+		config.scan_threads = c.scan_threads;
+		config.scrobble_secret = c.scrobble_secret;
+		config.search_default_field = c.search_default_field;
+		config.search_min_length = c.search_min_length;
+		config.search_bigram_threshold = c.search_bigram_threshold;
+		config.search_timeout_ms = c.search_timeout_ms;
+		config.search_max_candidates = c.search_max_candidates;
+		config.search_word_boundary_fields = c.search_word_boundary_fields;
+		config.text_normalization = c.text_normalization;
+		config.various_artists_label = c.various_artists_label;
+		config.warm_up_caches = c.warm_up_caches;
+		config.watch_filesystem = c.watch_filesystem;
+		config.write_scan_report = c.write_scan_report;
+
 		Ok(config)
 	}
 }
@@ -57,9 +141,40 @@ impl From<Config> for storage::Config {
 	fn from(c: Config) -> Self {
 		Self {
 			album_art_pattern: c.album_art_pattern.map(|p| p.as_str().to_owned()),
+			album_art_patterns: c
+				.album_art_patterns
+				.iter()
+				.map(|p| p.as_str().to_owned())
+				.collect(),
 			mount_dirs: c.mount_dirs.into_iter().map(|d| d.into()).collect(),
+			audio_extensions: c.audio_extensions,
+			custom_fields: c.custom_fields,
+			ddns_ip_lookup_url: c.ddns_ip_lookup_url.map(|u| u.to_string()),
 			ddns_update_url: c.ddns_update_url.map(|u| u.to_string()),
+			disabled_formats: c.disabled_formats,
+			follow_symlinks: c.follow_symlinks,
+			group_by_album_artist: c.group_by_album_artist,
+			guest_paths: c.guest_paths,
+			index_disabled_formats: c.index_disabled_formats,
+			infer_year_from_path: c.infer_year_from_path,
+			log_level: c.log_level.map(|l| l.as_str().to_lowercase()),
+			path_indexing: c.path_indexing,
+			peaks_concurrency: c.peaks_concurrency,
+			scan_threads: c.scan_threads,
+			scrobble_secret: c.scrobble_secret,
+			scrobble_url: c.scrobble_url.map(|u| u.to_string()),
+			search_default_field: c.search_default_field,
+			search_min_length: c.search_min_length,
+			search_bigram_threshold: c.search_bigram_threshold,
+			search_timeout_ms: c.search_timeout_ms,
+			search_max_candidates: c.search_max_candidates,
+			search_word_boundary_fields: c.search_word_boundary_fields,
+			text_normalization: c.text_normalization,
 			users: c.users.into_iter().map(|u| u.into()).collect(),
+			various_artists_label: c.various_artists_label,
+			warm_up_caches: c.warm_up_caches,
+			watch_filesystem: c.watch_filesystem,
+			write_scan_report: c.write_scan_report,
 		}
 	}
 }
@@ -68,14 +183,14 @@ impl From<Config> for storage::Config {
 pub struct Manager {
 	config_file_path: PathBuf,
 	config: Arc<RwLock<Config>>,
-	auth_secret: auth::Secret,
+	auth_keyring: Arc<RwLock<auth::Keyring>>,
 	#[allow(dead_code)]
 	file_watcher: Arc<Debouncer<RecommendedWatcher, FileIdMap>>,
 	change_notify: Arc<Notify>,
 }
 
 impl Manager {
-	pub async fn new(config_file_path: &Path, auth_secret: auth::Secret) -> Result<Self, Error> {
+	pub async fn new(config_file_path: &Path, auth_keyring: auth::Keyring) -> Result<Self, Error> {
 		if let Some(parent) = config_file_path.parent() {
 			tokio::fs::create_dir_all(parent)
 				.await
@@ -106,7 +221,7 @@ impl Manager {
 		let manager = Self {
 			config_file_path: config_file_path.to_owned(),
 			config: Arc::new(RwLock::new(Config::default())),
-			auth_secret,
+			auth_keyring: Arc::new(RwLock::new(auth_keyring)),
 			file_watcher: Arc::new(debouncer),
 			change_notify: Arc::default(),
 		};
@@ -146,6 +261,13 @@ impl Manager {
 		toml::de::from_str::<storage::Config>(&config_content).map_err(Error::ConfigDeserialization)
 	}
 
+	// Reads a configuration file and reports the first problem preventing it from being applied,
+	// without applying it
+	pub async fn check_config(config_file_path: &Path) -> Result<(), Error> {
+		let config = Self::read_config(config_file_path).await?;
+		Config::try_from(config).map(|_| ())
+	}
+
 	pub async fn save_config(&self) -> Result<(), Error> {
 		let serialized = toml::ser::to_string_pretty::<storage::Config>(
 			&self.config.read().await.clone().into(),
@@ -191,17 +313,37 @@ impl Manager {
 		pattern.unwrap_or_else(|| Regex::new("Folder.(jpeg|jpg|png)").unwrap())
 	}
 
+	// Patterns to try in order when looking for album art. A non-empty `album_art_patterns`
+	// list takes precedence over the single `album_art_pattern`.
+	pub async fn get_index_album_art_patterns(&self) -> Vec<Regex> {
+		let patterns = self.config.read().await.album_art_patterns.clone();
+		if patterns.is_empty() {
+			vec![self.get_index_album_art_pattern().await]
+		} else {
+			patterns
+		}
+	}
+
 	pub async fn set_index_album_art_pattern(&self, regex: Regex) -> Result<(), Error> {
 		self.mutate(|c| {
 			c.album_art_pattern = Some(regex);
+			c.album_art_patterns.clear();
 		})
 		.await
 	}
 
+	pub async fn get_log_level(&self) -> Option<LevelFilter> {
+		self.config.read().await.log_level
+	}
+
 	pub async fn get_ddns_update_url(&self) -> Option<http::Uri> {
 		self.config.read().await.ddns_update_url.clone()
 	}
 
+	pub async fn get_ddns_ip_lookup_url(&self) -> Option<http::Uri> {
+		self.config.read().await.ddns_ip_lookup_url.clone()
+	}
+
 	pub async fn set_ddns_update_url(&self, url: Option<http::Uri>) -> Result<(), Error> {
 		self.mutate(|c| {
 			c.ddns_update_url = url;
@@ -209,6 +351,141 @@ impl Manager {
 		.await
 	}
 
+	pub async fn get_audio_extensions(&self) -> Option<Vec<String>> {
+		self.config.read().await.audio_extensions.clone()
+	}
+
+	pub async fn get_custom_fields(&self) -> Vec<String> {
+		self.config.read().await.custom_fields.clone()
+	}
+
+	pub async fn get_disabled_formats(&self) -> Vec<AudioFormat> {
+		self.config.read().await.disabled_formats.clone()
+	}
+
+	pub async fn get_index_disabled_formats(&self) -> bool {
+		self.config
+			.read()
+			.await
+			.index_disabled_formats
+			.unwrap_or(false)
+	}
+
+	pub async fn get_peaks_concurrency(&self) -> NonZeroUsize {
+		let config = self.config.read().await;
+		config
+			.peaks_concurrency
+			.unwrap_or_else(|| NonZeroUsize::new(num_cpus::get() / 2).unwrap_or(NonZeroUsize::MIN))
+	}
+
+	pub async fn get_scan_threads(&self) -> Option<NonZeroUsize> {
+		self.config.read().await.scan_threads
+	}
+
+	pub async fn get_scrobble_url(&self) -> Option<http::Uri> {
+		self.config.read().await.scrobble_url.clone()
+	}
+
+	pub async fn get_scrobble_secret(&self) -> Option<String> {
+		self.config.read().await.scrobble_secret.clone()
+	}
+
+	pub async fn get_search_default_field(&self) -> Option<TextField> {
+		self.config.read().await.search_default_field
+	}
+
+	pub async fn get_follow_symlinks(&self) -> bool {
+		self.config.read().await.follow_symlinks.unwrap_or(false)
+	}
+
+	pub async fn get_write_scan_report(&self) -> bool {
+		self.config.read().await.write_scan_report.unwrap_or(false)
+	}
+
+	pub async fn get_group_by_album_artist(&self) -> bool {
+		self.config
+			.read()
+			.await
+			.group_by_album_artist
+			.unwrap_or(false)
+	}
+
+	pub async fn get_infer_year_from_path(&self) -> bool {
+		self.config
+			.read()
+			.await
+			.infer_year_from_path
+			.unwrap_or(false)
+	}
+
+	pub async fn get_various_artists_label(&self) -> String {
+		self.config
+			.read()
+			.await
+			.various_artists_label
+			.clone()
+			.unwrap_or_else(|| DEFAULT_VARIOUS_ARTISTS_LABEL.to_owned())
+	}
+
+	pub async fn get_warm_up_caches(&self) -> bool {
+		self.config.read().await.warm_up_caches.unwrap_or(false)
+	}
+
+	pub async fn get_watch_filesystem(&self) -> bool {
+		self.config.read().await.watch_filesystem.unwrap_or(false)
+	}
+
+	// Whether a virtual path can be read without logging in
+	pub async fn is_guest_path(&self, virtual_path: &Path) -> bool {
+		// Paths are joined onto mount sources as-is, so `..` could escape the guest prefix
+		let is_normal = |c: Component| matches!(c, Component::Normal(_));
+		if !virtual_path.components().all(is_normal) {
+			return false;
+		}
+		let config = self.config.read().await;
+		config
+			.guest_paths
+			.iter()
+			.any(|p| virtual_path.starts_with(p))
+	}
+
+	pub async fn get_search_min_length(&self) -> usize {
+		self.config.read().await.search_min_length.unwrap_or(0)
+	}
+
+	pub async fn get_search_bigram_threshold(&self) -> BigramThreshold {
+		let config = self.config.read().await;
+		config
+			.search_bigram_threshold
+			.map_or_else(BigramThreshold::default, BigramThreshold::new)
+	}
+
+	pub async fn get_search_word_boundary_fields(&self) -> Vec<TextField> {
+		self.config.read().await.search_word_boundary_fields.clone()
+	}
+
+	// Searches are not limited unless configured to be, a timeout of 0 also disables it
+	pub async fn get_search_limits(&self) -> SearchLimits {
+		let config = self.config.read().await;
+		SearchLimits {
+			timeout: config
+				.search_timeout_ms
+				.filter(|ms| *ms > 0)
+				.map(Duration::from_millis),
+			max_candidates: config.search_max_candidates,
+		}
+	}
+
+	pub async fn get_path_indexing(&self) -> PathIndexing {
+		let config = self.config.read().await;
+		config.path_indexing.unwrap_or_default()
+	}
+
+	pub async fn get_text_normalization(&self) -> Normalization {
+		let config = self.config.read().await;
+		config.text_normalization.unwrap_or_default()
+	}
+
 	pub async fn get_users(&self) -> Vec<User> {
 		self.config.read().await.users.to_vec()
 	}
@@ -233,7 +510,18 @@ impl Manager {
 
 	pub async fn login(&self, username: &str, password: &str) -> Result<auth::Token, Error> {
 		let config = self.config.read().await;
-		config.login(username, password, &self.auth_secret)
+		let auth_keyring = self.auth_keyring.read().await;
+		config.login(username, password, &auth_keyring.current)
+	}
+
+	pub async fn generate_share_token(
+		&self,
+		owner: &str,
+		share_id: &str,
+	) -> Result<auth::Token, Error> {
+		let config = self.config.read().await;
+		let auth_keyring = self.auth_keyring.read().await;
+		config.generate_share_token(owner, share_id, &auth_keyring.current)
 	}
 
 	pub async fn set_is_admin(&self, username: &str, is_admin: bool) -> Result<(), Error> {
@@ -241,6 +529,19 @@ impl Manager {
 			.await
 	}
 
+	pub async fn set_role(&self, username: &str, role: auth::Role) -> Result<(), Error> {
+		self.mutate_fallible(|c| c.set_role(username, role)).await
+	}
+
+	pub async fn set_permissions(
+		&self,
+		username: &str,
+		permissions: Option<Vec<auth::Permission>>,
+	) -> Result<(), Error> {
+		self.mutate_fallible(|c| c.set_permissions(username, permissions))
+			.await
+	}
+
 	pub async fn set_password(&self, username: &str, password: &str) -> Result<(), Error> {
 		self.mutate_fallible(|c| c.set_password(username, password))
 			.await
@@ -252,7 +553,23 @@ impl Manager {
 		scope: auth::Scope,
 	) -> Result<auth::Authorization, Error> {
 		let config = self.config.read().await;
-		config.authenticate(auth_token, scope, &self.auth_secret)
+		let auth_keyring = self.auth_keyring.read().await;
+		config.authenticate(auth_token, scope, &auth_keyring)
+	}
+
+	pub async fn get_permissions(
+		&self,
+		authorization: &auth::Authorization,
+	) -> Vec<auth::Permission> {
+		self.config.read().await.get_permissions(authorization)
+	}
+
+	// Starts signing new tokens with a new secret. Existing tokens remain valid
+	// for `auth::RETIRED_SECRET_GRACE_PERIOD`.
+	pub async fn rotate_auth_secret(&self) -> auth::Keyring {
+		let mut auth_keyring = self.auth_keyring.write().await;
+		auth_keyring.rotate();
+		auth_keyring.clone()
 	}
 
 	pub async fn delete_user(&self, username: &str) -> Result<(), Error> {
@@ -272,6 +589,11 @@ impl Manager {
 		config.resolve_virtual_path(virtual_path)
 	}
 
+	pub async fn map_to_virtual(&self, real_paths: &[PathBuf]) -> Vec<Result<PathBuf, Error>> {
+		let config = self.config.read().await;
+		config.map_to_virtual(real_paths)
+	}
+
 	pub async fn set_mounts(&self, mount_dirs: Vec<storage::MountDir>) -> Result<(), Error> {
 		self.mutate_fallible(|c| c.set_mounts(mount_dirs)).await
 	}
@@ -280,6 +602,7 @@ impl Manager {
 #[cfg(test)]
 mod test {
 	use crate::app::test;
+	use crate::test::prepare_test_directory;
 	use crate::test_name;
 
 	use super::*;
@@ -287,7 +610,7 @@ mod test {
 	#[tokio::test]
 	async fn blank_config_round_trip() {
 		let config_path = PathBuf::from_iter(["test-data", "blank.toml"]);
-		let manager = Manager::new(&config_path, auth::Secret([0; 32]))
+		let manager = Manager::new(&config_path, auth::Keyring::default())
 			.await
 			.unwrap();
 		let config: storage::Config = manager.config.read().await.clone().into();
@@ -297,7 +620,7 @@ mod test {
 	#[tokio::test]
 	async fn can_read_config() {
 		let config_path = PathBuf::from_iter(["test-data", "config.toml"]);
-		let manager = Manager::new(&config_path, auth::Secret([0; 32]))
+		let manager = Manager::new(&config_path, auth::Keyring::default())
 			.await
 			.unwrap();
 		let config: storage::Config = manager.config.read().await.clone().into();
@@ -311,6 +634,7 @@ mod test {
 			vec![storage::MountDir {
 				source: PathBuf::from("test-data/small-collection"),
 				name: "root".to_owned(),
+				..Default::default()
 			}]
 		);
 		assert_eq!(config.users[0].name, "test_user");
@@ -323,16 +647,170 @@ mod test {
 	}
 
 	#[tokio::test]
-	async fn can_write_config() {
+	async fn can_check_guest_paths() {
 		let ctx = test::ContextBuilder::new(test_name!()).build().await;
 		ctx.config_manager
-			.create_user("Walter", "example_password", false)
+			.apply_config(storage::Config {
+				guest_paths: vec![PathBuf::from_iter(["root", "My Band"])],
+				..Default::default()
+			})
 			.await
 			.unwrap();
 
-		let manager = Manager::new(&ctx.config_manager.config_file_path, auth::Secret([0; 32]))
+		let allowed = [
+			PathBuf::from_iter(["root", "My Band"]),
+			PathBuf::from_iter(["root", "My Band", "Demo", "01.mp3"]),
+		];
+		for path in allowed {
+			assert!(ctx.config_manager.is_guest_path(&path).await);
+		}
+
+		let denied = [
+			PathBuf::from_iter(["root", "My Band 2"]),
+			PathBuf::from_iter(["root"]),
+			PathBuf::from_iter(["other", "My Band"]),
+			PathBuf::from_iter(["root", "My Band", "..", "Private", "01.mp3"]),
+			PathBuf::from_iter(["root", "My Band", ".", "Demo", "01.mp3"]),
+			PathBuf::from_iter(["/", "root", "My Band"]),
+		];
+		for path in denied {
+			assert!(!ctx.config_manager.is_guest_path(&path).await);
+		}
+	}
+
+	#[tokio::test]
+	async fn album_art_patterns_take_precedence() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		ctx.config_manager
+			.apply_config(storage::Config {
+				album_art_pattern: Some("Folder.jpg".to_owned()),
+				..Default::default()
+			})
 			.await
 			.unwrap();
+		let patterns = ctx.config_manager.get_index_album_art_patterns().await;
+		assert_eq!(
+			patterns.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+			vec!["Folder.jpg"]
+		);
+
+		ctx.config_manager
+			.apply_config(storage::Config {
+				album_art_pattern: Some("Folder.jpg".to_owned()),
+				album_art_patterns: vec!["cover.jpg".to_owned(), "folder.png".to_owned()],
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		let patterns = ctx.config_manager.get_index_album_art_patterns().await;
+		assert_eq!(
+			patterns.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+			vec!["cover.jpg", "folder.png"]
+		);
+	}
+
+	#[tokio::test]
+	async fn rejects_invalid_album_art_patterns() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		let result = ctx
+			.config_manager
+			.apply_config(storage::Config {
+				album_art_patterns: vec!["cover.jpg".to_owned(), "Folder.(png".to_owned()],
+				..Default::default()
+			})
+			.await;
+		assert!(matches!(result, Err(Error::IndexAlbumArtPatternInvalid)));
+	}
+
+	#[tokio::test]
+	async fn can_read_log_level() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		ctx.config_manager
+			.apply_config(storage::Config {
+				log_level: Some("debug".to_owned()),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		assert_eq!(
+			ctx.config_manager.get_log_level().await,
+			Some(LevelFilter::Debug)
+		);
+
+		let result = ctx
+			.config_manager
+			.apply_config(storage::Config {
+				log_level: Some("loud".to_owned()),
+				..Default::default()
+			})
+			.await;
+		assert!(matches!(result, Err(Error::LogLevelInvalid(_))));
+	}
+
+	#[tokio::test]
+	async fn can_read_search_word_boundary_fields() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		assert!(ctx
+			.config_manager
+			.get_search_word_boundary_fields()
+			.await
+			.is_empty());
+
+		let config = toml::de::from_str::<storage::Config>(
+			r#"search_word_boundary_fields = ["Path", "Title"]"#,
+		)
+		.unwrap();
+		ctx.config_manager.apply_config(config).await.unwrap();
+		assert_eq!(
+			ctx.config_manager.get_search_word_boundary_fields().await,
+			vec![TextField::Path, TextField::Title]
+		);
+	}
+
+	#[tokio::test]
+	async fn can_check_config() {
+		let config_path = PathBuf::from("test-data/config.toml");
+		assert!(Manager::check_config(&config_path).await.is_ok());
+	}
+
+	#[tokio::test]
+	async fn check_config_reports_invalid_values() {
+		let config_path = prepare_test_directory(test_name!()).join("polaris.toml");
+		tokio::fs::write(&config_path, r#"ddns_update_url = "not a url""#)
+			.await
+			.unwrap();
+		assert!(matches!(
+			Manager::check_config(&config_path).await.unwrap_err(),
+			Error::DDNSUpdateURLInvalid
+		));
+	}
+
+	#[tokio::test]
+	async fn check_config_reports_syntax_errors() {
+		let config_path = prepare_test_directory(test_name!()).join("polaris.toml");
+		tokio::fs::write(&config_path, "mount_dirs = 3")
+			.await
+			.unwrap();
+		assert!(matches!(
+			Manager::check_config(&config_path).await.unwrap_err(),
+			Error::ConfigDeserialization(_)
+		));
+	}
+
+	#[tokio::test]
+	async fn can_write_config() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		ctx.config_manager
+			.create_user("Walter", "example_password", false)
+			.await
+			.unwrap();
+
+		let manager = Manager::new(
+			&ctx.config_manager.config_file_path,
+			auth::Keyring::default(),
+		)
+		.await
+		.unwrap();
 		assert!(manager.get_user("Walter").await.is_ok());
 	}
 }
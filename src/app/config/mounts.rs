@@ -14,6 +14,8 @@ use super::Config;
 pub struct MountDir {
 	pub source: PathBuf,
 	pub name: String,
+	// Replaces the global album art patterns for this mount. Known to be a valid regex.
+	pub album_art_pattern: Option<String>,
 }
 
 impl TryFrom<storage::MountDir> for MountDir {
@@ -21,9 +23,15 @@ impl TryFrom<storage::MountDir> for MountDir {
 
 	fn try_from(mount_dir: storage::MountDir) -> Result<Self, Self::Error> {
 		// TODO validation
+		if let Some(pattern) = &mount_dir.album_art_pattern {
+			if Regex::new(pattern).is_err() {
+				return Err(Error::MountAlbumArtPatternInvalid(mount_dir.name));
+			}
+		}
 		Ok(Self {
 			source: sanitize_path(&mount_dir.source),
 			name: mount_dir.name,
+			album_art_pattern: mount_dir.album_art_pattern,
 		})
 	}
 }
@@ -33,6 +41,7 @@ impl From<MountDir> for storage::MountDir {
 		Self {
 			source: m.source,
 			name: m.name,
+			album_art_pattern: m.album_art_pattern,
 		}
 	}
 }
@@ -61,6 +70,31 @@ impl Config {
 		}
 		Err(Error::CouldNotMapToRealPath(virtual_path.as_ref().into()))
 	}
+
+	// Maps real paths to virtual paths. Paths can be relative to mount sources as
+	// configured, or to their canonical form (as reported by filesystem watchers).
+	pub fn map_to_virtual(&self, real_paths: &[PathBuf]) -> Vec<Result<PathBuf, Error>> {
+		let mounts = self
+			.mount_dirs
+			.iter()
+			.flat_map(|m| {
+				let name = Path::new(&m.name);
+				let canonical = m.source.canonicalize().ok().filter(|c| *c != m.source);
+				std::iter::once((m.source.clone(), name)).chain(canonical.map(|c| (c, name)))
+			})
+			.collect::<Vec<_>>();
+		real_paths
+			.iter()
+			.map(|real_path| {
+				mounts
+					.iter()
+					.find_map(|(source, name)| {
+						real_path.strip_prefix(source).ok().map(|p| name.join(p))
+					})
+					.ok_or_else(|| Error::CouldNotMapToVirtualPath(real_path.clone()))
+			})
+			.collect()
+	}
 }
 
 fn sanitize_path(source: &Path) -> PathBuf {
@@ -82,6 +116,7 @@ mod test {
 			mount_dirs: vec![storage::MountDir {
 				name: "root".to_owned(),
 				source: PathBuf::from("test_dir"),
+				..Default::default()
 			}],
 			..Default::default()
 		};
@@ -104,6 +139,74 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn can_map_many_real_paths() {
+		let raw_config = storage::Config {
+			mount_dirs: vec![
+				storage::MountDir {
+					name: "root".to_owned(),
+					source: PathBuf::from("test_dir"),
+					..Default::default()
+				},
+				storage::MountDir {
+					name: "other".to_owned(),
+					source: PathBuf::from("other_dir"),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let config: Config = raw_config.try_into().unwrap();
+
+		let real_paths = vec![
+			PathBuf::from_iter(["test_dir", "somewhere", "something.mp3"]),
+			PathBuf::from("elsewhere"),
+			PathBuf::from_iter(["other_dir", "song.flac"]),
+		];
+		let virtual_paths = config.map_to_virtual(&real_paths);
+		assert_eq!(virtual_paths.len(), 3);
+		assert_eq!(
+			virtual_paths[0].as_ref().unwrap(),
+			&PathBuf::from_iter(["root", "somewhere", "something.mp3"])
+		);
+		assert!(matches!(
+			&virtual_paths[1],
+			Err(Error::CouldNotMapToVirtualPath(p)) if *p == real_paths[1]
+		));
+		assert_eq!(
+			virtual_paths[2].as_ref().unwrap(),
+			&PathBuf::from_iter(["other", "song.flac"])
+		);
+	}
+
+	#[test]
+	fn can_map_canonical_real_paths() {
+		let raw_config = storage::Config {
+			mount_dirs: vec![storage::MountDir {
+				name: "root".to_owned(),
+				source: PathBuf::from_iter(["test-data", "small-collection"]),
+				..Default::default()
+			}],
+			..Default::default()
+		};
+
+		let config: Config = raw_config.try_into().unwrap();
+
+		let real_paths = vec![
+			PathBuf::from_iter(["test-data", "small-collection", "Khemmis"]),
+			PathBuf::from_iter(["test-data", "small-collection", "Khemmis"])
+				.canonicalize()
+				.unwrap(),
+		];
+		for virtual_path in config.map_to_virtual(&real_paths) {
+			assert_eq!(
+				virtual_path.unwrap(),
+				PathBuf::from_iter(["root", "Khemmis"])
+			);
+		}
+	}
+
 	#[test]
 	fn sanitizes_paths() {
 		let mut correct_path = PathBuf::new();
@@ -138,6 +241,7 @@ mod test {
 				mount_dirs: vec![storage::MountDir {
 					name: "root".to_owned(),
 					source: PathBuf::from(test),
+					..Default::default()
 				}],
 				..Default::default()
 			};
@@ -146,4 +250,22 @@ mod test {
 			assert_eq!(converted_path, correct_path);
 		}
 	}
+
+	#[test]
+	fn invalid_album_art_pattern_names_mount() {
+		let raw_config = storage::Config {
+			mount_dirs: vec![storage::MountDir {
+				name: "root".to_owned(),
+				source: PathBuf::from("test_dir"),
+				album_art_pattern: Some("Folder.(jpg".to_owned()),
+			}],
+			..Default::default()
+		};
+
+		let config: Result<Config, _> = raw_config.try_into();
+		assert!(matches!(
+			config,
+			Err(Error::MountAlbumArtPatternInvalid(name)) if name == "root"
+		));
+	}
 }
@@ -1,13 +1,25 @@
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+	app::{
+		auth::{Permission, Role},
+		index::{Normalization, PathIndexing, TextField},
+	},
+	utils::AudioFormat,
+};
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct User {
 	pub name: String,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub admin: Option<bool>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub role: Option<Role>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub permissions: Option<Vec<Permission>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub initial_password: Option<String>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub hashed_password: Option<String>,
@@ -17,6 +29,8 @@ pub struct User {
 pub struct MountDir {
 	pub source: PathBuf,
 	pub name: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub album_art_pattern: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -24,9 +38,63 @@ pub struct Config {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub album_art_pattern: Option<String>,
 	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub album_art_patterns: Vec<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
 	pub mount_dirs: Vec<MountDir>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub audio_extensions: Option<Vec<String>>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub custom_fields: Vec<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub disabled_formats: Vec<AudioFormat>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub ddns_ip_lookup_url: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub ddns_update_url: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub follow_symlinks: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub group_by_album_artist: Option<bool>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub guest_paths: Vec<PathBuf>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub index_disabled_formats: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub infer_year_from_path: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub log_level: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub path_indexing: Option<PathIndexing>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub peaks_concurrency: Option<NonZeroUsize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub scan_threads: Option<NonZeroUsize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub scrobble_secret: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub scrobble_url: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub search_default_field: Option<TextField>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub search_min_length: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub search_bigram_threshold: Option<u8>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub search_timeout_ms: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub search_max_candidates: Option<usize>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub search_word_boundary_fields: Vec<TextField>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub text_normalization: Option<Normalization>,
 	#[serde(default, skip_serializing_if = "Vec::is_empty")]
 	pub users: Vec<User>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub various_artists_label: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub warm_up_caches: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub watch_filesystem: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub write_scan_report: Option<bool>,
 }
@@ -7,13 +7,35 @@ use super::Config;
 pub struct User {
 	pub name: String,
 	pub admin: Option<bool>,
+	pub role: Option<auth::Role>,
+	pub permissions: Option<Vec<auth::Permission>>, // Replaces the permissions granted by the role
 	pub initial_password: Option<String>,
 	pub hashed_password: String,
 }
 
 impl User {
+	// Users without an explicit role are derived from the older `admin` flag
+	pub fn role(&self) -> auth::Role {
+		match (self.role, self.admin) {
+			(Some(role), _) => role,
+			(None, Some(true)) => auth::Role::Admin,
+			(None, _) => auth::Role::User,
+		}
+	}
+
 	pub fn is_admin(&self) -> bool {
-		self.admin == Some(true)
+		self.role() == auth::Role::Admin
+	}
+
+	// Admins always have every permission, so they cannot lock themselves out
+	pub fn has_permission(&self, permission: auth::Permission) -> bool {
+		if self.is_admin() {
+			return true;
+		}
+		match &self.permissions {
+			Some(permissions) => permissions.contains(&permission),
+			None => self.role().grants(permission),
+		}
 	}
 }
 
@@ -30,6 +52,8 @@ impl TryFrom<storage::User> for User {
 		Ok(Self {
 			name: user.name,
 			admin: user.admin,
+			role: user.role,
+			permissions: user.permissions,
 			initial_password: user.initial_password,
 			hashed_password,
 		})
@@ -41,6 +65,8 @@ impl From<User> for storage::User {
 		Self {
 			name: user.name,
 			admin: user.admin,
+			role: user.role,
+			permissions: user.permissions,
 			initial_password: user.initial_password,
 			hashed_password: Some(user.hashed_password),
 		}
@@ -78,6 +104,8 @@ impl Config {
 		self.users.push(User {
 			name: username.to_owned(),
 			admin: Some(admin),
+			role: None,
+			permissions: None,
 			initial_password: None,
 			hashed_password: password_hash,
 		});
@@ -101,14 +129,30 @@ impl Config {
 		&self,
 		auth_token: &auth::Token,
 		scope: auth::Scope,
-		auth_secret: &auth::Secret,
+		auth_keyring: &auth::Keyring,
 	) -> Result<auth::Authorization, Error> {
-		let authorization = auth::decode_auth_token(auth_token, scope, auth_secret)?;
-		if self.exists(&authorization.username) {
-			Ok(authorization)
-		} else {
-			Err(Error::IncorrectUsername)
-		}
+		let mut authorization = auth::decode_auth_token(auth_token, scope, auth_keyring)?;
+		let user = self
+			.get_user(&authorization.username)
+			.ok_or(Error::IncorrectUsername)?;
+		// Tokens never grant more than the user's current role, so demotions apply immediately
+		let role = user.role();
+		authorization.role = Some(authorization.role.map_or(role, |r| r.min(role)));
+		Ok(authorization)
+	}
+
+	// Permissions granted by an authorization. Tokens issued while the user had a lesser
+	// role than they do now are limited to the permissions of that role.
+	pub fn get_permissions(&self, authorization: &auth::Authorization) -> Vec<auth::Permission> {
+		let Some(user) = self.get_user(&authorization.username) else {
+			return Vec::new();
+		};
+		let role = authorization.role.unwrap_or_default();
+		auth::Permission::ALL
+			.into_iter()
+			.filter(|p| user.has_permission(*p))
+			.filter(|p| role >= user.role() || role.grants(*p))
+			.collect()
 	}
 
 	pub fn login(
@@ -122,6 +166,8 @@ impl Config {
 			let authorization = auth::Authorization {
 				username: username.to_owned(),
 				scope: auth::Scope::PolarisAuth,
+				role: Some(user.role()),
+				share_id: None,
 			};
 			auth::generate_auth_token(&authorization, auth_secret)
 		} else {
@@ -129,9 +175,44 @@ impl Config {
 		}
 	}
 
+	// Signs a token granting read-only access to one of the user's shared playlists
+	pub fn generate_share_token(
+		&self,
+		owner: &str,
+		share_id: &str,
+		auth_secret: &auth::Secret,
+	) -> Result<auth::Token, Error> {
+		self.get_user(owner).ok_or(Error::UserNotFound)?;
+		let authorization = auth::Authorization {
+			username: owner.to_owned(),
+			scope: auth::Scope::PlaylistShare,
+			role: Some(auth::Role::ReadOnly),
+			share_id: Some(share_id.to_owned()),
+		};
+		auth::generate_auth_token(&authorization, auth_secret)
+	}
+
 	pub fn set_is_admin(&mut self, username: &str, is_admin: bool) -> Result<(), Error> {
 		let user = self.get_user_mut(username).ok_or(Error::UserNotFound)?;
 		user.admin = Some(is_admin);
+		user.role = None;
+		Ok(())
+	}
+
+	pub fn set_role(&mut self, username: &str, role: auth::Role) -> Result<(), Error> {
+		let user = self.get_user_mut(username).ok_or(Error::UserNotFound)?;
+		user.admin = Some(role == auth::Role::Admin);
+		user.role = Some(role);
+		Ok(())
+	}
+
+	pub fn set_permissions(
+		&mut self,
+		username: &str,
+		permissions: Option<Vec<auth::Permission>>,
+	) -> Result<(), Error> {
+		let user = self.get_user_mut(username).ok_or(Error::UserNotFound)?;
+		user.permissions = permissions;
 		Ok(())
 	}
 
@@ -302,7 +383,198 @@ mod test {
 			auth::Authorization {
 				username: TEST_USERNAME.to_owned(),
 				scope: auth::Scope::PolarisAuth,
+				role: Some(auth::Role::User),
+				share_id: None,
 			}
 		)
 	}
+
+	#[tokio::test]
+	async fn authenticate_accepts_tokens_from_retired_secret() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+
+		ctx.config_manager
+			.create_user(TEST_USERNAME, TEST_PASSWORD, false)
+			.await
+			.unwrap();
+
+		let old_token = ctx
+			.config_manager
+			.login(TEST_USERNAME, TEST_PASSWORD)
+			.await
+			.unwrap();
+
+		ctx.config_manager.rotate_auth_secret().await;
+
+		let new_token = ctx
+			.config_manager
+			.login(TEST_USERNAME, TEST_PASSWORD)
+			.await
+			.unwrap();
+
+		for token in [old_token, new_token] {
+			assert!(ctx
+				.config_manager
+				.authenticate(&token, auth::Scope::PolarisAuth)
+				.await
+				.is_ok());
+		}
+	}
+
+	#[test]
+	fn authenticate_rejects_tokens_from_expired_secret() {
+		let mut config = Config::default();
+		config
+			.create_user(TEST_USERNAME, TEST_PASSWORD, false)
+			.unwrap();
+
+		let old_secret = auth::Secret::generate();
+		let old_token = config
+			.login(TEST_USERNAME, TEST_PASSWORD, &old_secret)
+			.unwrap();
+
+		let auth_keyring = auth::Keyring {
+			current: auth::Secret::generate(),
+			retired: vec![auth::RetiredSecret {
+				secret: old_secret,
+				retired_at: 0,
+			}],
+		};
+
+		assert!(matches!(
+			config
+				.authenticate(&old_token, auth::Scope::PolarisAuth, &auth_keyring)
+				.unwrap_err(),
+			Error::InvalidAuthToken
+		));
+	}
+
+	#[test]
+	fn derives_role_from_admin_flag() {
+		let user = |admin, role| User {
+			admin,
+			role,
+			..Default::default()
+		};
+		assert_eq!(user(None, None).role(), auth::Role::User);
+		assert_eq!(user(Some(true), None).role(), auth::Role::Admin);
+		assert_eq!(
+			user(Some(true), Some(auth::Role::ReadOnly)).role(),
+			auth::Role::ReadOnly
+		);
+		assert!(!user(Some(true), Some(auth::Role::User)).is_admin());
+	}
+
+	async fn authenticated_role(ctx: &test::Context, token: &auth::Token) -> Option<auth::Role> {
+		ctx.config_manager
+			.authenticate(token, auth::Scope::PolarisAuth)
+			.await
+			.unwrap()
+			.role
+	}
+
+	#[tokio::test]
+	async fn authenticate_applies_role_changes() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+
+		ctx.config_manager
+			.create_user(TEST_USERNAME, TEST_PASSWORD, false)
+			.await
+			.unwrap();
+
+		let token = ctx
+			.config_manager
+			.login(TEST_USERNAME, TEST_PASSWORD)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			authenticated_role(&ctx, &token).await,
+			Some(auth::Role::User)
+		);
+
+		// Promotions only apply to new tokens
+		ctx.config_manager
+			.set_role(TEST_USERNAME, auth::Role::Admin)
+			.await
+			.unwrap();
+		assert_eq!(
+			authenticated_role(&ctx, &token).await,
+			Some(auth::Role::User)
+		);
+
+		// Demotions apply to existing tokens
+		ctx.config_manager
+			.set_role(TEST_USERNAME, auth::Role::ReadOnly)
+			.await
+			.unwrap();
+		assert_eq!(
+			authenticated_role(&ctx, &token).await,
+			Some(auth::Role::ReadOnly)
+		);
+	}
+
+	#[test]
+	fn read_only_guest_can_stream_but_not_manage_users() {
+		let guest = User {
+			role: Some(auth::Role::ReadOnly),
+			..Default::default()
+		};
+		assert!(guest.has_permission(auth::Permission::Stream));
+		assert!(!guest.has_permission(auth::Permission::ManageUsers));
+		assert!(!guest.has_permission(auth::Permission::Scan));
+		assert!(!guest.has_permission(auth::Permission::EditPlaylists));
+	}
+
+	#[test]
+	fn admin_keeps_all_permissions() {
+		let admin = User {
+			admin: Some(true),
+			permissions: Some(vec![auth::Permission::Stream]),
+			..Default::default()
+		};
+		for permission in auth::Permission::ALL {
+			assert!(admin.has_permission(permission));
+		}
+	}
+
+	#[test]
+	fn explicit_permissions_replace_role_defaults() {
+		let user = User {
+			role: Some(auth::Role::ReadOnly),
+			permissions: Some(vec![auth::Permission::Scan]),
+			..Default::default()
+		};
+		assert!(user.has_permission(auth::Permission::Scan));
+		assert!(!user.has_permission(auth::Permission::Stream));
+	}
+
+	#[tokio::test]
+	async fn token_permissions_follow_demotions() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+
+		ctx.config_manager
+			.create_user(TEST_USERNAME, TEST_PASSWORD, false)
+			.await
+			.unwrap();
+		let token = ctx
+			.config_manager
+			.login(TEST_USERNAME, TEST_PASSWORD)
+			.await
+			.unwrap();
+
+		ctx.config_manager
+			.set_role(TEST_USERNAME, auth::Role::ReadOnly)
+			.await
+			.unwrap();
+		let authorization = ctx
+			.config_manager
+			.authenticate(&token, auth::Scope::PolarisAuth)
+			.await
+			.unwrap();
+		assert_eq!(
+			ctx.config_manager.get_permissions(&authorization).await,
+			vec![auth::Permission::Stream]
+		);
+	}
 }
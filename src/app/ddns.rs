@@ -1,16 +1,39 @@
 use log::{debug, error};
-use std::time::Duration;
+use native_db::*;
+use native_model::{native_model, Model};
+use serde::{Deserialize, Serialize};
+use std::{net::IpAddr, time::Duration};
+use tokio::task::spawn_blocking;
 
-use crate::app::{config, Error};
+use crate::app::{config, ndb, Error};
+
+pub type DDNSStateModel = v1::DDNSStateModel;
+
+pub mod v1 {
+
+	use super::*;
+
+	#[derive(Debug, Default, Serialize, Deserialize)]
+	#[native_model(id = 2, version = 1)]
+	#[native_db]
+	pub struct DDNSStateModel {
+		#[primary_key]
+		pub id: u32,
+		pub last_pushed_ip: Option<String>,
+	}
+}
+
+const DDNS_STATE_ID: u32 = 0;
 
 #[derive(Clone)]
 pub struct Manager {
 	config_manager: config::Manager,
+	db: ndb::Manager,
 }
 
 impl Manager {
-	pub fn new(config_manager: config::Manager) -> Self {
-		Self { config_manager }
+	pub fn new(config_manager: config::Manager, db: ndb::Manager) -> Self {
+		Self { config_manager, db }
 	}
 
 	pub async fn update_ddns(&self) -> Result<(), Error> {
@@ -20,15 +43,66 @@ impl Manager {
 			return Ok(());
 		};
 
-		let response = ureq::get(&url.to_string()).call();
+		let current_ip = match self.config_manager.get_ddns_ip_lookup_url().await {
+			Some(lookup_url) => spawn_blocking(move || lookup_public_ip(&lookup_url)).await?,
+			None => None,
+		};
+		if let Some(ip) = current_ip {
+			if !self.needs_update(ip).await? {
+				debug!("Skipping DDNS update because IP address ({ip}) has not changed");
+				return Ok(());
+			}
+		}
+
+		let response = spawn_blocking(move || ureq::get(&url.to_string()).call()).await?;
 
 		match response {
-			Ok(_) => Ok(()),
+			Ok(_) => {
+				if let Some(ip) = current_ip {
+					self.record_update(ip).await?;
+				}
+				Ok(())
+			}
 			Err(ureq::Error::Status(code, _)) => Err(Error::UpdateQueryFailed(code)),
 			Err(ureq::Error::Transport(_)) => Err(Error::UpdateQueryTransport),
 		}
 	}
 
+	async fn needs_update(&self, ip: IpAddr) -> Result<bool, Error> {
+		let last_pushed_ip = self.read_last_pushed_ip().await?;
+		Ok(last_pushed_ip != Some(ip))
+	}
+
+	async fn read_last_pushed_ip(&self) -> Result<Option<IpAddr>, Error> {
+		spawn_blocking({
+			let manager = self.clone();
+			move || {
+				let transaction = manager.db.r_transaction()?;
+				let state = transaction.get().primary::<DDNSStateModel>(DDNS_STATE_ID)?;
+				Ok(state
+					.and_then(|s| s.last_pushed_ip)
+					.and_then(|ip| ip.parse().ok()))
+			}
+		})
+		.await?
+	}
+
+	async fn record_update(&self, ip: IpAddr) -> Result<(), Error> {
+		spawn_blocking({
+			let manager = self.clone();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				transaction.upsert::<DDNSStateModel>(DDNSStateModel {
+					id: DDNS_STATE_ID,
+					last_pushed_ip: Some(ip.to_string()),
+				})?;
+				transaction.commit()?;
+				Ok(())
+			}
+		})
+		.await?
+	}
+
 	pub fn begin_periodic_updates(&self) {
 		tokio::spawn({
 			let ddns = self.clone();
@@ -43,3 +117,92 @@ impl Manager {
 		});
 	}
 }
+
+fn lookup_public_ip(lookup_url: &http::Uri) -> Option<IpAddr> {
+	match ureq::get(&lookup_url.to_string()).call() {
+		Ok(response) => response.into_string().ok()?.trim().parse().ok(),
+		Err(e) => {
+			debug!("Could not determine public IP address: {e}");
+			None
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::app::test;
+	use crate::test_name;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn updates_are_only_sent_when_ip_changes() {
+		let lookup_server = test::HttpListener::new();
+		let update_server = test::HttpListener::new();
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		ctx.config_manager
+			.apply_config(config::storage::Config {
+				ddns_ip_lookup_url: Some(lookup_server.url()),
+				ddns_update_url: Some(update_server.url()),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		let db = ndb::Manager::new(&ctx.test_directory.join("ddns")).unwrap();
+		let ddns = Manager::new(ctx.config_manager.clone(), db);
+
+		lookup_server.set_response(200, "203.0.113.1");
+		ddns.update_ddns().await.unwrap();
+		assert_eq!(update_server.requests().len(), 1);
+
+		ddns.update_ddns().await.unwrap();
+		assert_eq!(update_server.requests().len(), 1);
+
+		lookup_server.set_response(200, "203.0.113.2");
+		ddns.update_ddns().await.unwrap();
+		assert_eq!(update_server.requests().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn updates_are_always_sent_without_ip_lookup() {
+		let update_server = test::HttpListener::new();
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		ctx.config_manager
+			.apply_config(config::storage::Config {
+				ddns_update_url: Some(update_server.url()),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		let db = ndb::Manager::new(&ctx.test_directory.join("ddns")).unwrap();
+		let ddns = Manager::new(ctx.config_manager.clone(), db);
+
+		ddns.update_ddns().await.unwrap();
+		ddns.update_ddns().await.unwrap();
+		assert_eq!(update_server.requests().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn remembers_last_pushed_ip_across_restarts() {
+		let builder = test::ContextBuilder::new(test_name!());
+		let db_directory = builder.test_directory.join("ddns");
+		let ctx = builder.build().await;
+
+		let old_ip: IpAddr = "203.0.113.1".parse().unwrap();
+		let new_ip: IpAddr = "203.0.113.2".parse().unwrap();
+
+		{
+			let db = ndb::Manager::new(&db_directory).unwrap();
+			let ddns = Manager::new(ctx.config_manager.clone(), db);
+			assert!(ddns.needs_update(old_ip).await.unwrap());
+			ddns.record_update(old_ip).await.unwrap();
+			assert!(!ddns.needs_update(old_ip).await.unwrap());
+		}
+
+		// Simulate a server restart
+		let db = ndb::Manager::new(&db_directory).unwrap();
+		let ddns = Manager::new(ctx.config_manager.clone(), db);
+		assert!(!ddns.needs_update(old_ip).await.unwrap());
+		assert!(ddns.needs_update(new_ip).await.unwrap());
+	}
+}
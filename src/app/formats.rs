@@ -1,9 +1,21 @@
 use id3::TagLike;
 use lewton::inside_ogg::OggStreamReader;
-use log::error;
 use std::fs;
-use std::io::{Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::num::NonZeroU32;
 use std::path::Path;
+use symphonia::core::{
+	audio::{AudioBufferRef, SampleBuffer},
+	codecs::{
+		CodecType, Decoder, DecoderOptions, CODEC_TYPE_FLAC, CODEC_TYPE_MP3, CODEC_TYPE_NULL,
+		CODEC_TYPE_OPUS,
+	},
+	formats::{FormatOptions, FormatReader},
+	io::{MediaSourceStream, MediaSourceStreamOptions},
+	meta::MetadataOptions,
+	probe::Hint,
+	units::TimeBase,
+};
 
 use crate::app::Error;
 use crate::utils;
@@ -18,16 +30,46 @@ pub struct SongMetadata {
 	pub artists: Vec<String>,
 	pub album_artists: Vec<String>,
 	pub album: Option<String>,
+	pub disc_subtitle: Option<String>,
+	pub grouping: Option<String>,
+	pub work: Option<String>, // Composition this song is a part of (eg. a symphony)
+	pub movement: Option<String>,
+	pub movement_number: Option<u32>,
 	pub year: Option<i32>,
+	pub original_year: Option<i32>, // Original release of reissued material, when tagged
+	pub rating: Option<u32>,        // Number of stars, from 1 to 5
 	pub has_artwork: bool,
 	pub lyricists: Vec<String>,
 	pub composers: Vec<String>,
 	pub genres: Vec<String>,
 	pub labels: Vec<String>,
+	pub conductors: Vec<String>,
+	pub ensembles: Vec<String>, // Orchestras, choirs and other groups performing a song
+	pub custom_fields: Vec<(String, String)>, // Description and value of ID3 `TXXX` frames
+	pub gapless: Option<GaplessInfo>,
 }
 
-pub fn read_metadata<P: AsRef<Path>>(path: P) -> Option<SongMetadata> {
-	let data = match utils::get_audio_format(&path) {
+// Number of samples added by the encoder at the start (delay) and end (padding) of a
+// song, which players need to trim for gapless playback.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GaplessInfo {
+	pub encoder_delay: u32,
+	pub encoder_padding: u32,
+}
+
+// Properties of the audio stream itself, as opposed to tags describing its content
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TechnicalProperties {
+	pub channels: Option<u32>,
+	pub bitrate: Option<u32>,     // Average over the whole file, in kbps
+	pub sample_rate: Option<u32>, // In Hz
+	pub bit_depth: Option<u32>,   // Only known for lossless codecs
+	pub codec: Option<String>,
+	pub duration: Option<u32>, // From the number of frames in the stream, in seconds
+}
+
+pub fn read_metadata<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
+	match utils::get_audio_format(&path) {
 		Some(AudioFormat::AIFF) => read_id3(&path),
 		Some(AudioFormat::FLAC) => read_flac(&path),
 		Some(AudioFormat::MP3) => read_mp3(&path),
@@ -36,19 +78,354 @@ pub fn read_metadata<P: AsRef<Path>>(path: P) -> Option<SongMetadata> {
 		Some(AudioFormat::WAVE) => read_id3(&path),
 		Some(AudioFormat::APE) | Some(AudioFormat::MPC) => read_ape(&path),
 		Some(AudioFormat::MP4) | Some(AudioFormat::M4B) => read_mp4(&path),
-		None => return None,
+		None => Err(Error::UnsupportedFormat("unknown extension")),
+	}
+}
+
+pub fn read_technical_properties<P: AsRef<Path>>(path: P) -> Option<TechnicalProperties> {
+	let file = fs::File::open(path.as_ref()).ok()?;
+	let file_size = file.metadata().ok()?.len();
+	let media_source = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
+	let format = symphonia::default::get_probe()
+		.format(
+			&Hint::new(),
+			media_source,
+			&FormatOptions::default(),
+			&MetadataOptions::default(),
+		)
+		.ok()?
+		.format;
+	let params = &format
+		.tracks()
+		.iter()
+		.find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?
+		.codec_params;
+
+	let duration = match (params.n_frames, params.sample_rate) {
+		(Some(n), Some(rate)) if n > 0 && rate > 0 => Some(n as f64 / rate as f64),
+		_ => None,
+	};
+
+	Some(TechnicalProperties {
+		channels: params.channels.map(|c| c.count() as u32),
+		// Derived from file size so that VBR files report their average bitrate
+		bitrate: duration.map(|d| (file_size as f64 * 8.0 / d / 1000.0).round() as u32),
+		sample_rate: params.sample_rate,
+		bit_depth: params.bits_per_sample,
+		codec: codec_name(params.codec),
+		duration: duration.map(|d| d as u32),
+	})
+}
+
+fn codec_name(codec: CodecType) -> Option<String> {
+	// Symphonia identifies Opus streams but has no decoder registered for them
+	if codec == CODEC_TYPE_OPUS {
+		return Some("opus".to_owned());
+	}
+	symphonia::default::get_codecs()
+		.get_codec(codec)
+		.map(|d| d.short_name.to_owned())
+}
+
+// Decoding state of the first audio track of a file
+pub struct Source {
+	pub format: Box<dyn FormatReader>,
+	pub decoder: Box<dyn Decoder>,
+	pub track_id: u32,
+	pub time_base: Option<TimeBase>,
+}
+
+impl Source {
+	// Decodes the next packet of the track, skipping packets which fail to decode.
+	// Returns the timestamp of the packet alongside its samples, or `None` at the end
+	// of the track.
+	pub fn next_packet(&mut self) -> Result<Option<(u64, AudioBufferRef<'_>)>, Error> {
+		loop {
+			let packet = match self.format.next_packet() {
+				Ok(packet) => packet,
+				Err(symphonia::core::errors::Error::IoError(e))
+					if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+				{
+					return Ok(None);
+				}
+				Err(e) => return Err(Error::MediaPacketError(e)),
+			};
+
+			if packet.track_id() != self.track_id {
+				continue;
+			}
+
+			// Returning the decoded buffer from within the match would keep the decoder borrowed
+			if self.decoder.decode(&packet).is_err() {
+				continue;
+			}
+			return Ok(Some((packet.ts(), self.decoder.last_decoded())));
+		}
+	}
+}
+
+fn open_format(audio_path: &Path) -> Result<Box<dyn FormatReader>, Error> {
+	let file = fs::File::open(audio_path).map_err(|e| Error::Io(audio_path.to_owned(), e))?;
+	let media_source = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
+
+	Ok(symphonia::default::get_probe()
+		.format(
+			&Hint::new(),
+			media_source,
+			&FormatOptions::default(),
+			&MetadataOptions::default(),
+		)
+		.map_err(Error::MediaProbeError)?
+		.format)
+}
+
+pub fn open_source(audio_path: &Path) -> Result<Source, Error> {
+	let format = open_format(audio_path)?;
+
+	let track = format
+		.tracks()
+		.iter()
+		.find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+		.ok_or_else(|| Error::MediaEmpty(audio_path.to_owned()))?;
+
+	let track_id = track.id;
+	let time_base = track.codec_params.time_base;
+
+	let decoder = symphonia::default::get_codecs()
+		.make(&track.codec_params, &DecoderOptions::default())
+		.map_err(Error::MediaDecoderError)?;
+
+	Ok(Source {
+		format,
+		decoder,
+		track_id,
+		time_base,
+	})
+}
+
+// Copies the audio stream of a file into the native container of `target` without
+// decoding it. This only applies to FLAC and MP3 streams, which may be wrapped in
+// other containers (eg. Matroska). Returns `Error::NotRemuxable` when the stream
+// cannot be stored in the target format as is, and would need to be transcoded.
+pub fn remux(audio_path: &Path, output_path: &Path, target: AudioFormat) -> Result<(), Error> {
+	let mut format = open_format(audio_path)?;
+
+	let track = format
+		.tracks()
+		.iter()
+		.find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+		.ok_or_else(|| Error::MediaEmpty(audio_path.to_owned()))?;
+	let track_id = track.id;
+
+	let header = match (target, track.codec_params.codec) {
+		(AudioFormat::FLAC, CODEC_TYPE_FLAC) => {
+			flac_header(track.codec_params.extra_data.as_deref())
+				.ok_or_else(|| Error::NotRemuxable(audio_path.to_owned(), target))?
+		}
+		(AudioFormat::MP3, CODEC_TYPE_MP3) => Vec::new(),
+		_ => return Err(Error::NotRemuxable(audio_path.to_owned(), target)),
+	};
+
+	let file = fs::File::create(output_path).map_err(|e| Error::Io(output_path.to_owned(), e))?;
+	let mut output = std::io::BufWriter::new(file);
+	let io_error = |e| Error::Io(output_path.to_owned(), e);
+	output.write_all(&header).map_err(io_error)?;
+
+	loop {
+		let packet = match format.next_packet() {
+			Ok(packet) => packet,
+			Err(symphonia::core::errors::Error::IoError(e))
+				if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+			{
+				break;
+			}
+			Err(e) => return Err(Error::MediaPacketError(e)),
+		};
+		if packet.track_id() == track_id {
+			output.write_all(&packet.data).map_err(io_error)?;
+		}
+	}
+
+	output.flush().map_err(io_error)
+}
+
+// Builds the `fLaC` marker and metadata preceding audio frames in a FLAC file. Demuxers
+// either expose the STREAMINFO block alone, or the whole header as found in the source.
+fn flac_header(extra_data: Option<&[u8]>) -> Option<Vec<u8>> {
+	const STREAMINFO_LENGTH: usize = 34;
+	let extra_data = extra_data?;
+	if extra_data.starts_with(b"fLaC") {
+		return Some(extra_data.to_vec());
+	}
+	if extra_data.len() != STREAMINFO_LENGTH {
+		return None;
+	}
+	let mut header = b"fLaC".to_vec();
+	// Block header: last-metadata-block flag, STREAMINFO type (0) and 24-bit length
+	header.extend_from_slice(&[0x80, 0, 0, STREAMINFO_LENGTH as u8]);
+	header.extend_from_slice(extra_data);
+	Some(header)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SampleFormat {
+	#[default]
+	F32,
+	I16,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PcmOptions {
+	pub sample_rate: Option<NonZeroU32>, // Defaults to the sample rate of the source
+	pub sample_format: SampleFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PcmFormat {
+	pub sample_rate: u32,
+	pub num_channels: usize,
+	pub sample_format: SampleFormat,
+}
+
+// Decodes an audio file into interleaved little-endian PCM samples, one chunk at a time
+pub struct PcmDecoder {
+	source: Source,
+	format: PcmFormat,
+	resampler: Resampler,
+	first_chunk: Option<Vec<u8>>,
+}
+
+impl PcmDecoder {
+	pub fn format(&self) -> PcmFormat {
+		self.format
+	}
+
+	// Returns `None` once the whole file has been decoded
+	pub fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+		if let Some(chunk) = self.first_chunk.take() {
+			return Ok(Some(chunk));
+		}
+		loop {
+			let Some((_, decoded)) = self.source.next_packet()? else {
+				return Ok(None);
+			};
+			// Packets whose channel layout differs from the start of the file are dropped
+			if decoded.spec().channels.count() != self.format.num_channels {
+				continue;
+			}
+			let samples = interleaved_samples(decoded);
+			let chunk = encode_pcm(&self.resampler.process(&samples), self.format.sample_format);
+			if !chunk.is_empty() {
+				return Ok(Some(chunk));
+			}
+		}
+	}
+}
+
+// The output format is only known for sure after decoding the first packet, which
+// is done before returning so that errors surface early.
+pub fn decode_pcm(audio_path: &Path, options: PcmOptions) -> Result<PcmDecoder, Error> {
+	let mut source = open_source(audio_path)?;
+
+	let Some((_, decoded)) = source.next_packet()? else {
+		return Err(Error::MediaEmpty(audio_path.to_owned()));
 	};
-	match data {
-		Ok(d) => Some(d),
-		Err(e) => {
-			error!(
-				"Error while reading file metadata for '{:?}': {}",
-				path.as_ref(),
-				e
-			);
-			None
+	let num_channels = decoded.spec().channels.count();
+	let source_rate = decoded.spec().rate;
+	let samples = interleaved_samples(decoded);
+
+	let format = PcmFormat {
+		sample_rate: options.sample_rate.map_or(source_rate, NonZeroU32::get),
+		num_channels,
+		sample_format: options.sample_format,
+	};
+
+	let mut resampler = Resampler::new(num_channels, source_rate, format.sample_rate);
+	let first_chunk = encode_pcm(&resampler.process(&samples), format.sample_format);
+
+	Ok(PcmDecoder {
+		source,
+		format,
+		resampler,
+		first_chunk: Some(first_chunk),
+	})
+}
+
+fn interleaved_samples(decoded: AudioBufferRef) -> Vec<f32> {
+	let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+	buffer.copy_interleaved_ref(decoded);
+	buffer.samples().to_vec()
+}
+
+fn encode_pcm(samples: &[f32], sample_format: SampleFormat) -> Vec<u8> {
+	match sample_format {
+		SampleFormat::F32 => samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+		SampleFormat::I16 => samples
+			.iter()
+			.map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+			.flat_map(|s| s.to_le_bytes())
+			.collect(),
+	}
+}
+
+// Linear interpolation between neighboring frames. Interpolation state carries over
+// from one packet to the next, so that packet boundaries are not audible.
+struct Resampler {
+	num_channels: usize,
+	step: f64,                // Input frames per output frame
+	position: f64,            // Next output frame, relative to the first frame of the next packet
+	previous_frame: Vec<f32>, // Last frame of the previous packet
+}
+
+impl Resampler {
+	fn new(num_channels: usize, input_rate: u32, output_rate: u32) -> Self {
+		let step = match (input_rate, output_rate) {
+			(0, _) | (_, 0) => 1.0,
+			(input, output) => input as f64 / output as f64,
+		};
+		Self {
+			num_channels,
+			step,
+			position: 0.0,
+			previous_frame: Vec::new(),
 		}
 	}
+
+	fn process(&mut self, input: &[f32]) -> Vec<f32> {
+		if self.step == 1.0 || self.num_channels == 0 {
+			return input.to_vec();
+		}
+
+		let num_frames = (input.len() / self.num_channels) as isize;
+		if num_frames == 0 {
+			return Vec::new();
+		}
+
+		let frame = |index: isize| -> &[f32] {
+			if index < 0 {
+				&self.previous_frame
+			} else {
+				let start = index as usize * self.num_channels;
+				&input[start..start + self.num_channels]
+			}
+		};
+
+		let mut output = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+		let mut position = self.position;
+		while position < (num_frames - 1) as f64 {
+			let index = position.floor() as isize;
+			let t = (position - index as f64) as f32;
+			let (a, b) = (frame(index), frame(index + 1));
+			for channel in 0..self.num_channels {
+				output.push(a[channel] + (b[channel] - a[channel]) * t);
+			}
+			position += self.step;
+		}
+
+		self.position = position - num_frames as f64;
+		self.previous_frame = frame(num_frames - 1).to_vec();
+		output
+	}
 }
 
 trait ID3Ext {
@@ -59,11 +436,134 @@ impl ID3Ext for id3::Tag {
 	fn get_text_values(&self, frame_name: &str) -> Vec<String> {
 		self.get(frame_name)
 			.and_then(|f| f.content().text_values())
-			.map(|i| i.map(str::to_string).collect())
+			.map(|i| i.map(repair_id3_text).collect())
 			.unwrap_or_default()
 	}
 }
 
+fn read_lame_gapless_info<R: Read + Seek>(reader: &mut R) -> Option<GaplessInfo> {
+	let mut header = [0; 10];
+	reader.read_exact(&mut header).ok()?;
+	let first_frame_start = if &header[0..3] == b"ID3" {
+		let tag_size = header[6..10]
+			.iter()
+			.fold(0, |size, b| (size << 7) | (*b & 0x7F) as u64);
+		let has_footer = header[5] & 0x10 != 0;
+		10 + tag_size + if has_footer { 10 } else { 0 }
+	} else {
+		0
+	};
+
+	reader.seek(SeekFrom::Start(first_frame_start)).ok()?;
+	let mut frame = Vec::new();
+	reader.take(512).read_to_end(&mut frame).ok()?;
+	parse_lame_gapless_info(&frame)
+}
+
+// Reads the LAME extension of the Xing/Info header which encoders write in place
+// of the first audio frame.
+fn parse_lame_gapless_info(frame: &[u8]) -> Option<GaplessInfo> {
+	let frame_header = frame.get(0..4)?;
+	if frame_header[0] != 0xFF || frame_header[1] & 0xE0 != 0xE0 {
+		return None;
+	}
+
+	let is_mpeg1 = (frame_header[1] >> 3) & 0b11 == 0b11;
+	let is_mono = frame_header[3] >> 6 == 0b11;
+	let side_info_size = match (is_mpeg1, is_mono) {
+		(true, false) => 32,
+		(true, true) | (false, false) => 17,
+		(false, true) => 9,
+	};
+
+	let mut offset = 4 + side_info_size;
+	let xing_id = frame.get(offset..offset + 4)?;
+	if xing_id != b"Xing" && xing_id != b"Info" {
+		return None;
+	}
+
+	let flags = u32::from_be_bytes(frame.get(offset + 4..offset + 8)?.try_into().ok()?);
+	offset += 8;
+	if flags & 0x1 != 0 {
+		offset += 4; // Number of frames
+	}
+	if flags & 0x2 != 0 {
+		offset += 4; // Number of bytes
+	}
+	if flags & 0x4 != 0 {
+		offset += 100; // Seek table
+	}
+	if flags & 0x8 != 0 {
+		offset += 4; // Quality indicator
+	}
+
+	let encoder = frame.get(offset..offset + 4)?;
+	if encoder != b"LAME" && encoder != b"Lavc" && encoder != b"Lavf" {
+		return None;
+	}
+
+	// Delay and padding are packed as two 12 bits values
+	let delay_padding = frame.get(offset + 21..offset + 24)?;
+	Some(GaplessInfo {
+		encoder_delay: ((delay_padding[0] as u32) << 4) | ((delay_padding[1] as u32) >> 4),
+		encoder_padding: (((delay_padding[1] & 0x0F) as u32) << 8) | (delay_padding[2] as u32),
+	})
+}
+
+// iTunSMPB values are a list of hexadecimal numbers, the second and third of which
+// are encoder delay and padding.
+fn parse_itunsmpb(value: &str) -> Option<GaplessInfo> {
+	let mut fields = value.split_whitespace().skip(1);
+	let encoder_delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+	let encoder_padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+	Some(GaplessInfo {
+		encoder_delay,
+		encoder_padding,
+	})
+}
+
+// ID3 frames declare their own encoding, which tagging tools do not always get right.
+// Text labeled as Latin-1 which actually holds UTF-8 (eg. `BjÃ¶rk`) is decoded again,
+// and byte order marks or control characters left over from UTF-16 are removed.
+fn repair_id3_text(value: &str) -> String {
+	let value = value
+		.chars()
+		.filter(|c| *c != '\u{feff}' && *c != '\u{fffe}' && !c.is_control())
+		.collect::<String>();
+
+	if value.is_ascii() || value.chars().any(|c| c as u32 > 0xFF) {
+		return value;
+	}
+	let bytes = value.chars().map(|c| c as u8).collect::<Vec<_>>();
+	String::from_utf8(bytes).unwrap_or(value)
+}
+
+// Reads the first number of values like `2/4`
+fn parse_number_of_total(value: &str) -> Option<u32> {
+	value.split('/').next()?.trim().parse().ok()
+}
+
+// Reads the year out of a full or partial date, such as `1975`, `1975-03` or `1975-03-01`
+fn parse_year(value: &str) -> Option<i32> {
+	value.trim().split('-').next()?.parse::<i32>().ok()
+}
+
+// `POPM` ratings range from 1 to 255, with 0 meaning the song is unrated
+fn parse_popm_rating(value: u8) -> Option<u32> {
+	(value > 0).then(|| (value as u32 * 5).div_ceil(255))
+}
+
+// `RATING` comments are written either as a number of stars (1 to 5) or as a
+// percentage (1 to 100), with 0 meaning the song is unrated
+fn parse_rating(value: &str) -> Option<u32> {
+	match value.trim().parse::<u32>().ok()? {
+		0 => None,
+		n @ 1..=5 => Some(n),
+		n @ 6..=100 => Some(n.div_ceil(20)),
+		_ => None,
+	}
+}
+
 fn read_id3<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 	let file = fs::File::open(path.as_ref()).map_err(|e| Error::Io(path.as_ref().to_owned(), e))?;
 	read_id3_from_file(&file, path)
@@ -82,8 +582,8 @@ fn read_id3_from_file<P: AsRef<Path>>(file: &fs::File, path: P) -> Result<SongMe
 
 	let artists = tag.get_text_values("TPE1");
 	let album_artists = tag.get_text_values("TPE2");
-	let album = tag.album().map(|s| s.to_string());
-	let title = tag.title().map(|s| s.to_string());
+	let album = tag.album().map(repair_id3_text);
+	let title = tag.title().map(repair_id3_text);
 	let duration = tag.duration();
 	let disc_number = tag.disc();
 	let track_number = tag.track();
@@ -92,11 +592,62 @@ fn read_id3_from_file<P: AsRef<Path>>(file: &fs::File, path: P) -> Result<SongMe
 		.or_else(|| tag.date_released().map(|d| d.year))
 		.or_else(|| tag.original_date_released().map(|d| d.year))
 		.or_else(|| tag.date_recorded().map(|d| d.year));
+	let original_year = tag
+		.original_date_released()
+		.map(|d| d.year)
+		.or_else(|| {
+			tag.get_text_values("TORY")
+				.first()
+				.and_then(|v| parse_year(v))
+		})
+		.or_else(|| {
+			tag.extended_texts()
+				.find(|t| t.description.eq_ignore_ascii_case("ORIGYEAR"))
+				.and_then(|t| parse_year(&t.value))
+		});
+	let rating = tag
+		.frames()
+		.find_map(|f| f.content().popularimeter())
+		.and_then(|p| parse_popm_rating(p.rating));
 	let has_artwork = tag.pictures().count() > 0;
 	let lyricists = tag.get_text_values("TEXT");
 	let composers = tag.get_text_values("TCOM");
 	let genres = tag.get_text_values("TCON");
 	let labels = tag.get_text_values("TPUB");
+	let conductors = tag.get_text_values("TPE3");
+	let ensembles = tag
+		.extended_texts()
+		.filter(|t| t.description.eq_ignore_ascii_case("ENSEMBLE"))
+		.flat_map(|t| t.value.split('\0').map(repair_id3_text).collect::<Vec<_>>())
+		.filter(|v| !v.is_empty())
+		.collect();
+	let disc_subtitle = tag.get_text_values("TSST").into_iter().next();
+	// iTunes stores the grouping in `GRP1` and the work in `TIT1`, which other taggers
+	// use for the grouping instead
+	let content_group = tag.get_text_values("TIT1").into_iter().next();
+	let (grouping, itunes_work) = match tag.get_text_values("GRP1").into_iter().next() {
+		Some(g) => (Some(g), content_group),
+		None => (content_group, None),
+	};
+	let work = tag
+		.extended_texts()
+		.find(|t| t.description.eq_ignore_ascii_case("WORK"))
+		.map(|t| repair_id3_text(&t.value))
+		.or(itunes_work);
+	let movement = tag.get_text_values("MVNM").into_iter().next();
+	let movement_number = tag
+		.get_text_values("MVIN")
+		.first()
+		.and_then(|v| parse_number_of_total(v));
+	let custom_fields = tag
+		.extended_texts()
+		.flat_map(|t| {
+			t.value
+				.split('\0')
+				.filter(|v| !v.is_empty())
+				.map(|v| (t.description.clone(), repair_id3_text(v)))
+		})
+		.collect();
 
 	Ok(SongMetadata {
 		disc_number,
@@ -106,24 +657,38 @@ fn read_id3_from_file<P: AsRef<Path>>(file: &fs::File, path: P) -> Result<SongMe
 		artists,
 		album_artists,
 		album,
+		disc_subtitle,
+		grouping,
+		work,
+		movement,
+		movement_number,
 		year,
+		original_year,
+		rating,
 		has_artwork,
 		lyricists,
 		composers,
 		genres,
 		labels,
+		conductors,
+		ensembles,
+		custom_fields,
+		gapless: None,
 	})
 }
 
 fn read_mp3<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
-	let mut file = fs::File::open(&path).unwrap();
+	let mut file = fs::File::open(&path).map_err(|e| Error::Io(path.as_ref().to_owned(), e))?;
 	let mut metadata = read_id3_from_file(&file, &path)?;
 	metadata.duration = metadata.duration.or_else(|| {
-		file.seek(SeekFrom::Start(0)).unwrap();
+		file.seek(SeekFrom::Start(0)).ok()?;
 		mp3_duration::from_file(&file)
 			.map(|d| d.as_secs() as u32)
 			.ok()
 	});
+	if file.seek(SeekFrom::Start(0)).is_ok() {
+		metadata.gapless = read_lame_gapless_info(&mut file);
+	}
 	Ok(metadata)
 }
 
@@ -171,26 +736,52 @@ fn read_ape<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 	let album_artists = ape_ext::read_strings(tag.item("Album artist"));
 	let title = tag.item("Title").and_then(ape_ext::read_string);
 	let year = tag.item("Year").and_then(ape_ext::read_i32);
+	let original_year = tag
+		.item("ORIGYEAR")
+		.and_then(ape_ext::read_string)
+		.and_then(|v| parse_year(&v));
+	let rating = tag
+		.item("RATING")
+		.and_then(ape_ext::read_string)
+		.and_then(|v| parse_rating(&v));
 	let disc_number = tag.item("Disc").and_then(ape_ext::read_x_of_y);
 	let track_number = tag.item("Track").and_then(ape_ext::read_x_of_y);
 	let lyricists = ape_ext::read_strings(tag.item("LYRICIST"));
 	let composers = ape_ext::read_strings(tag.item("COMPOSER"));
 	let genres = ape_ext::read_strings(tag.item("GENRE"));
 	let labels = ape_ext::read_strings(tag.item("PUBLISHER"));
+	let conductors = ape_ext::read_strings(tag.item("CONDUCTOR"));
+	let ensembles = ape_ext::read_strings(tag.item("ENSEMBLE"));
+	let disc_subtitle = tag.item("DISCSUBTITLE").and_then(ape_ext::read_string);
+	let grouping = tag.item("GROUPING").and_then(ape_ext::read_string);
+	let work = tag.item("WORK").and_then(ape_ext::read_string);
+	let movement = tag.item("MOVEMENTNAME").and_then(ape_ext::read_string);
+	let movement_number = tag.item("MOVEMENT").and_then(ape_ext::read_x_of_y);
 	Ok(SongMetadata {
 		artists,
 		album_artists,
 		album,
+		disc_subtitle,
+		grouping,
+		work,
+		movement,
+		movement_number,
 		title,
 		duration: None,
 		disc_number,
 		track_number,
 		year,
+		original_year,
+		rating,
 		has_artwork: false,
 		lyricists,
 		composers,
 		genres,
 		labels,
+		conductors,
+		ensembles,
+		custom_fields: Vec::new(),
+		gapless: None,
 	})
 }
 
@@ -209,10 +800,21 @@ fn read_vorbis<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 				"TRACKNUMBER" => metadata.track_number = value.parse::<u32>().ok(),
 				"DISCNUMBER" => metadata.disc_number = value.parse::<u32>().ok(),
 				"DATE" => metadata.year = value.parse::<i32>().ok(),
+				"ORIGINALDATE" => metadata.original_year = parse_year(&value),
+				"ORIGINALYEAR" => metadata.original_year = parse_year(&value),
+				"ORIGYEAR" => metadata.original_year = parse_year(&value),
+				"RATING" => metadata.rating = parse_rating(&value),
 				"LYRICIST" => metadata.lyricists.push(value),
 				"COMPOSER" => metadata.composers.push(value),
 				"GENRE" => metadata.genres.push(value),
 				"PUBLISHER" => metadata.labels.push(value),
+				"CONDUCTOR" => metadata.conductors.push(value),
+				"ENSEMBLE" => metadata.ensembles.push(value),
+				"DISCSUBTITLE" => metadata.disc_subtitle = Some(value),
+				"GROUPING" => metadata.grouping = Some(value),
+				"WORK" => metadata.work = Some(value),
+				"MOVEMENTNAME" => metadata.movement = Some(value),
+				"MOVEMENT" => metadata.movement_number = parse_number_of_total(&value),
 				_ => (),
 			}
 		}
@@ -235,10 +837,21 @@ fn read_opus<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 				"TRACKNUMBER" => metadata.track_number = value.parse::<u32>().ok(),
 				"DISCNUMBER" => metadata.disc_number = value.parse::<u32>().ok(),
 				"DATE" => metadata.year = value.parse::<i32>().ok(),
+				"ORIGINALDATE" => metadata.original_year = parse_year(&value),
+				"ORIGINALYEAR" => metadata.original_year = parse_year(&value),
+				"ORIGYEAR" => metadata.original_year = parse_year(&value),
+				"RATING" => metadata.rating = parse_rating(&value),
 				"LYRICIST" => metadata.lyricists.push(value),
 				"COMPOSER" => metadata.composers.push(value),
 				"GENRE" => metadata.genres.push(value),
 				"PUBLISHER" => metadata.labels.push(value),
+				"CONDUCTOR" => metadata.conductors.push(value),
+				"ENSEMBLE" => metadata.ensembles.push(value),
+				"DISCSUBTITLE" => metadata.disc_subtitle = Some(value),
+				"GROUPING" => metadata.grouping = Some(value),
+				"WORK" => metadata.work = Some(value),
+				"MOVEMENTNAME" => metadata.movement = Some(value),
+				"MOVEMENT" => metadata.movement_number = parse_number_of_total(&value),
 				_ => (),
 			}
 		}
@@ -257,6 +870,11 @@ fn read_flac<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 		.get("DISCNUMBER")
 		.and_then(|d| d[0].parse::<u32>().ok());
 	let year = vorbis.get("DATE").and_then(|d| d[0].parse::<i32>().ok());
+	let original_year = ["ORIGINALDATE", "ORIGINALYEAR", "ORIGYEAR"]
+		.iter()
+		.find_map(|key| vorbis.get(key))
+		.and_then(|d| parse_year(&d[0]));
+	let rating = vorbis.get("RATING").and_then(|d| parse_rating(&d[0]));
 	let mut streaminfo = tag.get_blocks(metaflac::BlockType::StreamInfo);
 	let duration = match streaminfo.next() {
 		Some(metaflac::Block::StreamInfo(s)) => Some(s.total_samples as u32 / s.sample_rate),
@@ -265,21 +883,35 @@ fn read_flac<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 	let has_artwork = tag.pictures().count() > 0;
 
 	let multivalue = |o: Option<&Vec<String>>| o.cloned().unwrap_or_default();
+	let first_value = |key: &str| vorbis.get(key).and_then(|v| v.first().cloned());
 
 	Ok(SongMetadata {
 		artists: multivalue(vorbis.artist()),
 		album_artists: multivalue(vorbis.album_artist()),
 		album: vorbis.album().map(|v| v[0].clone()),
+		disc_subtitle: first_value("DISCSUBTITLE"),
+		grouping: first_value("GROUPING"),
+		work: first_value("WORK"),
+		movement: first_value("MOVEMENTNAME"),
+		movement_number: vorbis
+			.get("MOVEMENT")
+			.and_then(|v| parse_number_of_total(&v[0])),
 		title: vorbis.title().map(|v| v[0].clone()),
 		duration,
 		disc_number,
 		track_number: vorbis.track(),
 		year,
+		original_year,
+		rating,
 		has_artwork,
 		lyricists: multivalue(vorbis.get("LYRICIST")),
 		composers: multivalue(vorbis.get("COMPOSER")),
 		genres: multivalue(vorbis.get("GENRE")),
 		labels: multivalue(vorbis.get("PUBLISHER")),
+		conductors: multivalue(vorbis.get("CONDUCTOR")),
+		ensembles: multivalue(vorbis.get("ENSEMBLE")),
+		custom_fields: Vec::new(),
+		gapless: None,
 	})
 }
 
@@ -287,21 +919,43 @@ fn read_mp4<P: AsRef<Path>>(path: P) -> Result<SongMetadata, Error> {
 	let mut tag = mp4ameta::Tag::read_from_path(&path)
 		.map_err(|e| Error::Mp4aMeta(path.as_ref().to_owned(), e))?;
 	let label_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "Label");
+	let original_date_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "ORIGINALDATE");
+	let disc_subtitle_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "DISCSUBTITLE");
+	let conductor_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "CONDUCTOR");
+	let ensemble_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "ENSEMBLE");
+	let itunsmpb_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "iTunSMPB");
 
 	Ok(SongMetadata {
 		artists: tag.take_artists().collect(),
 		album_artists: tag.take_album_artists().collect(),
 		album: tag.take_album(),
+		disc_subtitle: tag
+			.strings_of(&disc_subtitle_ident)
+			.next()
+			.map(str::to_owned),
+		grouping: tag.grouping().map(str::to_owned),
+		work: tag.work().map(str::to_owned),
+		movement: tag.movement().map(str::to_owned),
+		movement_number: tag.movement_index().map(u32::from),
 		title: tag.take_title(),
 		duration: tag.duration().map(|v| v.as_secs() as u32),
 		disc_number: tag.disc_number().map(|d| d as u32),
 		track_number: tag.track_number().map(|d| d as u32),
 		year: tag.year().and_then(|v| v.parse::<i32>().ok()),
+		original_year: tag.strings_of(&original_date_ident).find_map(parse_year),
+		rating: None,
 		has_artwork: tag.artwork().is_some(),
 		lyricists: tag.take_lyricists().collect(),
 		composers: tag.take_composers().collect(),
 		genres: tag.take_genres().collect(),
 		labels: tag.take_strings_of(&label_ident).collect(),
+		conductors: tag.take_strings_of(&conductor_ident).collect(),
+		ensembles: tag.take_strings_of(&ensemble_ident).collect(),
+		custom_fields: Vec::new(),
+		gapless: tag
+			.strings_of(&itunsmpb_ident)
+			.next()
+			.and_then(parse_itunsmpb),
 	})
 }
 
@@ -314,25 +968,47 @@ fn reads_file_metadata() {
 		artists: vec!["TEST ARTIST".into()],
 		album_artists: vec!["TEST ALBUM ARTIST".into()],
 		album: Some("TEST ALBUM".into()),
+		disc_subtitle: None,
+		grouping: None,
+		work: None,
+		movement: None,
+		movement_number: None,
 		duration: None,
 		year: Some(2016),
+		original_year: None,
+		rating: None,
 		has_artwork: false,
 		lyricists: vec!["TEST LYRICIST".into()],
 		composers: vec!["TEST COMPOSER".into()],
 		genres: vec!["TEST GENRE".into()],
 		labels: vec!["TEST LABEL".into()],
+		conductors: vec![],
+		ensembles: vec![],
+		custom_fields: vec![],
+		gapless: None,
 	};
 	let expected_with_duration = SongMetadata {
 		duration: Some(0),
 		..expected_without_duration.clone()
 	};
+	let id3_custom_fields = vec![("Category".into(), "TEST CATEGORY".into())];
 	assert_eq!(
 		read_metadata(Path::new("test-data/formats/sample.aif")).unwrap(),
-		expected_without_duration
+		SongMetadata {
+			custom_fields: id3_custom_fields.clone(),
+			..expected_without_duration.clone()
+		}
 	);
 	assert_eq!(
 		read_metadata(Path::new("test-data/formats/sample.mp3")).unwrap(),
-		expected_with_duration
+		SongMetadata {
+			custom_fields: id3_custom_fields.clone(),
+			gapless: Some(GaplessInfo {
+				encoder_delay: 576,
+				encoder_padding: 2089,
+			}),
+			..expected_with_duration.clone()
+		}
 	);
 	assert_eq!(
 		read_metadata(Path::new("test-data/formats/sample.ogg")).unwrap(),
@@ -356,7 +1032,10 @@ fn reads_file_metadata() {
 	);
 	assert_eq!(
 		read_metadata(Path::new("test-data/formats/sample.wav")).unwrap(),
-		expected_without_duration
+		SongMetadata {
+			custom_fields: id3_custom_fields.clone(),
+			..expected_without_duration.clone()
+		}
 	);
 }
 
@@ -389,6 +1068,111 @@ fn reads_embedded_artwork() {
 	);
 }
 
+#[test]
+fn reads_technical_properties() {
+	let flac = read_technical_properties(Path::new("test-data/formats/sample.flac")).unwrap();
+	assert_eq!(flac.channels, Some(1));
+	assert_eq!(flac.codec.as_deref(), Some("flac"));
+	assert!(flac.bitrate.is_some());
+	assert!(flac.sample_rate.is_some());
+	assert!(flac.bit_depth.is_some());
+
+	let mp3 = read_technical_properties(Path::new("test-data/formats/sample.mp3")).unwrap();
+	assert!(mp3.channels.is_some());
+	assert_eq!(mp3.codec.as_deref(), Some("mp3"));
+	assert!(mp3.bitrate.is_some());
+	assert!(mp3.sample_rate.is_some());
+	assert_eq!(mp3.bit_depth, None);
+
+	let m4a = read_technical_properties(Path::new("test-data/formats/sample.m4a")).unwrap();
+	assert_eq!(m4a.codec.as_deref(), Some("aac"));
+	assert!(m4a.duration.is_some());
+
+	assert_eq!(
+		read_technical_properties(Path::new("test-data/blank.toml")),
+		None
+	);
+}
+
+#[test]
+fn reads_lame_gapless_info() {
+	assert_eq!(
+		read_metadata(Path::new("test-data/formats/sample.mp3"))
+			.unwrap()
+			.gapless,
+		Some(GaplessInfo {
+			encoder_delay: 576,
+			encoder_padding: 2089,
+		})
+	);
+	assert_eq!(
+		read_metadata(Path::new("test-data/formats/sample.flac"))
+			.unwrap()
+			.gapless,
+		None
+	);
+}
+
+#[test]
+fn reads_itunsmpb_gapless_info() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.m4a");
+	fs::copy("test-data/formats/sample.m4a", &path).unwrap();
+	assert_eq!(read_metadata(&path).unwrap().gapless, None);
+
+	let mut tag = mp4ameta::Tag::read_from_path(&path).unwrap();
+	tag.set_data(
+		mp4ameta::FreeformIdent::new("com.apple.iTunes", "iTunSMPB"),
+		mp4ameta::Data::Utf8(
+			" 00000000 00000840 000001CA 00000000003F31F6 00000000 00000000 00000000 00000000"
+				.to_owned(),
+		),
+	);
+	tag.write_to_path(&path).unwrap();
+
+	assert_eq!(
+		read_metadata(&path).unwrap().gapless,
+		Some(GaplessInfo {
+			encoder_delay: 2112,
+			encoder_padding: 458,
+		})
+	);
+}
+
+#[test]
+fn reads_original_year_of_reissues() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.year, Some(2016));
+	assert_eq!(metadata.original_year, None);
+
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.set_original_date_released(id3::Timestamp {
+		year: 1975,
+		month: Some(3),
+		day: None,
+		hour: None,
+		minute: None,
+		second: None,
+	});
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.year, Some(2016));
+	assert_eq!(metadata.original_year, Some(1975));
+}
+
+#[test]
+fn parses_partial_dates() {
+	assert_eq!(parse_year("1975"), Some(1975));
+	assert_eq!(parse_year("1975-03"), Some(1975));
+	assert_eq!(parse_year(" 1975-03-01 "), Some(1975));
+	assert_eq!(parse_year("March 1975"), None);
+}
+
 #[test]
 fn reads_multivalue_fields() {
 	let expected_without_duration = SongMetadata {
@@ -398,25 +1182,47 @@ fn reads_multivalue_fields() {
 		artists: vec!["TEST ARTIST".into(), "OTHER ARTIST".into()],
 		album_artists: vec!["TEST ALBUM ARTIST".into(), "OTHER ALBUM ARTIST".into()],
 		album: Some("TEST ALBUM".into()),
+		disc_subtitle: None,
+		grouping: None,
+		work: None,
+		movement: None,
+		movement_number: None,
 		duration: None,
 		year: Some(2016),
+		original_year: None,
+		rating: None,
 		has_artwork: false,
 		lyricists: vec!["TEST LYRICIST".into(), "OTHER LYRICIST".into()],
 		composers: vec!["TEST COMPOSER".into(), "OTHER COMPOSER".into()],
 		genres: vec!["TEST GENRE".into(), "OTHER GENRE".into()],
 		labels: vec!["TEST LABEL".into(), "OTHER LABEL".into()],
+		conductors: vec![],
+		ensembles: vec![],
+		custom_fields: vec![],
+		gapless: None,
 	};
 	let expected_with_duration = SongMetadata {
 		duration: Some(0),
 		..expected_without_duration.clone()
 	};
+	let id3_custom_fields = vec![("Category".into(), "TEST CATEGORY".into())];
 	assert_eq!(
 		read_metadata(Path::new("test-data/multivalue/multivalue.aif")).unwrap(),
-		expected_without_duration
+		SongMetadata {
+			custom_fields: id3_custom_fields.clone(),
+			..expected_without_duration.clone()
+		}
 	);
 	assert_eq!(
 		read_metadata(Path::new("test-data/multivalue/multivalue.mp3")).unwrap(),
-		expected_with_duration
+		SongMetadata {
+			custom_fields: id3_custom_fields.clone(),
+			gapless: Some(GaplessInfo {
+				encoder_delay: 576,
+				encoder_padding: 2089,
+			}),
+			..expected_with_duration.clone()
+		}
 	);
 	assert_eq!(
 		read_metadata(Path::new("test-data/multivalue/multivalue.ogg")).unwrap(),
@@ -437,6 +1243,299 @@ fn reads_multivalue_fields() {
 	);
 	assert_eq!(
 		read_metadata(Path::new("test-data/multivalue/multivalue.wav")).unwrap(),
-		expected_without_duration
+		SongMetadata {
+			custom_fields: id3_custom_fields.clone(),
+			..expected_without_duration.clone()
+		}
+	);
+}
+
+#[cfg(test)]
+fn decode_pcm_to_end(path: &str, options: PcmOptions) -> (PcmFormat, Vec<u8>) {
+	let mut decoder = decode_pcm(Path::new(path), options).unwrap();
+	let mut bytes = Vec::new();
+	while let Some(chunk) = decoder.next_chunk().unwrap() {
+		bytes.extend(chunk);
+	}
+	(decoder.format(), bytes)
+}
+
+#[test]
+fn decodes_pcm() {
+	let (format, f32_bytes) =
+		decode_pcm_to_end("test-data/formats/sample.flac", PcmOptions::default());
+	assert!(format.sample_rate > 0);
+	assert!(format.num_channels > 0);
+	assert_eq!(format.sample_format, SampleFormat::F32);
+	assert!(!f32_bytes.is_empty());
+	assert_eq!(f32_bytes.len() % (4 * format.num_channels), 0);
+
+	let (format, i16_bytes) = decode_pcm_to_end(
+		"test-data/formats/sample.flac",
+		PcmOptions {
+			sample_format: SampleFormat::I16,
+			..Default::default()
+		},
+	);
+	assert_eq!(format.sample_format, SampleFormat::I16);
+	assert_eq!(i16_bytes.len() * 2, f32_bytes.len());
+}
+
+#[test]
+fn decodes_pcm_at_requested_sample_rate() {
+	let path = "test-data/formats/sample.flac";
+	let (native_format, native_bytes) = decode_pcm_to_end(path, PcmOptions::default());
+	let half_rate = NonZeroU32::new(native_format.sample_rate / 2).unwrap();
+	let (format, bytes) = decode_pcm_to_end(
+		path,
+		PcmOptions {
+			sample_rate: Some(half_rate),
+			..Default::default()
+		},
+	);
+	assert_eq!(format.sample_rate, half_rate.get());
+	let expected = native_bytes.len() as f64 / 2.0;
+	assert!((bytes.len() as f64 - expected).abs() < expected * 0.01);
+}
+
+#[test]
+fn remuxes_compatible_streams() {
+	use crate::test::prepare_test_directory;
+
+	let test_directory = prepare_test_directory(crate::test_name!());
+	for (source, target) in [
+		("test-data/formats/sample.flac", AudioFormat::FLAC),
+		("test-data/formats/sample.mp3", AudioFormat::MP3),
+	] {
+		let output = test_directory.join("remuxed");
+		remux(Path::new(source), &output, target).unwrap();
+		let expected = decode_pcm_to_end(source, PcmOptions::default());
+		let actual = decode_pcm_to_end(output.to_str().unwrap(), PcmOptions::default());
+		assert_eq!(actual, expected);
+	}
+}
+
+#[test]
+fn does_not_remux_incompatible_streams() {
+	use crate::test::prepare_test_directory;
+
+	let source = Path::new("test-data/formats/sample.mp3");
+	let output = prepare_test_directory(crate::test_name!()).join("remuxed");
+	assert!(matches!(
+		remux(source, &output, AudioFormat::FLAC),
+		Err(Error::NotRemuxable(_, AudioFormat::FLAC))
+	));
+}
+
+#[test]
+fn resampler_interpolates_across_packets() {
+	let mut resampler = Resampler::new(1, 1, 2);
+	let mut output = resampler.process(&[0.0, 1.0]);
+	output.extend(resampler.process(&[2.0, 3.0]));
+	assert_eq!(output, vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5]);
+}
+
+// Builds an ID3v2.3 tag holding a single text frame, with the given encoding byte
+#[cfg(test)]
+fn make_id3_text_tag(frame_id: &[u8; 4], encoding: u8, text: &[u8]) -> Vec<u8> {
+	let mut frame = frame_id.to_vec();
+	frame.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+	frame.extend_from_slice(&[0, 0, encoding]);
+	frame.extend_from_slice(text);
+
+	let size = frame.len() as u32;
+	let mut tag = b"ID3\x03\x00\x00".to_vec();
+	tag.extend([21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8));
+	tag.extend(frame);
+	tag
+}
+
+#[test]
+fn reads_utf16_id3_text() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.mp3");
+
+	let mut text = vec![0xFF, 0xFE];
+	text.extend("Sigur Rós".encode_utf16().flat_map(u16::to_le_bytes));
+	let mut content = make_id3_text_tag(b"TPE1", 1, &text);
+	content.extend(fs::read("test-data/formats/sample.mp3").unwrap());
+	fs::write(&path, content).unwrap();
+
+	assert_eq!(
+		read_metadata(&path).unwrap().artists,
+		vec!["Sigur Rós".to_owned()]
 	);
 }
+
+#[test]
+fn repairs_mislabeled_latin1_id3_text() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.mp3");
+
+	let mut content = make_id3_text_tag(b"TPE1", 0, "Björk".as_bytes());
+	content.extend(fs::read("test-data/formats/sample.mp3").unwrap());
+	fs::write(&path, content).unwrap();
+
+	assert_eq!(
+		read_metadata(&path).unwrap().artists,
+		vec!["Björk".to_owned()]
+	);
+	assert_eq!(repair_id3_text("Café"), "Café");
+	assert_eq!(repair_id3_text("\u{feff}Sigur Rós"), "Sigur Rós");
+}
+
+#[test]
+fn reads_popm_rating() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.add_frame(id3::Frame::with_content(
+		"POPM",
+		id3::Content::Popularimeter(id3::frame::Popularimeter {
+			user: "test@example.com".to_owned(),
+			rating: 196,
+			counter: 0,
+		}),
+	));
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+	assert_eq!(read_metadata(&path).unwrap().rating, Some(4));
+}
+
+#[test]
+fn reads_vorbis_rating() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.flac");
+	fs::copy("test-data/formats/sample.flac", &path).unwrap();
+
+	let mut tag = metaflac::Tag::read_from_path(&path).unwrap();
+	tag.set_vorbis("RATING", vec!["80"]);
+	tag.write_to_path(&path).unwrap();
+
+	assert_eq!(read_metadata(&path).unwrap().rating, Some(4));
+}
+
+#[test]
+fn reads_id3_work_and_movement() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.set_text("TSST", "Live in Vienna");
+	tag.set_text("GRP1", "Favorites");
+	tag.set_text("TIT1", "Symphony No. 9");
+	tag.set_text("MVNM", "Ode to Joy");
+	tag.set_text("MVIN", "4/4");
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.disc_subtitle.as_deref(), Some("Live in Vienna"));
+	assert_eq!(metadata.grouping.as_deref(), Some("Favorites"));
+	assert_eq!(metadata.work.as_deref(), Some("Symphony No. 9"));
+	assert_eq!(metadata.movement.as_deref(), Some("Ode to Joy"));
+	assert_eq!(metadata.movement_number, Some(4));
+}
+
+#[test]
+fn reads_mp4_work_and_movement() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.m4a");
+	fs::copy("test-data/formats/sample.m4a", &path).unwrap();
+
+	let mut tag = mp4ameta::Tag::read_from_path(&path).unwrap();
+	tag.set_grouping("Favorites");
+	tag.set_work("Symphony No. 9");
+	tag.set_movement("Ode to Joy");
+	tag.set_movement_index(4);
+	tag.write_to_path(&path).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.grouping.as_deref(), Some("Favorites"));
+	assert_eq!(metadata.work.as_deref(), Some("Symphony No. 9"));
+	assert_eq!(metadata.movement.as_deref(), Some("Ode to Joy"));
+	assert_eq!(metadata.movement_number, Some(4));
+}
+
+#[test]
+fn reads_vorbis_work_and_movement() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.flac");
+	fs::copy("test-data/formats/sample.flac", &path).unwrap();
+
+	let mut tag = metaflac::Tag::read_from_path(&path).unwrap();
+	tag.set_vorbis("DISCSUBTITLE", vec!["Live in Vienna"]);
+	tag.set_vorbis("GROUPING", vec!["Favorites"]);
+	tag.set_vorbis("WORK", vec!["Symphony No. 9"]);
+	tag.set_vorbis("MOVEMENTNAME", vec!["Ode to Joy"]);
+	tag.set_vorbis("MOVEMENT", vec!["4"]);
+	tag.write_to_path(&path).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.disc_subtitle.as_deref(), Some("Live in Vienna"));
+	assert_eq!(metadata.grouping.as_deref(), Some("Favorites"));
+	assert_eq!(metadata.work.as_deref(), Some("Symphony No. 9"));
+	assert_eq!(metadata.movement.as_deref(), Some("Ode to Joy"));
+	assert_eq!(metadata.movement_number, Some(4));
+}
+
+#[test]
+fn reads_id3_conductor_and_ensemble() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.set_text("TPE3", "Herbert von Karajan");
+	tag.add_frame(id3::frame::ExtendedText {
+		description: "ENSEMBLE".to_owned(),
+		value: "Berliner Philharmoniker".to_owned(),
+	});
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.conductors, vec!["Herbert von Karajan".to_owned()]);
+	assert_eq!(
+		metadata.ensembles,
+		vec!["Berliner Philharmoniker".to_owned()]
+	);
+}
+
+#[test]
+fn reads_vorbis_conductor_and_ensemble() {
+	let test_directory = crate::test::prepare_test_directory(crate::test_name!());
+	let path = test_directory.join("sample.flac");
+	fs::copy("test-data/formats/sample.flac", &path).unwrap();
+
+	let mut tag = metaflac::Tag::read_from_path(&path).unwrap();
+	tag.set_vorbis("CONDUCTOR", vec!["Herbert von Karajan"]);
+	tag.set_vorbis(
+		"ENSEMBLE",
+		vec!["Berliner Philharmoniker", "Wiener Singverein"],
+	);
+	tag.write_to_path(&path).unwrap();
+
+	let metadata = read_metadata(&path).unwrap();
+	assert_eq!(metadata.conductors, vec!["Herbert von Karajan".to_owned()]);
+	assert_eq!(
+		metadata.ensembles,
+		vec![
+			"Berliner Philharmoniker".to_owned(),
+			"Wiener Singverein".to_owned()
+		]
+	);
+}
+
+#[test]
+fn normalizes_ratings() {
+	assert_eq!(parse_popm_rating(0), None);
+	assert_eq!(parse_popm_rating(1), Some(1));
+	assert_eq!(parse_popm_rating(128), Some(3));
+	assert_eq!(parse_popm_rating(255), Some(5));
+	assert_eq!(parse_rating("0"), None);
+	assert_eq!(parse_rating("3"), Some(3));
+	assert_eq!(parse_rating("100"), Some(5));
+	assert_eq!(parse_rating("great"), None);
+}
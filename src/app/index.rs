@@ -1,8 +1,13 @@
 use std::{
+	collections::{HashMap, HashSet},
+	io::Write,
+	ops::RangeInclusive,
 	path::{Path, PathBuf},
 	sync::{Arc, RwLock},
+	time::SystemTime,
 };
 
+use enum_map::EnumMap;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use tokio::task::spawn_blocking;
@@ -16,14 +21,35 @@ mod query;
 mod search;
 mod storage;
 
-pub use browser::File;
-pub use collection::{Album, AlbumHeader, Artist, ArtistHeader, Genre, GenreHeader, Song};
+pub use browser::{File, Page};
+pub use collection::{
+	Album, AlbumHeader, Artist, ArtistHeader, Collaborator, Genre, GenreHeader, Song,
+	SortDirection, SortField, SortOrder,
+};
+pub use dictionary::Normalization;
+pub use query::{
+	parse_query, validate_query, BoolField, BoolOp, Expr, Literal, NumberField, NumberOp,
+	TextField, TextOp,
+};
+pub use search::{
+	BigramThreshold, MatchKind, PathIndexing, PredicateEstimate, SearchLimits, SearchOptions,
+	ValueOrdering,
+};
 use storage::{store_song, AlbumKey, ArtistKey, GenreKey, InternPath, SongKey};
 
+// Written at the start of the index file. Must change whenever the serialized layout of
+// `Index` does, so that indexes written by other versions are rebuilt instead of misread.
+const INDEX_VERSION: u32 = 24;
+
+// Every query works on a snapshot of the index taken when it starts, so it runs against a
+// single consistent version of the browser, collection and search data. Scans and rebuilds
+// never modify the index in place: they build a new one and publish it with `replace_index`.
+// Queries already in flight keep their snapshot, and the old index is freed once the last of
+// them completes.
 #[derive(Clone)]
 pub struct Manager {
 	index_file_path: PathBuf,
-	index: Arc<RwLock<Index>>, // Not a tokio RwLock as we want to do CPU-bound work with Index and lock this inside spawn_blocking()
+	index: Arc<RwLock<Arc<Index>>>, // Not a tokio RwLock as we want to do CPU-bound work with Index and lock this inside spawn_blocking()
 }
 
 impl Manager {
@@ -40,17 +66,49 @@ impl Manager {
 		match index_manager.try_restore_index().await {
 			Ok(true) => info!("Restored collection index from disk"),
 			Ok(false) => info!("No existing collection index to restore"),
+			Err(Error::IndexDeserializationError) => {
+				// Truncated file or incompatible version, the next scan will write a new one
+				error!("Collection index on disk could not be read and will be discarded");
+				if let Err(e) = tokio::fs::remove_file(&index_manager.index_file_path).await {
+					error!("Failed to discard collection index: {}", e);
+				}
+			}
 			Err(e) => error!("Failed to restore collection index: {}", e),
 		};
 
 		Ok(index_manager)
 	}
 
+	// Builds a manager around an in-memory index of the given songs, without using a data
+	// directory. Directories are derived from the virtual paths of the songs.
+	#[cfg(test)]
+	pub fn from_songs(songs: Vec<scanner::Song>) -> Self {
+		let directories = songs
+			.iter()
+			.flat_map(|s| s.virtual_path.ancestors().skip(1))
+			.filter(|p| !p.as_os_str().is_empty())
+			.map(Path::to_owned)
+			.collect::<HashSet<_>>();
+
+		let mut builder = Builder::default();
+		for virtual_path in directories {
+			builder.add_directory(scanner::Directory { virtual_path });
+		}
+		for song in songs {
+			builder.add_song(song);
+		}
+
+		Self {
+			index_file_path: PathBuf::new(),
+			index: Arc::new(RwLock::new(Arc::new(builder.build()))),
+		}
+	}
+
 	pub async fn is_index_empty(&self) -> bool {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				index.collection.num_songs() == 0
 			}
 		})
@@ -58,21 +116,79 @@ impl Manager {
 		.unwrap()
 	}
 
+	fn snapshot(&self) -> Arc<Index> {
+		self.index.read().unwrap().clone()
+	}
+
 	pub async fn replace_index(&self, new_index: Index) {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
 				let mut lock = index_manager.index.write().unwrap();
-				*lock = new_index;
+				let old_index = std::mem::replace(&mut *lock, Arc::new(new_index));
+				// Freeing a large index takes a while, readers need not wait for it
+				drop(lock);
+				drop(old_index);
 			}
 		})
 		.await
 		.unwrap()
 	}
 
+	// Re-indexes the current collection with different text settings, without reading any
+	// file. Queries keep being served from the current index until the new one is complete.
+	// If a scan publishes another index in the meantime, the rebuilt one is discarded.
+	pub async fn rebuild(
+		&self,
+		normalization: Normalization,
+		path_indexing: PathIndexing,
+		bigram_threshold: BigramThreshold,
+		word_boundary_fields: Vec<TextField>,
+	) -> Result<(), Error> {
+		let old_index = self.snapshot();
+		let new_index = spawn_blocking({
+			let old_index = old_index.clone();
+			move || {
+				let builder = Builder::new(normalization)
+					.with_path_indexing(path_indexing)
+					.with_bigram_threshold(bigram_threshold)
+					.with_word_boundary_fields(word_boundary_fields);
+				Arc::new(old_index.fill_builder(builder, None).build())
+			}
+		})
+		.await
+		.unwrap();
+
+		let is_replaced = spawn_blocking({
+			let index_manager = self.clone();
+			let new_index = new_index.clone();
+			move || {
+				let mut lock = index_manager.index.write().unwrap();
+				if !Arc::ptr_eq(&lock, &old_index) {
+					return false;
+				}
+				*lock = new_index;
+				drop(lock);
+				drop(old_index);
+				true
+			}
+		})
+		.await
+		.unwrap();
+
+		if !is_replaced {
+			info!("Discarded rebuilt collection index, as the collection changed in the meantime");
+			return Ok(());
+		}
+
+		info!("Rebuilt collection index");
+		self.persist_index(&new_index).await
+	}
+
 	pub async fn persist_index(&self, index: &Index) -> Result<(), Error> {
-		let serialized = match bitcode::serialize(index) {
-			Ok(s) => s,
+		let mut serialized = INDEX_VERSION.to_le_bytes().to_vec();
+		match bitcode::serialize(index) {
+			Ok(s) => serialized.extend(s),
 			Err(_) => return Err(Error::IndexSerializationError),
 		};
 		tokio::fs::write(&self.index_file_path, &serialized[..])
@@ -92,7 +208,11 @@ impl Manager {
 			.await
 			.map_err(|e| Error::Io(self.index_file_path.clone(), e))?;
 
-		let index = match bitcode::deserialize(&serialized[..]) {
+		let Some(serialized) = serialized.strip_prefix(&INDEX_VERSION.to_le_bytes()[..]) else {
+			return Err(Error::IndexDeserializationError);
+		};
+
+		let index = match bitcode::deserialize(serialized) {
 			Ok(i) => i,
 			Err(_) => return Err(Error::IndexDeserializationError),
 		};
@@ -102,12 +222,69 @@ impl Manager {
 		Ok(true)
 	}
 
-	pub async fn browse(&self, virtual_path: PathBuf) -> Result<Vec<browser::File>, Error> {
+	pub async fn stats(&self) -> Stats {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				Stats {
+					num_songs: index.collection.num_songs(),
+					num_albums: index.collection.num_albums(),
+					num_artists: index.collection.num_artists(),
+					num_genres: index.collection.num_genres(),
+					total_duration: index.collection.total_duration(),
+					total_file_size: index.collection.total_file_size(),
+					last_scan_time: index.build_time,
+				}
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	// Writes every song in the collection as a JSON array. Songs are serialized one
+	// at a time, so the export never holds more than one song in memory.
+	pub async fn export_json<W: Write + Send + 'static>(&self, mut writer: W) -> Result<W, Error> {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
-				index.browser.browse(&index.dictionary, virtual_path)
+				let index = index_manager.snapshot();
+				let mut write_songs = || -> std::io::Result<()> {
+					writer.write_all(b"[")?;
+					for (i, song) in index
+						.collection
+						.get_all_songs(&index.dictionary)
+						.enumerate()
+					{
+						if i > 0 {
+							writer.write_all(b",")?;
+						}
+						serde_json::to_writer(&mut writer, &song)?;
+					}
+					writer.write_all(b"]")?;
+					writer.flush()
+				};
+				write_songs().map_err(Error::IndexExportError)?;
+				Ok(writer)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn browse(
+		&self,
+		virtual_path: PathBuf,
+		offset: usize,
+		count: Option<usize>,
+	) -> Result<browser::Page, Error> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.browser
+					.browse_page(&index.dictionary, virtual_path, offset, count)
 			}
 		})
 		.await
@@ -118,7 +295,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				index.browser.flatten(&index.dictionary, virtual_path)
 			}
 		})
@@ -130,7 +307,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				index.collection.get_genres(&index.dictionary)
 			}
 		})
@@ -142,7 +319,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				let name = index
 					.dictionary
 					.get(&name)
@@ -162,7 +339,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				index.collection.get_albums(&index.dictionary)
 			}
 		})
@@ -170,12 +347,20 @@ impl Manager {
 		.unwrap()
 	}
 
-	pub async fn get_artists(&self) -> Vec<ArtistHeader> {
+	pub async fn get_artists(
+		&self,
+		various_artists_label: String,
+		group_by_album_artist: bool,
+	) -> Vec<ArtistHeader> {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
-				index.collection.get_artists(&index.dictionary)
+				let index = index_manager.snapshot();
+				index.collection.get_artists(
+					&index.dictionary,
+					&various_artists_label,
+					group_by_album_artist,
+				)
 			}
 		})
 		.await
@@ -186,7 +371,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				let name = index
 					.dictionary
 					.get(name)
@@ -202,26 +387,101 @@ impl Manager {
 		.unwrap()
 	}
 
-	pub async fn get_album(&self, artists: Vec<String>, name: String) -> Result<Album, Error> {
+	pub async fn get_collaborators(&self, name: String) -> Result<Vec<Collaborator>, Error> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				let name = index
+					.dictionary
+					.get(name)
+					.ok_or_else(|| Error::ArtistNotFound)?;
+				let artist_key = ArtistKey(name);
+				index
+					.collection
+					.get_collaborators(&index.dictionary, artist_key)
+					.ok_or_else(|| Error::ArtistNotFound)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	// Without a year, the earliest album matching `artists` and `name` is returned
+	pub async fn get_album(
+		&self,
+		artists: Vec<String>,
+		name: String,
+		year: Option<i64>,
+	) -> Result<Album, Error> {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				let name = index
 					.dictionary
 					.get(&name)
 					.ok_or_else(|| Error::AlbumNotFound)?;
-				let album_key = AlbumKey {
-					artists: artists
-						.into_iter()
-						.filter_map(|a| index.dictionary.get(a))
-						.map(ArtistKey)
-						.collect(),
-					name,
+				let artists = artists
+					.into_iter()
+					.filter_map(|a| index.dictionary.get(a))
+					.map(ArtistKey)
+					.collect();
+				let album = match year {
+					Some(year) => {
+						let album_key = AlbumKey {
+							artists,
+							name,
+							year: Some(year),
+						};
+						index.collection.get_album(&index.dictionary, album_key)
+					}
+					None => index
+						.collection
+						.find_album(&index.dictionary, &artists, name),
+				};
+				album.ok_or_else(|| Error::AlbumNotFound)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	// Without a year, albums similar to the earliest album matching `artists` and `name` are returned
+	pub async fn get_similar_albums(
+		&self,
+		artists: Vec<String>,
+		name: String,
+		year: Option<i64>,
+		count: usize,
+	) -> Result<Vec<AlbumHeader>, Error> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				let name = index
+					.dictionary
+					.get(&name)
+					.ok_or_else(|| Error::AlbumNotFound)?;
+				let artists = artists
+					.into_iter()
+					.filter_map(|a| index.dictionary.get(a))
+					.map(ArtistKey)
+					.collect();
+				let album_key = match year {
+					Some(year) => AlbumKey {
+						artists,
+						name,
+						year: Some(year),
+					},
+					None => index
+						.collection
+						.find_album_key(&artists, name)
+						.ok_or_else(|| Error::AlbumNotFound)?,
 				};
 				index
 					.collection
-					.get_album(&index.dictionary, album_key)
+					.get_similar_albums(&index.dictionary, &album_key, count)
 					.ok_or_else(|| Error::AlbumNotFound)
 			}
 		})
@@ -229,6 +489,20 @@ impl Manager {
 		.unwrap()
 	}
 
+	pub async fn get_albums_from_years(&self, years: RangeInclusive<i64>) -> Vec<AlbumHeader> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.collection
+					.get_albums_from_years(&index.dictionary, years)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
 	pub async fn get_random_albums(
 		&self,
 		seed: Option<u64>,
@@ -238,7 +512,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				Ok(index
 					.collection
 					.get_random_albums(&index.dictionary, seed, offset, count))
@@ -256,7 +530,7 @@ impl Manager {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				Ok(index
 					.collection
 					.get_recent_albums(&index.dictionary, offset, count))
@@ -269,18 +543,101 @@ impl Manager {
 	pub async fn get_songs(&self, virtual_paths: Vec<PathBuf>) -> Vec<Result<Song, Error>> {
 		spawn_blocking({
 			let index_manager = self.clone();
-			move || {
-				let index = index_manager.index.read().unwrap();
-				virtual_paths
-					.into_iter()
-					.map(|p| {
-						p.get(&index.dictionary)
-							.and_then(|virtual_path| {
-								let key = SongKey { virtual_path };
-								index.collection.get_song(&index.dictionary, key)
-							})
-							.ok_or_else(|| Error::SongNotFound)
+			move || index_manager.get_songs_blocking(virtual_paths)
+		})
+		.await
+		.unwrap()
+	}
+
+	pub fn get_songs_blocking(&self, virtual_paths: Vec<PathBuf>) -> Vec<Result<Song, Error>> {
+		let index = self.snapshot();
+		virtual_paths
+			.into_iter()
+			.map(|p| {
+				p.get(&index.dictionary)
+					.and_then(|virtual_path| {
+						let key = SongKey { virtual_path };
+						index.collection.get_song(&index.dictionary, key)
 					})
+					.ok_or_else(|| Error::SongNotFound)
+			})
+			.collect()
+	}
+
+	pub async fn find_songs_loosely(&self, virtual_paths: Vec<PathBuf>) -> Vec<Option<Song>> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.collection
+					.find_songs_loosely(&index.dictionary, &virtual_paths)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn search(
+		&self,
+		query: String,
+		options: SearchOptions,
+	) -> Result<Vec<(Song, MatchKind)>, Error> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.search
+					.find_songs(&index.collection, &index.dictionary, &query, options)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn explain_search(
+		&self,
+		query: String,
+		default_field: Option<TextField>,
+	) -> Result<Vec<PredicateEstimate>, Error> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.search
+					.explain(&index.dictionary, &query, default_field)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn get_album_songs(&self, album: String) -> Result<Vec<PathBuf>, Error> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.search
+					.get_album_songs(&index.collection, &index.dictionary, &album)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	// Returns the real path of every song, along with the virtual path of its artwork
+	pub async fn get_song_files(&self) -> Vec<(PathBuf, Option<PathBuf>)> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.collection
+					.get_all_songs(&index.dictionary)
+					.map(|s| (s.real_path, s.artwork))
 					.collect()
 			}
 		})
@@ -288,14 +645,85 @@ impl Manager {
 		.unwrap()
 	}
 
-	pub async fn search(&self, query: String) -> Result<Vec<Song>, Error> {
+	// Returns the songs of the current index along with when and how they were scanned,
+	// so a new scan can reuse the ones whose files did not change since.
+	pub async fn get_known_songs(&self) -> Option<KnownSongs> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				let scan_start_time = index.scan_start_time?;
+				let songs = index
+					.collection
+					.get_all_songs(&index.dictionary)
+					.map(|s| (s.real_path.clone(), scanner::Song::from(s)))
+					.collect();
+				Some(KnownSongs {
+					scan_start_time,
+					scan_fingerprint: index.scan_fingerprint,
+					songs,
+				})
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn get_builder_excluding(&self, virtual_path: PathBuf) -> Builder {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index.to_builder_excluding(&virtual_path)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn get_distinct_values(
+		&self,
+		field: TextField,
+		ordering: ValueOrdering,
+	) -> Vec<(String, usize)> {
 		spawn_blocking({
 			let index_manager = self.clone();
 			move || {
-				let index = index_manager.index.read().unwrap();
+				let index = index_manager.snapshot();
 				index
 					.search
-					.find_songs(&index.collection, &index.dictionary, &query)
+					.distinct_values(&index.dictionary, field, ordering)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn get_field_values(
+		&self,
+		field: TextField,
+		offset: usize,
+		count: usize,
+	) -> Vec<String> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index
+					.search
+					.get_values(&index.dictionary, field, offset, count)
+			}
+		})
+		.await
+		.unwrap()
+	}
+
+	pub async fn get_search_facets(&self, term: String) -> EnumMap<TextField, usize> {
+		spawn_blocking({
+			let index_manager = self.clone();
+			move || {
+				let index = index_manager.snapshot();
+				index.search.count_fuzzy_matches(&index.dictionary, &term)
 			}
 		})
 		.await
@@ -303,12 +731,76 @@ impl Manager {
 	}
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+	pub num_songs: usize,
+	pub num_albums: usize,
+	pub num_artists: usize,
+	pub num_genres: usize,
+	pub total_duration: i64,
+	pub total_file_size: i64,
+	pub last_scan_time: Option<SystemTime>,
+}
+
+// Songs from a previous scan, keyed by real path
+pub struct KnownSongs {
+	pub scan_start_time: SystemTime,
+	pub scan_fingerprint: u64,
+	pub songs: HashMap<PathBuf, scanner::Song>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Index {
 	pub dictionary: dictionary::Dictionary,
 	pub browser: browser::Browser,
 	pub collection: collection::Collection,
 	pub search: search::Search,
+	pub build_time: Option<SystemTime>,
+	// No song in this index was read before this time
+	pub scan_start_time: Option<SystemTime>,
+	// Identifies the scanner settings used to read the songs in this index
+	pub scan_fingerprint: u64,
+}
+
+impl Index {
+	// Returns a builder pre-populated with the content of this index, except for
+	// directories and songs within `virtual_path`. This allows rebuilding the index
+	// after changes to part of the collection without reading metadata again for the
+	// rest of it.
+	pub fn to_builder_excluding<P: AsRef<Path>>(&self, virtual_path: P) -> Builder {
+		let builder = Builder::new(self.dictionary.normalization())
+			.with_path_indexing(self.search.path_indexing())
+			.with_bigram_threshold(self.search.bigram_threshold())
+			.with_word_boundary_fields(self.search.word_boundary_fields().to_vec());
+		self.fill_builder(builder, Some(virtual_path.as_ref()))
+	}
+
+	// Adds every directory and song of this index to the builder, except those under
+	// `excluded`. The builder keeps the scan time and fingerprint of this index.
+	fn fill_builder(&self, builder: Builder, excluded: Option<&Path>) -> Builder {
+		let mut builder = builder.with_fingerprint(self.scan_fingerprint);
+		if let Some(scan_start_time) = self.scan_start_time {
+			builder.start_time = scan_start_time;
+		}
+
+		let is_excluded = |path: &Path| excluded.is_some_and(|e| path.starts_with(e));
+
+		for directory in self.browser.get_all_directories(&self.dictionary) {
+			if !is_excluded(&directory) {
+				builder.add_directory(scanner::Directory {
+					virtual_path: directory,
+				});
+			}
+		}
+
+		for song in self.collection.get_all_songs(&self.dictionary) {
+			if !is_excluded(&song.virtual_path) {
+				builder.add_song(song.into());
+			}
+		}
+
+		builder
+	}
 }
 
 impl Default for Index {
@@ -318,6 +810,9 @@ impl Default for Index {
 			browser: Default::default(),
 			collection: Default::default(),
 			search: Default::default(),
+			build_time: None,
+			scan_start_time: None,
+			scan_fingerprint: 0,
 		}
 	}
 }
@@ -328,18 +823,42 @@ pub struct Builder {
 	browser_builder: browser::Builder,
 	collection_builder: collection::Builder,
 	search_builder: search::Builder,
+	start_time: SystemTime,
+	fingerprint: u64,
 }
 
 impl Builder {
-	pub fn new() -> Self {
+	pub fn new(normalization: Normalization) -> Self {
 		Self {
-			dictionary_builder: dictionary::Builder::default(),
+			dictionary_builder: dictionary::Builder::new(normalization),
 			browser_builder: browser::Builder::default(),
 			collection_builder: collection::Builder::default(),
-			search_builder: search::Builder::default(),
+			search_builder: search::Builder::new(normalization),
+			start_time: SystemTime::now(),
+			fingerprint: 0,
 		}
 	}
 
+	pub fn with_path_indexing(mut self, path_indexing: PathIndexing) -> Self {
+		self.search_builder = self.search_builder.with_path_indexing(path_indexing);
+		self
+	}
+
+	pub fn with_bigram_threshold(mut self, bigram_threshold: BigramThreshold) -> Self {
+		self.search_builder = self.search_builder.with_bigram_threshold(bigram_threshold);
+		self
+	}
+
+	pub fn with_word_boundary_fields(mut self, fields: Vec<TextField>) -> Self {
+		self.search_builder = self.search_builder.with_word_boundary_fields(fields);
+		self
+	}
+
+	pub fn with_fingerprint(mut self, fingerprint: u64) -> Self {
+		self.fingerprint = fingerprint;
+		self
+	}
+
 	pub fn add_directory(&mut self, directory: scanner::Directory) {
 		self.browser_builder
 			.add_directory(&mut self.dictionary_builder, directory);
@@ -350,39 +869,213 @@ impl Builder {
 			self.browser_builder
 				.add_song(&mut self.dictionary_builder, &scanner_song);
 			self.collection_builder.add_song(&storage_song);
-			self.search_builder.add_song(&scanner_song, &storage_song);
+			self.search_builder
+				.add_song(&self.dictionary_builder, &scanner_song, &storage_song);
 		}
 	}
 
+	#[tracing::instrument(name = "build_index", level = "debug", skip_all)]
 	pub fn build(self) -> Index {
 		Index {
 			dictionary: self.dictionary_builder.build(),
 			browser: self.browser_builder.build(),
 			collection: self.collection_builder.build(),
 			search: self.search_builder.build(),
+			build_time: Some(SystemTime::now()),
+			scan_start_time: Some(self.start_time),
+			scan_fingerprint: self.fingerprint,
 		}
 	}
 }
 
 impl Default for Builder {
 	fn default() -> Self {
-		Self::new()
+		Self::new(Normalization::default())
 	}
 }
 
 #[cfg(test)]
 mod test {
+	use std::{
+		path::PathBuf,
+		sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	};
+
 	use crate::{
-		app::{index, test},
+		app::{index, scanner, test},
 		test_name,
 	};
 
+	#[tokio::test]
+	async fn can_build_index_in_memory() {
+		let index_manager = index::Manager::from_songs(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from_iter(["root", "Visions", "Black Diamond.mp3"]),
+				title: Some("Black Diamond".to_owned()),
+				artists: vec!["Stratovarius".to_owned()],
+				album: Some("Visions".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from_iter(["root", "Lifeforms", "Cerebral.mp3"]),
+				title: Some("Cerebral".to_owned()),
+				artists: vec!["FSOL".to_owned()],
+				album: Some("Lifeforms".to_owned()),
+				..Default::default()
+			},
+		]);
+
+		let page = index_manager
+			.browse(PathBuf::from("root"), 0, None)
+			.await
+			.unwrap();
+		assert_eq!(page.files.len(), 2);
+
+		let songs = index_manager
+			.search("diamond".to_owned(), index::SearchOptions::default())
+			.await
+			.unwrap();
+		assert_eq!(songs.len(), 1);
+		assert_eq!(songs[0].0.album, Some("Visions".to_owned()));
+
+		assert_eq!(index_manager.get_albums().await.len(), 2);
+	}
+
 	#[tokio::test]
 	async fn can_persist_index() {
 		let ctx = test::ContextBuilder::new(test_name!()).build().await;
 		assert_eq!(ctx.index_manager.try_restore_index().await.unwrap(), false);
-		let index = index::Builder::new().build();
+		let index = index::Builder::default().build();
 		ctx.index_manager.persist_index(&index).await.unwrap();
 		assert_eq!(ctx.index_manager.try_restore_index().await.unwrap(), true);
 	}
+
+	#[tokio::test]
+	async fn discards_corrupt_index() {
+		let directory = crate::test::prepare_test_directory(test_name!());
+		let index_file_path = directory.join("collection.index");
+		std::fs::write(&index_file_path, b"definitely not an index").unwrap();
+
+		let index_manager = index::Manager::new(&directory).await.unwrap();
+		assert!(index_manager.is_index_empty().await);
+		assert!(!index_file_path.exists());
+	}
+
+	#[tokio::test]
+	async fn discards_index_from_other_version() {
+		let directory = crate::test::prepare_test_directory(test_name!());
+		let index_file_path = directory.join("collection.index");
+		let mut serialized = (index::INDEX_VERSION + 1).to_le_bytes().to_vec();
+		serialized.extend(bitcode::serialize(&index::Builder::default().build()).unwrap());
+		std::fs::write(&index_file_path, serialized).unwrap();
+
+		let index_manager = index::Manager::new(&directory).await.unwrap();
+		assert!(index_manager.is_index_empty().await);
+		assert!(!index_file_path.exists());
+	}
+
+	#[tokio::test]
+	async fn can_rebuild_while_serving() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		let mut builder = index::Builder::default();
+		for i in 0..1000 {
+			builder.add_song(scanner::Song {
+				virtual_path: PathBuf::from(format!("lossless/seasons {i}.flac")),
+				title: Some(format!("Seasons {i}")),
+				..Default::default()
+			});
+		}
+		ctx.index_manager.replace_index(builder.build()).await;
+
+		let search = |query: &str| {
+			ctx.index_manager
+				.search(query.to_owned(), index::SearchOptions::default())
+		};
+		assert!(search("flac").await.unwrap().is_empty());
+
+		let rebuilt = AtomicBool::new(false);
+		let num_queries = AtomicUsize::new(0);
+		let serve = async {
+			while !rebuilt.load(Ordering::Relaxed) {
+				assert_eq!(search("seasons").await.unwrap().len(), 1000);
+				num_queries.fetch_add(1, Ordering::Relaxed);
+			}
+		};
+		let rebuild = async {
+			ctx.index_manager
+				.rebuild(
+					index::Normalization::default(),
+					index::PathIndexing::Full,
+					index::BigramThreshold::default(),
+					Vec::new(),
+				)
+				.await
+				.unwrap();
+			rebuilt.store(true, Ordering::Relaxed);
+		};
+		tokio::join!(serve, rebuild);
+
+		assert!(num_queries.load(Ordering::Relaxed) > 0);
+		assert_eq!(search("flac").await.unwrap().len(), 1000);
+		assert_eq!(search("seasons").await.unwrap().len(), 1000);
+		assert_eq!(ctx.index_manager.try_restore_index().await.unwrap(), true);
+		assert_eq!(search("flac").await.unwrap().len(), 1000);
+	}
+
+	#[tokio::test]
+	async fn can_compute_stats() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		assert_eq!(ctx.index_manager.stats().await, index::Stats::default());
+
+		let mut builder = index::Builder::default();
+		for (path, artist, album, genre) in [
+			("a.mp3", "Stratovarius", "Visions", "Power Metal"),
+			("b.mp3", "Stratovarius", "Visions", "Power Metal"),
+			("c.mp3", "Rhapsody", "Dawn of Victory", "Power Metal"),
+			("d.mp3", "FSOL", "Lifeforms", "Ambient"),
+		] {
+			builder.add_song(scanner::Song {
+				virtual_path: PathBuf::from(path),
+				artists: vec![artist.to_owned()],
+				album: Some(album.to_owned()),
+				genres: vec![genre.to_owned()],
+				duration: Some(100),
+				file_size: Some(2000),
+				..Default::default()
+			});
+		}
+		ctx.index_manager.replace_index(builder.build()).await;
+
+		let stats = ctx.index_manager.stats().await;
+		assert_eq!(stats.num_songs, 4);
+		assert_eq!(stats.num_albums, 3);
+		assert_eq!(stats.num_artists, 3);
+		assert_eq!(stats.num_genres, 2);
+		assert_eq!(stats.total_duration, 400);
+		assert_eq!(stats.total_file_size, 8000);
+		assert!(stats.last_scan_time.is_some());
+	}
+
+	#[tokio::test]
+	async fn can_export_json() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		let mut builder = index::Builder::default();
+		for (path, title) in [("a.mp3", "Kai"), ("b.mp3", "Fantasy")] {
+			builder.add_song(scanner::Song {
+				virtual_path: PathBuf::from(path),
+				title: Some(title.to_owned()),
+				..Default::default()
+			});
+		}
+		ctx.index_manager.replace_index(builder.build()).await;
+
+		let output = ctx.index_manager.export_json(Vec::new()).await.unwrap();
+		let songs: Vec<index::Song> = serde_json::from_slice(&output).unwrap();
+		let mut titles = songs
+			.into_iter()
+			.filter_map(|s| s.title)
+			.collect::<Vec<_>>();
+		titles.sort();
+		assert_eq!(titles, vec!["Fantasy", "Kai"]);
+	}
 }
@@ -24,6 +24,13 @@ pub enum File {
 	Song(PathBuf),
 }
 
+// A slice of the content of a directory, in the same order as a full listing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page {
+	pub files: Vec<File>,
+	pub num_files: usize, // Total number of entries in the directory
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Browser {
 	directories: HashMap<PathKey, BTreeSet<storage::File>>,
@@ -92,6 +99,32 @@ impl Browser {
 		Ok(files)
 	}
 
+	pub fn browse_page<P: AsRef<Path>>(
+		&self,
+		dictionary: &Dictionary,
+		virtual_path: P,
+		offset: usize,
+		count: Option<usize>,
+	) -> Result<Page, Error> {
+		let files = self.browse(dictionary, virtual_path)?;
+		let num_files = files.len();
+		let files = files
+			.into_iter()
+			.skip(offset)
+			.take(count.unwrap_or(usize::MAX))
+			.collect();
+		Ok(Page { files, num_files })
+	}
+
+	pub fn get_all_directories<'a>(
+		&'a self,
+		dictionary: &'a Dictionary,
+	) -> impl Iterator<Item = PathBuf> + 'a {
+		self.directories
+			.keys()
+			.map(|k| PathBuf::from(dictionary.resolve(&k.0)))
+	}
+
 	pub fn flatten<P: AsRef<Path>>(
 		&self,
 		dictionary: &Dictionary,
@@ -316,6 +349,42 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn can_browse_by_page() {
+		let directory = PathBuf::from_iter(["Music", "Field Recordings"]);
+		let (browser, strings) = setup_test(
+			(0..250)
+				.map(|n| directory.join(format!("Recording {n:03}.mp3")))
+				.collect(),
+		);
+
+		let all_files = browser.browse(&strings, &directory).unwrap();
+		let mut paged_files = vec![];
+		for offset in (0..all_files.len()).step_by(100) {
+			let page = browser
+				.browse_page(&strings, &directory, offset, Some(100))
+				.unwrap();
+			assert_eq!(page.num_files, 250);
+			paged_files.extend(page.files);
+		}
+		assert_eq!(paged_files, all_files);
+		assert_eq!(
+			paged_files[100],
+			File::Song(directory.join("Recording 100.mp3"))
+		);
+
+		let page = browser
+			.browse_page(&strings, &directory, 240, Some(100))
+			.unwrap();
+		assert_eq!(page.files.len(), 10);
+
+		let page = browser
+			.browse_page(&strings, &directory, 300, None)
+			.unwrap();
+		assert!(page.files.is_empty());
+		assert_eq!(page.num_files, 250);
+	}
+
 	#[test]
 	fn can_flatten_root() {
 		let song_a = PathBuf::from_iter(["Music", "Electronic", "Papua New Guinea.mp3"]);
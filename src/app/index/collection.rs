@@ -2,20 +2,52 @@ use std::{
 	borrow::BorrowMut,
 	cmp::Ordering,
 	collections::{HashMap, HashSet},
+	ops::RangeInclusive,
 	path::PathBuf,
 };
 
+use lasso2::Spur;
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use rayon::slice::ParallelSliceMut;
 use serde::{Deserialize, Serialize};
 use tinyvec::TinyVec;
 use unicase::UniCase;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
 use crate::app::index::dictionary::Dictionary;
 use crate::app::index::storage::{self, AlbumKey, ArtistKey, GenreKey, SongKey};
 
 use super::{dictionary, storage::fetch_song};
 
+// Relative importance of what similar albums have in common
+const SIMILARITY_GENRE_WEIGHT: f32 = 1.0;
+const SIMILARITY_ARTIST_WEIGHT: f32 = 2.0;
+const SIMILARITY_ERA_WEIGHT: f32 = 1.0;
+// Albums released this many years apart or more are not considered from the same era
+const SIMILARITY_ERA_SPAN: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortField {
+	Title,
+	Artist,
+	Album,
+	Year,
+	Duration,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortDirection {
+	#[default]
+	Ascending,
+	Descending,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortOrder {
+	pub field: SortField,
+	pub direction: SortDirection,
+}
+
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct GenreHeader {
 	pub name: String,
@@ -41,6 +73,12 @@ pub struct ArtistHeader {
 	pub num_songs: u32,
 }
 
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Collaborator {
+	pub header: ArtistHeader,
+	pub num_shared_songs: u32,
+}
+
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct Artist {
 	pub header: ArtistHeader,
@@ -72,13 +110,33 @@ pub struct Song {
 	pub artists: Vec<String>,
 	pub album_artists: Vec<String>,
 	pub year: Option<i64>,
+	pub year_inferred: bool,
+	pub original_year: Option<i64>,
+	pub rating: Option<i64>,
 	pub album: Option<String>,
+	pub disc_subtitle: Option<String>,
+	pub grouping: Option<String>,
+	pub work: Option<String>,
+	pub movement: Option<String>,
+	pub movement_number: Option<i64>,
 	pub artwork: Option<PathBuf>,
 	pub duration: Option<i64>,
+	pub channels: Option<i64>,
+	pub bitrate: Option<i64>,
+	pub sample_rate: Option<i64>,
+	pub bit_depth: Option<i64>,
+	pub codec: Option<String>,
+	pub lossless: Option<bool>,
+	pub encoder_delay: Option<i64>,
+	pub encoder_padding: Option<i64>,
+	pub file_size: Option<i64>,
 	pub lyricists: Vec<String>,
 	pub composers: Vec<String>,
 	pub genres: Vec<String>,
 	pub labels: Vec<String>,
+	pub conductors: Vec<String>,
+	pub ensembles: Vec<String>,
+	pub custom_fields: Vec<(String, String)>,
 	pub date_added: i64,
 }
 
@@ -89,6 +147,8 @@ pub struct Collection {
 	genres: HashMap<GenreKey, storage::Genre>,
 	songs: HashMap<SongKey, storage::Song>,
 	recent_albums: Vec<AlbumKey>,
+	total_duration: i64,
+	total_file_size: i64,
 }
 
 impl Collection {
@@ -103,12 +163,45 @@ impl Collection {
 		albums
 	}
 
-	pub fn get_artists(&self, dictionary: &Dictionary) -> Vec<ArtistHeader> {
-		let exceptions = [dictionary.get("Various Artists"), dictionary.get("VA")];
+	pub fn get_albums_from_years(
+		&self,
+		dictionary: &Dictionary,
+		years: RangeInclusive<i64>,
+	) -> Vec<AlbumHeader> {
+		let mut albums = self
+			.albums
+			.values()
+			.filter(|a| a.year.is_some_and(|y| years.contains(&y)))
+			.map(|a| make_album_header(a, dictionary))
+			.collect::<Vec<_>>();
+		let collator = dictionary::make_collator();
+		albums.sort_by(|a, b| {
+			a.year
+				.cmp(&b.year)
+				.then_with(|| collator.compare(&a.name, &b.name))
+		});
+		albums
+	}
+
+	// Placeholder artists given to compilations are not listed. When grouping by album
+	// artist, artists who only appear on albums credited to someone else are not listed
+	// either, so that compilations do not add an entry for each of their track artists.
+	pub fn get_artists(
+		&self,
+		dictionary: &Dictionary,
+		various_artists_label: &str,
+		group_by_album_artist: bool,
+	) -> Vec<ArtistHeader> {
+		let exceptions = [
+			dictionary.get(various_artists_label),
+			dictionary.get("Various Artists"),
+			dictionary.get("VA"),
+		];
 		let mut artists = self
 			.artists
 			.values()
 			.filter(|a| !exceptions.contains(&Some(a.name)))
+			.filter(|a| !group_by_album_artist || !a.is_only_additional_performer())
 			.map(|a| make_artist_header(a, dictionary))
 			.collect::<Vec<_>>();
 		let collator = dictionary::make_collator();
@@ -136,6 +229,52 @@ impl Collection {
 		})
 	}
 
+	// Artists credited on the same songs as the given artist, most frequent first
+	pub fn get_collaborators(
+		&self,
+		dictionary: &Dictionary,
+		artist_key: ArtistKey,
+	) -> Option<Vec<Collaborator>> {
+		let artist = self.artists.get(&artist_key)?;
+		let mut collaborators = artist
+			.collaborators
+			.iter()
+			.filter_map(|(key, num_shared_songs)| {
+				Some(Collaborator {
+					header: make_artist_header(self.artists.get(key)?, dictionary),
+					num_shared_songs: *num_shared_songs,
+				})
+			})
+			.collect::<Vec<_>>();
+		let collator = dictionary::make_collator();
+		collaborators.sort_by(|a, b| {
+			b.num_shared_songs
+				.cmp(&a.num_shared_songs)
+				.then_with(|| collator.compare(&a.header.name, &b.header.name))
+		});
+		Some(collaborators)
+	}
+
+	// Looks up an album by artists and name alone. When several albums match (eg. releases
+	// from different years), the earliest one is returned.
+	pub fn find_album(
+		&self,
+		dictionary: &Dictionary,
+		artists: &[ArtistKey],
+		name: Spur,
+	) -> Option<Album> {
+		let album_key = self.find_album_key(artists, name)?;
+		self.get_album(dictionary, album_key)
+	}
+
+	pub fn find_album_key(&self, artists: &[ArtistKey], name: Spur) -> Option<AlbumKey> {
+		self.albums
+			.keys()
+			.filter(|k| k.name == name && k.artists.as_slice() == artists)
+			.min_by_key(|k| k.year)
+			.cloned()
+	}
+
 	pub fn get_album(&self, dictionary: &Dictionary, album_key: AlbumKey) -> Option<Album> {
 		self.albums.get(&album_key).map(|a| {
 			let mut songs = a
@@ -160,6 +299,74 @@ impl Collection {
 		})
 	}
 
+	// Albums sharing genres or artists with the given album, most similar first. Albums
+	// released around the same time rank higher.
+	pub fn get_similar_albums(
+		&self,
+		dictionary: &Dictionary,
+		album_key: &AlbumKey,
+		count: usize,
+	) -> Option<Vec<AlbumHeader>> {
+		let album = self.albums.get(album_key)?;
+		let genres = self.get_album_genres(album);
+
+		let mut candidates = HashSet::new();
+		for genre in genres.iter().filter_map(|g| self.genres.get(&GenreKey(*g))) {
+			candidates.extend(genre.albums.iter());
+		}
+		for artist in album_key.artists.iter().filter_map(|a| self.artists.get(a)) {
+			candidates.extend(artist.all_albums.iter());
+		}
+		candidates.remove(album_key);
+
+		let mut scored_albums = candidates
+			.into_iter()
+			.filter_map(|k| {
+				let other = self.albums.get(k)?;
+				let shared_genres = self.get_album_genres(other).intersection(&genres).count();
+				let shared_artists = k
+					.artists
+					.iter()
+					.filter(|a| album_key.artists.contains(a))
+					.count();
+				let era = match (album.year, other.year) {
+					(Some(a), Some(b)) => {
+						(1.0 - (a - b).abs() as f32 / SIMILARITY_ERA_SPAN).max(0.0)
+					}
+					_ => 0.0,
+				};
+				let score = SIMILARITY_GENRE_WEIGHT * shared_genres as f32
+					+ SIMILARITY_ARTIST_WEIGHT * shared_artists as f32
+					+ SIMILARITY_ERA_WEIGHT * era;
+				Some((score, make_album_header(other, dictionary)))
+			})
+			.collect::<Vec<_>>();
+
+		let collator = dictionary::make_collator();
+		scored_albums.sort_by(|(score_a, a), (score_b, b)| {
+			score_b
+				.total_cmp(score_a)
+				.then_with(|| collator.compare(&a.name, &b.name))
+		});
+
+		Some(
+			scored_albums
+				.into_iter()
+				.take(count)
+				.map(|(_, header)| header)
+				.collect(),
+		)
+	}
+
+	fn get_album_genres(&self, album: &storage::Album) -> HashSet<Spur> {
+		album
+			.songs
+			.iter()
+			.filter_map(|k| self.songs.get(k))
+			.flat_map(|s| s.genres.iter().copied())
+			.collect()
+	}
+
 	pub fn get_random_albums(
 		&self,
 		dictionary: &Dictionary,
@@ -266,14 +473,117 @@ impl Collection {
 		self.songs.len()
 	}
 
+	pub fn num_albums(&self) -> usize {
+		self.albums.len()
+	}
+
+	pub fn num_artists(&self) -> usize {
+		self.artists.len()
+	}
+
+	pub fn num_genres(&self) -> usize {
+		self.genres.len()
+	}
+
+	pub fn total_duration(&self) -> i64 {
+		self.total_duration
+	}
+
+	pub fn total_file_size(&self) -> i64 {
+		self.total_file_size
+	}
+
+	pub fn get_all_songs<'a>(
+		&'a self,
+		dictionary: &'a Dictionary,
+	) -> impl Iterator<Item = Song> + 'a {
+		self.songs.values().map(|s| fetch_song(dictionary, s))
+	}
+
 	pub fn get_song(&self, dictionary: &Dictionary, song_key: SongKey) -> Option<Song> {
 		self.songs.get(&song_key).map(|s| fetch_song(dictionary, s))
 	}
 
+	// Finds songs whose virtual paths only differ from `virtual_paths` by case or diacritics,
+	// as a fallback when exact lookups fail. When several songs match the same path, the one
+	// whose path sorts first (byte-wise) is returned.
+	pub fn find_songs_loosely(
+		&self,
+		dictionary: &Dictionary,
+		virtual_paths: &[PathBuf],
+	) -> Vec<Option<Song>> {
+		if virtual_paths.is_empty() {
+			return Vec::new();
+		}
+
+		let mut songs_by_key = HashMap::<String, &storage::Song>::new();
+		for song in self.songs.values() {
+			let path = dictionary.resolve(&song.virtual_path.0);
+			songs_by_key
+				.entry(loose_key(path))
+				.and_modify(|s| {
+					if path < dictionary.resolve(&s.virtual_path.0) {
+						*s = song;
+					}
+				})
+				.or_insert(song);
+		}
+
+		virtual_paths
+			.iter()
+			.map(|p| {
+				songs_by_key
+					.get(&loose_key(&p.to_string_lossy()))
+					.map(|s| fetch_song(dictionary, s))
+			})
+			.collect()
+	}
+
 	pub fn sort_songs(&self, songs: &mut [SongKey], dictionary: &Dictionary) {
 		songs.par_sort_unstable_by(|a, b| self.compare_songs(*a, *b, dictionary));
 	}
 
+	// Stable, so that songs with equal values keep their relative order. Sort tags (eg. `TSOP`)
+	// are not read by the scanner, so values are compared as displayed.
+	pub fn sort_songs_by(&self, songs: &mut [SongKey], dictionary: &Dictionary, order: SortOrder) {
+		songs.par_sort_by(|a, b| {
+			let (a, b) = (self.songs.get(a), self.songs.get(b));
+			let direction = order.direction;
+			match order.field {
+				SortField::Title => compare_values(
+					a.and_then(|s| s.title),
+					b.and_then(|s| s.title),
+					direction,
+					|a, b| dictionary.cmp(a, b),
+				),
+				SortField::Artist => compare_values(
+					a.and_then(|s| s.artists.first().copied()),
+					b.and_then(|s| s.artists.first().copied()),
+					direction,
+					|a, b| dictionary.cmp(&a.0, &b.0),
+				),
+				SortField::Album => compare_values(
+					a.and_then(|s| s.album),
+					b.and_then(|s| s.album),
+					direction,
+					|a, b| dictionary.cmp(a, b),
+				),
+				SortField::Year => compare_values(
+					a.and_then(|s| s.year),
+					b.and_then(|s| s.year),
+					direction,
+					Ord::cmp,
+				),
+				SortField::Duration => compare_values(
+					a.and_then(|s| s.duration),
+					b.and_then(|s| s.duration),
+					direction,
+					Ord::cmp,
+				),
+			}
+		});
+	}
+
 	fn compare_songs(&self, a: SongKey, b: SongKey, dictionary: &Dictionary) -> Ordering {
 		let (a, b) = match (self.songs.get(&a), self.songs.get(&b)) {
 			(None, None) => return Ordering::Equal,
@@ -322,7 +632,10 @@ impl Collection {
 		let a_key = (a.disc_number, a.track_number);
 		let b_key = (b.disc_number, b.track_number);
 
-		a_key.cmp(&b_key)
+		// Songs with identical metadata are ordered by path, so that results are stable
+		a_key
+			.cmp(&b_key)
+			.then_with(|| dictionary.cmp(&a.virtual_path.0, &b.virtual_path.0))
 	}
 }
 
@@ -366,6 +679,32 @@ fn make_genre_header(genre: &storage::Genre, dictionary: &Dictionary) -> GenreHe
 	}
 }
 
+// Folds case and strips diacritics, so that paths which only differ by those share a key
+fn loose_key(path: &str) -> String {
+	path.nfd()
+		.filter(|c| !is_combining_mark(*c))
+		.flat_map(char::to_lowercase)
+		.collect()
+}
+
+// Missing values are listed last, regardless of direction
+fn compare_values<T>(
+	a: Option<T>,
+	b: Option<T>,
+	direction: SortDirection,
+	cmp: impl Fn(&T, &T) -> Ordering,
+) -> Ordering {
+	match (a, b) {
+		(None, None) => Ordering::Equal,
+		(None, Some(_)) => Ordering::Greater,
+		(Some(_), None) => Ordering::Less,
+		(Some(a), Some(b)) => match direction {
+			SortDirection::Ascending => cmp(&a, &b),
+			SortDirection::Descending => cmp(&b, &a),
+		},
+	}
+}
+
 #[derive(Clone, Default)]
 pub struct Builder {
 	artists: HashMap<ArtistKey, storage::Artist>,
@@ -388,6 +727,7 @@ impl Builder {
 		);
 	}
 
+	#[tracing::instrument(name = "build_collection", level = "debug", skip_all, fields(songs = self.songs.len()))]
 	pub fn build(self) -> Collection {
 		let mut recent_albums = self.albums.keys().cloned().collect::<Vec<_>>();
 		recent_albums.sort_by_key(|a| {
@@ -397,12 +737,17 @@ impl Builder {
 				.unwrap_or_default()
 		});
 
+		let total_duration = self.songs.values().filter_map(|s| s.duration).sum();
+		let total_file_size = self.songs.values().filter_map(|s| s.file_size).sum();
+
 		Collection {
 			artists: self.artists,
 			albums: self.albums,
 			genres: self.genres,
 			songs: self.songs,
 			recent_albums,
+			total_duration,
+			total_file_size,
 		}
 	}
 
@@ -449,6 +794,15 @@ impl Builder {
 			}
 		}
 
+		for artist_key in &song.artists {
+			for other_artist_key in &song.artists {
+				if other_artist_key != artist_key {
+					let artist = self.get_or_create_artist(*artist_key);
+					*artist.collaborators.entry(*other_artist_key).or_default() += 1;
+				}
+			}
+		}
+
 		for artist_key in all_artists {
 			let artist = self.get_or_create_artist(artist_key);
 			artist.num_songs += 1;
@@ -477,6 +831,7 @@ impl Builder {
 				albums_as_lyricist: HashSet::new(),
 				num_songs_by_genre: HashMap::new(),
 				num_songs: 0,
+				collaborators: HashMap::new(),
 			})
 			.borrow_mut()
 	}
@@ -591,6 +946,41 @@ mod test {
 		(browser, dictionary)
 	}
 
+	#[test]
+	fn can_find_songs_loosely() {
+		let (collection, dictionary) = setup_test(
+			["kai.mp3", "KAI.mp3", "Kai.mp3", "Fantasy.mp3"]
+				.into_iter()
+				.map(|path| scanner::Song {
+					virtual_path: PathBuf::from(path),
+					..Default::default()
+				})
+				.collect(),
+		);
+
+		let songs = collection.find_songs_loosely(
+			&dictionary,
+			&[
+				PathBuf::from("kái.mp3"),
+				PathBuf::from("FANTASY.mp3"),
+				PathBuf::from("Kai.flac"),
+			],
+		);
+
+		let paths = songs
+			.into_iter()
+			.map(|s| s.map(|s| s.virtual_path))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			paths,
+			vec![
+				Some(PathBuf::from("KAI.mp3")),
+				Some(PathBuf::from("Fantasy.mp3")),
+				None,
+			]
+		);
+	}
+
 	#[test]
 	fn can_list_artists() {
 		let (collection, strings) = setup_test(Vec::from([
@@ -609,7 +999,7 @@ mod test {
 		]));
 
 		let artists = collection
-			.get_artists(&strings)
+			.get_artists(&strings, "Various Artists", false)
 			.into_iter()
 			.map(|a| a.name)
 			.collect::<Vec<_>>();
@@ -641,7 +1031,7 @@ mod test {
 		]));
 
 		let artists = collection
-			.get_artists(&strings)
+			.get_artists(&strings, "Various Artists", false)
 			.into_iter()
 			.map(|a| a.name)
 			.collect::<Vec<_>>();
@@ -673,7 +1063,7 @@ mod test {
 		]));
 
 		let artists = collection
-			.get_artists(&strings)
+			.get_artists(&strings, "Various Artists", false)
 			.into_iter()
 			.map(|a| a.name)
 			.collect::<Vec<_>>();
@@ -701,7 +1091,7 @@ mod test {
 		]));
 
 		let artists = collection
-			.get_artists(&strings)
+			.get_artists(&strings, "Various Artists", false)
 			.into_iter()
 			.map(|a| a.name)
 			.collect::<Vec<_>>();
@@ -748,6 +1138,90 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn can_get_albums_from_year() {
+		let (collection, strings) = setup_test(Vec::from([
+			scanner::Song {
+				album: Some("ISDN".to_owned()),
+				artists: vec!["FSOL".to_owned()],
+				year: Some(1994),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Lifeforms".to_owned()),
+				artists: vec!["FSOL".to_owned()],
+				year: Some(1994),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Elysium".to_owned()),
+				artists: vec!["Stratovarius".to_owned()],
+				year: Some(2011),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Undated".to_owned()),
+				artists: vec!["Stratovarius".to_owned()],
+				..Default::default()
+			},
+		]));
+
+		let albums = collection.get_albums_from_years(&strings, 1994..=1994);
+		assert_eq!(
+			albums.into_iter().map(|a| a.name).collect::<Vec<_>>(),
+			vec!["ISDN".to_owned(), "Lifeforms".to_owned()]
+		);
+
+		let albums = collection.get_albums_from_years(&strings, 2000..=2009);
+		assert!(albums.is_empty());
+	}
+
+	#[test]
+	fn can_get_albums_from_decade() {
+		let (collection, strings) = setup_test(Vec::from([
+			scanner::Song {
+				album: Some("Lifeforms".to_owned()),
+				artists: vec!["FSOL".to_owned()],
+				year: Some(1994),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Accelerator".to_owned()),
+				artists: vec!["FSOL".to_owned()],
+				year: Some(1992),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Visions".to_owned()),
+				artists: vec!["Stratovarius".to_owned()],
+				year: Some(1997),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Elysium".to_owned()),
+				artists: vec!["Stratovarius".to_owned()],
+				year: Some(2011),
+				..Default::default()
+			},
+			scanner::Song {
+				album: Some("Episode".to_owned()),
+				artists: vec!["Stratovarius".to_owned()],
+				year: Some(1989),
+				..Default::default()
+			},
+		]));
+
+		let albums = collection.get_albums_from_years(&strings, 1990..=1999);
+		assert_eq!(
+			albums.into_iter().map(|a| a.name).collect::<Vec<_>>(),
+			vec![
+				"Accelerator".to_owned(),
+				"Lifeforms".to_owned(),
+				"Visions".to_owned()
+			]
+		);
+	}
+
 	#[test]
 	fn can_get_random_albums() {
 		let (collection, strings) = setup_test(Vec::from([
@@ -882,7 +1356,7 @@ mod test {
 				..Default::default()
 			}]));
 
-			let artists = collection.get_artists(&strings);
+			let artists = collection.get_artists(&strings, "Various Artists", false);
 
 			if test.expect_listed {
 				assert!(artists.iter().any(|a| a.name == UniCase::new(artist_name)));
@@ -892,6 +1366,90 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn artists_can_be_grouped_by_album_artist() {
+		let (collection, strings) = setup_test(Vec::from([
+			scanner::Song {
+				virtual_path: PathBuf::from("Tabula Rasa.mp3"),
+				album: Some("Metal Opera".to_owned()),
+				artists: vec!["Michael Kiske".to_owned()],
+				album_artists: vec!["Avantasia".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("Breaking Away.mp3"),
+				album: Some("Metal Opera".to_owned()),
+				artists: vec!["Kai Hansen".to_owned()],
+				album_artists: vec!["Avantasia".to_owned()],
+				..Default::default()
+			},
+		]));
+
+		let artists = collection
+			.get_artists(&strings, "Various Artists", true)
+			.into_iter()
+			.map(|a| a.name)
+			.collect::<Vec<_>>();
+		assert_eq!(artists, vec![UniCase::new("Avantasia".to_owned())]);
+
+		let albums = collection.get_albums(&strings);
+		assert_eq!(albums.len(), 1);
+		assert_eq!(albums[0].artists, vec!["Avantasia".to_owned()]);
+
+		let artist = collection
+			.get_artist(&strings, ArtistKey(strings.get("Avantasia").unwrap()))
+			.unwrap();
+		assert_eq!(artist.albums.len(), 1);
+		assert_eq!(artist.albums[0].header.name, "Metal Opera");
+	}
+
+	#[test]
+	fn can_get_collaborators() {
+		let (collection, strings) = setup_test(Vec::from([
+			scanner::Song {
+				virtual_path: PathBuf::from("Shine.mp3"),
+				artists: vec!["Tarja".to_owned(), "Sharon den Adel".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("Phantom.mp3"),
+				artists: vec!["Tarja".to_owned(), "Marko Hietala".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("Wish I Had.mp3"),
+				artists: vec!["Tarja".to_owned(), "Marko Hietala".to_owned()],
+				..Default::default()
+			},
+		]));
+
+		let collaborators = |name: &str| {
+			let artist_key = ArtistKey(strings.get(name).unwrap());
+			collection
+				.get_collaborators(&strings, artist_key)
+				.unwrap()
+				.into_iter()
+				.map(|c| (c.header.name.to_string(), c.num_shared_songs))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			collaborators("Tarja"),
+			vec![
+				("Marko Hietala".to_owned(), 2),
+				("Sharon den Adel".to_owned(), 1),
+			]
+		);
+		assert_eq!(
+			collaborators("Sharon den Adel"),
+			vec![("Tarja".to_owned(), 1)]
+		);
+		assert_eq!(
+			collaborators("Marko Hietala"),
+			vec![("Tarja".to_owned(), 2)]
+		);
+	}
+
 	#[test]
 	fn albums_are_sorted_by_year() {
 		let (collection, strings) = setup_test(Vec::from([
@@ -989,6 +1547,7 @@ mod test {
 			AlbumKey {
 				artists: tiny_vec!([ArtistKey; 4] => artist),
 				name: strings.get("Lifeforms").unwrap(),
+				year: None,
 			},
 		);
 
@@ -1010,6 +1569,110 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn albums_are_distinguished_by_artists_and_year() {
+		let (collection, strings) = setup_test(Vec::from([
+			scanner::Song {
+				virtual_path: PathBuf::from("a.mp3"),
+				artists: vec!["Queen".to_owned()],
+				album: Some("Greatest Hits".to_owned()),
+				year: Some(1981),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("b.mp3"),
+				artists: vec!["ABBA".to_owned()],
+				album: Some("Greatest Hits".to_owned()),
+				year: Some(1975),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("c.mp3"),
+				artists: vec!["ABBA".to_owned()],
+				album: Some("Greatest Hits".to_owned()),
+				year: Some(1975),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("d.mp3"),
+				artists: vec!["ABBA".to_owned()],
+				album: Some("Greatest Hits".to_owned()),
+				year: Some(1992),
+				..Default::default()
+			},
+		]));
+
+		assert_eq!(collection.num_albums(), 3);
+
+		let abba = ArtistKey(strings.get("ABBA").unwrap());
+		let name = strings.get("Greatest Hits").unwrap();
+		let album = collection
+			.get_album(
+				&strings,
+				AlbumKey {
+					artists: tiny_vec!([ArtistKey; 4] => abba),
+					name,
+					year: Some(1975),
+				},
+			)
+			.unwrap();
+		assert_eq!(album.songs.len(), 2);
+
+		let album = collection.find_album(&strings, &[abba], name).unwrap();
+		assert_eq!(album.header.year, Some(1975));
+	}
+
+	#[test]
+	fn can_get_similar_albums() {
+		let (collection, strings) = setup_test(Vec::from([
+			scanner::Song {
+				virtual_path: PathBuf::from("Painkiller.mp3"),
+				album_artists: vec!["Judas Priest".to_owned()],
+				album: Some("Painkiller".to_owned()),
+				genres: vec!["Metal".to_owned()],
+				year: Some(1990),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("Jugulator.mp3"),
+				album_artists: vec!["Judas Priest".to_owned()],
+				album: Some("Jugulator".to_owned()),
+				genres: vec!["Metal".to_owned()],
+				year: Some(1997),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("Rust in Peace.mp3"),
+				album_artists: vec!["Megadeth".to_owned()],
+				album: Some("Rust in Peace".to_owned()),
+				genres: vec!["Metal".to_owned()],
+				year: Some(1990),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("Kind of Blue.mp3"),
+				album_artists: vec!["Miles Davis".to_owned()],
+				album: Some("Kind of Blue".to_owned()),
+				genres: vec!["Jazz".to_owned()],
+				year: Some(1959),
+				..Default::default()
+			},
+		]));
+
+		let album_key = AlbumKey {
+			artists: tiny_vec!([ArtistKey; 4] => ArtistKey(strings.get("Judas Priest").unwrap())),
+			name: strings.get("Painkiller").unwrap(),
+			year: Some(1990),
+		};
+		let names = collection
+			.get_similar_albums(&strings, &album_key, 10)
+			.unwrap()
+			.into_iter()
+			.map(|a| a.name)
+			.collect::<Vec<_>>();
+		assert_eq!(names, vec!["Jugulator", "Rust in Peace"]);
+	}
+
 	#[test]
 	fn can_get_a_song() {
 		let song_path = PathBuf::from_iter(["FSOL", "ISDN", "Kai.mp3"]);
@@ -5,11 +5,82 @@ use lasso2::{Rodeo, RodeoReader, Spur};
 use rayon::slice::ParallelSliceMut;
 use serde::{Deserialize, Serialize};
 
-pub fn sanitize(s: &str) -> String {
-	// TODO merge inconsistent diacritic usage
-	let mut cleaned = s.to_owned();
-	cleaned.retain(|c| !matches!(c, ' ' | '_' | '-' | '\''));
-	cleaned.to_lowercase()
+// Apostrophes and other punctuation which are commonly used inconsistently across tags,
+// eg. "Don’t" vs "Dont" or "R.E.M." vs "REM".
+const PUNCTUATION: &[char] = &[
+	'\'', '‘', '’', '`', // Apostrophes
+	'&', '.', ',', // Other punctuation
+];
+
+// Hyphens and dashes join words which tags just as often separate with a space, eg.
+// "Post-Rock" vs "Post Rock". They are read as whitespace so that both spellings agree
+// whether or not whitespace is kept, without merging the words they separate.
+const HYPHENS: &[char] = &['-', '‐', '‑', '‒', '–', '—'];
+
+// Describes how strings are simplified before being compared against each other.
+// The same transformation is applied to indexed values and search terms. It is
+// stored alongside the index so that a reloaded index keeps matching queries
+// the same way it was built.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Normalization {
+	pub strip_punctuation: bool, // Ignore characters listed in `PUNCTUATION`, read `HYPHENS` as whitespace
+	pub strip_whitespace: bool, // Ignore whitespace entirely, instead of collapsing it to single spaces
+	pub segment_by_script: bool, // Keep fuzzy search bigrams from straddling CJK and other scripts
+}
+
+impl Default for Normalization {
+	fn default() -> Self {
+		Self {
+			strip_punctuation: true,
+			strip_whitespace: true,
+			segment_by_script: false,
+		}
+	}
+}
+
+impl Normalization {
+	pub fn apply(&self, s: &str) -> String {
+		// TODO merge inconsistent diacritic usage
+		let mut cleaned = String::with_capacity(s.len());
+		let mut pending_space = false;
+		for c in s.chars() {
+			let is_hyphen = self.strip_punctuation && HYPHENS.contains(&c);
+			if c.is_whitespace() || c == '_' || is_hyphen {
+				pending_space = !self.strip_whitespace && !cleaned.is_empty();
+				continue;
+			}
+			if self.strip_punctuation && PUNCTUATION.contains(&c) {
+				continue;
+			}
+			if pending_space {
+				cleaned.push(' ');
+				pending_space = false;
+			}
+			cleaned.extend(c.to_lowercase());
+		}
+		cleaned
+	}
+
+	// Whether adjacent characters belong to separate segments, which no bigram should span
+	pub fn is_boundary(&self, a: char, b: char) -> bool {
+		self.segment_by_script && is_cjk(a) != is_cjk(b)
+	}
+}
+
+// Chinese, Japanese and Korean scripts, which are written without spaces between words
+fn is_cjk(c: char) -> bool {
+	matches!(c,
+		'\u{1100}'..='\u{11FF}' // Hangul Jamo
+		| '\u{3000}'..='\u{303F}' // CJK symbols and punctuation
+		| '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+		| '\u{3400}'..='\u{4DBF}' // CJK unified ideographs extension A
+		| '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+		| '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+		| '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+		| '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+		| '\u{20000}'..='\u{2FFFF}' // Supplementary ideographic plane
+	)
 }
 
 pub fn make_collator() -> Collator {
@@ -26,6 +97,7 @@ pub struct Dictionary {
 	strings: RodeoReader,          // Interned strings
 	canon: HashMap<String, Spur>,  // Canonical representation of similar strings
 	sort_keys: HashMap<Spur, u32>, // All spurs sorted against each other
+	normalization: Normalization,  // How strings are simplified to compute canonical representations
 }
 
 impl Dictionary {
@@ -34,7 +106,15 @@ impl Dictionary {
 	}
 
 	pub fn get_canon<S: AsRef<str>>(&self, string: S) -> Option<Spur> {
-		self.canon.get(&sanitize(string.as_ref())).copied()
+		self.canon.get(&self.sanitize(string.as_ref())).copied()
+	}
+
+	pub fn normalization(&self) -> Normalization {
+		self.normalization
+	}
+
+	pub fn sanitize(&self, string: &str) -> String {
+		self.normalization.apply(string)
 	}
 
 	pub fn resolve(&self, spur: &Spur) -> &str {
@@ -56,6 +136,7 @@ impl Default for Dictionary {
 			strings: Rodeo::default().into_reader(),
 			canon: Default::default(),
 			sort_keys: Default::default(),
+			normalization: Default::default(),
 		}
 	}
 }
@@ -64,9 +145,18 @@ impl Default for Dictionary {
 pub struct Builder {
 	strings: Rodeo,
 	canon: HashMap<String, Spur>,
+	normalization: Normalization,
 }
 
 impl Builder {
+	pub fn new(normalization: Normalization) -> Self {
+		Self {
+			normalization,
+			..Default::default()
+		}
+	}
+
+	#[tracing::instrument(name = "build_dictionary", level = "debug", skip_all, fields(strings = self.strings.len()))]
 	pub fn build(self) -> Dictionary {
 		let mut sorted_spurs = self.strings.iter().collect::<Vec<_>>();
 		// TODO this is too slow!
@@ -85,6 +175,7 @@ impl Builder {
 			strings: self.strings.into_reader(),
 			canon: self.canon,
 			sort_keys,
+			normalization: self.normalization,
 		}
 	}
 
@@ -92,8 +183,12 @@ impl Builder {
 		self.strings.get_or_intern(string)
 	}
 
+	pub fn resolve(&self, spur: &Spur) -> &str {
+		self.strings.resolve(spur)
+	}
+
 	pub fn get_or_intern_canon<S: AsRef<str>>(&mut self, string: S) -> Option<Spur> {
-		let cleaned = sanitize(string.as_ref());
+		let cleaned = self.normalization.apply(string.as_ref());
 		match cleaned.is_empty() {
 			true => None,
 			false => Some(
@@ -105,3 +200,81 @@ impl Builder {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn default_normalization_ignores_punctuation_variants() {
+		let sanitize = |s| Normalization::default().apply(s);
+		assert_eq!(sanitize("Don't"), sanitize("Don’t"));
+		assert_eq!(sanitize("Don't"), sanitize("dont"));
+		assert_eq!(sanitize("Simon & Garfunkel"), sanitize("simon&garfunkel"));
+		assert_eq!(sanitize("Hip-Hop"), sanitize("hip hop"));
+		assert_eq!(sanitize("Hip–Hop"), sanitize("HipHop"));
+		assert_eq!(sanitize("R.E.M."), sanitize("REM"));
+	}
+
+	#[test]
+	fn can_keep_punctuation() {
+		let normalization = Normalization {
+			strip_punctuation: false,
+			..Default::default()
+		};
+		assert_eq!(normalization.apply("Don't Stop"), "don'tstop");
+		assert_eq!(normalization.apply("Hip-Hop"), "hip-hop");
+	}
+
+	#[test]
+	fn can_collapse_whitespace() {
+		let normalization = Normalization {
+			strip_whitespace: false,
+			..Default::default()
+		};
+		assert_eq!(normalization.apply("  Hip   Hop "), "hip hop");
+		assert_eq!(normalization.apply("Hip-Hop"), "hip hop");
+		assert_eq!(normalization.apply("Space_Whale"), "space whale");
+	}
+
+	#[test]
+	fn hyphens_and_spaces_are_equivalent() {
+		for strip_whitespace in [true, false] {
+			let normalization = Normalization {
+				strip_whitespace,
+				..Default::default()
+			};
+			let sanitize = |s| normalization.apply(s);
+			assert_eq!(sanitize("post-rock"), sanitize("post rock"));
+			assert_eq!(sanitize("Post—Rock"), sanitize("post_rock"));
+			assert_eq!(sanitize("post - rock"), sanitize("post rock"));
+			assert_eq!(sanitize("-post-rock-"), sanitize("post rock"));
+		}
+
+		let normalization = Normalization {
+			strip_whitespace: false,
+			..Default::default()
+		};
+		assert_eq!(normalization.apply("Post-Rock"), "post rock");
+		assert_ne!(
+			normalization.apply("post-rock"),
+			normalization.apply("postrock")
+		);
+		assert_eq!(Normalization::default().apply("Post-Rock"), "postrock");
+	}
+
+	#[test]
+	fn can_segment_by_script() {
+		assert!(!Normalization::default().is_boundary('o', '東'));
+
+		let normalization = Normalization {
+			segment_by_script: true,
+			..Default::default()
+		};
+		assert!(normalization.is_boundary('o', '東'));
+		assert!(normalization.is_boundary('京', '1'));
+		assert!(!normalization.is_boundary('東', '京'));
+		assert!(!normalization.is_boundary('ト', '京'));
+		assert!(!normalization.is_boundary('k', 'y'));
+	}
+}
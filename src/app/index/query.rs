@@ -2,40 +2,61 @@ use std::collections::HashSet;
 
 use chumsky::{
 	error::Simple,
-	prelude::{choice, end, filter, just, none_of, recursive},
+	prelude::{any, choice, end, filter, just, none_of, recursive},
 	text::{int, keyword, whitespace, TextParser},
 	Parser,
 };
 use enum_map::Enum;
 use serde::{Deserialize, Serialize};
 
+use crate::app::Error;
+
 #[derive(Clone, Copy, Debug, Deserialize, Enum, Eq, Hash, PartialEq, Serialize)]
 pub enum TextField {
 	Album,
 	AlbumArtist,
 	Artist,
+	Codec,
 	Composer,
+	Conductor,
+	Ensemble,
+	Extension, // Of the audio file, which tells its container but not always its codec
 	Genre,
+	Grouping,
 	Label,
 	Lyricist,
 	Path,
 	Title,
+	Work,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum TextOp {
 	Eq,
+	NotEq, // Also matches songs without a value for the field
 	Like,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Enum, Eq, Hash, PartialEq, Serialize)]
 pub enum NumberField {
+	Bitrate,
+	Channels,
+	DateAdded, // Unix timestamp, in seconds
 	DiscNumber,
+	OriginalYear,
+	Rating,
 	TrackNumber,
 	Year,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Enum, Eq, Hash, PartialEq, Serialize)]
+pub enum BoolField {
+	Artwork,  // Embedded in the audio file, as opposed to a separate image file
+	Lossless, // Songs whose format is unknown are neither lossless nor lossy
+	Lossy,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum NumberOp {
 	Eq,
 	Greater,
@@ -44,66 +65,230 @@ pub enum NumberOp {
 	LessOrEq,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Literal {
 	Text(String),
 	Number(i32),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum BoolOp {
 	And,
 	Or,
 	Not,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Expr {
 	Fuzzy(Literal),
+	FuzzyIn(Vec<TextField>, Literal), // Selected with a `@field,field:` prefix
 	TextCmp(TextField, TextOp, String),
-	NumberCmp(NumberField, NumberOp, i32),
+	TextIn(TextField, Vec<String>),
+	CustomCmp(String, TextOp, String), // Compares against a configured `TXXX` field
+	NumberCmp(NumberField, NumberOp, i64),
+	Has(BoolField, bool), // False when negated with `!has:`
 	Combined(Box<Expr>, BoolOp, Box<Expr>),
 }
 
+// Parses a search query without evaluating it. Errors report the position (in characters)
+// of the furthest input the parser could not make sense of.
+pub fn parse_query(query: &str) -> Result<Expr, Error> {
+	make_parser().parse(query).map_err(|errors| {
+		let position = errors.iter().map(|e| e.span().start).max().unwrap_or(0);
+		Error::SearchQueryParseError(position)
+	})
+}
+
+// Checks the syntax of a search query, without evaluating it against the index
+pub fn validate_query(query: &str) -> Result<(), Error> {
+	parse_query(query).map(|_| ())
+}
+
+// Unix timestamp of midnight (UTC) at the start of a calendar day, or None if the
+// day does not exist
+fn date_to_timestamp(year: i64, month: u32, day: u32) -> Option<i64> {
+	let is_leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+	let days_in_month = match month {
+		1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+		4 | 6 | 9 | 11 => 30,
+		2 if is_leap_year => 29,
+		2 => 28,
+		_ => return None,
+	};
+	if day == 0 || day > days_in_month {
+		return None;
+	}
+
+	// Days since 1970-01-01, counting years from March so that leap days come last
+	let (year, month) = match month {
+		1 | 2 => (year - 1, month as i64 + 9),
+		_ => (year, month as i64 - 3),
+	};
+	let era = year.div_euclid(400);
+	let year_of_era = year.rem_euclid(400);
+	let day_of_year = (153 * month + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146_097 + day_of_era - 719_468;
+	Some(days * 24 * 60 * 60)
+}
+
+fn text_field() -> impl Parser<char, TextField, Error = Simple<char>> + Clone {
+	choice((
+		keyword("album").to(TextField::Album),
+		keyword("albumartist").to(TextField::AlbumArtist),
+		keyword("artist").to(TextField::Artist),
+		keyword("codec").to(TextField::Codec),
+		keyword("composer").to(TextField::Composer),
+		keyword("conductor").to(TextField::Conductor),
+		keyword("ensemble").to(TextField::Ensemble),
+		keyword("ext").to(TextField::Extension),
+		keyword("genre").to(TextField::Genre),
+		keyword("grouping").to(TextField::Grouping),
+		keyword("label").to(TextField::Label),
+		keyword("lyricist").to(TextField::Lyricist),
+		keyword("path").to(TextField::Path),
+		keyword("title").to(TextField::Title),
+		keyword("work").to(TextField::Work),
+	))
+	.padded()
+}
+
+// Applies the fields listed in a query prefix to all of its bare search terms
+fn restrict_fuzzy(expr: Expr, fields: &[TextField]) -> Expr {
+	match expr {
+		Expr::Fuzzy(literal) => Expr::FuzzyIn(fields.to_vec(), literal),
+		Expr::Combined(e, op, f) => Expr::Combined(
+			Box::new(restrict_fuzzy(*e, fields)),
+			op,
+			Box::new(restrict_fuzzy(*f, fields)),
+		),
+		e => e,
+	}
+}
+
 pub fn make_parser() -> impl Parser<char, Expr, Error = Simple<char>> {
-	recursive(|expr| {
+	// Queries starting with `@` must begin with a valid list of fields
+	let fuzzy_fields = choice((
+		text_field()
+			.separated_by(just(','))
+			.at_least(1)
+			.delimited_by(just('@'), just(':'))
+			.map(Some),
+		none_of("@").rewind().to(None),
+	));
+
+	let query = recursive(|expr| {
+		// Outside of quotes, a backslash makes the next character part of the search term,
+		// even if it is an operator
+		let escaped_char = just('\\').ignore_then(any());
+
 		let quoted_str = just('"')
 			.ignore_then(none_of('"').repeated().collect::<String>())
 			.then_ignore(just('"'));
 
-		let symbols = r#"()<>"|&=!"#.chars().collect::<HashSet<_>>();
+		let symbols = r#"()<>"|&=!\"#.chars().collect::<HashSet<_>>();
+
+		let raw_str = choice((
+			escaped_char.clone(),
+			filter(move |c: &char| !c.is_whitespace() && !symbols.contains(c)),
+		))
+		.repeated()
+		.at_least(1)
+		.collect::<String>();
+
+		let str_ = choice((quoted_str.clone(), raw_str)).padded();
+
+		// Unquoted values in a list also stop at commas
+		let list_symbols = r#"()<>"|&=!\,"#.chars().collect::<HashSet<_>>();
+		let list_raw_str = choice((
+			escaped_char,
+			filter(move |c: &char| !c.is_whitespace() && !list_symbols.contains(c)),
+		))
+		.repeated()
+		.at_least(1)
+		.collect::<String>();
 
-		let raw_str = filter(move |c: &char| !c.is_whitespace() && !symbols.contains(c))
-			.repeated()
+		let list_str = choice((quoted_str.clone(), list_raw_str)).padded();
+		let str_list = list_str
+			.separated_by(just(','))
 			.at_least(1)
-			.collect::<String>();
-
-		let str_ = choice((quoted_str, raw_str)).padded();
-
-		let number = int(10).from_str().unwrapped().padded();
-
-		let text_field = choice((
-			keyword("album").to(TextField::Album),
-			keyword("albumartist").to(TextField::AlbumArtist),
-			keyword("artist").to(TextField::Artist),
-			keyword("composer").to(TextField::Composer),
-			keyword("genre").to(TextField::Genre),
-			keyword("label").to(TextField::Label),
-			keyword("lyricist").to(TextField::Lyricist),
-			keyword("path").to(TextField::Path),
-			keyword("title").to(TextField::Title),
+			.delimited_by(just('('), just(')'))
+			.padded();
+
+		let number = int(10)
+			.try_map(|s: String, span| {
+				s.parse::<i32>()
+					.map_err(|e| Simple::custom(span, format!("{}", e)))
+			})
+			.padded();
+
+		let text_field = text_field();
+
+		let text_op = choice((
+			just("=").to(TextOp::Eq),
+			just("!=").to(TextOp::NotEq),
+			just("%").to(TextOp::Like),
 		))
 		.padded();
 
-		let text_op = choice((just("=").to(TextOp::Eq), just("%").to(TextOp::Like))).padded();
+		let text_in = text_field
+			.clone()
+			.then_ignore(just('=').padded())
+			.then(str_list)
+			.map(|(field, values)| Expr::TextIn(field, values));
 
 		let text_cmp = text_field
-			.then(text_op)
+			.then(text_op.clone())
 			.then(str_.clone())
 			.map(|((a, b), c)| Expr::TextCmp(a, b, c));
 
+		let custom_key = choice((
+			quoted_str,
+			filter(|c: &char| c.is_alphanumeric() || *c == '_' || *c == '-')
+				.repeated()
+				.at_least(1)
+				.collect::<String>(),
+		));
+
+		let custom_cmp = keyword("custom")
+			.padded()
+			.ignore_then(just(':'))
+			.ignore_then(custom_key)
+			.then(text_op.clone())
+			.then(str_.clone())
+			.map(|((key, op), s)| Expr::CustomCmp(key, op, s));
+
+		// `creator` is shorthand for any of the fields describing who made a song
+		let creator_cmp = keyword("creator")
+			.padded()
+			.ignore_then(text_op)
+			.then(str_.clone())
+			.map(|(op, s)| {
+				let creator_fields = [
+					TextField::AlbumArtist,
+					TextField::Composer,
+					TextField::Lyricist,
+				];
+				// Songs must differ from the value in every field to match `!=`
+				let bool_op = match op {
+					TextOp::NotEq => BoolOp::And,
+					TextOp::Eq | TextOp::Like => BoolOp::Or,
+				};
+				let mut expr = Expr::TextCmp(TextField::Artist, op, s.clone());
+				for field in creator_fields {
+					let cmp = Expr::TextCmp(field, op, s.clone());
+					expr = Expr::Combined(Box::new(expr), bool_op, Box::new(cmp));
+				}
+				expr
+			});
+
 		let number_field = choice((
+			keyword("bitrate").to(NumberField::Bitrate),
+			keyword("channels").to(NumberField::Channels),
+			keyword("added").to(NumberField::DateAdded),
 			keyword("discnumber").to(NumberField::DiscNumber),
+			keyword("originalyear").to(NumberField::OriginalYear),
+			keyword("rating").to(NumberField::Rating),
 			keyword("tracknumber").to(NumberField::TrackNumber),
 			keyword("year").to(NumberField::Year),
 		))
@@ -118,15 +303,57 @@ pub fn make_parser() -> impl Parser<char, Expr, Error = Simple<char>> {
 		))
 		.padded();
 
+		// Dates such as `2024-01-31` compare as the timestamp of the start of that day.
+		// Invalid dates are rejected here rather than read as a number followed by text.
+		let date = int(10)
+			.then_ignore(just('-'))
+			.then(int(10))
+			.then_ignore(just('-'))
+			.then(int(10))
+			.map(|((year, month), day): ((String, String), String)| {
+				let year = year.parse::<i64>().ok()?;
+				let month = month.parse::<u32>().ok()?;
+				let day = day.parse::<u32>().ok()?;
+				date_to_timestamp(year, month, day)
+			})
+			.padded();
+
 		let number_cmp = number_field
 			.then(number_op)
-			.then(number)
-			.map(|((a, b), c)| Expr::NumberCmp(a, b, c));
+			.then(choice((date, number.clone().map(|n| Some(n as i64)))))
+			.try_map(|((field, op), value), span| match value {
+				Some(value) => Ok(Expr::NumberCmp(field, op, value)),
+				None => Err(Simple::custom(span, "invalid date")),
+			});
+
+		let bool_field = choice((
+			keyword("has")
+				.ignore_then(just(':'))
+				.ignore_then(keyword("artwork").to(BoolField::Artwork)),
+			keyword("is").ignore_then(just(':')).ignore_then(choice((
+				keyword("lossless").to(BoolField::Lossless),
+				keyword("lossy").to(BoolField::Lossy),
+			))),
+		));
+
+		let has = just('!')
+			.or_not()
+			.then(bool_field)
+			.padded()
+			.map(|(negation, field)| Expr::Has(field, negation.is_none()));
 
 		let literal = choice((number.map(Literal::Number), str_.map(Literal::Text)));
 		let fuzzy = literal.map(Expr::Fuzzy);
 
-		let filter = choice((text_cmp, number_cmp, fuzzy));
+		let filter = choice((
+			text_in,
+			text_cmp,
+			custom_cmp,
+			creator_cmp,
+			number_cmp,
+			has,
+			fuzzy,
+		));
 		let atom = choice((filter, expr.delimited_by(just('('), just(')'))));
 
 		let bool_op = choice((
@@ -147,8 +374,16 @@ pub fn make_parser() -> impl Parser<char, Expr, Error = Simple<char>> {
 			.foldl(|a: Expr, b: Expr| Expr::Combined(Box::new(a), BoolOp::And, Box::new(b)));
 
 		implicit_and
-	})
-	.then_ignore(end())
+	});
+
+	whitespace()
+		.ignore_then(fuzzy_fields)
+		.then(query)
+		.then_ignore(end())
+		.map(|(fields, expr)| match fields {
+			Some(fields) => restrict_fuzzy(expr, &fields),
+			None => expr,
+		})
 }
 
 #[test]
@@ -164,6 +399,45 @@ fn can_parse_fuzzy_query() {
 	);
 }
 
+#[test]
+fn can_parse_oversized_numbers_as_text() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"99999999999"#).unwrap(),
+		Expr::Fuzzy(Literal::Text("99999999999".to_owned())),
+	);
+}
+
+#[test]
+fn can_escape_operators() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"AT\&T"#).unwrap(),
+		Expr::Fuzzy(Literal::Text("AT&T".to_owned())),
+	);
+	assert_eq!(
+		parser.parse(r#"title = \(untitled\)"#).unwrap(),
+		Expr::TextCmp(TextField::Title, TextOp::Eq, "(untitled)".to_owned()),
+	);
+	assert_eq!(
+		parser.parse(r#"C:\\Music"#).unwrap(),
+		Expr::Fuzzy(Literal::Text("C:\\Music".to_owned())),
+	);
+}
+
+#[test]
+fn quoted_strings_keep_backslashes() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#""C:\Music""#).unwrap(),
+		Expr::Fuzzy(Literal::Text(r#"C:\Music"#.to_owned())),
+	);
+	assert_eq!(
+		parser.parse(r#"path % "Music\Rock""#).unwrap(),
+		Expr::TextCmp(TextField::Path, TextOp::Like, r#"Music\Rock"#.to_owned()),
+	);
+}
+
 #[test]
 fn can_repeat_fuzzy_queries() {
 	let parser = make_parser();
@@ -194,6 +468,33 @@ fn can_mix_fuzzy_and_structured() {
 	);
 }
 
+#[test]
+fn can_restrict_fuzzy_fields() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"@title: dragon"#).unwrap(),
+		Expr::FuzzyIn(vec![TextField::Title], Literal::Text("dragon".to_owned())),
+	);
+	assert_eq!(
+		parser
+			.parse(r#"@title,artist: dragon year = 2005"#)
+			.unwrap(),
+		Expr::Combined(
+			Box::new(Expr::FuzzyIn(
+				vec![TextField::Title, TextField::Artist],
+				Literal::Text("dragon".to_owned())
+			)),
+			BoolOp::And,
+			Box::new(Expr::NumberCmp(NumberField::Year, NumberOp::Eq, 2005)),
+		),
+	);
+	assert_eq!(
+		parser.parse(r#"\@home"#).unwrap(),
+		Expr::Fuzzy(Literal::Text("@home".to_owned())),
+	);
+	assert!(parser.parse(r#"@mood: chill"#).is_err());
+}
+
 #[test]
 fn can_parse_text_fields() {
 	let parser = make_parser();
@@ -209,14 +510,40 @@ fn can_parse_text_fields() {
 		parser.parse(r#"artist = "rhapsody""#).unwrap(),
 		Expr::TextCmp(TextField::Artist, TextOp::Eq, "rhapsody".to_owned()),
 	);
+	assert_eq!(
+		parser.parse(r#"codec = mp3"#).unwrap(),
+		Expr::TextCmp(TextField::Codec, TextOp::Eq, "mp3".to_owned()),
+	);
 	assert_eq!(
 		parser.parse(r#"composer = "yoko kanno""#).unwrap(),
 		Expr::TextCmp(TextField::Composer, TextOp::Eq, "yoko kanno".to_owned()),
 	);
+	assert_eq!(
+		parser.parse(r#"ext = m4a"#).unwrap(),
+		Expr::TextCmp(TextField::Extension, TextOp::Eq, "m4a".to_owned()),
+	);
+	assert_eq!(
+		parser.parse(r#"conductor % karajan"#).unwrap(),
+		Expr::TextCmp(TextField::Conductor, TextOp::Like, "karajan".to_owned()),
+	);
+	assert_eq!(
+		parser
+			.parse(r#"ensemble = "berliner philharmoniker""#)
+			.unwrap(),
+		Expr::TextCmp(
+			TextField::Ensemble,
+			TextOp::Eq,
+			"berliner philharmoniker".to_owned()
+		),
+	);
 	assert_eq!(
 		parser.parse(r#"genre = "jazz""#).unwrap(),
 		Expr::TextCmp(TextField::Genre, TextOp::Eq, "jazz".to_owned()),
 	);
+	assert_eq!(
+		parser.parse(r#"grouping = favorites"#).unwrap(),
+		Expr::TextCmp(TextField::Grouping, TextOp::Eq, "favorites".to_owned()),
+	);
 	assert_eq!(
 		parser.parse(r#"label = "diverse system""#).unwrap(),
 		Expr::TextCmp(TextField::Label, TextOp::Eq, "diverse system".to_owned()),
@@ -239,6 +566,60 @@ fn can_parse_text_fields() {
 	);
 }
 
+#[test]
+fn can_parse_value_lists() {
+	let parser = make_parser();
+	assert_eq!(
+		parser
+			.parse(r#"artist = (rhapsody, "blind guardian", sabaton)"#)
+			.unwrap(),
+		Expr::TextIn(
+			TextField::Artist,
+			vec![
+				"rhapsody".to_owned(),
+				"blind guardian".to_owned(),
+				"sabaton".to_owned()
+			]
+		),
+	);
+	assert_eq!(
+		parser.parse(r#"genre=(jazz)"#).unwrap(),
+		Expr::TextIn(TextField::Genre, vec!["jazz".to_owned()]),
+	);
+	assert_eq!(
+		parser
+			.parse(r#"genre = (jazz, blues) && year = 1960"#)
+			.unwrap(),
+		Expr::Combined(
+			Box::new(Expr::TextIn(
+				TextField::Genre,
+				vec!["jazz".to_owned(), "blues".to_owned()]
+			)),
+			BoolOp::And,
+			Box::new(Expr::NumberCmp(NumberField::Year, NumberOp::Eq, 1960)),
+		),
+	);
+}
+
+#[test]
+fn can_parse_custom_fields() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"custom:mood = chill"#).unwrap(),
+		Expr::CustomCmp("mood".to_owned(), TextOp::Eq, "chill".to_owned()),
+	);
+	assert_eq!(
+		parser
+			.parse(r#"custom:"work place" % "late night""#)
+			.unwrap(),
+		Expr::CustomCmp(
+			"work place".to_owned(),
+			TextOp::Like,
+			"late night".to_owned()
+		),
+	);
+}
+
 #[test]
 fn can_parse_text_operators() {
 	let parser = make_parser();
@@ -250,11 +631,45 @@ fn can_parse_text_operators() {
 		parser.parse(r#"album % "legendary tales""#).unwrap(),
 		Expr::TextCmp(TextField::Album, TextOp::Like, "legendary tales".to_owned()),
 	);
+	assert_eq!(
+		parser.parse(r#"genre != podcast"#).unwrap(),
+		Expr::TextCmp(TextField::Genre, TextOp::NotEq, "podcast".to_owned()),
+	);
+}
+
+#[test]
+fn can_parse_creator_alias() {
+	let parser = make_parser();
+	let cmp = |field| Box::new(Expr::TextCmp(field, TextOp::Like, "bach".to_owned()));
+	assert_eq!(
+		parser.parse(r#"creator % bach"#).unwrap(),
+		Expr::Combined(
+			Box::new(Expr::Combined(
+				Box::new(Expr::Combined(
+					cmp(TextField::Artist),
+					BoolOp::Or,
+					cmp(TextField::AlbumArtist)
+				)),
+				BoolOp::Or,
+				cmp(TextField::Composer)
+			)),
+			BoolOp::Or,
+			cmp(TextField::Lyricist)
+		),
+	);
 }
 
 #[test]
 fn can_parse_number_fields() {
 	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"bitrate < 128"#).unwrap(),
+		Expr::NumberCmp(NumberField::Bitrate, NumberOp::Less, 128),
+	);
+	assert_eq!(
+		parser.parse(r#"channels = 6"#).unwrap(),
+		Expr::NumberCmp(NumberField::Channels, NumberOp::Eq, 6),
+	);
 	assert_eq!(
 		parser.parse(r#"discnumber = 6"#).unwrap(),
 		Expr::NumberCmp(NumberField::DiscNumber, NumberOp::Eq, 6),
@@ -263,12 +678,72 @@ fn can_parse_number_fields() {
 		parser.parse(r#"tracknumber = 12"#).unwrap(),
 		Expr::NumberCmp(NumberField::TrackNumber, NumberOp::Eq, 12),
 	);
+	assert_eq!(
+		parser.parse(r#"originalyear = 1975"#).unwrap(),
+		Expr::NumberCmp(NumberField::OriginalYear, NumberOp::Eq, 1975),
+	);
 	assert_eq!(
 		parser.parse(r#"year = 1999"#).unwrap(),
 		Expr::NumberCmp(NumberField::Year, NumberOp::Eq, 1999),
 	);
 }
 
+#[test]
+fn can_parse_dates() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"added > 2024-01-01"#).unwrap(),
+		Expr::NumberCmp(NumberField::DateAdded, NumberOp::Greater, 1_704_067_200),
+	);
+	assert_eq!(
+		parser.parse(r#"added <= 1970-01-01"#).unwrap(),
+		Expr::NumberCmp(NumberField::DateAdded, NumberOp::LessOrEq, 0),
+	);
+	assert_eq!(
+		parser.parse(r#"added >= 2000-02-29"#).unwrap(),
+		Expr::NumberCmp(NumberField::DateAdded, NumberOp::GreaterOrEq, 951_782_400),
+	);
+	assert_eq!(
+		parser.parse(r#"added > 1700000000"#).unwrap(),
+		Expr::NumberCmp(NumberField::DateAdded, NumberOp::Greater, 1_700_000_000),
+	);
+	assert!(parser.parse(r#"added > 2023-02-29"#).is_err());
+	assert!(parser.parse(r#"added > 2024-13-01"#).is_err());
+}
+
+#[test]
+fn can_parse_bool_fields() {
+	let parser = make_parser();
+	assert_eq!(
+		parser.parse(r#"has:artwork"#).unwrap(),
+		Expr::Has(BoolField::Artwork, true),
+	);
+	assert_eq!(
+		parser.parse(r#"!has:artwork && album = """#).unwrap(),
+		Expr::Combined(
+			Box::new(Expr::Has(BoolField::Artwork, false)),
+			BoolOp::And,
+			Box::new(Expr::TextCmp(TextField::Album, TextOp::Eq, "".to_owned())),
+		),
+	);
+	assert_eq!(
+		parser.parse(r#"hash"#).unwrap(),
+		Expr::Fuzzy(Literal::Text("hash".to_owned())),
+	);
+	assert_eq!(
+		parser.parse(r#"is:lossless"#).unwrap(),
+		Expr::Has(BoolField::Lossless, true),
+	);
+	assert_eq!(
+		parser.parse(r#"!is:lossy"#).unwrap(),
+		Expr::Has(BoolField::Lossy, false),
+	);
+	assert_eq!(
+		parser.parse(r#"island"#).unwrap(),
+		Expr::Fuzzy(Literal::Text("island".to_owned())),
+	);
+}
+
 #[test]
 fn can_parse_number_operators() {
 	let parser = make_parser();
@@ -476,3 +951,45 @@ fn can_use_parenthesis_for_precedence() {
 		),
 	);
 }
+
+#[test]
+fn parse_query_returns_expression_tree() {
+	assert_eq!(
+		parse_query(r#"artist = "Blind Guardian" && (year > 1995 || !has:artwork)"#).unwrap(),
+		Expr::Combined(
+			Box::new(Expr::TextCmp(
+				TextField::Artist,
+				TextOp::Eq,
+				"Blind Guardian".to_owned()
+			)),
+			BoolOp::And,
+			Box::new(Expr::Combined(
+				Box::new(Expr::NumberCmp(NumberField::Year, NumberOp::Greater, 1995)),
+				BoolOp::Or,
+				Box::new(Expr::Has(BoolField::Artwork, false)),
+			)),
+		),
+	);
+}
+
+#[test]
+fn parse_query_reports_error_position() {
+	let position = match parse_query(r#"rhapsody )"#) {
+		Err(Error::SearchQueryParseError(p)) => p,
+		_ => panic!("Expected a parse error"),
+	};
+	assert!(position >= "rhapsody".len());
+}
+
+#[test]
+fn can_validate_query() {
+	assert!(validate_query(r#"artist = Stratovarius && year > 2000"#).is_ok());
+	assert!(matches!(
+		validate_query(""),
+		Err(Error::SearchQueryParseError(0))
+	));
+	assert!(matches!(
+		validate_query("year >> 2000"),
+		Err(Error::SearchQueryParseError(_))
+	));
+}
@@ -1,26 +1,352 @@
-use chumsky::Parser;
 use enum_map::EnumMap;
 use lasso2::Spur;
-use nohash_hasher::IntSet;
+use nohash_hasher::{IntMap, IntSet};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::{
+	borrow::Cow,
+	cell::Cell,
+	collections::{BTreeMap, HashMap, HashSet},
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
 use tinyvec::TinyVec;
+use tracing::{field::Empty, Span};
 
 use crate::app::{
 	index::{
 		dictionary::Dictionary,
-		query::{BoolOp, Expr, Literal, NumberField, NumberOp, TextField, TextOp},
+		query::{BoolField, BoolOp, Expr, Literal, NumberField, NumberOp, TextField, TextOp},
 		storage::SongKey,
 	},
 	scanner, Error,
 };
 
-use super::{collection, dictionary::sanitize, query::make_parser, storage};
+use super::{
+	collection,
+	dictionary::{self, Normalization},
+	query::parse_query,
+	storage,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValueOrdering {
+	#[default]
+	Alphabetical,
+	SongCount, // Most frequent values first
+}
+
+// Which part of a song's virtual path is searchable through the `path` field
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathIndexing {
+	Full,
+	#[default]
+	WithoutExtension,
+	FileName, // File name without its extension
+}
+
+impl PathIndexing {
+	pub fn apply<'a>(&self, virtual_path: &'a str) -> &'a str {
+		let path = Path::new(virtual_path);
+		match self {
+			PathIndexing::Full => virtual_path,
+			PathIndexing::WithoutExtension => match path.extension().and_then(|e| e.to_str()) {
+				Some(extension) => &virtual_path[..virtual_path.len() - extension.len() - 1],
+				None => virtual_path,
+			},
+			PathIndexing::FileName => path
+				.file_stem()
+				.and_then(|s| s.to_str())
+				.unwrap_or(virtual_path),
+		}
+	}
+}
+
+// Where a fuzzy search term may start within the values it matches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anchoring {
+	Anywhere,
+	WordStart, // Eg. `son` matches `Sonata` and `Moonlight Sonata`, but not `Seasons`
+}
+
+// Byte offsets of the start of a value and of the words within it, where a word is a run
+// of letters or digits
+fn word_starts(value: &str) -> impl Iterator<Item = usize> + '_ {
+	let mut previous = None;
+	value.char_indices().filter_map(move |(i, c)| {
+		let is_start = match previous {
+			None => true,
+			Some(p) => c.is_alphanumeric() && !p.is_alphanumeric(),
+		};
+		previous = Some(c);
+		is_start.then_some(i)
+	})
+}
+
+// Percentage of the bigrams in a fuzzy search term which a value must contain to be
+// considered a candidate match. Below 100, values within a few typos of the term also match.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BigramThreshold(u8);
+
+impl Default for BigramThreshold {
+	fn default() -> Self {
+		Self(100)
+	}
+}
+
+impl BigramThreshold {
+	pub fn new(percent: u8) -> Self {
+		Self(percent.clamp(1, 100))
+	}
+
+	fn is_strict(&self) -> bool {
+		self.0 >= 100
+	}
+
+	fn min_bigrams(&self, num_bigrams: usize) -> usize {
+		(num_bigrams * self.0 as usize).div_ceil(100).max(1)
+	}
+
+	// A single typo changes at most two bigrams of a term
+	fn max_edits(&self, num_bigrams: usize) -> usize {
+		num_bigrams
+			.saturating_sub(self.min_bigrams(num_bigrams))
+			.div_ceil(2)
+	}
+}
+
+// Bounds the time a single search query may spend checking indexed values against its fuzzy
+// search terms, so that pathological queries fail with `Error::SearchQueryTimeout` instead
+// of tying up the server. Looking up candidate values and sorting results are not bounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+	pub timeout: Option<Duration>,
+	pub max_candidates: Option<usize>, // Values inspected by fuzzy matching, across the query
+}
+
+// Everything besides the query itself which affects the results of a search
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+	pub default_field: Option<TextField>, // Searched by terms without a field prefix
+	pub min_fuzzy_length: usize,
+	pub limits: SearchLimits,
+	pub sort_order: Option<collection::SortOrder>, // Relevance order when absent
+}
+
+// The clock is only read every so many candidates
+const BUDGET_CHECK_INTERVAL: usize = 64;
+
+// Work left for the query being evaluated
+struct Budget {
+	deadline: Option<Instant>,
+	max_candidates: Option<usize>,
+	num_candidates: Cell<usize>,
+	exhausted: Cell<bool>,
+}
+
+impl Budget {
+	fn new(limits: SearchLimits) -> Self {
+		Self {
+			deadline: limits.timeout.map(|t| Instant::now() + t),
+			max_candidates: limits.max_candidates,
+			num_candidates: Cell::new(0),
+			exhausted: Cell::new(false),
+		}
+	}
+
+	fn unlimited() -> Self {
+		Self::new(SearchLimits::default())
+	}
+
+	// Accounts for one more candidate, returns false once the budget is exhausted
+	fn spend(&self) -> bool {
+		if self.exhausted.get() {
+			return false;
+		}
+		let num_candidates = self.num_candidates.get() + 1;
+		self.num_candidates.set(num_candidates);
+		let over_count = self.max_candidates.is_some_and(|m| num_candidates > m);
+		let over_time = num_candidates % BUDGET_CHECK_INTERVAL == 1
+			&& self.deadline.is_some_and(|d| Instant::now() >= d);
+		self.exhausted.set(over_count || over_time);
+		!self.exhausted.get()
+	}
+
+	fn is_exhausted(&self) -> bool {
+		self.exhausted.get()
+	}
+}
+
+// Smallest edit distance between `term` and any substring of `value`
+fn substring_edit_distance(term: &[char], value: &str) -> usize {
+	edit_distance(term, value, 0)
+}
+
+// Smallest edit distance between `term` and any prefix of `value`
+fn prefix_edit_distance(term: &[char], value: &str) -> usize {
+	edit_distance(term, value, 1)
+}
+
+// Characters of `value` preceding the match each cost `skip_cost` edits
+fn edit_distance(term: &[char], value: &str, skip_cost: usize) -> usize {
+	let mut column = (0..=term.len()).collect::<Vec<_>>();
+	let mut best = term.len();
+	for c in value.chars() {
+		let mut diagonal = column[0];
+		column[0] += skip_cost;
+		for i in 1..=term.len() {
+			let substitution = diagonal + usize::from(term[i - 1] != c);
+			diagonal = column[i];
+			column[i] = substitution.min(column[i] + 1).min(column[i - 1] + 1);
+		}
+		best = best.min(column[term.len()]);
+	}
+	best
+}
+
+// How closely a song matched a search query. Stronger matches compare greater.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+	Path, // Only matched a term without a field prefix through its file path
+	Fuzzy,
+	Exact,
+}
+
+type Matches = IntMap<SongKey, MatchKind>;
+
+// Estimated cost of evaluating one predicate of a search query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PredicateEstimate {
+	pub predicate: Expr,
+	pub num_candidates: usize, // Upper bound on the number of songs the predicate inspects
+}
+
+// Estimated number of songs an expression inspects, along with the estimates of its operands.
+// Computed once per query, bottom-up, so that ordering the operands of nested `And`s does not
+// estimate the same subtrees again at every level.
+struct Estimate {
+	num_candidates: usize,
+	operands: Option<Box<(Estimate, Estimate)>>,
+}
+
+impl Estimate {
+	fn operands(&self) -> (&Estimate, &Estimate) {
+		let operands = self
+			.operands
+			.as_deref()
+			.expect("Combined expressions have estimated operands");
+		(&operands.0, &operands.1)
+	}
+}
+
+// Both sides of an `And` are evaluated starting with the most selective one, so that
+// the other side can be skipped entirely when it comes up empty.
+fn evaluation_order<'a>(
+	e: (&'a Expr, &'a Estimate),
+	op: BoolOp,
+	f: (&'a Expr, &'a Estimate),
+) -> [(&'a Expr, &'a Estimate); 2] {
+	if op == BoolOp::And
+		&& is_operable(e.0)
+		&& is_operable(f.0)
+		&& f.1.num_candidates < e.1.num_candidates
+	{
+		return [f, e];
+	}
+	[e, f]
+}
+
+fn explain_expr(expr: &Expr, estimate: &Estimate, estimates: &mut Vec<PredicateEstimate>) {
+	match expr {
+		Expr::Combined(e, op, f) => {
+			let (e_estimate, f_estimate) = estimate.operands();
+			let operands =
+				evaluation_order((e.as_ref(), e_estimate), *op, (f.as_ref(), f_estimate));
+			for (side, side_estimate) in operands {
+				if is_operable(side) {
+					explain_expr(side, side_estimate, estimates);
+				}
+			}
+		}
+		_ => estimates.push(PredicateEstimate {
+			predicate: expr.clone(),
+			num_candidates: estimate.num_candidates,
+		}),
+	}
+}
+
+fn with_kind(songs: IntSet<SongKey>, kind: MatchKind) -> Matches {
+	songs.into_iter().map(|s| (s, kind)).collect()
+}
+
+// Technical properties, ratings and timestamps are too noisy to match bare numbers against
+fn matches_bare_numbers(field: NumberField) -> bool {
+	!matches!(
+		field,
+		NumberField::Bitrate | NumberField::Channels | NumberField::DateAdded | NumberField::Rating
+	)
+}
+
+// Numbers restricted to text fields are matched as text
+fn literal_text(literal: &Literal) -> String {
+	match literal {
+		Literal::Text(s) => s.clone(),
+		Literal::Number(n) => n.to_string(),
+	}
+}
+
+// Codec names and file extensions would match large parts of the collection
+fn matches_bare_text(field: TextField) -> bool {
+	!matches!(field, TextField::Codec | TextField::Extension)
+}
+
+// Terms too short to look up in the bigram index are ignored when combined with other terms
+fn is_operable(expr: &Expr) -> bool {
+	match expr {
+		Expr::Fuzzy(Literal::Text(s)) | Expr::FuzzyIn(_, Literal::Text(s))
+			if s.chars().count() < BIGRAM_SIZE =>
+		{
+			false
+		}
+		Expr::Fuzzy(Literal::Number(n)) | Expr::FuzzyIn(_, Literal::Number(n)) if *n < 10 => false,
+		Expr::TextCmp(_, _, s) if s.chars().count() < BIGRAM_SIZE => false,
+		Expr::CustomCmp(_, _, s) if s.chars().count() < BIGRAM_SIZE => false,
+		_ => true,
+	}
+}
+
+// Rejects queries containing bare search terms shorter than `min_length`, as these
+// match large portions of the collection. Terms targeting a specific field are exempt.
+fn check_fuzzy_length(expr: &Expr, min_length: usize) -> Result<(), Error> {
+	let length = match expr {
+		Expr::Fuzzy(Literal::Text(s)) => s.chars().count(),
+		Expr::Fuzzy(Literal::Number(n)) => n.to_string().len(),
+		Expr::Combined(e, _, f) => {
+			check_fuzzy_length(e, min_length)?;
+			return check_fuzzy_length(f, min_length);
+		}
+		Expr::FuzzyIn(..)
+		| Expr::TextCmp(..)
+		| Expr::TextIn(..)
+		| Expr::CustomCmp(..)
+		| Expr::NumberCmp(..)
+		| Expr::Has(..) => return Ok(()),
+	};
+	if length < min_length {
+		return Err(Error::SearchQueryTooShort(min_length));
+	}
+	Ok(())
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Search {
 	text_fields: EnumMap<TextField, TextFieldIndex>,
 	number_fields: EnumMap<NumberField, NumberFieldIndex>,
+	bool_fields: EnumMap<BoolField, IntSet<SongKey>>, // Songs for which each field is true
+	custom_fields: HashMap<String, TextFieldIndex>,   // Keyed by lowercase `TXXX` description
+	path_indexing: PathIndexing,
+	bigram_threshold: BigramThreshold,
+	word_boundary_fields: Vec<TextField>, // Fuzzy terms only match from the start of a word
 }
 
 impl Default for Search {
@@ -28,120 +354,522 @@ impl Default for Search {
 		Self {
 			text_fields: Default::default(),
 			number_fields: Default::default(),
+			bool_fields: Default::default(),
+			custom_fields: Default::default(),
+			path_indexing: Default::default(),
+			bigram_threshold: Default::default(),
+			word_boundary_fields: Default::default(),
 		}
 	}
 }
 
 impl Search {
+	pub fn path_indexing(&self) -> PathIndexing {
+		self.path_indexing
+	}
+
+	pub fn bigram_threshold(&self) -> BigramThreshold {
+		self.bigram_threshold
+	}
+
+	pub fn word_boundary_fields(&self) -> &[TextField] {
+		&self.word_boundary_fields
+	}
+
+	#[tracing::instrument(level = "debug", skip_all, fields(query = query, matches = Empty))]
 	pub fn find_songs(
 		&self,
 		collection: &collection::Collection,
 		dictionary: &Dictionary,
 		query: &str,
-	) -> Result<Vec<collection::Song>, Error> {
-		let parser = make_parser();
-		let parsed_query = parser
-			.parse(query)
-			.map_err(|_| Error::SearchQueryParseError)?;
-
-		let mut songs = self
-			.eval(dictionary, &parsed_query)
-			.into_iter()
-			.collect::<Vec<_>>();
+		options: SearchOptions,
+	) -> Result<Vec<(collection::Song, MatchKind)>, Error> {
+		let parsed_query = parse_query(query)?;
+		check_fuzzy_length(&parsed_query, options.min_fuzzy_length)?;
+
+		let default_field = options.default_field;
+		let budget = Budget::new(options.limits);
+		let estimate = self.estimate(dictionary, &parsed_query, default_field);
+		let matches = self.eval(dictionary, &parsed_query, &estimate, default_field, &budget);
+		if budget.is_exhausted() {
+			return Err(Error::SearchQueryTimeout);
+		}
+		Span::current().record("matches", matches.len());
+		let mut songs = matches.keys().copied().collect::<Vec<_>>();
 		collection.sort_songs(&mut songs, dictionary);
+		// Songs only found through their file path are listed last
+		songs.sort_by_key(|song_key| matches[song_key] == MatchKind::Path);
+		if let Some(sort_order) = options.sort_order {
+			collection.sort_songs_by(&mut songs, dictionary, sort_order);
+		}
 		let songs = songs
 			.into_iter()
-			.filter_map(|song_key| collection.get_song(dictionary, song_key))
+			.filter_map(|song_key| {
+				let song = collection.get_song(dictionary, song_key)?;
+				Some((song, matches[&song_key]))
+			})
 			.collect::<Vec<_>>();
 
 		Ok(songs)
 	}
 
-	fn eval(&self, dictionary: &Dictionary, expr: &Expr) -> IntSet<SongKey> {
+	// Lists the predicates of a query in the order they would be evaluated, along with
+	// an estimate of how many songs each of them inspects. Nothing is actually evaluated,
+	// estimates are derived from the size of index buckets.
+	pub fn explain(
+		&self,
+		dictionary: &Dictionary,
+		query: &str,
+		default_field: Option<TextField>,
+	) -> Result<Vec<PredicateEstimate>, Error> {
+		let parsed_query = parse_query(query)?;
+		let estimate = self.estimate(dictionary, &parsed_query, default_field);
+		let mut estimates = Vec::new();
+		explain_expr(&parsed_query, &estimate, &mut estimates);
+		Ok(estimates)
+	}
+
+	pub fn get_album_songs(
+		&self,
+		collection: &collection::Collection,
+		dictionary: &Dictionary,
+		album: &str,
+	) -> Result<Vec<PathBuf>, Error> {
+		let mut songs = self.text_fields[TextField::Album]
+			.find_exact(dictionary, album)
+			.into_iter()
+			.collect::<Vec<_>>();
+		if songs.is_empty() {
+			return Err(Error::AlbumNotFound);
+		}
+		collection.sort_songs(&mut songs, dictionary);
+		Ok(songs
+			.into_iter()
+			.map(|s| PathBuf::from(dictionary.resolve(&s.virtual_path.0)))
+			.collect())
+	}
+
+	pub fn get_values(
+		&self,
+		dictionary: &Dictionary,
+		field: TextField,
+		offset: usize,
+		count: usize,
+	) -> Vec<String> {
+		let mut values = self.text_fields[field].exact.keys().collect::<Vec<_>>();
+		values.sort_by(|a, b| dictionary.cmp(a, b));
+		values
+			.into_iter()
+			.skip(offset)
+			.take(count)
+			.map(|v| dictionary.resolve(v).to_owned())
+			.collect()
+	}
+
+	// Lists every value of a text field along with how many songs use it.
+	pub fn distinct_values(
+		&self,
+		dictionary: &Dictionary,
+		field: TextField,
+		ordering: ValueOrdering,
+	) -> Vec<(String, usize)> {
+		let mut values = self.text_fields[field]
+			.exact
+			.iter()
+			.map(|(value, songs)| (value, songs.len()))
+			.collect::<Vec<_>>();
+
+		values.sort_by(|(a, a_count), (b, b_count)| match ordering {
+			ValueOrdering::Alphabetical => dictionary.cmp(a, b),
+			ValueOrdering::SongCount => b_count.cmp(a_count).then_with(|| dictionary.cmp(a, b)),
+		});
+
+		values
+			.into_iter()
+			.map(|(value, count)| (dictionary.resolve(value).to_owned(), count))
+			.collect()
+	}
+
+	// Counts how many songs match a fuzzy search term within each text field.
+	// A song matching in several fields is counted once per field.
+	pub fn count_fuzzy_matches(
+		&self,
+		dictionary: &Dictionary,
+		term: &str,
+	) -> EnumMap<TextField, usize> {
+		let budget = Budget::unlimited();
+		EnumMap::from_fn(|field| self.find_like(dictionary, field, term, &budget).len())
+	}
+
+	fn eval(
+		&self,
+		dictionary: &Dictionary,
+		expr: &Expr,
+		estimate: &Estimate,
+		default_field: Option<TextField>,
+		budget: &Budget,
+	) -> Matches {
 		match expr {
-			Expr::Fuzzy(s) => self.eval_fuzzy(dictionary, s),
-			Expr::TextCmp(field, op, s) => self.eval_text_operator(dictionary, *field, *op, s),
+			Expr::Fuzzy(s) => match default_field {
+				Some(field) => self.eval_fuzzy_fields(dictionary, &[field], s, budget),
+				None => self.eval_fuzzy(dictionary, s, budget),
+			},
+			Expr::FuzzyIn(fields, s) => self.eval_fuzzy_fields(dictionary, fields, s, budget),
+			Expr::TextCmp(field, op, s) => {
+				self.eval_text_operator(dictionary, *field, *op, s, budget)
+			}
+			Expr::TextIn(field, values) => with_kind(
+				self.text_fields[*field].find_exact_any(dictionary, values),
+				MatchKind::Exact,
+			),
+			Expr::CustomCmp(key, op, s) => {
+				self.eval_custom_operator(dictionary, key, *op, s, budget)
+			}
 			Expr::NumberCmp(field, op, n) => self.eval_number_operator(*field, *op, *n),
-			Expr::Combined(e, op, f) => self.combine(dictionary, e, *op, f),
+			Expr::Has(field, true) => with_kind(self.bool_fields[*field].clone(), MatchKind::Exact),
+			Expr::Has(field, false) => self.eval_not_equal(self.bool_fields[*field].clone()),
+			Expr::Combined(e, op, f) => {
+				let (e_estimate, f_estimate) = estimate.operands();
+				self.combine(
+					dictionary,
+					(e.as_ref(), e_estimate),
+					*op,
+					(f.as_ref(), f_estimate),
+					default_field,
+					budget,
+				)
+			}
 		}
 	}
 
 	fn combine(
 		&self,
 		dictionary: &Dictionary,
-		e: &Expr,
+		e: (&Expr, &Estimate),
 		op: BoolOp,
-		f: &Expr,
-	) -> IntSet<SongKey> {
-		let is_operable = |expr: &Expr| match expr {
-			Expr::Fuzzy(Literal::Text(s)) if s.chars().count() < BIGRAM_SIZE => false,
-			Expr::Fuzzy(Literal::Number(n)) if *n < 10 => false,
-			Expr::TextCmp(_, _, s) if s.chars().count() < BIGRAM_SIZE => false,
-			_ => true,
-		};
-
-		let left = is_operable(e).then(|| self.eval(dictionary, e));
-		let right = is_operable(f).then(|| self.eval(dictionary, f));
+		f: (&Expr, &Estimate),
+		default_field: Option<TextField>,
+		budget: &Budget,
+	) -> Matches {
+		let [(e, e_estimate), (f, f_estimate)] = evaluation_order(e, op, f);
+
+		let left =
+			is_operable(e).then(|| self.eval(dictionary, e, e_estimate, default_field, budget));
+		if op == BoolOp::And && left.as_ref().is_some_and(|l| l.is_empty()) {
+			return Matches::default();
+		}
+		let right =
+			is_operable(f).then(|| self.eval(dictionary, f, f_estimate, default_field, budget));
 
+		// Songs matched by both sides keep their strongest match kind
 		match (left, op, right) {
-			(Some(l), BoolOp::And, Some(r)) => l.intersection(&r).cloned().collect(),
-			(Some(l), BoolOp::Or, Some(r)) => l.union(&r).cloned().collect(),
-			(Some(l), BoolOp::Not, Some(r)) => l.difference(&r).cloned().collect(),
-			(None, BoolOp::Not, _) => IntSet::default(),
+			(Some(l), BoolOp::And, Some(r)) => l
+				.into_iter()
+				.filter_map(|(song, kind)| r.get(&song).map(|k| (song, kind.max(*k))))
+				.collect(),
+			(Some(mut l), BoolOp::Or, Some(r)) => {
+				for (song, kind) in r {
+					let entry = l.entry(song).or_insert(kind);
+					*entry = (*entry).max(kind);
+				}
+				l
+			}
+			(Some(l), BoolOp::Not, Some(r)) => l
+				.into_iter()
+				.filter(|(song, _)| !r.contains_key(song))
+				.collect(),
+			(None, BoolOp::Not, _) => Matches::default(),
 			(Some(l), _, None) => l,
 			(None, _, Some(r)) => r,
-			(None, _, None) => IntSet::default(),
+			(None, _, None) => Matches::default(),
+		}
+	}
+
+	fn estimate(
+		&self,
+		dictionary: &Dictionary,
+		expr: &Expr,
+		default_field: Option<TextField>,
+	) -> Estimate {
+		match expr {
+			Expr::Combined(e, op, f) => {
+				let e = self.estimate(dictionary, e, default_field);
+				let f = self.estimate(dictionary, f, default_field);
+				let num_candidates = match op {
+					BoolOp::And => e.num_candidates.min(f.num_candidates),
+					BoolOp::Or => e.num_candidates + f.num_candidates,
+					BoolOp::Not => e.num_candidates,
+				};
+				Estimate {
+					num_candidates,
+					operands: Some(Box::new((e, f))),
+				}
+			}
+			_ => Estimate {
+				num_candidates: self.estimate_predicate(dictionary, expr, default_field),
+				operands: None,
+			},
+		}
+	}
+
+	fn estimate_predicate(
+		&self,
+		dictionary: &Dictionary,
+		expr: &Expr,
+		default_field: Option<TextField>,
+	) -> usize {
+		match expr {
+			Expr::Fuzzy(Literal::Text(s)) => match default_field {
+				Some(field) => {
+					self.text_fields[field].estimate_like(dictionary, s, self.bigram_threshold)
+				}
+				None => self
+					.text_fields
+					.iter()
+					.filter(|(field, _)| matches_bare_text(*field))
+					.map(|(_, index)| index.estimate_like(dictionary, s, self.bigram_threshold))
+					.sum(),
+			},
+			Expr::FuzzyIn(fields, literal) => {
+				let value = literal_text(literal);
+				fields
+					.iter()
+					.map(|field| {
+						self.text_fields[*field].estimate_like(
+							dictionary,
+							&value,
+							self.bigram_threshold,
+						)
+					})
+					.sum()
+			}
+			Expr::Fuzzy(Literal::Number(n)) => {
+				let text = Expr::Fuzzy(Literal::Text(n.to_string()));
+				let numbers = self
+					.number_fields
+					.iter()
+					.filter(|(field, _)| matches_bare_numbers(*field))
+					.map(|(_, index)| index.count(*n as i64, NumberOp::Eq))
+					.sum::<usize>();
+				numbers + self.estimate_predicate(dictionary, &text, default_field)
+			}
+			Expr::TextCmp(_, TextOp::Eq | TextOp::NotEq, s) if s.is_empty() => self.num_songs(),
+			Expr::TextCmp(field, TextOp::Eq, s) => {
+				self.text_fields[*field].estimate_exact(dictionary, s)
+			}
+			Expr::TextCmp(field, TextOp::NotEq, s) => {
+				let equal = self.text_fields[*field].estimate_exact(dictionary, s);
+				self.num_songs().saturating_sub(equal)
+			}
+			Expr::TextCmp(field, TextOp::Like, s) => {
+				self.text_fields[*field].estimate_like(dictionary, s, self.bigram_threshold)
+			}
+			Expr::TextIn(field, values) => values
+				.iter()
+				.map(|v| self.text_fields[*field].estimate_exact(dictionary, v))
+				.sum(),
+			Expr::CustomCmp(key, op, s) => {
+				match (self.custom_fields.get(&key.to_lowercase()), op) {
+					(None, TextOp::NotEq) => self.num_songs(),
+					(None, _) => 0,
+					(Some(index), TextOp::Eq) => index.estimate_exact(dictionary, s),
+					(Some(index), TextOp::NotEq) => {
+						let equal = index.estimate_exact(dictionary, s);
+						self.num_songs().saturating_sub(equal)
+					}
+					(Some(index), TextOp::Like) => {
+						index.estimate_like(dictionary, s, self.bigram_threshold)
+					}
+				}
+			}
+			Expr::NumberCmp(field, op, n) => self.number_fields[*field].count(*n, *op),
+			Expr::Has(field, true) => self.bool_fields[*field].len(),
+			Expr::Has(field, false) => self
+				.num_songs()
+				.saturating_sub(self.bool_fields[*field].len()),
+			Expr::Combined(..) => {
+				self.estimate(dictionary, expr, default_field)
+					.num_candidates
+			}
 		}
 	}
 
-	fn eval_fuzzy(&self, dictionary: &Dictionary, value: &Literal) -> IntSet<SongKey> {
+	fn eval_fuzzy(&self, dictionary: &Dictionary, value: &Literal, budget: &Budget) -> Matches {
 		match value {
 			Literal::Text(s) => {
-				let mut songs = IntSet::default();
-				for field in self.text_fields.values() {
-					songs.extend(field.find_like(dictionary, s));
+				let mut matches = Matches::default();
+				for (field, _) in &self.text_fields {
+					if !matches_bare_text(field) {
+						continue;
+					}
+					let kind = match field {
+						TextField::Path => MatchKind::Path,
+						_ => MatchKind::Fuzzy,
+					};
+					for song in self.find_like(dictionary, field, s, budget) {
+						let entry = matches.entry(song).or_insert(kind);
+						*entry = (*entry).max(kind);
+					}
 				}
-				songs
+				matches
 			}
 			Literal::Number(n) => {
 				let mut songs = IntSet::default();
-				for field in self.number_fields.values() {
-					songs.extend(field.find(*n as i64, NumberOp::Eq));
+				for (field, index) in &self.number_fields {
+					if !matches_bare_numbers(field) {
+						continue;
+					}
+					songs.extend(index.find(*n as i64, NumberOp::Eq));
 				}
-				songs
-					.union(&self.eval_fuzzy(dictionary, &Literal::Text(n.to_string())))
-					.copied()
-					.collect()
+				let text = Literal::Text(n.to_string());
+				let mut matches = self.eval_fuzzy(dictionary, &text, budget);
+				matches.extend(songs.into_iter().map(|s| (s, MatchKind::Exact)));
+				matches
 			}
 		}
 	}
 
+	fn eval_fuzzy_fields(
+		&self,
+		dictionary: &Dictionary,
+		fields: &[TextField],
+		value: &Literal,
+		budget: &Budget,
+	) -> Matches {
+		let value = literal_text(value);
+		let mut songs = IntSet::default();
+		for field in fields {
+			songs.extend(self.find_like(dictionary, *field, &value, budget));
+		}
+		with_kind(songs, MatchKind::Fuzzy)
+	}
+
 	fn eval_text_operator(
 		&self,
 		dictionary: &Dictionary,
 		field: TextField,
 		operator: TextOp,
 		value: &str,
-	) -> IntSet<SongKey> {
+		budget: &Budget,
+	) -> Matches {
 		match operator {
-			TextOp::Eq => self.text_fields[field].find_exact(dictionary, value),
-			TextOp::Like => self.text_fields[field].find_like(dictionary, value),
+			// An empty value stands for songs without any value for the field
+			TextOp::Eq if value.is_empty() => self.eval_not_equal(self.text_fields[field].songs()),
+			TextOp::NotEq if value.is_empty() => {
+				with_kind(self.text_fields[field].songs(), MatchKind::Exact)
+			}
+			TextOp::Eq => with_kind(
+				self.text_fields[field].find_exact(dictionary, value),
+				MatchKind::Exact,
+			),
+			TextOp::NotEq => {
+				self.eval_not_equal(self.text_fields[field].find_exact(dictionary, value))
+			}
+			TextOp::Like => with_kind(
+				self.find_like(dictionary, field, value, budget),
+				MatchKind::Fuzzy,
+			),
 		}
 	}
 
-	fn eval_number_operator(
+	// Unknown custom fields match nothing, except for `!=` which matches every song
+	fn eval_custom_operator(
 		&self,
-		field: NumberField,
-		operator: NumberOp,
-		value: i32,
+		dictionary: &Dictionary,
+		key: &str,
+		operator: TextOp,
+		value: &str,
+		budget: &Budget,
+	) -> Matches {
+		let Some(index) = self.custom_fields.get(&key.to_lowercase()) else {
+			return match operator {
+				TextOp::NotEq => self.eval_not_equal(IntSet::default()),
+				TextOp::Eq | TextOp::Like => Matches::default(),
+			};
+		};
+		match operator {
+			TextOp::Eq => with_kind(index.find_exact(dictionary, value), MatchKind::Exact),
+			TextOp::NotEq => self.eval_not_equal(index.find_exact(dictionary, value)),
+			TextOp::Like => with_kind(
+				index.find_like(
+					dictionary,
+					value,
+					self.bigram_threshold,
+					Anchoring::Anywhere,
+					budget,
+				),
+				MatchKind::Fuzzy,
+			),
+		}
+	}
+
+	fn find_like(
+		&self,
+		dictionary: &Dictionary,
+		field: TextField,
+		value: &str,
+		budget: &Budget,
 	) -> IntSet<SongKey> {
-		self.number_fields[field].find(value as i64, operator)
+		let index = &self.text_fields[field];
+		let anchoring = match self.word_boundary_fields.contains(&field) {
+			true => Anchoring::WordStart,
+			false => Anchoring::Anywhere,
+		};
+		match field {
+			TextField::Path => index.find_like_within(
+				dictionary,
+				value,
+				self.bigram_threshold,
+				anchoring,
+				budget,
+				|p| self.path_indexing.apply(p),
+			),
+			_ => index.find_like(dictionary, value, self.bigram_threshold, anchoring, budget),
+		}
+	}
+
+	// Songs which do not have a value for a field count as not equal to anything
+	fn eval_not_equal(&self, equal: IntSet<SongKey>) -> Matches {
+		self.text_fields[TextField::Path]
+			.exact
+			.values()
+			.flatten()
+			.filter(|song| !equal.contains(song))
+			.map(|song| (*song, MatchKind::Exact))
+			.collect()
+	}
+
+	// Every song is indexed under its path
+	fn num_songs(&self) -> usize {
+		self.text_fields[TextField::Path]
+			.exact
+			.values()
+			.map(|songs| songs.len())
+			.sum()
+	}
+
+	fn eval_number_operator(&self, field: NumberField, operator: NumberOp, value: i64) -> Matches {
+		with_kind(
+			self.number_fields[field].find(value, operator),
+			MatchKind::Exact,
+		)
 	}
 }
 
 const BIGRAM_SIZE: usize = 2;
 const ASCII_RANGE: usize = u8::MAX as usize;
 
+// Bigrams of a sanitized value, skipping those which span a segment boundary
+fn bigrams<'a>(
+	normalization: &Normalization,
+	characters: &'a [char],
+) -> impl Iterator<Item = &'a [char]> + 'a {
+	let normalization = *normalization;
+	characters
+		.windows(BIGRAM_SIZE)
+		.filter(move |w| !normalization.is_boundary(w[0], w[1]))
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 struct TextFieldIndex {
 	exact: HashMap<Spur, IntSet<SongKey>>,
@@ -166,9 +894,18 @@ impl TextFieldIndex {
 		(a as usize) * ASCII_RANGE + (b as usize)
 	}
 
-	pub fn insert(&mut self, raw_value: &str, value: Spur, song: SongKey) {
-		let characters = sanitize(raw_value).chars().collect::<TinyVec<[char; 32]>>();
-		for substring in characters[..].windows(BIGRAM_SIZE) {
+	pub fn insert(
+		&mut self,
+		normalization: &Normalization,
+		raw_value: &str,
+		value: Spur,
+		song: SongKey,
+	) {
+		let characters = normalization
+			.apply(raw_value)
+			.chars()
+			.collect::<TinyVec<[char; 32]>>();
+		for substring in bigrams(normalization, &characters[..]) {
 			if substring.iter().all(|c| c.is_ascii()) {
 				let index = Self::ascii_bigram_to_index(substring[0], substring[1]);
 				self.ascii_bigrams[index].push((song, value));
@@ -183,40 +920,133 @@ impl TextFieldIndex {
 		self.exact.entry(value).or_default().insert(song);
 	}
 
-	pub fn find_like(&self, dictionary: &Dictionary, value: &str) -> IntSet<SongKey> {
-		let sanitized = sanitize(value);
+	fn bigram_songs(&self, bigram: &[char]) -> &[(SongKey, Spur)] {
+		if bigram.iter().all(|c| c.is_ascii()) {
+			let index = Self::ascii_bigram_to_index(bigram[0], bigram[1]);
+			&self.ascii_bigrams[index][..]
+		} else {
+			self.other_bigrams
+				.get::<[char; BIGRAM_SIZE]>(bigram.try_into().unwrap())
+				.map_or(&[][..], |v| &v[..])
+		}
+	}
+
+	// When strict, songs containing the least common bigram from the (sanitized) search term.
+	// Otherwise, songs containing enough of its bigrams to satisfy the threshold.
+	#[tracing::instrument(level = "trace", skip_all, fields(term = sanitized, candidates = Empty))]
+	fn find_like_candidates(
+		&self,
+		normalization: &Normalization,
+		sanitized: &str,
+		threshold: BigramThreshold,
+	) -> Cow<'_, [(SongKey, Spur)]> {
 		let characters = sanitized.chars().collect::<Vec<_>>();
-		let empty = Vec::new();
-
-		let candidates_by_bigram = characters[..]
-			.windows(BIGRAM_SIZE)
-			.map(|s| {
-				if s.iter().all(|c| c.is_ascii()) {
-					let index = Self::ascii_bigram_to_index(s[0], s[1]);
-					&self.ascii_bigrams[index]
-				} else {
-					self.other_bigrams
-						.get::<[char; BIGRAM_SIZE]>(s.try_into().unwrap())
-						.unwrap_or(&empty)
+		let bigrams = bigrams(normalization, &characters[..]);
+
+		let candidates = if threshold.is_strict() {
+			Cow::Borrowed(
+				bigrams
+					.map(|s| self.bigram_songs(s))
+					.min_by_key(|h| h.len())
+					.unwrap_or(&[]),
+			)
+		} else {
+			let bigrams = bigrams.collect::<HashSet<_>>();
+			let min_bigrams = threshold.min_bigrams(bigrams.len());
+			let mut num_bigrams = HashMap::<(SongKey, Spur), usize>::new();
+			for bigram in bigrams {
+				// Values containing a bigram several times are only counted once
+				let songs = self.bigram_songs(bigram).iter().collect::<HashSet<_>>();
+				for song in songs {
+					*num_bigrams.entry(*song).or_default() += 1;
 				}
-			})
-			.collect::<Vec<_>>();
+			}
+			Cow::Owned(
+				num_bigrams
+					.into_iter()
+					.filter(|(_song, n)| *n >= min_bigrams)
+					.map(|(song, _n)| song)
+					.collect(),
+			)
+		};
 
-		candidates_by_bigram
-			.into_iter()
-			.min_by_key(|h| h.len()) // Only check songs that contain the least common bigram from the search term
-			.unwrap_or(&empty)
+		Span::current().record("candidates", candidates.len());
+		candidates
+	}
+
+	fn find_like(
+		&self,
+		dictionary: &Dictionary,
+		value: &str,
+		threshold: BigramThreshold,
+		anchoring: Anchoring,
+		budget: &Budget,
+	) -> IntSet<SongKey> {
+		self.find_like_within(dictionary, value, threshold, anchoring, budget, |v| v)
+	}
+
+	// Only looks for the search term within the part of each value selected by `searchable`
+	fn find_like_within(
+		&self,
+		dictionary: &Dictionary,
+		value: &str,
+		threshold: BigramThreshold,
+		anchoring: Anchoring,
+		budget: &Budget,
+		searchable: impl Fn(&str) -> &str,
+	) -> IntSet<SongKey> {
+		let sanitized = dictionary.sanitize(value);
+		let characters = sanitized.chars().collect::<Vec<_>>();
+		let max_edits = threshold.max_edits(characters.len().saturating_sub(1));
+		self.find_like_candidates(&dictionary.normalization(), &sanitized, threshold)
 			.iter()
+			.take_while(|_| budget.spend())
 			.filter(|(_song_key, indexed_value)| {
-				// Only keep songs that actually contain the search term in full
-				let resolved = dictionary.resolve(indexed_value);
-				sanitize(resolved).contains(&sanitized)
+				// Only keep songs that actually contain the search term in full, or close enough
+				let searchable_value = searchable(dictionary.resolve(indexed_value));
+				match anchoring {
+					Anchoring::Anywhere => {
+						let resolved = dictionary.sanitize(searchable_value);
+						resolved.contains(&sanitized)
+							|| (max_edits > 0
+								&& substring_edit_distance(&characters, &resolved) <= max_edits)
+					}
+					Anchoring::WordStart => word_starts(searchable_value).any(|start| {
+						let resolved = dictionary.sanitize(&searchable_value[start..]);
+						resolved.starts_with(&sanitized)
+							|| (max_edits > 0
+								&& prefix_edit_distance(&characters, &resolved) <= max_edits)
+					}),
+				}
 			})
 			.map(|(k, _v)| k)
 			.copied()
 			.collect()
 	}
 
+	pub fn estimate_like(
+		&self,
+		dictionary: &Dictionary,
+		value: &str,
+		threshold: BigramThreshold,
+	) -> usize {
+		let normalization = dictionary.normalization();
+		self.find_like_candidates(&normalization, &dictionary.sanitize(value), threshold)
+			.len()
+	}
+
+	pub fn estimate_exact(&self, dictionary: &Dictionary, value: &str) -> usize {
+		dictionary
+			.get_canon(value)
+			.and_then(|s| self.exact.get(&s))
+			.map_or(0, |songs| songs.len())
+	}
+
+	// Songs with at least one value for this field
+	pub fn songs(&self) -> IntSet<SongKey> {
+		self.exact.values().flatten().copied().collect()
+	}
+
 	pub fn find_exact(&self, dictionary: &Dictionary, value: &str) -> IntSet<SongKey> {
 		dictionary
 			.get_canon(value)
@@ -224,6 +1054,16 @@ impl TextFieldIndex {
 			.cloned()
 			.unwrap_or_default()
 	}
+
+	pub fn find_exact_any(&self, dictionary: &Dictionary, values: &[String]) -> IntSet<SongKey> {
+		values
+			.iter()
+			.filter_map(|v| dictionary.get_canon(v))
+			.filter_map(|s| self.exact.get(&s))
+			.flatten()
+			.copied()
+			.collect()
+	}
 }
 
 #[derive(Clone, Default, Deserialize, Serialize)]
@@ -236,15 +1076,31 @@ impl NumberFieldIndex {
 		self.values.entry(value).or_default().insert(key);
 	}
 
-	pub fn find(&self, value: i64, operator: NumberOp) -> IntSet<SongKey> {
-		let range = match operator {
+	fn range(
+		&self,
+		value: i64,
+		operator: NumberOp,
+	) -> std::collections::btree_map::Range<'_, i64, IntSet<SongKey>> {
+		match operator {
 			NumberOp::Eq => self.values.range(value..=value),
 			NumberOp::Greater => self.values.range((value + 1)..),
 			NumberOp::GreaterOrEq => self.values.range(value..),
 			NumberOp::Less => self.values.range(..value),
 			NumberOp::LessOrEq => self.values.range(..=value),
-		};
-		let candidates = range.map(|(_n, songs)| songs).collect::<Vec<_>>();
+		}
+	}
+
+	pub fn count(&self, value: i64, operator: NumberOp) -> usize {
+		self.range(value, operator)
+			.map(|(_n, songs)| songs.len())
+			.sum()
+	}
+
+	pub fn find(&self, value: i64, operator: NumberOp) -> IntSet<SongKey> {
+		let candidates = self
+			.range(value, operator)
+			.map(|(_n, songs)| songs)
+			.collect::<Vec<_>>();
 		let mut results = Vec::with_capacity(candidates.iter().map(|c| c.len()).sum());
 		candidates
 			.into_iter()
@@ -255,18 +1111,52 @@ impl NumberFieldIndex {
 
 #[derive(Clone, Default)]
 pub struct Builder {
+	normalization: Normalization,
+	path_indexing: PathIndexing,
+	bigram_threshold: BigramThreshold,
+	word_boundary_fields: Vec<TextField>,
 	text_fields: EnumMap<TextField, TextFieldIndex>,
 	number_fields: EnumMap<NumberField, NumberFieldIndex>,
+	bool_fields: EnumMap<BoolField, IntSet<SongKey>>,
+	custom_fields: HashMap<String, TextFieldIndex>,
 }
 
 impl Builder {
-	pub fn add_song(&mut self, scanner_song: &scanner::Song, storage_song: &storage::Song) {
+	pub fn new(normalization: Normalization) -> Self {
+		Self {
+			normalization,
+			..Default::default()
+		}
+	}
+
+	pub fn with_path_indexing(mut self, path_indexing: PathIndexing) -> Self {
+		self.path_indexing = path_indexing;
+		self
+	}
+
+	pub fn with_bigram_threshold(mut self, bigram_threshold: BigramThreshold) -> Self {
+		self.bigram_threshold = bigram_threshold;
+		self
+	}
+
+	pub fn with_word_boundary_fields(mut self, fields: Vec<TextField>) -> Self {
+		self.word_boundary_fields = fields;
+		self
+	}
+
+	pub fn add_song(
+		&mut self,
+		dictionary_builder: &dictionary::Builder,
+		scanner_song: &scanner::Song,
+		storage_song: &storage::Song,
+	) {
 		let song_key = SongKey {
 			virtual_path: storage_song.virtual_path,
 		};
+		let normalization = &self.normalization;
 
 		if let (Some(str), Some(spur)) = (&scanner_song.album, storage_song.album) {
-			self.text_fields[TextField::Album].insert(str, spur, song_key);
+			self.text_fields[TextField::Album].insert(normalization, str, spur, song_key);
 		}
 
 		for (str, artist_key) in scanner_song
@@ -274,11 +1164,25 @@ impl Builder {
 			.iter()
 			.zip(storage_song.album_artists.iter())
 		{
-			self.text_fields[TextField::AlbumArtist].insert(str, artist_key.0, song_key);
+			self.text_fields[TextField::AlbumArtist].insert(
+				normalization,
+				str,
+				artist_key.0,
+				song_key,
+			);
 		}
 
 		for (str, artist_key) in scanner_song.artists.iter().zip(storage_song.artists.iter()) {
-			self.text_fields[TextField::Artist].insert(str, artist_key.0, song_key);
+			self.text_fields[TextField::Artist].insert(normalization, str, artist_key.0, song_key);
+		}
+
+		if let (Some(str), Some(spur)) = (&scanner_song.codec, storage_song.codec) {
+			self.text_fields[TextField::Codec].insert(normalization, str, spur, song_key);
+		}
+
+		let extension = scanner_song.real_path.extension().and_then(|e| e.to_str());
+		if let (Some(str), Some(spur)) = (extension, storage_song.extension) {
+			self.text_fields[TextField::Extension].insert(normalization, str, spur, song_key);
 		}
 
 		for (str, artist_key) in scanner_song
@@ -286,7 +1190,36 @@ impl Builder {
 			.iter()
 			.zip(storage_song.composers.iter())
 		{
-			self.text_fields[TextField::Composer].insert(str, artist_key.0, song_key);
+			self.text_fields[TextField::Composer].insert(
+				normalization,
+				str,
+				artist_key.0,
+				song_key,
+			);
+		}
+
+		for (str, spur) in scanner_song
+			.conductors
+			.iter()
+			.zip(storage_song.conductors.iter())
+		{
+			self.text_fields[TextField::Conductor].insert(normalization, str, *spur, song_key);
+		}
+
+		for (str, spur) in scanner_song
+			.ensembles
+			.iter()
+			.zip(storage_song.ensembles.iter())
+		{
+			self.text_fields[TextField::Ensemble].insert(normalization, str, *spur, song_key);
+		}
+
+		if let Some(bitrate) = &scanner_song.bitrate {
+			self.number_fields[NumberField::Bitrate].insert(*bitrate, song_key);
+		}
+
+		if let Some(channels) = &scanner_song.channels {
+			self.number_fields[NumberField::Channels].insert(*channels, song_key);
 		}
 
 		if let Some(disc_number) = &scanner_song.disc_number {
@@ -294,11 +1227,15 @@ impl Builder {
 		}
 
 		for (str, spur) in scanner_song.genres.iter().zip(storage_song.genres.iter()) {
-			self.text_fields[TextField::Genre].insert(str, *spur, song_key);
+			self.text_fields[TextField::Genre].insert(normalization, str, *spur, song_key);
+		}
+
+		if let (Some(str), Some(spur)) = (&scanner_song.grouping, storage_song.grouping) {
+			self.text_fields[TextField::Grouping].insert(normalization, str, spur, song_key);
 		}
 
 		for (str, spur) in scanner_song.labels.iter().zip(storage_song.labels.iter()) {
-			self.text_fields[TextField::Label].insert(str, *spur, song_key);
+			self.text_fields[TextField::Label].insert(normalization, str, *spur, song_key);
 		}
 
 		for (str, artist_key) in scanner_song
@@ -306,17 +1243,28 @@ impl Builder {
 			.iter()
 			.zip(storage_song.lyricists.iter())
 		{
-			self.text_fields[TextField::Lyricist].insert(str, artist_key.0, song_key);
+			self.text_fields[TextField::Lyricist].insert(
+				normalization,
+				str,
+				artist_key.0,
+				song_key,
+			);
 		}
 
 		self.text_fields[TextField::Path].insert(
-			scanner_song.virtual_path.to_string_lossy().as_ref(),
+			normalization,
+			self.path_indexing
+				.apply(scanner_song.virtual_path.to_string_lossy().as_ref()),
 			storage_song.virtual_path.0,
 			song_key,
 		);
 
 		if let (Some(str), Some(spur)) = (&scanner_song.title, storage_song.title) {
-			self.text_fields[TextField::Title].insert(str, spur, song_key);
+			self.text_fields[TextField::Title].insert(normalization, str, spur, song_key);
+		}
+
+		if let (Some(str), Some(spur)) = (&scanner_song.work, storage_song.work) {
+			self.text_fields[TextField::Work].insert(normalization, str, spur, song_key);
 		}
 
 		if let Some(track_number) = &scanner_song.track_number {
@@ -326,12 +1274,51 @@ impl Builder {
 		if let Some(year) = &scanner_song.year {
 			self.number_fields[NumberField::Year].insert(*year, song_key);
 		}
+
+		if let Some(original_year) = &scanner_song.original_year {
+			self.number_fields[NumberField::OriginalYear].insert(*original_year, song_key);
+		}
+
+		if let Some(rating) = &scanner_song.rating {
+			self.number_fields[NumberField::Rating].insert(*rating, song_key);
+		}
+
+		self.number_fields[NumberField::DateAdded].insert(scanner_song.date_added, song_key);
+
+		if scanner_song.artwork.as_ref() == Some(&scanner_song.virtual_path) {
+			self.bool_fields[BoolField::Artwork].insert(song_key);
+		}
+
+		if let Some(lossless) = scanner_song.lossless {
+			let field = match lossless {
+				true => BoolField::Lossless,
+				false => BoolField::Lossy,
+			};
+			self.bool_fields[field].insert(song_key);
+		}
+
+		// Pairs whose key or value is blank are left out of storage songs, so the
+		// scanner song's pairs cannot be lined up with them
+		for (key, spur) in &storage_song.custom_fields {
+			let key = dictionary_builder.resolve(key).to_lowercase();
+			let str = dictionary_builder.resolve(spur);
+			self.custom_fields
+				.entry(key)
+				.or_default()
+				.insert(normalization, str, *spur, song_key);
+		}
 	}
 
+	#[tracing::instrument(name = "build_search", level = "debug", skip_all)]
 	pub fn build(self) -> Search {
 		Search {
 			text_fields: self.text_fields,
 			number_fields: self.number_fields,
+			bool_fields: self.bool_fields,
+			custom_fields: self.custom_fields,
+			path_indexing: self.path_indexing,
+			bigram_threshold: self.bigram_threshold,
+			word_boundary_fields: self.word_boundary_fields,
 		}
 	}
 }
@@ -341,7 +1328,6 @@ mod test {
 	use std::path::PathBuf;
 
 	use super::*;
-	use crate::app::index::dictionary;
 	use collection::Collection;
 	use storage::store_song;
 
@@ -353,23 +1339,34 @@ mod test {
 
 	impl Context {
 		pub fn search(&self, query: &str) -> Vec<PathBuf> {
-			self.search
-				.find_songs(&self.collection, &self.dictionary, query)
-				.unwrap()
+			self.search_with(query, SearchOptions::default()).unwrap()
+		}
+
+		pub fn search_with(
+			&self,
+			query: &str,
+			options: SearchOptions,
+		) -> Result<Vec<PathBuf>, Error> {
+			Ok(self
+				.search
+				.find_songs(&self.collection, &self.dictionary, query, options)?
 				.into_iter()
-				.map(|s| s.virtual_path)
-				.collect()
+				.map(|(s, _)| s.virtual_path)
+				.collect())
 		}
 	}
 
 	fn setup_test(songs: Vec<scanner::Song>) -> Context {
-		let mut dictionary_builder = dictionary::Builder::default();
+		setup_test_with_builder(songs, Builder::default())
+	}
+
+	fn setup_test_with_builder(songs: Vec<scanner::Song>, mut search_builder: Builder) -> Context {
+		let mut dictionary_builder = dictionary::Builder::new(search_builder.normalization);
 		let mut collection_builder = collection::Builder::default();
-		let mut search_builder = Builder::default();
 		for song in songs {
 			let storage_song = store_song(&mut dictionary_builder, &song).unwrap();
 			collection_builder.add_song(&storage_song);
-			search_builder.add_song(&song, &storage_song);
+			search_builder.add_song(&dictionary_builder, &song, &storage_song);
 		}
 
 		Context {
@@ -409,7 +1406,7 @@ mod test {
 	}
 
 	#[test]
-	fn can_find_field_like() {
+	fn can_restrict_fuzzy_to_default_field() {
 		let ctx = setup_test(vec![
 			scanner::Song {
 				virtual_path: PathBuf::from("seasons.mp3"),
@@ -425,30 +1422,83 @@ mod test {
 			},
 		]);
 
-		let songs = ctx.search("artist % agon");
-		assert_eq!(songs.len(), 1);
-		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+		let songs = ctx.search("dragonf");
+		assert_eq!(songs.len(), 2);
+
+		let songs = ctx
+			.search_with(
+				"dragonf",
+				SearchOptions {
+					default_field: Some(TextField::Title),
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("potd.mp3")]);
+
+		let songs = ctx
+			.search_with(
+				"artist % dragonf",
+				SearchOptions {
+					default_field: Some(TextField::Title),
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("seasons.mp3")]);
 	}
 
 	#[test]
-	fn text_is_case_insensitive() {
-		let ctx = setup_test(vec![scanner::Song {
-			virtual_path: PathBuf::from("seasons.mp3"),
-			artists: vec!["Dragonforce".to_owned()],
-			..Default::default()
-		}]);
+	fn can_restrict_fuzzy_to_query_fields() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("seasons.mp3"),
+				title: Some("Seasons".to_owned()),
+				artists: vec!["Dragonforce".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("potd.mp3"),
+				title: Some("Power of the Dragonflame".to_owned()),
+				artists: vec!["Rhapsody".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("legend.mp3"),
+				album: Some("Dragonfly".to_owned()),
+				artists: vec!["Rhapsody".to_owned()],
+				..Default::default()
+			},
+		]);
 
-		let songs = ctx.search("dragonforce");
-		assert_eq!(songs.len(), 1);
-		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+		let songs = ctx.search("@title: dragonf");
+		assert_eq!(songs, vec![PathBuf::from("potd.mp3")]);
 
-		let songs = ctx.search("artist = dragonforce");
-		assert_eq!(songs.len(), 1);
+		let songs = ctx.search("@title,artist: dragonf");
+		assert_eq!(songs.len(), 2);
 		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+		assert!(songs.contains(&PathBuf::from("potd.mp3")));
+
+		// Query fields take precedence over the default field
+		let songs = ctx
+			.search_with(
+				"@album: dragonf",
+				SearchOptions {
+					default_field: Some(TextField::Title),
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("legend.mp3")]);
+
+		assert!(matches!(
+			ctx.search_with("@mood: chill", SearchOptions::default()),
+			Err(Error::SearchQueryParseError(_))
+		));
 	}
 
 	#[test]
-	fn can_find_field_exact() {
+	fn can_count_fuzzy_matches_per_field() {
 		let ctx = setup_test(vec![
 			scanner::Song {
 				virtual_path: PathBuf::from("seasons.mp3"),
@@ -460,83 +1510,1021 @@ mod test {
 				virtual_path: PathBuf::from("potd.mp3"),
 				title: Some("Power of the Dragonflame".to_owned()),
 				artists: vec!["Rhapsody".to_owned()],
+				album: Some("Power of the Dragonflame".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("calcium.mp3"),
+				title: Some("Calcium".to_owned()),
+				artists: vec!["FSOL".to_owned()],
 				..Default::default()
 			},
 		]);
 
-		let songs = ctx.search("artist = Dragon");
-		assert!(songs.is_empty());
+		let counts = ctx.search.count_fuzzy_matches(&ctx.dictionary, "dragon");
+		assert_eq!(counts[TextField::Artist], 1);
+		assert_eq!(counts[TextField::Title], 1);
+		assert_eq!(counts[TextField::Album], 1);
+		assert_eq!(counts[TextField::Genre], 0);
+	}
 
-		let songs = ctx.search("artist = Dragonforce");
-		assert_eq!(songs.len(), 1);
-		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+	#[test]
+	fn can_get_album_songs() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("b.mp3"),
+				album: Some("Power of the Dragonflame".to_owned()),
+				track_number: Some(2),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("a.mp3"),
+				album: Some("Power of the Dragonflame".to_owned()),
+				track_number: Some(1),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("c.mp3"),
+				album: Some("Symphony of Enchanted Lands".to_owned()),
+				track_number: Some(1),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx
+			.search
+			.get_album_songs(&ctx.collection, &ctx.dictionary, "power of the dragonflame")
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("a.mp3"), PathBuf::from("b.mp3")]);
+
+		let missing = ctx.search.get_album_songs(
+			&ctx.collection,
+			&ctx.dictionary,
+			"Rain of a Thousand Flames",
+		);
+		assert!(matches!(missing, Err(Error::AlbumNotFound)));
 	}
 
 	#[test]
-	fn can_query_number_fields() {
+	fn can_list_field_values() {
 		let ctx = setup_test(vec![
 			scanner::Song {
-				virtual_path: PathBuf::from("1999.mp3"),
-				year: Some(1999),
+				virtual_path: PathBuf::from("a.mp3"),
+				genres: vec!["Metal".to_owned(), "Ambient".to_owned()],
 				..Default::default()
 			},
 			scanner::Song {
-				virtual_path: PathBuf::from("2000.mp3"),
-				year: Some(2000),
+				virtual_path: PathBuf::from("b.mp3"),
+				genres: vec!["metal".to_owned(), "Electronic".to_owned()],
 				..Default::default()
 			},
+		]);
+
+		let genres = ctx
+			.search
+			.get_values(&ctx.dictionary, TextField::Genre, 0, usize::MAX);
+		assert_eq!(genres, vec!["Ambient", "Electronic", "Metal"]);
+
+		let genres = ctx
+			.search
+			.get_values(&ctx.dictionary, TextField::Genre, 1, 1);
+		assert_eq!(genres, vec!["Electronic"]);
+	}
+
+	#[test]
+	fn reports_match_kind() {
+		let ctx = setup_test(vec![
 			scanner::Song {
-				virtual_path: PathBuf::from("2001.mp3"),
-				year: Some(2001),
+				virtual_path: PathBuf::from("seasons.mp3"),
+				track_number: Some(2),
+				title: Some("Seasons".to_owned()),
+				artists: vec!["Dragonforce".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("fury.mp3"),
+				track_number: Some(1),
+				title: Some("Fury of the Storm".to_owned()),
+				artists: vec!["Dragonforce".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("seasonal.mp3"),
+				title: Some("Seasonal".to_owned()),
+				artists: vec!["Other Band".to_owned()],
 				..Default::default()
 			},
 		]);
 
-		let songs = ctx.search("year=2000");
-		assert_eq!(songs.len(), 1);
-		assert!(songs.contains(&PathBuf::from("2000.mp3")));
+		let match_kinds = |query: &str| -> Vec<(PathBuf, MatchKind)> {
+			ctx.search
+				.find_songs(
+					&ctx.collection,
+					&ctx.dictionary,
+					query,
+					SearchOptions::default(),
+				)
+				.unwrap()
+				.into_iter()
+				.map(|(s, kind)| (s.virtual_path, kind))
+				.collect()
+		};
 
-		let songs = ctx.search("year>2000");
-		assert_eq!(songs.len(), 1);
-		assert!(songs.contains(&PathBuf::from("2001.mp3")));
+		let songs = match_kinds("artist = Dragonforce || title % seas");
+		assert_eq!(
+			songs,
+			vec![
+				(PathBuf::from("fury.mp3"), MatchKind::Exact),
+				(PathBuf::from("seasons.mp3"), MatchKind::Exact),
+				(PathBuf::from("seasonal.mp3"), MatchKind::Fuzzy),
+			]
+		);
 
-		let songs = ctx.search("year<2000");
-		assert_eq!(songs.len(), 1);
-		assert!(songs.contains(&PathBuf::from("1999.mp3")));
+		let songs = match_kinds("artist = Dragonforce && title % seas");
+		assert_eq!(
+			songs,
+			vec![(PathBuf::from("seasons.mp3"), MatchKind::Exact)]
+		);
 
-		let songs = ctx.search("year>=2000");
-		assert_eq!(songs.len(), 2);
-		assert!(songs.contains(&PathBuf::from("2000.mp3")));
-		assert!(songs.contains(&PathBuf::from("2001.mp3")));
+		let songs = match_kinds("seas");
+		assert!(songs.iter().all(|(_, kind)| *kind == MatchKind::Fuzzy));
+	}
+
+	#[test]
+	fn enforces_min_fuzzy_length() {
+		let ctx = setup_test(vec![scanner::Song {
+			virtual_path: PathBuf::from("seasons.mp3"),
+			title: Some("Seasons".to_owned()),
+			artists: vec!["Dragonforce".to_owned()],
+			..Default::default()
+		}]);
+
+		let songs = ctx
+			.search_with(
+				"sea",
+				SearchOptions {
+					min_fuzzy_length: 3,
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("seasons.mp3")]);
+
+		let songs = ctx
+			.search_with(
+				"dragon && sea",
+				SearchOptions {
+					min_fuzzy_length: 3,
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("seasons.mp3")]);
+
+		let result = ctx.search_with(
+			"se",
+			SearchOptions {
+				min_fuzzy_length: 3,
+				..Default::default()
+			},
+		);
+		assert!(matches!(result, Err(Error::SearchQueryTooShort(3))));
+
+		let result = ctx.search_with(
+			"dragon && se",
+			SearchOptions {
+				min_fuzzy_length: 3,
+				..Default::default()
+			},
+		);
+		assert!(matches!(result, Err(Error::SearchQueryTooShort(3))));
+
+		let result = ctx.search_with(
+			"se",
+			SearchOptions {
+				default_field: Some(TextField::Title),
+				min_fuzzy_length: 3,
+				..Default::default()
+			},
+		);
+		assert!(matches!(result, Err(Error::SearchQueryTooShort(3))));
+	}
+
+	#[test]
+	fn slow_queries_time_out() {
+		let songs = (0..2000)
+			.map(|i| scanner::Song {
+				virtual_path: PathBuf::from(format!("{i}.mp3")),
+				title: Some(format!("Variation {i} on a theme")),
+				..Default::default()
+			})
+			.collect();
+		// A low threshold makes nearly every value a candidate needing an edit distance check
+		let ctx = setup_test_with_builder(
+			songs,
+			Builder::default().with_bigram_threshold(BigramThreshold::new(10)),
+		);
+		let search = |limits| {
+			ctx.search.find_songs(
+				&ctx.collection,
+				&ctx.dictionary,
+				"variations on a theme by haydn",
+				SearchOptions {
+					limits,
+					..Default::default()
+				},
+			)
+		};
+
+		let result = search(SearchLimits {
+			timeout: Some(Duration::ZERO),
+			max_candidates: None,
+		});
+		assert!(matches!(result, Err(Error::SearchQueryTimeout)));
+
+		let result = search(SearchLimits {
+			timeout: None,
+			max_candidates: Some(100),
+		});
+		assert!(matches!(result, Err(Error::SearchQueryTimeout)));
+
+		let result = search(SearchLimits {
+			timeout: Some(Duration::from_secs(600)),
+			max_candidates: Some(100_000),
+		});
+		assert!(result.is_ok());
+	}
+
+	#[test]
+	fn relaxed_bigram_threshold_tolerates_typos() {
+		let songs = || {
+			vec![
+				scanner::Song {
+					virtual_path: PathBuf::from("diamond.mp3"),
+					title: Some("Black Diamond".to_owned()),
+					artists: vec!["Stratovarius".to_owned()],
+					..Default::default()
+				},
+				scanner::Song {
+					virtual_path: PathBuf::from("guitar.mp3"),
+					title: Some("Stratocaster".to_owned()),
+					artists: vec!["FSOL".to_owned()],
+					..Default::default()
+				},
+			]
+		};
+
+		let ctx = setup_test(songs());
+		assert!(ctx.search("stratovarious").is_empty());
+		assert_eq!(
+			ctx.search("stratovarius"),
+			vec![PathBuf::from("diamond.mp3")]
+		);
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::default().with_bigram_threshold(BigramThreshold::new(80)),
+		);
+		assert_eq!(
+			ctx.search("stratovarious"),
+			vec![PathBuf::from("diamond.mp3")]
+		);
+		assert_eq!(
+			ctx.search("stratovarius"),
+			vec![PathBuf::from("diamond.mp3")]
+		);
+		assert!(ctx.search("stradivarius").is_empty());
+	}
+
+	#[test]
+	fn measures_substring_edit_distance() {
+		let term = "varius".chars().collect::<Vec<_>>();
+		assert_eq!(substring_edit_distance(&term, "stratovarius"), 0);
+		assert_eq!(substring_edit_distance(&term, "stratovarious"), 1);
+		assert_eq!(substring_edit_distance(&term, "stratovrius"), 1);
+		assert_eq!(substring_edit_distance(&term, "ius"), 3);
+		assert_eq!(substring_edit_distance(&term, ""), 6);
+	}
+
+	#[test]
+	fn field_queries_ignore_min_fuzzy_length() {
+		let ctx = setup_test(vec![scanner::Song {
+			virtual_path: PathBuf::from("seasons.mp3"),
+			title: Some("Seasons".to_owned()),
+			year: Some(1999),
+			..Default::default()
+		}]);
+
+		let songs = ctx
+			.search_with(
+				"title % se",
+				SearchOptions {
+					min_fuzzy_length: 3,
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("seasons.mp3")]);
+
+		let songs = ctx
+			.search_with(
+				"year > 5",
+				SearchOptions {
+					min_fuzzy_length: 3,
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("seasons.mp3")]);
+	}
+
+	#[test]
+	fn can_list_distinct_values() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("a.mp3"),
+				genres: vec!["Metal".to_owned(), "Ambient".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("b.mp3"),
+				genres: vec!["metal".to_owned(), "Electronic".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("c.mp3"),
+				genres: vec!["Electronic".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("d.mp3"),
+				genres: vec!["Metal".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		let genres = ctx.search.distinct_values(
+			&ctx.dictionary,
+			TextField::Genre,
+			ValueOrdering::Alphabetical,
+		);
+		assert_eq!(
+			genres,
+			vec![
+				("Ambient".to_owned(), 1),
+				("Electronic".to_owned(), 2),
+				("Metal".to_owned(), 3),
+			]
+		);
+
+		let genres =
+			ctx.search
+				.distinct_values(&ctx.dictionary, TextField::Genre, ValueOrdering::SongCount);
+		assert_eq!(
+			genres,
+			vec![
+				("Metal".to_owned(), 3),
+				("Electronic".to_owned(), 2),
+				("Ambient".to_owned(), 1),
+			]
+		);
+	}
+
+	#[test]
+	fn can_find_field_like() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("seasons.mp3"),
+				title: Some("Seasons".to_owned()),
+				artists: vec!["Dragonforce".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("potd.mp3"),
+				title: Some("Power of the Dragonflame".to_owned()),
+				artists: vec!["Rhapsody".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("artist % agon");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+	}
+
+	#[test]
+	fn text_is_case_insensitive() {
+		let ctx = setup_test(vec![scanner::Song {
+			virtual_path: PathBuf::from("seasons.mp3"),
+			artists: vec!["Dragonforce".to_owned()],
+			..Default::default()
+		}]);
+
+		let songs = ctx.search("dragonforce");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+
+		let songs = ctx.search("artist = dragonforce");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+	}
+
+	#[test]
+	fn can_match_list_of_values() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("emerald sword.mp3"),
+				artists: vec!["Rhapsody".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("valhalla.mp3"),
+				artists: vec!["Blind Guardian".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("primo victoria.mp3"),
+				artists: vec!["Sabaton".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("fear of the dark.mp3"),
+				artists: vec!["Iron Maiden".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search(r#"artist = (rhapsody, "blind guardian", sabaton)"#);
+		assert_eq!(songs.len(), 3);
+		assert!(songs.contains(&PathBuf::from("emerald sword.mp3")));
+		assert!(songs.contains(&PathBuf::from("valhalla.mp3")));
+		assert!(songs.contains(&PathBuf::from("primo victoria.mp3")));
+
+		let songs = ctx.search(r#"artist = (rhapsody, dragonforce)"#);
+		assert_eq!(songs, vec![PathBuf::from("emerald sword.mp3")]);
+	}
+
+	#[test]
+	fn can_query_custom_fields() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("lullaby.mp3"),
+				custom_fields: vec![
+					("mood".to_owned(), "chill".to_owned()),
+					("occasion".to_owned(), "bedtime".to_owned()),
+				],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("anthem.mp3"),
+				custom_fields: vec![
+					("mood".to_owned(), "energetic".to_owned()),
+					("mood".to_owned(), "epic".to_owned()),
+				],
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("custom:mood = chill");
+		assert_eq!(songs, vec![PathBuf::from("lullaby.mp3")]);
+
+		let songs = ctx.search("custom:MOOD = epic");
+		assert_eq!(songs, vec![PathBuf::from("anthem.mp3")]);
+
+		let songs = ctx.search("custom:occasion % bed");
+		assert_eq!(songs, vec![PathBuf::from("lullaby.mp3")]);
+
+		let songs = ctx.search("custom:tempo = fast");
+		assert!(songs.is_empty());
+	}
+
+	#[test]
+	fn custom_fields_with_blank_keys_are_ignored() {
+		let ctx = setup_test(vec![scanner::Song {
+			virtual_path: PathBuf::from("lullaby.mp3"),
+			custom_fields: vec![
+				("".to_owned(), "ignored".to_owned()),
+				("mood".to_owned(), "chill".to_owned()),
+				("tempo".to_owned(), "".to_owned()),
+				("occasion".to_owned(), "bedtime".to_owned()),
+			],
+			..Default::default()
+		}]);
+
+		let songs = ctx.search("custom:mood = chill");
+		assert_eq!(songs, vec![PathBuf::from("lullaby.mp3")]);
+
+		let songs = ctx.search("custom:occasion = bedtime");
+		assert_eq!(songs, vec![PathBuf::from("lullaby.mp3")]);
+
+		let songs = ctx.search("custom:mood = ignored");
+		assert!(songs.is_empty());
+
+		let songs = ctx.search("custom:tempo = bedtime");
+		assert!(songs.is_empty());
+	}
+
+	#[test]
+	fn can_find_creator_alias() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("cantata.mp3"),
+				title: Some("Cantata BWV 140".to_owned()),
+				artists: vec!["Amsterdam Baroque Orchestra".to_owned()],
+				composers: vec!["Johann Sebastian Bach".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("bach.mp3"),
+				title: Some("Bach".to_owned()),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("creator % bach");
+		assert_eq!(songs, vec![PathBuf::from("cantata.mp3")]);
+	}
+
+	#[test]
+	fn punctuation_variants_match() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("dont.mp3"),
+				title: Some("Don’t Stop".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("sounds.mp3"),
+				artists: vec!["Simon & Garfunkel".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("rapper.mp3"),
+				genres: vec!["Hip-Hop".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		assert_eq!(ctx.search("don't"), vec![PathBuf::from("dont.mp3")]);
+		assert_eq!(ctx.search("dont"), vec![PathBuf::from("dont.mp3")]);
+		assert_eq!(
+			ctx.search(r#""simon&garfunkel""#),
+			vec![PathBuf::from("sounds.mp3")]
+		);
+		assert_eq!(
+			ctx.search(r#"artist = "Simon&Garfunkel""#),
+			vec![PathBuf::from("sounds.mp3")]
+		);
+		assert_eq!(
+			ctx.search(r#""hip hop""#),
+			vec![PathBuf::from("rapper.mp3")]
+		);
+		assert_eq!(ctx.search("hiphop"), vec![PathBuf::from("rapper.mp3")]);
+		assert_eq!(
+			ctx.search("genre = hip–hop"),
+			vec![PathBuf::from("rapper.mp3")]
+		);
+	}
+
+	#[test]
+	fn hyphenated_and_spaced_values_match_each_other() {
+		let songs = || {
+			vec![
+				scanner::Song {
+					virtual_path: PathBuf::from("hyphenated.mp3"),
+					genres: vec!["Post-Rock".to_owned()],
+					..Default::default()
+				},
+				scanner::Song {
+					virtual_path: PathBuf::from("spaced.mp3"),
+					genres: vec!["Post Rock".to_owned()],
+					..Default::default()
+				},
+			]
+		};
+		let both = vec![PathBuf::from("hyphenated.mp3"), PathBuf::from("spaced.mp3")];
+		let sorted = |mut paths: Vec<PathBuf>| {
+			paths.sort();
+			paths
+		};
+
+		let ctx = setup_test(songs());
+		for query in [
+			"post-rock",
+			r#""post rock""#,
+			"postrock",
+			"genre = post-rock",
+			r#"genre = "post rock""#,
+			"genre = postrock",
+		] {
+			assert_eq!(sorted(ctx.search(query)), both, "{query}");
+		}
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::new(Normalization {
+				strip_whitespace: false,
+				..Default::default()
+			}),
+		);
+		for query in [
+			"genre = post-rock",
+			r#"genre = "post rock""#,
+			"genre = post—rock",
+		] {
+			assert_eq!(sorted(ctx.search(query)), both, "{query}");
+		}
+		assert!(ctx.search("genre = postrock").is_empty());
+	}
+
+	#[test]
+	fn respects_normalization() {
+		let songs = || {
+			vec![scanner::Song {
+				virtual_path: PathBuf::from("rapper.mp3"),
+				genres: vec!["Hip Hop".to_owned()],
+				..Default::default()
+			}]
+		};
+
+		let ctx = setup_test(songs());
+		assert_eq!(ctx.search("hiphop"), vec![PathBuf::from("rapper.mp3")]);
+		assert_eq!(
+			ctx.search("genre = hiphop"),
+			vec![PathBuf::from("rapper.mp3")]
+		);
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::new(Normalization {
+				strip_whitespace: false,
+				..Default::default()
+			}),
+		);
+		assert!(ctx.search("hiphop").is_empty());
+		assert!(ctx.search("genre = hiphop").is_empty());
+		assert_eq!(
+			ctx.search(r#"genre = "hip   hop""#),
+			vec![PathBuf::from("rapper.mp3")]
+		);
+	}
+
+	#[test]
+	fn can_segment_bigrams_by_script() {
+		let ctx = setup_test_with_builder(
+			vec![
+				scanner::Song {
+					virtual_path: PathBuf::from("tokyo.mp3"),
+					title: Some("Tokyo 東京".to_owned()),
+					..Default::default()
+				},
+				scanner::Song {
+					virtual_path: PathBuf::from("kyoto.mp3"),
+					title: Some("Kyoto 京都".to_owned()),
+					..Default::default()
+				},
+			],
+			Builder::new(Normalization {
+				segment_by_script: true,
+				..Default::default()
+			}),
+		);
+
+		assert_eq!(ctx.search("tokyo"), vec![PathBuf::from("tokyo.mp3")]);
+		assert_eq!(ctx.search("東京"), vec![PathBuf::from("tokyo.mp3")]);
+		assert_eq!(ctx.search("title % 東京"), vec![PathBuf::from("tokyo.mp3")]);
+		assert_eq!(ctx.search("tokyo 東京"), vec![PathBuf::from("tokyo.mp3")]);
+		assert_eq!(ctx.search("京都"), vec![PathBuf::from("kyoto.mp3")]);
+	}
+
+	#[test]
+	fn can_find_field_exact() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("seasons.mp3"),
+				title: Some("Seasons".to_owned()),
+				artists: vec!["Dragonforce".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("potd.mp3"),
+				title: Some("Power of the Dragonflame".to_owned()),
+				artists: vec!["Rhapsody".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("artist = Dragon");
+		assert!(songs.is_empty());
+
+		let songs = ctx.search("artist = Dragonforce");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("seasons.mp3")));
+	}
+
+	#[test]
+	fn not_equal_matches_songs_without_the_field() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("podcast.mp3"),
+				genres: vec!["Podcast".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("metal.mp3"),
+				genres: vec!["Metal".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("untagged.mp3"),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("metal podcast.mp3"),
+				genres: vec!["Metal".to_owned(), "Podcast".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		// Songs with several values are excluded if any of them is equal
+		let songs = ctx.search("genre != podcast");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("metal.mp3")));
+		assert!(songs.contains(&PathBuf::from("untagged.mp3")));
+
+		let songs = ctx.search("genre != jazz");
+		assert_eq!(songs.len(), 4);
+
+		let songs = ctx.search("custom:mood != chill");
+		assert_eq!(songs.len(), 4);
+	}
+
+	#[test]
+	fn can_query_date_added() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("old.mp3"),
+				date_added: 1_700_000_000, // 2023-11-14
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("new_year.mp3"),
+				date_added: 1_704_067_200, // 2024-01-01 00:00:00
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("recent.mp3"),
+				date_added: 1_706_000_000, // 2024-01-23
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("added >= 2024-01-01");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("new_year.mp3")));
+		assert!(songs.contains(&PathBuf::from("recent.mp3")));
+
+		assert_eq!(
+			ctx.search("added > 2024-01-01"),
+			vec![PathBuf::from("recent.mp3")]
+		);
+		assert_eq!(
+			ctx.search("added < 2024-01-01"),
+			vec![PathBuf::from("old.mp3")]
+		);
+		assert_eq!(
+			ctx.search("added >= 2024-01-01 && added < 2024-01-02"),
+			vec![PathBuf::from("new_year.mp3")]
+		);
+		assert!(ctx.search("1700000000").is_empty());
+	}
+
+	#[test]
+	fn can_query_number_fields() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("1999.mp3"),
+				year: Some(1999),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("2000.mp3"),
+				year: Some(2000),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("2001.mp3"),
+				year: Some(2001),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("year=2000");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("2000.mp3")));
+
+		let songs = ctx.search("year>2000");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("2001.mp3")));
+
+		let songs = ctx.search("year<2000");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("1999.mp3")));
+
+		let songs = ctx.search("year>=2000");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("2000.mp3")));
+		assert!(songs.contains(&PathBuf::from("2001.mp3")));
+
+		let songs = ctx.search("year<=2000");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("1999.mp3")));
+		assert!(songs.contains(&PathBuf::from("2000.mp3")));
+	}
+
+	#[test]
+	fn can_query_original_year() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("reissue.mp3"),
+				year: Some(2011),
+				original_year: Some(1975),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("original.mp3"),
+				year: Some(2011),
+				original_year: Some(2011),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("originalyear < 1980");
+		assert_eq!(songs.len(), 1);
+		assert!(songs.contains(&PathBuf::from("reissue.mp3")));
+
+		let songs = ctx.search("year < 1980");
+		assert!(songs.is_empty());
+
+		let songs = ctx.search("year = 2011");
+		assert_eq!(songs.len(), 2);
+	}
+
+	#[test]
+	fn can_query_rating() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("favorite.mp3"),
+				rating: Some(5),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("good.mp3"),
+				rating: Some(4),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("meh.mp3"),
+				rating: Some(2),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("unrated.mp3"),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("rating >= 4");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("favorite.mp3")));
+		assert!(songs.contains(&PathBuf::from("good.mp3")));
+
+		// Bare numbers do not match ratings
+		let songs = ctx.search("5");
+		assert!(songs.is_empty());
+	}
+
+	#[test]
+	fn can_query_channels() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("stereo.flac"),
+				channels: Some(2),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("surround.flac"),
+				channels: Some(6),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("channels = 6");
+		assert_eq!(songs, vec![PathBuf::from("surround.flac")]);
+
+		let songs = ctx.search("channels > 2");
+		assert_eq!(songs, vec![PathBuf::from("surround.flac")]);
+
+		let songs = ctx.search("6");
+		assert!(songs.is_empty());
+	}
+
+	#[test]
+	fn can_query_technical_properties() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("low.mp3"),
+				bitrate: Some(128),
+				codec: Some("mp3".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("high.mp3"),
+				bitrate: Some(320),
+				codec: Some("mp3".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("lossless.flac"),
+				bitrate: Some(900),
+				codec: Some("flac".to_owned()),
+				..Default::default()
+			},
+		]);
+
+		let songs = ctx.search("codec = mp3 && bitrate <= 128");
+		assert_eq!(songs, vec![PathBuf::from("low.mp3")]);
+
+		let songs = ctx.search("codec = flac");
+		assert_eq!(songs, vec![PathBuf::from("lossless.flac")]);
+
+		let songs = ctx.search("bitrate > 128 && bitrate < 500");
+		assert_eq!(songs, vec![PathBuf::from("high.mp3")]);
+
+		let songs = ctx.search("128");
+		assert!(songs.is_empty());
+	}
+
+	#[test]
+	fn fuzzy_numbers_query_all_fields() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("music.mp3"),
+				year: Some(2000),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("fireworks 2000.mp3"),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("calcium.mp3"),
+				..Default::default()
+			},
+		]);
 
-		let songs = ctx.search("year<=2000");
+		let songs = ctx.search("2000");
 		assert_eq!(songs.len(), 2);
-		assert!(songs.contains(&PathBuf::from("1999.mp3")));
-		assert!(songs.contains(&PathBuf::from("2000.mp3")));
+		assert!(songs.contains(&PathBuf::from("music.mp3")));
+		assert!(songs.contains(&PathBuf::from("fireworks 2000.mp3")));
 	}
 
 	#[test]
-	fn fuzzy_numbers_query_all_fields() {
+	fn fuzzy_numbers_also_match_text() {
 		let ctx = setup_test(vec![
 			scanner::Song {
-				virtual_path: PathBuf::from("music.mp3"),
-				year: Some(2000),
+				virtual_path: PathBuf::from("summer.mp3"),
+				title: Some("Summer 2024".to_owned()),
 				..Default::default()
 			},
 			scanner::Song {
-				virtual_path: PathBuf::from("fireworks 2000.mp3"),
+				virtual_path: PathBuf::from("winter.mp3"),
+				title: Some("Winter".to_owned()),
+				year: Some(2024),
 				..Default::default()
 			},
 			scanner::Song {
-				virtual_path: PathBuf::from("calcium.mp3"),
+				virtual_path: PathBuf::from("autumn.mp3"),
+				title: Some("Autumn 2023".to_owned()),
 				..Default::default()
 			},
 		]);
 
-		let songs = ctx.search("2000");
+		let songs = ctx.search("2024");
 		assert_eq!(songs.len(), 2);
-		assert!(songs.contains(&PathBuf::from("music.mp3")));
-		assert!(songs.contains(&PathBuf::from("fireworks 2000.mp3")));
+		assert!(songs.contains(&PathBuf::from("summer.mp3")));
+		assert!(songs.contains(&PathBuf::from("winter.mp3")));
+
+		let songs = ctx
+			.search_with(
+				"2024",
+				SearchOptions {
+					default_field: Some(TextField::Title),
+					..Default::default()
+				},
+			)
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from("summer.mp3")]);
 	}
 
 	#[test]
@@ -565,6 +2553,84 @@ mod test {
 		assert!(songs.contains(&PathBuf::from("whales in space.mp3")));
 	}
 
+	#[test]
+	fn repeated_constraints_on_a_field_intersect() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("unplugged.mp3"),
+				genres: vec!["Rock".to_owned(), "Acoustic".to_owned()],
+				year: Some(1993),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("plugged.mp3"),
+				genres: vec!["Rock".to_owned()],
+				year: Some(1991),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("folk.mp3"),
+				genres: vec!["Acoustic".to_owned()],
+				year: Some(2003),
+				..Default::default()
+			},
+		]);
+
+		let unplugged = vec![PathBuf::from("unplugged.mp3")];
+		assert_eq!(ctx.search("genre = Rock && genre = Acoustic"), unplugged);
+		assert_eq!(ctx.search("genre = Acoustic && genre = Rock"), unplugged);
+		assert_eq!(ctx.search("genre % roc && genre % acous"), unplugged);
+		assert_eq!(
+			ctx.search("genre = (Rock) && genre = (Acoustic)"),
+			unplugged
+		);
+		assert!(ctx.search("genre = Rock && genre = Jazz").is_empty());
+
+		assert_eq!(ctx.search("year > 1991 && year < 2003"), unplugged);
+		assert_eq!(
+			ctx.search("genre = Rock && genre = Acoustic && year > 1991"),
+			unplugged
+		);
+	}
+
+	#[test]
+	fn explain_evaluates_selective_predicates_first() {
+		let mut songs = (0..10)
+			.map(|i| scanner::Song {
+				virtual_path: PathBuf::from(format!("love_{i}.mp3")),
+				title: Some(format!("Love Song {i}")),
+				artists: vec!["Whoever".to_owned()],
+				..Default::default()
+			})
+			.collect::<Vec<_>>();
+		songs.push(scanner::Song {
+			virtual_path: PathBuf::from("forever.mp3"),
+			title: Some("Love Will Find a Way".to_owned()),
+			artists: vec!["Stratovarius".to_owned()],
+			..Default::default()
+		});
+		let ctx = setup_test(songs);
+
+		let estimates = ctx
+			.search
+			.explain(&ctx.dictionary, "love && artist = stratovarius", None)
+			.unwrap();
+		assert_eq!(estimates.len(), 2);
+		assert_eq!(
+			estimates[0].predicate,
+			Expr::TextCmp(TextField::Artist, TextOp::Eq, "stratovarius".to_owned())
+		);
+		assert_eq!(estimates[0].num_candidates, 1);
+		assert_eq!(
+			estimates[1].predicate,
+			Expr::Fuzzy(Literal::Text("love".to_owned()))
+		);
+		assert!(estimates[1].num_candidates >= 11);
+
+		let songs = ctx.search("love && artist = stratovarius");
+		assert_eq!(songs, vec![PathBuf::from("forever.mp3")]);
+	}
+
 	#[test]
 	fn can_use_or_operator() {
 		let ctx = setup_test(vec![
@@ -705,4 +2771,445 @@ mod test {
 		let songs = ctx.search("seas || 2");
 		assert_eq!(songs.len(), 1);
 	}
+
+	#[test]
+	fn path_indexing_strips_extensions() {
+		let songs = || {
+			vec![
+				scanner::Song {
+					virtual_path: PathBuf::from("mp3 rips/calcium.mp3"),
+					title: Some("Calcium".to_owned()),
+					..Default::default()
+				},
+				scanner::Song {
+					virtual_path: PathBuf::from("lossless/seasons.flac"),
+					title: Some("Seasons".to_owned()),
+					..Default::default()
+				},
+			]
+		};
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::default().with_path_indexing(PathIndexing::Full),
+		);
+		assert_eq!(
+			ctx.search("flac"),
+			vec![PathBuf::from("lossless/seasons.flac")]
+		);
+
+		let ctx = setup_test(songs());
+		assert!(ctx.search("flac").is_empty());
+		assert_eq!(
+			ctx.search("lossless"),
+			vec![PathBuf::from("lossless/seasons.flac")]
+		);
+		assert_eq!(
+			ctx.search("mp3"),
+			vec![PathBuf::from("mp3 rips/calcium.mp3")]
+		);
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::default().with_path_indexing(PathIndexing::FileName),
+		);
+		assert!(ctx.search("mp3").is_empty());
+		assert!(ctx.search("lossless").is_empty());
+		assert_eq!(
+			ctx.search("path % seas"),
+			vec![PathBuf::from("lossless/seasons.flac")]
+		);
+	}
+
+	#[test]
+	fn word_boundary_fields_exclude_mid_word_matches() {
+		let songs = || {
+			vec![
+				scanner::Song {
+					virtual_path: PathBuf::from("classical/moonlight sonata.mp3"),
+					title: Some("Moonlight Sonata".to_owned()),
+					..Default::default()
+				},
+				scanner::Song {
+					virtual_path: PathBuf::from("dragonforce/seasons.mp3"),
+					title: Some("Seasons".to_owned()),
+					..Default::default()
+				},
+			]
+		};
+		let sonata = vec![PathBuf::from("classical/moonlight sonata.mp3")];
+		let both = vec![
+			PathBuf::from("classical/moonlight sonata.mp3"),
+			PathBuf::from("dragonforce/seasons.mp3"),
+		];
+		let sorted = |mut paths: Vec<PathBuf>| {
+			paths.sort();
+			paths
+		};
+
+		let ctx = setup_test(songs());
+		assert_eq!(sorted(ctx.search("path % son")), both);
+		assert_eq!(sorted(ctx.search("title % son")), both);
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::default().with_word_boundary_fields(vec![TextField::Path]),
+		);
+		assert_eq!(ctx.search("path % son"), sonata);
+		assert_eq!(ctx.search("path % moon"), sonata);
+		assert_eq!(ctx.search("path % classical/moon"), sonata);
+		assert!(ctx.search("path % light").is_empty());
+		assert!(ctx.search("path % easons").is_empty());
+		assert_eq!(sorted(ctx.search("title % son")), both);
+
+		let ctx = setup_test_with_builder(
+			songs(),
+			Builder::default()
+				.with_bigram_threshold(BigramThreshold::new(50))
+				.with_word_boundary_fields(vec![TextField::Title]),
+		);
+		assert_eq!(ctx.search("title % sonatta"), sonata);
+		assert!(ctx.search("title % easonz").is_empty());
+	}
+
+	#[test]
+	fn can_find_word_starts() {
+		assert_eq!(
+			word_starts("Moonlight Sonata").collect::<Vec<_>>(),
+			vec![0, 10]
+		);
+		assert_eq!(
+			word_starts("(untitled) - 01").collect::<Vec<_>>(),
+			vec![0, 1, 13]
+		);
+		assert_eq!(word_starts("東京 Tokyo").collect::<Vec<_>>(), vec![0, 7]);
+	}
+
+	#[test]
+	fn prefix_edit_distance_is_anchored() {
+		let term = ['s', 'o', 'n'];
+		assert_eq!(prefix_edit_distance(&term, "sonata"), 0);
+		assert_eq!(prefix_edit_distance(&term, "sinatra"), 1);
+		assert_eq!(prefix_edit_distance(&term, "seasons"), 2);
+		assert_eq!(substring_edit_distance(&term, "seasons"), 0);
+	}
+
+	#[test]
+	fn path_matches_are_listed_last() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("a.mp3"),
+				artists: vec!["Storm".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("storm/b.mp3"),
+				artists: vec!["Someone".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("storm/c.mp3"),
+				title: Some("Storm".to_owned()),
+				artists: vec!["Someone Else".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		let match_kinds = |query: &str| -> Vec<(PathBuf, MatchKind)> {
+			ctx.search
+				.find_songs(
+					&ctx.collection,
+					&ctx.dictionary,
+					query,
+					SearchOptions::default(),
+				)
+				.unwrap()
+				.into_iter()
+				.map(|(s, kind)| (s.virtual_path, kind))
+				.collect()
+		};
+
+		let songs = match_kinds("storm");
+		assert_eq!(
+			songs,
+			vec![
+				(PathBuf::from("storm/c.mp3"), MatchKind::Fuzzy),
+				(PathBuf::from("a.mp3"), MatchKind::Fuzzy),
+				(PathBuf::from("storm/b.mp3"), MatchKind::Path),
+			]
+		);
+
+		let songs = match_kinds("path % storm");
+		assert!(songs.iter().all(|(_, kind)| *kind == MatchKind::Fuzzy));
+	}
+
+	#[test]
+	fn can_query_work_and_grouping() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("allegro.flac"),
+				work: Some("Symphony No. 5".to_owned()),
+				movement: Some("Allegro con brio".to_owned()),
+				grouping: Some("Beethoven Symphonies".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("adagio.flac"),
+				work: Some("Piano Concerto No. 5".to_owned()),
+				movement: Some("Adagio un poco mosso".to_owned()),
+				..Default::default()
+			},
+		]);
+
+		assert_eq!(
+			ctx.search("work % symphony"),
+			vec![PathBuf::from("allegro.flac")]
+		);
+		assert_eq!(
+			ctx.search("grouping = \"beethoven symphonies\""),
+			vec![PathBuf::from("allegro.flac")]
+		);
+		assert_eq!(ctx.search("concerto").len(), 1);
+	}
+
+	#[test]
+	fn can_query_conductor_and_ensemble() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("karajan.flac"),
+				conductors: vec!["Herbert von Karajan".to_owned()],
+				ensembles: vec!["Berliner Philharmoniker".to_owned()],
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("abbado.flac"),
+				conductors: vec!["Claudio Abbado".to_owned()],
+				ensembles: vec!["Wiener Philharmoniker".to_owned()],
+				..Default::default()
+			},
+		]);
+
+		assert_eq!(
+			ctx.search("conductor % karajan"),
+			vec![PathBuf::from("karajan.flac")]
+		);
+		assert_eq!(
+			ctx.search("ensemble = \"wiener philharmoniker\""),
+			vec![PathBuf::from("abbado.flac")]
+		);
+		assert_eq!(ctx.search("philharmoniker").len(), 2);
+	}
+
+	#[test]
+	fn can_query_lossless_songs() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("lossless.flac"),
+				lossless: Some(true),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("lossy.mp3"),
+				lossless: Some(false),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("unknown.m4a"),
+				..Default::default()
+			},
+		]);
+
+		assert_eq!(
+			ctx.search("is:lossless"),
+			vec![PathBuf::from("lossless.flac")]
+		);
+		assert_eq!(ctx.search("is:lossy"), vec![PathBuf::from("lossy.mp3")]);
+
+		let songs = ctx.search("!is:lossless");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("lossy.mp3")));
+		assert!(songs.contains(&PathBuf::from("unknown.m4a")));
+	}
+
+	#[test]
+	fn can_query_embedded_artwork() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("embedded.mp3"),
+				album: Some("Hunted".to_owned()),
+				artwork: Some(PathBuf::from("embedded.mp3")),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("folder.mp3"),
+				album: Some("Picnic".to_owned()),
+				artwork: Some(PathBuf::from("Folder.png")),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("untagged.mp3"),
+				..Default::default()
+			},
+		]);
+
+		assert_eq!(
+			ctx.search("has:artwork"),
+			vec![PathBuf::from("embedded.mp3")]
+		);
+
+		let songs = ctx.search("!has:artwork");
+		assert_eq!(songs.len(), 2);
+		assert!(songs.contains(&PathBuf::from("folder.mp3")));
+		assert!(songs.contains(&PathBuf::from("untagged.mp3")));
+
+		assert_eq!(
+			ctx.search(r#"!has:artwork && album = """#),
+			vec![PathBuf::from("untagged.mp3")]
+		);
+		assert_eq!(
+			ctx.search(r#"!has:artwork && album != """#),
+			vec![PathBuf::from("folder.mp3")]
+		);
+	}
+
+	#[test]
+	fn can_query_file_extension() {
+		let ctx = setup_test(vec![
+			scanner::Song {
+				real_path: PathBuf::from("music/seasons.FLAC"),
+				virtual_path: PathBuf::from("seasons.FLAC"),
+				codec: Some("FLAC".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				real_path: PathBuf::from("music/potd.m4a"),
+				virtual_path: PathBuf::from("potd.m4a"),
+				codec: Some("ALAC".to_owned()),
+				..Default::default()
+			},
+			scanner::Song {
+				real_path: PathBuf::from("music/calcium.mp3"),
+				virtual_path: PathBuf::from("calcium.mp3"),
+				codec: Some("MP3".to_owned()),
+				..Default::default()
+			},
+		]);
+
+		assert_eq!(
+			ctx.search("ext = flac"),
+			vec![PathBuf::from("seasons.FLAC")]
+		);
+		assert_eq!(
+			ctx.search("ext = FLAC"),
+			vec![PathBuf::from("seasons.FLAC")]
+		);
+		assert_eq!(ctx.search("ext = m4a"), vec![PathBuf::from("potd.m4a")]);
+		assert!(ctx.search("ext = alac").is_empty());
+	}
+
+	#[test]
+	fn results_order_is_deterministic() {
+		let paths = ["d.mp3", "b.mp3", "e.mp3", "a.mp3", "c.mp3"];
+		let ctx = setup_test(
+			paths
+				.iter()
+				.map(|p| scanner::Song {
+					virtual_path: PathBuf::from(p),
+					title: Some("Encore".to_owned()),
+					..Default::default()
+				})
+				.collect(),
+		);
+
+		let mut expected = paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+		expected.sort();
+		for _ in 0..10 {
+			assert_eq!(ctx.search("encore"), expected);
+		}
+	}
+
+	#[test]
+	fn can_sort_results() {
+		use collection::{SortDirection, SortField};
+
+		let ctx = setup_test(vec![
+			scanner::Song {
+				virtual_path: PathBuf::from("seasons.mp3"),
+				title: Some("Seasons of Loneliness".to_owned()),
+				artists: vec!["Sonata Arctica".to_owned()],
+				year: Some(2001),
+				duration: Some(300),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("black.mp3"),
+				title: Some("Black Sheep".to_owned()),
+				artists: vec!["Sonata Arctica".to_owned()],
+				year: Some(2004),
+				..Default::default()
+			},
+			scanner::Song {
+				virtual_path: PathBuf::from("anthem.mp3"),
+				title: Some("Anthem for Sonata".to_owned()),
+				artists: vec!["Avantasia".to_owned()],
+				duration: Some(200),
+				..Default::default()
+			},
+		]);
+
+		let search_sorted = |field, direction| {
+			let sort_order = Some(collection::SortOrder { field, direction });
+			ctx.search_with(
+				"sonata",
+				SearchOptions {
+					sort_order,
+					..Default::default()
+				},
+			)
+			.unwrap()
+		};
+
+		assert_eq!(
+			search_sorted(SortField::Title, SortDirection::Ascending),
+			vec![
+				PathBuf::from("anthem.mp3"),
+				PathBuf::from("black.mp3"),
+				PathBuf::from("seasons.mp3"),
+			]
+		);
+		assert_eq!(
+			search_sorted(SortField::Artist, SortDirection::Descending),
+			vec![
+				PathBuf::from("seasons.mp3"),
+				PathBuf::from("black.mp3"),
+				PathBuf::from("anthem.mp3"),
+			]
+		);
+
+		// Missing values sort last in either direction
+		assert_eq!(
+			search_sorted(SortField::Year, SortDirection::Ascending),
+			vec![
+				PathBuf::from("seasons.mp3"),
+				PathBuf::from("black.mp3"),
+				PathBuf::from("anthem.mp3"),
+			]
+		);
+		assert_eq!(
+			search_sorted(SortField::Year, SortDirection::Descending),
+			vec![
+				PathBuf::from("black.mp3"),
+				PathBuf::from("seasons.mp3"),
+				PathBuf::from("anthem.mp3"),
+			]
+		);
+		assert_eq!(
+			search_sorted(SortField::Duration, SortDirection::Descending),
+			vec![
+				PathBuf::from("seasons.mp3"),
+				PathBuf::from("anthem.mp3"),
+				PathBuf::from("black.mp3"),
+			]
+		);
+	}
 }
@@ -37,6 +37,17 @@ pub struct Artist {
 	pub albums_as_lyricist: HashSet<AlbumKey>,
 	pub num_songs_by_genre: HashMap<Spur, u32>,
 	pub num_songs: u32,
+	pub collaborators: HashMap<ArtistKey, u32>, // Number of songs shared with other artists
+}
+
+impl Artist {
+	// Whether all of the albums this artist appears on are credited to other album artists
+	pub fn is_only_additional_performer(&self) -> bool {
+		!self.albums_as_additional_performer.is_empty()
+			&& self.albums_as_performer.is_empty()
+			&& self.albums_as_composer.is_empty()
+			&& self.albums_as_lyricist.is_empty()
+	}
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -59,13 +70,34 @@ pub struct Song {
 	pub artists: TinyVec<[ArtistKey; 1]>,
 	pub album_artists: TinyVec<[ArtistKey; 1]>,
 	pub year: Option<i64>,
+	pub year_inferred: bool,
+	pub original_year: Option<i64>,
+	pub rating: Option<i64>,
 	pub album: Option<Spur>,
+	pub disc_subtitle: Option<Spur>,
+	pub grouping: Option<Spur>,
+	pub work: Option<Spur>,
+	pub movement: Option<Spur>,
+	pub movement_number: Option<i64>,
 	pub artwork: Option<PathKey>,
 	pub duration: Option<i64>,
+	pub channels: Option<i64>,
+	pub bitrate: Option<i64>,
+	pub sample_rate: Option<i64>,
+	pub bit_depth: Option<i64>,
+	pub codec: Option<Spur>,
+	pub lossless: Option<bool>,
+	pub extension: Option<Spur>, // Lowercase extension of the real path
+	pub encoder_delay: Option<i64>,
+	pub encoder_padding: Option<i64>,
+	pub file_size: Option<i64>,
 	pub lyricists: TinyVec<[ArtistKey; 0]>,
 	pub composers: TinyVec<[ArtistKey; 0]>,
 	pub genres: TinyVec<[Spur; 1]>,
 	pub labels: TinyVec<[Spur; 0]>,
+	pub conductors: TinyVec<[Spur; 0]>,
+	pub ensembles: TinyVec<[Spur; 0]>,
+	pub custom_fields: Vec<(Spur, Spur)>,
 	pub date_added: i64,
 }
 
@@ -84,6 +116,7 @@ pub struct ArtistKey(pub Spur);
 pub struct AlbumKey {
 	pub artists: TinyVec<[ArtistKey; 4]>,
 	pub name: Spur,
+	pub year: Option<i64>, // Tells apart releases sharing a name, like reissues or live albums
 }
 
 #[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
@@ -106,6 +139,7 @@ impl Song {
 		self.album.map(|name| AlbumKey {
 			artists: main_artists.iter().cloned().collect(),
 			name,
+			year: self.year,
 		})
 	}
 }
@@ -125,6 +159,12 @@ pub fn store_song(
 		None => None,
 	};
 
+	let extension = song
+		.real_path
+		.extension()
+		.and_then(|e| e.to_str())
+		.and_then(|e| dictionary_builder.get_or_intern_canon(e.to_lowercase()));
+
 	let mut canonicalize = |s: &String| dictionary_builder.get_or_intern_canon(s);
 
 	Some(Song {
@@ -146,9 +186,27 @@ pub fn store_song(
 			.map(ArtistKey)
 			.collect(),
 		year: song.year,
+		year_inferred: song.year_inferred,
+		original_year: song.original_year,
+		rating: song.rating,
 		album: song.album.as_ref().and_then(&mut canonicalize),
+		disc_subtitle: song.disc_subtitle.as_ref().and_then(&mut canonicalize),
+		grouping: song.grouping.as_ref().and_then(&mut canonicalize),
+		work: song.work.as_ref().and_then(&mut canonicalize),
+		movement: song.movement.as_ref().and_then(&mut canonicalize),
+		movement_number: song.movement_number,
 		artwork: artwork,
 		duration: song.duration,
+		channels: song.channels,
+		bitrate: song.bitrate,
+		sample_rate: song.sample_rate,
+		bit_depth: song.bit_depth,
+		codec: song.codec.as_ref().and_then(&mut canonicalize),
+		lossless: song.lossless,
+		extension,
+		encoder_delay: song.encoder_delay,
+		encoder_padding: song.encoder_padding,
+		file_size: song.file_size,
 		lyricists: song
 			.lyricists
 			.iter()
@@ -163,6 +221,21 @@ pub fn store_song(
 			.collect(),
 		genres: song.genres.iter().filter_map(&mut canonicalize).collect(),
 		labels: song.labels.iter().filter_map(&mut canonicalize).collect(),
+		conductors: song
+			.conductors
+			.iter()
+			.filter_map(&mut canonicalize)
+			.collect(),
+		ensembles: song
+			.ensembles
+			.iter()
+			.filter_map(&mut canonicalize)
+			.collect(),
+		custom_fields: song
+			.custom_fields
+			.iter()
+			.filter_map(|(key, value)| Some((canonicalize(key)?, canonicalize(value)?)))
+			.collect(),
 		date_added: song.date_added,
 	})
 }
@@ -185,11 +258,30 @@ pub fn fetch_song(dictionary: &Dictionary, song: &Song) -> super::Song {
 			.map(|k| dictionary.resolve(&k.0).to_string())
 			.collect(),
 		year: song.year,
+		year_inferred: song.year_inferred,
+		original_year: song.original_year,
+		rating: song.rating,
 		album: song.album.map(|s| dictionary.resolve(&s).to_string()),
+		disc_subtitle: song
+			.disc_subtitle
+			.map(|s| dictionary.resolve(&s).to_string()),
+		grouping: song.grouping.map(|s| dictionary.resolve(&s).to_string()),
+		work: song.work.map(|s| dictionary.resolve(&s).to_string()),
+		movement: song.movement.map(|s| dictionary.resolve(&s).to_string()),
+		movement_number: song.movement_number,
 		artwork: song
 			.artwork
 			.map(|a| PathBuf::from(dictionary.resolve(&a.0))),
 		duration: song.duration,
+		channels: song.channels,
+		bitrate: song.bitrate,
+		sample_rate: song.sample_rate,
+		bit_depth: song.bit_depth,
+		codec: song.codec.map(|s| dictionary.resolve(&s).to_string()),
+		lossless: song.lossless,
+		encoder_delay: song.encoder_delay,
+		encoder_padding: song.encoder_padding,
+		file_size: song.file_size,
 		lyricists: song
 			.lyricists
 			.iter()
@@ -210,6 +302,26 @@ pub fn fetch_song(dictionary: &Dictionary, song: &Song) -> super::Song {
 			.iter()
 			.map(|s| dictionary.resolve(s).to_string())
 			.collect(),
+		conductors: song
+			.conductors
+			.iter()
+			.map(|s| dictionary.resolve(s).to_string())
+			.collect(),
+		ensembles: song
+			.ensembles
+			.iter()
+			.map(|s| dictionary.resolve(s).to_string())
+			.collect(),
+		custom_fields: song
+			.custom_fields
+			.iter()
+			.map(|(key, value)| {
+				(
+					dictionary.resolve(key).to_string(),
+					dictionary.resolve(value).to_string(),
+				)
+			})
+			.collect(),
 		date_added: song.date_added,
 	}
 }
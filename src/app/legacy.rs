@@ -36,8 +36,8 @@ pub fn read_legacy_config(
 	Ok(Some(config::storage::Config {
 		album_art_pattern: Some(album_art_pattern),
 		mount_dirs,
-		ddns_update_url: None,
 		users: users.into_values().collect(),
+		..Default::default()
 	}))
 }
 
@@ -52,6 +52,7 @@ fn read_mount_dirs(db_file_path: &PathBuf) -> Result<Vec<config::storage::MountD
 		Ok(config::storage::MountDir {
 			source,
 			name: row.get::<_, String>(1)?,
+			album_art_pattern: None,
 		})
 	})?;
 
@@ -73,6 +74,8 @@ fn read_users(db_file_path: &PathBuf) -> Result<HashMap<u32, config::storage::Us
 			config::storage::User {
 				name: row.get(1)?,
 				admin: row.get(3)?,
+				role: None,
+				permissions: None,
 				initial_password: None,
 				hashed_password: row.get(2)?,
 			},
@@ -210,8 +213,8 @@ mod test {
 		let expected = config::storage::Config {
 			album_art_pattern: Some("Folder.(jpeg|jpg|png)".to_owned()),
 			mount_dirs: vec![],
-			ddns_update_url: None,
 			users: vec![],
+			..Default::default()
 		};
 
 		assert_eq!(actual, expected);
@@ -231,14 +234,17 @@ mod test {
 			mount_dirs: vec![config::storage::MountDir {
 				source: PathBuf::from_iter(["test-data", "small-collection"]),
 				name: "root".to_owned(),
+				..Default::default()
 			}],
-			ddns_update_url: None,
 			users: vec![config::storage::User {
 				name: "example_user".to_owned(),
 				admin: Some(true),
+				role: None,
+				permissions: None,
 				initial_password: None,
 				hashed_password: Some("$pbkdf2-sha256$i=10000,l=32$ADvDnwBv3kLUtjTJEwGcFA$oK43ICpNt2rbH21diMo6cSXL62qqLWOM7qs8f0s/9Oo".to_owned()),
 			}],
+			..Default::default()
 		};
 
 		assert_eq!(actual, expected);
@@ -6,11 +6,16 @@ use std::{
 
 use native_db::{Database, Models};
 
-use crate::app::{playlist, Error};
+use crate::app::{ddns, playlist, Error};
 
 static MODELS: LazyLock<Models> = LazyLock::new(|| {
 	let mut models = Models::new();
 	models.define::<playlist::v1::PlaylistModel>().unwrap();
+	models.define::<playlist::v2::PlaylistModel>().unwrap();
+	models
+		.define::<playlist::share_v1::PlaylistShareModel>()
+		.unwrap();
+	models.define::<ddns::v1::DDNSStateModel>().unwrap();
 	models
 });
 
@@ -26,6 +31,11 @@ impl Manager {
 		let database = native_db::Builder::new()
 			.create(&MODELS, path)
 			.map_err(Error::NativeDatabaseCreationError)?;
+
+		// Upgrades records saved by previous versions of their models
+		let transaction = database.rw_transaction()?;
+		transaction.migrate::<playlist::PlaylistModel>()?;
+		transaction.commit()?;
 		let database = Arc::new(database);
 		Ok(Self { database })
 	}
@@ -1,34 +1,55 @@
 use std::{
 	hash::{DefaultHasher, Hash, Hasher},
+	num::NonZeroUsize,
 	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
 };
 
+use log::error;
 use serde::{Deserialize, Serialize};
 use symphonia::core::{
 	audio::SampleBuffer,
-	codecs::{DecoderOptions, CODEC_TYPE_NULL},
-	formats::FormatOptions,
-	io::{MediaSourceStream, MediaSourceStreamOptions},
-	meta::MetadataOptions,
-	probe::Hint,
+	formats::{SeekMode, SeekTo},
+	units::Time,
+};
+use tokio::{
+	io::AsyncWriteExt,
+	sync::Semaphore,
+	task::{spawn_blocking, JoinSet},
 };
-use tokio::{io::AsyncWriteExt, task::spawn_blocking};
 
-use crate::app::Error;
+use crate::app::{
+	formats::{open_source, Source},
+	Error,
+};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Peaks {
 	pub interleaved: Vec<u8>,
 }
 
+// Continuous waveform overview spanning multiple tracks, such as a whole album
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlbumPeaks {
+	pub interleaved: Vec<u8>,
+	pub track_offsets: Vec<usize>, // Index of the first min/max pair belonging to each track
+}
+
 #[derive(Clone)]
 pub struct Manager {
 	peaks_dir_path: PathBuf,
+	decode_permits: Arc<Semaphore>, // Bounds how many files are decoded at once, extra requests wait in line
+	max_concurrent_decodes: NonZeroUsize,
 }
 
 impl Manager {
-	pub fn new(peaks_dir_path: PathBuf) -> Self {
-		Self { peaks_dir_path }
+	pub fn new(peaks_dir_path: PathBuf, max_concurrent_decodes: NonZeroUsize) -> Self {
+		Self {
+			peaks_dir_path,
+			decode_permits: Arc::new(Semaphore::new(max_concurrent_decodes.get())),
+			max_concurrent_decodes,
+		}
 	}
 
 	pub async fn get_peaks(&self, audio_path: &Path) -> Result<Peaks, Error> {
@@ -38,6 +59,83 @@ impl Manager {
 		}
 	}
 
+	pub async fn is_cached(&self, audio_path: &Path) -> bool {
+		self.get_fresh_peaks_path(audio_path).await.is_some()
+	}
+
+	// Computes peaks for the files which have no up-to-date cache entry, without exceeding
+	// the decode limit. Dropping the returned future cancels decodes not yet started.
+	pub async fn precompute(&self, audio_paths: Vec<PathBuf>) {
+		let mut tasks = JoinSet::new();
+		for audio_path in audio_paths {
+			if self.is_cached(&audio_path).await {
+				continue;
+			}
+			if tasks.len() >= self.max_concurrent_decodes.get() {
+				tasks.join_next().await;
+			}
+			tasks.spawn({
+				let manager = self.clone();
+				async move {
+					if let Err(e) = manager.read_from_source(&audio_path).await {
+						error!(
+							"Could not compute peaks for `{}`: {e}",
+							audio_path.display()
+						);
+					}
+				}
+			});
+		}
+		while tasks.join_next().await.is_some() {}
+	}
+
+	// Concatenates the peaks of multiple tracks into a single overview of at most
+	// `num_buckets` min/max pairs, with amplitudes normalized to the full range.
+	// Per-track peaks come from the cache and are generated when missing.
+	pub async fn get_album_peaks(
+		&self,
+		audio_paths: &[PathBuf],
+		num_buckets: usize,
+	) -> Result<AlbumPeaks, Error> {
+		let handles = audio_paths
+			.iter()
+			.map(|audio_path| {
+				let manager = self.clone();
+				let audio_path = audio_path.clone();
+				tokio::spawn(async move { manager.get_peaks(&audio_path).await })
+			})
+			.collect::<Vec<_>>();
+
+		let mut tracks = Vec::with_capacity(handles.len());
+		for handle in handles {
+			tracks.push(handle.await??);
+		}
+
+		Ok(merge_peaks(&tracks, num_buckets))
+	}
+
+	// Computes peaks for a sub-range of the audio file, split into `num_buckets`
+	// min/max pairs. Only the requested window is decoded. These are not cached.
+	pub async fn get_peaks_window(
+		&self,
+		audio_path: &Path,
+		start: Duration,
+		end: Duration,
+		num_buckets: usize,
+	) -> Result<Peaks, Error> {
+		let audio_path = audio_path.to_owned();
+		self.decode(move || compute_peaks_window(&audio_path, start, end, num_buckets))
+			.await
+	}
+
+	async fn decode<F>(&self, job: F) -> Result<Peaks, Error>
+	where
+		F: FnOnce() -> Result<Peaks, Error> + Send + 'static,
+	{
+		let _permit = self.decode_permits.acquire().await.unwrap();
+		spawn_blocking(job).await?
+	}
+
 	fn get_peaks_path(&self, audio_path: &Path) -> PathBuf {
 		let hash = Manager::hash(audio_path);
 		let mut peaks_path = self.peaks_dir_path.clone();
@@ -45,26 +143,41 @@ impl Manager {
 		peaks_path
 	}
 
-	async fn read_from_cache(&self, audio_path: &Path) -> Result<Option<Peaks>, Error> {
+	// Cache entries are stale once the audio file is modified after they were written
+	async fn get_fresh_peaks_path(&self, audio_path: &Path) -> Option<PathBuf> {
 		let peaks_path = self.get_peaks_path(audio_path);
-		if peaks_path.exists() {
-			let serialized = tokio::fs::read(&peaks_path)
-				.await
-				.map_err(|e| Error::Io(peaks_path.clone(), e))?;
-			let peaks =
-				bitcode::deserialize::<Peaks>(&serialized).map_err(Error::PeaksDeserialization)?;
-			Ok(Some(peaks))
-		} else {
-			Ok(None)
+		let cached = tokio::fs::metadata(&peaks_path)
+			.await
+			.and_then(|m| m.modified())
+			.ok()?;
+		match tokio::fs::metadata(audio_path)
+			.await
+			.and_then(|m| m.modified())
+		{
+			Ok(modified) if modified > cached => None,
+			_ => Some(peaks_path),
 		}
 	}
 
+	async fn read_from_cache(&self, audio_path: &Path) -> Result<Option<Peaks>, Error> {
+		let Some(peaks_path) = self.get_fresh_peaks_path(audio_path).await else {
+			return Ok(None);
+		};
+		let serialized = tokio::fs::read(&peaks_path)
+			.await
+			.map_err(|e| Error::Io(peaks_path.clone(), e))?;
+		let peaks =
+			bitcode::deserialize::<Peaks>(&serialized).map_err(Error::PeaksDeserialization)?;
+		Ok(Some(peaks))
+	}
+
 	async fn read_from_source(&self, audio_path: &Path) -> Result<Peaks, Error> {
-		let peaks = spawn_blocking({
-			let audio_path = audio_path.to_owned();
-			move || compute_peaks(&audio_path)
-		})
-		.await??;
+		let peaks = self
+			.decode({
+				let audio_path = audio_path.to_owned();
+				move || compute_peaks(&audio_path)
+			})
+			.await?;
 
 		let serialized = bitcode::serialize(&peaks).map_err(Error::PeaksSerialization)?;
 
@@ -92,78 +205,67 @@ impl Manager {
 	}
 }
 
-fn compute_peaks(audio_path: &Path) -> Result<Peaks, Error> {
-	let peaks_per_minute = 4000;
+// Decodes packets until the end of the track (or until `on_packet` returns false).
+// The callback receives the timestamp of the packet in seconds, the sample rate
+// and the packet samples merged into a mono signal.
+fn decode_mono<F>(source: &mut Source, mut on_packet: F) -> Result<(), Error>
+where
+	F: FnMut(Option<f64>, u32, &[u8]) -> bool,
+{
+	let mut mono = Vec::new();
+	let time_base = source.time_base;
 
-	let file =
-		std::fs::File::open(audio_path).map_err(|e| Error::Io(audio_path.to_owned(), e))?;
-	let media_source = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
-
-	let mut peaks = Peaks::default();
-	peaks.interleaved.reserve(5 * peaks_per_minute);
+	while let Some((ts, decoded)) = source.next_packet()? {
+		let num_channels = decoded.spec().channels.count();
+		let sample_rate = decoded.spec().rate;
+		let timestamp = match time_base {
+			Some(time_base) => {
+				let time = time_base.calc_time(ts);
+				Some(time.seconds as f64 + time.frac)
+			}
+			None if sample_rate > 0 => Some(ts as f64 / sample_rate as f64),
+			None => None,
+		};
 
-	let mut format = symphonia::default::get_probe()
-		.format(
-			&Hint::new(),
-			media_source,
-			&FormatOptions::default(),
-			&MetadataOptions::default(),
-		)
-		.map_err(Error::MediaProbeError)?
-		.format;
+		let mut buffer = SampleBuffer::<u8>::new(decoded.capacity() as u64, *decoded.spec());
+		buffer.copy_interleaved_ref(decoded);
 
-	let track = format
-		.tracks()
-		.iter()
-		.find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-		.ok_or_else(|| Error::MediaEmpty(audio_path.to_owned()))?;
+		mono.clear();
+		for samples in buffer.samples().chunks_exact(num_channels) {
+			// Merge channels into mono signal
+			let mut sum: u32 = 0;
+			for sample in samples {
+				sum += *sample as u32;
+			}
+			mono.push((sum / samples.len() as u32) as u8);
+		}
 
-	let track_id = track.id;
+		if !on_packet(timestamp, sample_rate, &mono) {
+			break;
+		}
+	}
 
-	let mut decoder = symphonia::default::get_codecs()
-		.make(&track.codec_params, &DecoderOptions::default())
-		.map_err(Error::MediaDecoderError)?;
+	Ok(())
+}
 
-	let (mut min, mut max) = (u8::MAX, u8::MIN);
-	let mut num_ingested = 0;
+fn compute_peaks(audio_path: &Path) -> Result<Peaks, Error> {
+	let peaks_per_minute = 4000;
 
-	loop {
-		let packet = match format.next_packet() {
-			Ok(packet) => packet,
-			Err(symphonia::core::errors::Error::IoError(e))
-				if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-			{
-				break;
-			}
-			Err(e) => return Err(Error::MediaPacketError(e)),
-		};
+	let mut source = open_source(audio_path)?;
 
-		if packet.track_id() != track_id {
-			continue;
-		}
+	let mut peaks = Peaks::default();
+	peaks.interleaved.reserve(5 * peaks_per_minute);
 
-		let decoded = match decoder.decode(&packet) {
-			Ok(d) => d,
-			Err(_) => continue,
-		};
+	let (mut min, mut max) = (u8::MAX, u8::MIN);
+	let mut num_ingested = 0;
 
-		let num_channels = decoded.spec().channels.count();
-		let sample_rate = decoded.spec().rate;
+	decode_mono(&mut source, |_, sample_rate, samples| {
 		let num_samples_per_peak =
 			((sample_rate as f32) * 60.0 / (peaks_per_minute as f32)).round() as usize;
 
-		let mut buffer = SampleBuffer::<u8>::new(decoded.capacity() as u64, *decoded.spec());
-		buffer.copy_interleaved_ref(decoded);
-		for samples in buffer.samples().chunks_exact(num_channels) {
-			// Merge channels into mono signal
-			let mut mono: u32 = 0;
-			for sample in samples {
-				mono += *sample as u32;
-			}
-			mono /= samples.len() as u32;
-
-			min = u8::min(min, mono as u8);
-			max = u8::max(max, mono as u8);
+		for sample in samples {
+			min = u8::min(min, *sample);
+			max = u8::max(max, *sample);
 			num_ingested += 1;
 
 			if num_ingested >= num_samples_per_peak {
@@ -173,7 +275,294 @@ fn compute_peaks(audio_path: &Path) -> Result<Peaks, Error> {
 				num_ingested = 0;
 			}
 		}
+		true
+	})?;
+
+	Ok(peaks)
+}
+
+fn compute_peaks_window(
+	audio_path: &Path,
+	start: Duration,
+	end: Duration,
+	num_buckets: usize,
+) -> Result<Peaks, Error> {
+	if num_buckets == 0 || end <= start {
+		return Ok(Peaks::default());
+	}
+
+	let mut source = open_source(audio_path)?;
+
+	if !start.is_zero() {
+		let track_id = source.track_id;
+		source
+			.format
+			.seek(
+				SeekMode::Accurate,
+				SeekTo::Time {
+					time: Time::new(
+						start.as_secs(),
+						start.subsec_nanos() as f64 / 1_000_000_000.0,
+					),
+					track_id: Some(track_id),
+				},
+			)
+			.map_err(Error::MediaSeekError)?;
+		source.decoder.reset();
+	}
+
+	let start = start.as_secs_f64();
+	let end = end.as_secs_f64();
+	let span = end - start;
+
+	let mut min = vec![u8::MAX; num_buckets];
+	let mut max = vec![u8::MIN; num_buckets];
+
+	// Fallback position tracking for packets without a timestamp
+	let mut position = start;
+
+	decode_mono(&mut source, |timestamp, sample_rate, samples| {
+		if sample_rate == 0 {
+			return true;
+		}
+		let packet_start = timestamp.unwrap_or(position);
+		let frame_duration = 1.0 / sample_rate as f64;
+		position = packet_start + samples.len() as f64 * frame_duration;
+
+		for (index, sample) in samples.iter().enumerate() {
+			let time = packet_start + index as f64 * frame_duration;
+			if time < start {
+				continue;
+			}
+			if time >= end {
+				return false;
+			}
+			let bucket = (((time - start) / span) * num_buckets as f64) as usize;
+			let bucket = bucket.min(num_buckets - 1);
+			min[bucket] = u8::min(min[bucket], *sample);
+			max[bucket] = u8::max(max[bucket], *sample);
+		}
+
+		position < end
+	})?;
+
+	let mut peaks = Peaks::default();
+	peaks.interleaved.reserve(2 * num_buckets);
+	for (min, max) in min.into_iter().zip(max) {
+		// Buckets past the end of the audio are reported as silence
+		if min > max {
+			peaks.interleaved.push(u8::MAX / 2 + 1);
+			peaks.interleaved.push(u8::MAX / 2 + 1);
+		} else {
+			peaks.interleaved.push(min);
+			peaks.interleaved.push(max);
+		}
 	}
 
 	Ok(peaks)
 }
+
+fn merge_peaks(tracks: &[Peaks], num_buckets: usize) -> AlbumPeaks {
+	let mut track_offsets = Vec::with_capacity(tracks.len());
+	let mut pairs = Vec::new();
+	for track in tracks {
+		track_offsets.push(pairs.len());
+		pairs.extend(track.interleaved.chunks_exact(2).map(|p| (p[0], p[1])));
+	}
+
+	let num_pairs = pairs.len();
+	let num_buckets = num_buckets.min(num_pairs);
+	if num_buckets == 0 {
+		return AlbumPeaks {
+			interleaved: Vec::new(),
+			track_offsets: vec![0; tracks.len()],
+		};
+	}
+
+	// Downsample so that each bucket covers the same duration
+	let mut buckets = Vec::with_capacity(num_buckets);
+	for bucket in 0..num_buckets {
+		let first = bucket * num_pairs / num_buckets;
+		let last = ((bucket + 1) * num_pairs / num_buckets).max(first + 1);
+		let (min, max) = pairs[first..last]
+			.iter()
+			.fold((u8::MAX, u8::MIN), |(min, max), (lo, hi)| {
+				(u8::min(min, *lo), u8::max(max, *hi))
+			});
+		buckets.push((min, max));
+	}
+
+	// Stretch amplitudes so the loudest peak reaches the edge of the range
+	let center = (u8::MAX / 2 + 1) as i32;
+	let loudest = buckets
+		.iter()
+		.flat_map(|(min, max)| [*min, *max])
+		.map(|v| (v as i32 - center).abs())
+		.max()
+		.unwrap_or_default();
+	let normalize = |v: u8| -> u8 {
+		if loudest == 0 {
+			return v;
+		}
+		let scaled = center + (v as i32 - center) * (center - 1) / loudest;
+		scaled.clamp(u8::MIN as i32, u8::MAX as i32) as u8
+	};
+
+	let mut interleaved = Vec::with_capacity(2 * num_buckets);
+	for (min, max) in buckets {
+		interleaved.push(normalize(min));
+		interleaved.push(normalize(max));
+	}
+
+	let track_offsets = track_offsets
+		.into_iter()
+		.map(|offset| offset * num_buckets / num_pairs)
+		.collect();
+
+	AlbumPeaks {
+		interleaved,
+		track_offsets,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use super::*;
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	#[tokio::test]
+	async fn limits_concurrent_decodes() {
+		let max_concurrent_decodes = 2;
+		let manager = Manager::new(
+			prepare_test_directory(test_name!()),
+			NonZeroUsize::new(max_concurrent_decodes).unwrap(),
+		);
+
+		let active = Arc::new(AtomicUsize::new(0));
+		let peak_active = Arc::new(AtomicUsize::new(0));
+
+		let mut jobs = tokio::task::JoinSet::new();
+		for _ in 0..8 {
+			let manager = manager.clone();
+			let active = active.clone();
+			let peak_active = peak_active.clone();
+			jobs.spawn(async move {
+				manager
+					.decode(move || {
+						let now_active = active.fetch_add(1, Ordering::SeqCst) + 1;
+						peak_active.fetch_max(now_active, Ordering::SeqCst);
+						std::thread::sleep(Duration::from_millis(50));
+						active.fetch_sub(1, Ordering::SeqCst);
+						Ok(Peaks::default())
+					})
+					.await
+			});
+		}
+
+		while let Some(result) = jobs.join_next().await {
+			result.unwrap().unwrap();
+		}
+
+		assert!(peak_active.load(Ordering::SeqCst) <= max_concurrent_decodes);
+		assert!(peak_active.load(Ordering::SeqCst) > 0);
+	}
+
+	#[test]
+	fn can_compute_peaks_for_window() {
+		let path: PathBuf = ["test-data", "formats", "sample.mp3"].iter().collect();
+		let peaks =
+			compute_peaks_window(&path, Duration::ZERO, Duration::from_millis(500), 50).unwrap();
+		assert_eq!(peaks.interleaved.len(), 100);
+		for pair in peaks.interleaved.chunks_exact(2) {
+			assert!(pair[0] <= pair[1]);
+		}
+	}
+
+	#[test]
+	fn empty_window_has_no_peaks() {
+		let path: PathBuf = ["test-data", "formats", "sample.mp3"].iter().collect();
+		let start = Duration::from_millis(200);
+		let peaks = compute_peaks_window(&path, start, start, 50).unwrap();
+		assert!(peaks.interleaved.is_empty());
+	}
+
+	#[test]
+	fn merged_peaks_mark_track_boundaries() {
+		let tracks = vec![
+			Peaks {
+				interleaved: vec![100, 150, 110, 140, 120, 130, 128, 128],
+			},
+			Peaks {
+				interleaved: vec![64, 192, 96, 160, 128, 128, 128, 128, 128, 128, 128, 128],
+			},
+		];
+
+		let merged = merge_peaks(&tracks, 100);
+		assert_eq!(merged.track_offsets, vec![0, 4]);
+		assert_eq!(merged.interleaved.len(), 20);
+		assert_eq!(merged.interleaved[8], 1);
+		assert_eq!(merged.interleaved[9], 255);
+
+		let merged = merge_peaks(&tracks, 5);
+		assert_eq!(merged.track_offsets, vec![0, 2]);
+		assert_eq!(merged.interleaved.len(), 10);
+		for pair in merged.interleaved.chunks_exact(2) {
+			assert!(pair[0] <= pair[1]);
+		}
+	}
+
+	#[tokio::test]
+	async fn precompute_skips_fresh_entries() {
+		let directory = prepare_test_directory(test_name!());
+		let manager = Manager::new(directory.join("peaks"), NonZeroUsize::new(2).unwrap());
+		let track = directory.join("sample.mp3");
+		std::fs::copy(
+			["test-data", "formats", "sample.mp3"]
+				.iter()
+				.collect::<PathBuf>(),
+			&track,
+		)
+		.unwrap();
+
+		manager.precompute(vec![track.clone()]).await;
+		assert!(manager.is_cached(&track).await);
+
+		let peaks_path = manager.get_peaks_path(&track);
+		let written = std::fs::metadata(&peaks_path).unwrap().modified().unwrap();
+		manager.precompute(vec![track.clone()]).await;
+		let rewritten = std::fs::metadata(&peaks_path).unwrap().modified().unwrap();
+		assert_eq!(written, rewritten);
+
+		std::fs::File::options()
+			.write(true)
+			.open(&track)
+			.unwrap()
+			.set_modified(written + Duration::from_secs(60))
+			.unwrap();
+		assert!(!manager.is_cached(&track).await);
+
+		manager.precompute(vec![track.clone()]).await;
+		assert!(manager.is_cached(&track).await);
+	}
+
+	#[tokio::test]
+	async fn can_compute_album_peaks() {
+		let manager = Manager::new(
+			prepare_test_directory(test_name!()),
+			NonZeroUsize::new(2).unwrap(),
+		);
+		let track: PathBuf = ["test-data", "formats", "sample.mp3"].iter().collect();
+		let single = manager.get_peaks(&track).await.unwrap();
+
+		let album = manager
+			.get_album_peaks(&[track.clone(), track.clone()], usize::MAX)
+			.await
+			.unwrap();
+		let num_pairs = single.interleaved.len() / 2;
+		assert_eq!(album.interleaved.len(), 4 * num_pairs);
+		assert_eq!(album.track_offsets, vec![0, num_pairs]);
+	}
+}
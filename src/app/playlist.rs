@@ -1,24 +1,38 @@
 use core::clone::Clone;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::time::Duration;
 
 use icu_collator::{Collator, CollatorOptions, Strength};
+use native_db::transaction::RwTransaction;
 use native_db::*;
 use native_model::{native_model, Model};
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use tokio::task::spawn_blocking;
 
-use crate::app::{index, ndb, Error};
+use crate::app::{auth, config, index, ndb, Error};
+
+mod pls;
+
+// Number of album arts in a playlist collage (2x2 grid)
+const COLLAGE_SIZE: usize = 4;
+
+// Size of the random identifiers of playlist shares, in bytes
+const SHARE_ID_LENGTH: usize = 16;
 
 #[derive(Clone)]
 pub struct Manager {
 	db: ndb::Manager,
+	index_manager: index::Manager,
+	config_manager: config::Manager,
 }
 
 #[derive(Debug)]
 pub struct PlaylistHeader {
 	pub name: String,
+	pub owner: String,
+	pub public: bool, // Readable (but not editable) by all users
 	pub duration: Duration,
 	pub num_songs_by_genre: HashMap<String, u32>,
 }
@@ -29,8 +43,10 @@ pub struct Playlist {
 	pub songs: Vec<PathBuf>,
 }
 
-pub type PlaylistModel = v1::PlaylistModel;
-type PlaylistModelKey = v1::PlaylistModelKey;
+pub type PlaylistModel = v2::PlaylistModel;
+type PlaylistModelKey = v2::PlaylistModelKey;
+pub type PlaylistShareModel = share_v1::PlaylistShareModel;
+type PlaylistShareModelKey = share_v1::PlaylistShareModelKey;
 
 pub mod v1 {
 
@@ -55,10 +71,78 @@ pub mod v1 {
 	}
 }
 
+pub mod v2 {
+
+	use super::*;
+
+	#[derive(Debug, Default, Serialize, Deserialize)]
+	#[native_model(id = 1, version = 2, from = v1::PlaylistModel)]
+	#[native_db(primary_key(custom_id -> (&str, &str)))]
+	pub struct PlaylistModel {
+		#[secondary_key]
+		pub owner: String,
+		pub name: String,
+		pub public: bool,
+		pub duration: Duration,
+		pub num_songs_by_genre: BTreeMap<String, u32>,
+		pub virtual_paths: Vec<PathBuf>,
+	}
+
+	impl PlaylistModel {
+		fn custom_id(&self) -> (&str, &str) {
+			(&self.owner, &self.name)
+		}
+	}
+
+	impl From<v1::PlaylistModel> for PlaylistModel {
+		fn from(p: v1::PlaylistModel) -> Self {
+			Self {
+				owner: p.owner,
+				name: p.name,
+				public: false,
+				duration: p.duration,
+				num_songs_by_genre: p.num_songs_by_genre,
+				virtual_paths: p.virtual_paths,
+			}
+		}
+	}
+
+	impl From<PlaylistModel> for v1::PlaylistModel {
+		fn from(p: PlaylistModel) -> Self {
+			Self {
+				owner: p.owner,
+				name: p.name,
+				duration: p.duration,
+				num_songs_by_genre: p.num_songs_by_genre,
+				virtual_paths: p.virtual_paths,
+			}
+		}
+	}
+}
+
+pub mod share_v1 {
+
+	use super::*;
+
+	// A playlist readable by anyone holding a token which carries this share's id
+	#[derive(Debug, Default, Serialize, Deserialize)]
+	#[native_model(id = 3, version = 1)]
+	#[native_db]
+	pub struct PlaylistShareModel {
+		#[primary_key]
+		pub share_id: String,
+		#[secondary_key]
+		pub owner: String,
+		pub name: String,
+	}
+}
+
 impl From<PlaylistModel> for PlaylistHeader {
 	fn from(p: PlaylistModel) -> Self {
 		Self {
 			name: p.name,
+			owner: p.owner,
+			public: p.public,
 			duration: p.duration,
 			num_songs_by_genre: p.num_songs_by_genre.into_iter().collect(),
 		}
@@ -76,10 +160,52 @@ impl From<PlaylistModel> for Playlist {
 }
 
 impl Manager {
-	pub fn new(db: ndb::Manager) -> Self {
-		Self { db }
+	pub fn new(
+		db: ndb::Manager,
+		index_manager: index::Manager,
+		config_manager: config::Manager,
+	) -> Self {
+		Self {
+			db,
+			index_manager,
+			config_manager,
+		}
+	}
+
+	// Looks up songs by virtual path. Paths which do not exactly match a song in the
+	// index are compared again while ignoring case and diacritics.
+	pub async fn resolve_songs(
+		&self,
+		virtual_paths: Vec<PathBuf>,
+	) -> Vec<Result<index::Song, Error>> {
+		let songs = self.index_manager.get_songs(virtual_paths.clone()).await;
+
+		let missing_paths = songs
+			.iter()
+			.zip(&virtual_paths)
+			.filter(|(song, _)| song.is_err())
+			.map(|(_, virtual_path)| virtual_path.clone())
+			.collect::<Vec<_>>();
+		let mut loose_matches = self
+			.index_manager
+			.find_songs_loosely(missing_paths)
+			.await
+			.into_iter();
+
+		songs
+			.into_iter()
+			.zip(virtual_paths)
+			.map(|(song, virtual_path)| match song {
+				Ok(s) => Ok(s),
+				Err(_) => loose_matches
+					.next()
+					.flatten()
+					.ok_or(Error::CouldNotMapToRealPath(virtual_path)),
+			})
+			.collect()
 	}
 
+	// Lists the playlists owned by a user, followed by public playlists of other users
 	pub async fn list_playlists(&self, owner: &str) -> Result<Vec<PlaylistHeader>, Error> {
 		spawn_blocking({
 			let manager = self.clone();
@@ -93,6 +219,17 @@ impl Manager {
 					.filter_map(|p| p.ok())
 					.map(PlaylistHeader::from)
 					.collect::<Vec<_>>();
+				let num_owned = playlists.len();
+
+				playlists.extend(
+					transaction
+						.scan()
+						.primary::<PlaylistModel>()?
+						.all()?
+						.filter_map(|p| p.ok())
+						.filter(|p| p.public && p.owner != owner)
+						.map(PlaylistHeader::from),
+				);
 
 				let collator_options = {
 					let mut o = CollatorOptions::new();
@@ -101,7 +238,12 @@ impl Manager {
 				};
 				let collator = Collator::try_new(&Default::default(), collator_options).unwrap();
 
-				playlists.sort_by(|a, b| collator.compare(&a.name, &b.name));
+				playlists[..num_owned].sort_by(|a, b| collator.compare(&a.name, &b.name));
+				playlists[num_owned..].sort_by(|a, b| {
+					collator
+						.compare(&a.name, &b.name)
+						.then_with(|| collator.compare(&a.owner, &b.owner))
+				});
 				Ok(playlists)
 			}
 		})
@@ -135,9 +277,16 @@ impl Manager {
 
 				let virtual_paths = songs.into_iter().map(|s| s.virtual_path).collect();
 
+				// Saving over an existing playlist keeps it shared
+				let public = transaction
+					.get()
+					.primary::<PlaylistModel>((owner.as_str(), name.as_str()))?
+					.is_some_and(|p| p.public);
+
 				transaction.upsert::<PlaylistModel>(PlaylistModel {
 					owner: owner.to_owned(),
 					name: name.to_owned(),
+					public,
 					duration: Duration::from_secs(duration),
 					num_songs_by_genre,
 					virtual_paths,
@@ -168,6 +317,253 @@ impl Manager {
 		.await?
 	}
 
+	// Reads a playlist on behalf of `reader`, who may not be its owner if it is public
+	pub async fn read_playlist_as(
+		&self,
+		name: &str,
+		owner: &str,
+		reader: &str,
+	) -> Result<Playlist, Error> {
+		let playlist = self.read_playlist(name, owner).await?;
+		if owner != reader && !playlist.header.public {
+			return Err(Error::PlaylistNotFound);
+		}
+		Ok(playlist)
+	}
+
+	// Hands a playlist over to another user, who becomes the only one able to edit it
+	pub async fn set_playlist_owner(
+		&self,
+		name: &str,
+		owner: &str,
+		new_owner: &str,
+	) -> Result<(), Error> {
+		spawn_blocking({
+			let manager = self.clone();
+			let owner = owner.to_owned();
+			let new_owner = new_owner.to_owned();
+			let name = name.to_owned();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				let Some(playlist) = transaction
+					.get()
+					.primary::<PlaylistModel>((owner.as_str(), name.as_str()))?
+				else {
+					return Err(Error::PlaylistNotFound);
+				};
+				if owner == new_owner {
+					return Ok(());
+				}
+				if transaction
+					.get()
+					.primary::<PlaylistModel>((new_owner.as_str(), name.as_str()))?
+					.is_some()
+				{
+					return Err(Error::DuplicatePlaylist);
+				}
+				let transferred = PlaylistModel {
+					owner: new_owner,
+					name: playlist.name.clone(),
+					public: playlist.public,
+					duration: playlist.duration,
+					num_songs_by_genre: playlist.num_songs_by_genre.clone(),
+					virtual_paths: playlist.virtual_paths.clone(),
+				};
+				// Links shared by the previous owner do not carry over
+				remove_shares(&transaction, &owner, &name)?;
+				transaction.remove::<PlaylistModel>(playlist)?;
+				transaction.insert::<PlaylistModel>(transferred)?;
+				transaction.commit()?;
+				Ok(())
+			}
+		})
+		.await?
+	}
+
+	// Inserts a song in a playlist at `position`, or at the end when it is `None`. Unlike
+	// `save_playlist`, this does not overwrite changes made concurrently by other clients.
+	pub async fn add_song(
+		&self,
+		name: &str,
+		owner: &str,
+		virtual_path: PathBuf,
+		position: Option<usize>,
+	) -> Result<(), Error> {
+		let song = self
+			.resolve_songs(vec![virtual_path.clone()])
+			.await
+			.pop()
+			.ok_or(Error::CouldNotMapToRealPath(virtual_path))??;
+
+		spawn_blocking({
+			let manager = self.clone();
+			let owner = owner.to_owned();
+			let name = name.to_owned();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				let Some(mut playlist) = transaction
+					.get()
+					.primary::<PlaylistModel>((owner.as_str(), name.as_str()))?
+				else {
+					return Err(Error::PlaylistNotFound);
+				};
+
+				let position = position.unwrap_or(playlist.virtual_paths.len());
+				if position > playlist.virtual_paths.len() {
+					return Err(Error::PlaylistPositionOutOfBounds(position));
+				}
+				playlist.virtual_paths.insert(position, song.virtual_path);
+
+				let duration = song.duration.unwrap_or_default() as u64;
+				playlist.duration += Duration::from_secs(duration);
+				for genre in song.genres {
+					*playlist.num_songs_by_genre.entry(genre).or_default() += 1;
+				}
+
+				transaction.upsert::<PlaylistModel>(playlist)?;
+				transaction.commit()?;
+				Ok(())
+			}
+		})
+		.await?
+	}
+
+	pub async fn remove_song_at(&self, name: &str, owner: &str, index: usize) -> Result<(), Error> {
+		spawn_blocking({
+			let manager = self.clone();
+			let owner = owner.to_owned();
+			let name = name.to_owned();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				let Some(mut playlist) = transaction
+					.get()
+					.primary::<PlaylistModel>((owner.as_str(), name.as_str()))?
+				else {
+					return Err(Error::PlaylistNotFound);
+				};
+
+				if index >= playlist.virtual_paths.len() {
+					return Err(Error::PlaylistPositionOutOfBounds(index));
+				}
+				let virtual_path = playlist.virtual_paths.remove(index);
+
+				// Songs no longer in the index leave the playlist statistics unchanged, as
+				// they did not count towards them when the playlist was saved
+				let songs = manager.index_manager.get_songs_blocking(vec![virtual_path]);
+				if let Some(Ok(song)) = songs.into_iter().next() {
+					let duration = song.duration.unwrap_or_default() as u64;
+					playlist.duration = playlist
+						.duration
+						.saturating_sub(Duration::from_secs(duration));
+					for genre in song.genres {
+						if let btree_map::Entry::Occupied(mut count) =
+							playlist.num_songs_by_genre.entry(genre)
+						{
+							*count.get_mut() -= 1;
+							if *count.get() == 0 {
+								count.remove();
+							}
+						}
+					}
+				}
+
+				transaction.upsert::<PlaylistModel>(playlist)?;
+				transaction.commit()?;
+				Ok(())
+			}
+		})
+		.await?
+	}
+
+	pub async fn set_playlist_public(
+		&self,
+		name: &str,
+		owner: &str,
+		public: bool,
+	) -> Result<(), Error> {
+		spawn_blocking({
+			let manager = self.clone();
+			let owner = owner.to_owned();
+			let name = name.to_owned();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				let Some(mut playlist) = transaction
+					.get()
+					.primary::<PlaylistModel>((owner.as_str(), name.as_str()))?
+				else {
+					return Err(Error::PlaylistNotFound);
+				};
+				playlist.public = public;
+				transaction.upsert::<PlaylistModel>(playlist)?;
+				transaction.commit()?;
+				Ok(())
+			}
+		})
+		.await?
+	}
+
+	// Picks the first distinct album arts found among the songs of a playlist, in
+	// playlist order. Returns an empty list when there are none (eg. empty playlists),
+	// in which case clients should display a placeholder.
+	pub async fn get_collage(&self, name: &str, owner: &str) -> Result<Vec<PathBuf>, Error> {
+		let playlist = self.read_playlist(name, owner).await?;
+		let songs = self.index_manager.get_songs(playlist.songs).await;
+
+		let mut seen = HashSet::new();
+		let artworks = songs
+			.into_iter()
+			.filter_map(|s| s.ok()?.artwork)
+			.filter(|a| seen.insert(a.clone()))
+			.take(COLLAGE_SIZE)
+			.collect();
+
+		Ok(artworks)
+	}
+
+	// Creates or replaces a playlist from the content of a PLS file. Entries which cannot be
+	// read or do not match a song in the collection are returned, the other songs are saved.
+	pub async fn import_pls(
+		&self,
+		name: &str,
+		owner: &str,
+		content: &str,
+	) -> Result<Vec<Error>, Error> {
+		let (entries, mut problems) = pls::parse(content);
+		let virtual_paths = entries.into_iter().map(|e| e.file).collect();
+
+		let mut songs = Vec::new();
+		for song in self.resolve_songs(virtual_paths).await {
+			match song {
+				Ok(s) => songs.push(s),
+				Err(e) => problems.push(e),
+			}
+		}
+
+		self.save_playlist(name, owner, songs).await?;
+		Ok(problems)
+	}
+
+	pub async fn export_pls(&self, name: &str, owner: &str) -> Result<String, Error> {
+		let playlist = self.read_playlist(name, owner).await?;
+		let songs = self.index_manager.get_songs(playlist.songs.clone()).await;
+
+		let entries = playlist
+			.songs
+			.into_iter()
+			.zip(songs)
+			.map(|(file, song)| {
+				let song = song.ok();
+				pls::Entry {
+					file,
+					title: song.as_ref().and_then(display_title),
+					length: song.and_then(|s| s.duration),
+				}
+			})
+			.collect::<Vec<_>>();
+
+		Ok(pls::write(&entries))
+	}
+
 	pub async fn delete_playlist(&self, name: &str, owner: &str) -> Result<(), Error> {
 		spawn_blocking({
 			let manager = self.clone();
@@ -183,6 +579,7 @@ impl Manager {
 					Ok(None) => Err(Error::PlaylistNotFound),
 					Err(e) => Err(Error::NativeDatabase(e)),
 				}?;
+				remove_shares(&transaction, &owner, &name)?;
 				transaction.remove::<PlaylistModel>(playlist)?;
 				transaction.commit()?;
 				Ok(())
@@ -190,17 +587,129 @@ impl Manager {
 		})
 		.await?
 	}
+
+	// Returns a token granting read-only access to a playlist and to streaming its songs,
+	// without exposing the rest of the collection. Sharing a playlist again returns a new
+	// token for the same share, and all of them stay valid until the share is revoked.
+	pub async fn share_playlist(&self, name: &str, owner: &str) -> Result<auth::Token, Error> {
+		let share_id = spawn_blocking({
+			let manager = self.clone();
+			let owner = owner.to_owned();
+			let name = name.to_owned();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				if transaction
+					.get()
+					.primary::<PlaylistModel>((owner.as_str(), name.as_str()))?
+					.is_none()
+				{
+					return Err(Error::PlaylistNotFound);
+				}
+				if let Some(share) = find_share(&transaction, &owner, &name)? {
+					return Ok(share.share_id);
+				}
+				let share_id = generate_share_id();
+				transaction.insert::<PlaylistShareModel>(PlaylistShareModel {
+					share_id: share_id.clone(),
+					owner,
+					name,
+				})?;
+				transaction.commit()?;
+				Ok(share_id)
+			}
+		})
+		.await??;
+
+		self.config_manager
+			.generate_share_token(owner, &share_id)
+			.await
+	}
+
+	// Invalidates every token previously issued by `share_playlist` for this playlist
+	pub async fn revoke_playlist_share(&self, name: &str, owner: &str) -> Result<(), Error> {
+		spawn_blocking({
+			let manager = self.clone();
+			let owner = owner.to_owned();
+			let name = name.to_owned();
+			move || {
+				let transaction = manager.db.rw_transaction()?;
+				remove_shares(&transaction, &owner, &name)?;
+				transaction.commit()?;
+				Ok(())
+			}
+		})
+		.await?
+	}
+
+	// Reads the playlist a share token grants access to
+	pub async fn read_shared_playlist(&self, token: &auth::Token) -> Result<Playlist, Error> {
+		let authorization = self
+			.config_manager
+			.authenticate(token, auth::Scope::PlaylistShare)
+			.await?;
+		let share_id = authorization.share_id.ok_or(Error::InvalidAuthToken)?;
+
+		let (name, owner) = spawn_blocking({
+			let manager = self.clone();
+			move || {
+				let transaction = manager.db.r_transaction()?;
+				match transaction.get().primary::<PlaylistShareModel>(share_id)? {
+					Some(s) if s.owner == authorization.username => Ok((s.name, s.owner)),
+					_ => Err(Error::InvalidAuthToken),
+				}
+			}
+		})
+		.await??;
+
+		self.read_playlist(&name, &owner).await
+	}
+}
+
+fn generate_share_id() -> String {
+	let mut bytes = [0; SHARE_ID_LENGTH];
+	OsRng.fill_bytes(&mut bytes);
+	bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn find_share(
+	transaction: &RwTransaction,
+	owner: &str,
+	name: &str,
+) -> Result<Option<PlaylistShareModel>, Error> {
+	Ok(transaction
+		.scan()
+		.secondary::<PlaylistShareModel>(PlaylistShareModelKey::owner)?
+		.range(owner..=owner)?
+		.filter_map(|s| s.ok())
+		.find(|s| s.name == name))
+}
+
+fn remove_shares(transaction: &RwTransaction, owner: &str, name: &str) -> Result<(), Error> {
+	while let Some(share) = find_share(transaction, owner, name)? {
+		transaction.remove::<PlaylistShareModel>(share)?;
+	}
+	Ok(())
+}
+
+// Title shown by players for a song, such as `Artist - Title`
+fn display_title(song: &index::Song) -> Option<String> {
+	let title = song.title.as_ref()?;
+	match song.artists.is_empty() {
+		true => Some(title.clone()),
+		false => Some(format!("{} - {title}", song.artists.join(", "))),
+	}
 }
 
 #[cfg(test)]
 mod test {
 	use std::path::PathBuf;
 
-	use crate::app::index;
 	use crate::app::test::{self, Context};
+	use crate::app::{auth, index, Error};
 	use crate::test_name;
 
 	const TEST_USER: &str = "test_user";
+	const OTHER_USER: &str = "other_user";
 	const TEST_PASSWORD: &str = "password";
 	const TEST_PLAYLIST_NAME: &str = "Chill & Grill";
 	const TEST_MOUNT_NAME: &str = "root";
@@ -344,31 +853,547 @@ mod test {
 	}
 
 	#[tokio::test]
-	async fn playlists_are_sorted_alphabetically() {
+	async fn collage_uses_distinct_artworks() {
 		let ctx = test::ContextBuilder::new(test_name!())
 			.user(TEST_USER, TEST_PASSWORD, false)
 			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 			.build()
 			.await;
 
-		for name in ["ax", "b", "Ay", "B", "àz"] {
-			ctx.playlist_manager
-				.save_playlist(name, TEST_USER, Vec::new())
-				.await
-				.unwrap();
-		}
+		ctx.scanner.run_scan().await.unwrap();
+		let songs = list_all_songs(&ctx).await;
 
-		let playlists = ctx
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs)
+			.await
+			.unwrap();
+
+		let collage = ctx
 			.playlist_manager
-			.list_playlists(TEST_USER)
+			.get_collage(TEST_PLAYLIST_NAME, TEST_USER)
 			.await
 			.unwrap();
+		assert!(!collage.is_empty());
+		assert!(collage.len() <= 4);
+		for (i, artwork) in collage.iter().enumerate() {
+			assert!(!collage[..i].contains(artwork));
+		}
+	}
 
-		let names = playlists
-			.into_iter()
-			.map(|p| p.name.to_string())
-			.collect::<Vec<_>>();
+	#[tokio::test]
+	async fn empty_playlist_has_empty_collage() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.build()
+			.await;
 
-		assert_eq!(names, vec!["ax", "Ay", "àz", "B", "b"]);
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, Vec::new())
+			.await
+			.unwrap();
+
+		let collage = ctx
+			.playlist_manager
+			.get_collage(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		assert!(collage.is_empty());
+	}
+
+	#[tokio::test]
+	async fn playlists_are_sorted_alphabetically() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		for name in ["ax", "b", "Ay", "B", "àz"] {
+			ctx.playlist_manager
+				.save_playlist(name, TEST_USER, Vec::new())
+				.await
+				.unwrap();
+		}
+
+		let playlists = ctx
+			.playlist_manager
+			.list_playlists(TEST_USER)
+			.await
+			.unwrap();
+
+		let names = playlists
+			.into_iter()
+			.map(|p| p.name.to_string())
+			.collect::<Vec<_>>();
+
+		assert_eq!(names, vec!["ax", "Ay", "àz", "B", "b"]);
+	}
+
+	fn song_path(components: &[&str]) -> PathBuf {
+		std::iter::once(TEST_MOUNT_NAME)
+			.chain(components.iter().copied())
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn can_add_songs() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, Vec::new())
+			.await
+			.unwrap();
+
+		let first = song_path(&["Khemmis", "Hunted", "01 - Above The Water.mp3"]);
+		let second = song_path(&["Khemmis", "Hunted", "02 - Candlelight.mp3"]);
+		let third = song_path(&["Khemmis", "Hunted", "03 - Three Gates.mp3"]);
+		for (path, position) in [(&first, None), (&third, None), (&second, Some(1))] {
+			ctx.playlist_manager
+				.add_song(TEST_PLAYLIST_NAME, TEST_USER, path.clone(), position)
+				.await
+				.unwrap();
+		}
+
+		let playlist = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		assert_eq!(playlist.songs, vec![first, second, third]);
+		assert_eq!(playlist.header.num_songs_by_genre.get("Metal"), Some(&3));
+
+		let result = ctx
+			.playlist_manager
+			.add_song(
+				TEST_PLAYLIST_NAME,
+				TEST_USER,
+				song_path(&["Khemmis", "Hunted", "Not a song.mp3"]),
+				None,
+			)
+			.await;
+		assert!(matches!(result, Err(Error::CouldNotMapToRealPath(_))));
+
+		let result = ctx
+			.playlist_manager
+			.add_song(
+				TEST_PLAYLIST_NAME,
+				TEST_USER,
+				song_path(&["Khemmis", "Hunted", "04 - Beyond The Door.mp3"]),
+				Some(4),
+			)
+			.await;
+		assert!(matches!(result, Err(Error::PlaylistPositionOutOfBounds(4))));
+	}
+
+	#[tokio::test]
+	async fn can_remove_songs() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+		let songs = list_all_songs(&ctx).await;
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs.clone())
+			.await
+			.unwrap();
+		let before = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+
+		ctx.playlist_manager
+			.remove_song_at(TEST_PLAYLIST_NAME, TEST_USER, 0)
+			.await
+			.unwrap();
+
+		let after = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		assert_eq!(after.songs, before.songs[1..]);
+		let removed_duration = songs[0].duration.unwrap() as u64;
+		assert_eq!(
+			after.header.duration.as_secs(),
+			before.header.duration.as_secs() - removed_duration
+		);
+
+		let result = ctx
+			.playlist_manager
+			.remove_song_at(TEST_PLAYLIST_NAME, TEST_USER, 12)
+			.await;
+		assert!(matches!(
+			result,
+			Err(Error::PlaylistPositionOutOfBounds(12))
+		));
+	}
+
+	#[tokio::test]
+	async fn can_transfer_playlist_ownership() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.user(OTHER_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+		let songs = list_all_songs(&ctx).await;
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs)
+			.await
+			.unwrap();
+		ctx.playlist_manager
+			.set_playlist_owner(TEST_PLAYLIST_NAME, TEST_USER, OTHER_USER)
+			.await
+			.unwrap();
+
+		let playlists = ctx.playlist_manager.list_playlists(TEST_USER).await;
+		assert!(playlists.unwrap().is_empty());
+		let result = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await;
+		assert!(matches!(result, Err(Error::PlaylistNotFound)));
+
+		let playlist = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, OTHER_USER)
+			.await
+			.unwrap();
+		assert_eq!(playlist.header.owner, OTHER_USER);
+		assert_eq!(playlist.songs.len(), 13);
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, Vec::new())
+			.await
+			.unwrap();
+		let result = ctx
+			.playlist_manager
+			.set_playlist_owner(TEST_PLAYLIST_NAME, TEST_USER, OTHER_USER)
+			.await;
+		assert!(matches!(result, Err(Error::DuplicatePlaylist)));
+	}
+
+	#[tokio::test]
+	async fn public_playlists_are_read_only_for_others() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.user(OTHER_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+		let songs = list_all_songs(&ctx).await;
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs.clone())
+			.await
+			.unwrap();
+
+		let playlists = ctx.playlist_manager.list_playlists(OTHER_USER).await;
+		assert!(playlists.unwrap().is_empty());
+		let result = ctx
+			.playlist_manager
+			.read_playlist_as(TEST_PLAYLIST_NAME, TEST_USER, OTHER_USER)
+			.await;
+		assert!(matches!(result, Err(Error::PlaylistNotFound)));
+
+		ctx.playlist_manager
+			.set_playlist_public(TEST_PLAYLIST_NAME, TEST_USER, true)
+			.await
+			.unwrap();
+
+		// Saving the playlist again keeps it public
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs)
+			.await
+			.unwrap();
+
+		let playlists = ctx
+			.playlist_manager
+			.list_playlists(OTHER_USER)
+			.await
+			.unwrap();
+		assert_eq!(playlists.len(), 1);
+		assert_eq!(playlists[0].owner, TEST_USER);
+		assert!(playlists[0].public);
+
+		let playlist = ctx
+			.playlist_manager
+			.read_playlist_as(TEST_PLAYLIST_NAME, TEST_USER, OTHER_USER)
+			.await
+			.unwrap();
+		assert_eq!(playlist.songs.len(), 13);
+
+		// Edits from other users only affect playlists of their own
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, OTHER_USER, Vec::new())
+			.await
+			.unwrap();
+		ctx.playlist_manager
+			.delete_playlist(TEST_PLAYLIST_NAME, OTHER_USER)
+			.await
+			.unwrap();
+		let playlist = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		assert_eq!(playlist.songs.len(), 13);
+	}
+
+	#[tokio::test]
+	async fn can_share_playlist() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+		ctx.scanner.run_scan().await.unwrap();
+
+		let songs = list_all_songs(&ctx).await;
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs)
+			.await
+			.unwrap();
+
+		let token = ctx
+			.playlist_manager
+			.share_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		let playlist = ctx
+			.playlist_manager
+			.read_shared_playlist(&token)
+			.await
+			.unwrap();
+		assert_eq!(playlist.header.name, TEST_PLAYLIST_NAME);
+		assert_eq!(playlist.header.owner, TEST_USER);
+		assert_eq!(playlist.songs.len(), 13);
+
+		// Share tokens do not grant access to anything else
+		assert!(matches!(
+			ctx.config_manager
+				.authenticate(&token, auth::Scope::PolarisAuth)
+				.await,
+			Err(Error::IncorrectAuthorizationScope)
+		));
+	}
+
+	#[tokio::test]
+	async fn revoking_share_invalidates_tokens() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.build()
+			.await;
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, Vec::new())
+			.await
+			.unwrap();
+
+		let share = || {
+			ctx.playlist_manager
+				.share_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		};
+		let first_token = share().await.unwrap();
+		let second_token = share().await.unwrap();
+		for token in [&first_token, &second_token] {
+			assert!(ctx
+				.playlist_manager
+				.read_shared_playlist(token)
+				.await
+				.is_ok());
+		}
+
+		ctx.playlist_manager
+			.revoke_playlist_share(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		for token in [&first_token, &second_token] {
+			assert!(matches!(
+				ctx.playlist_manager.read_shared_playlist(token).await,
+				Err(Error::InvalidAuthToken)
+			));
+		}
+
+		// Sharing again does not revive revoked tokens
+		let third_token = share().await.unwrap();
+		assert!(ctx
+			.playlist_manager
+			.read_shared_playlist(&third_token)
+			.await
+			.is_ok());
+		assert!(ctx
+			.playlist_manager
+			.read_shared_playlist(&first_token)
+			.await
+			.is_err());
+
+		// Neither does deleting and recreating the playlist
+		ctx.playlist_manager
+			.delete_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, Vec::new())
+			.await
+			.unwrap();
+		assert!(ctx
+			.playlist_manager
+			.read_shared_playlist(&third_token)
+			.await
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn cannot_share_missing_playlist() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.build()
+			.await;
+
+		assert!(matches!(
+			ctx.playlist_manager
+				.share_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+				.await,
+			Err(Error::PlaylistNotFound)
+		));
+	}
+
+	#[tokio::test]
+	async fn can_round_trip_pls() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+
+		let songs = list_all_songs(&ctx).await;
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, songs)
+			.await
+			.unwrap();
+
+		let pls = ctx
+			.playlist_manager
+			.export_pls(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		assert!(pls.contains("Title1=Khemmis - Above The Water\n"));
+		assert!(pls.contains("NumberOfEntries=13\n"));
+
+		let problems = ctx
+			.playlist_manager
+			.import_pls("Imported", TEST_USER, &pls)
+			.await
+			.unwrap();
+		assert!(problems.is_empty());
+
+		let original = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		let imported = ctx
+			.playlist_manager
+			.read_playlist("Imported", TEST_USER)
+			.await
+			.unwrap();
+		assert_eq!(imported.songs, original.songs);
+		assert_eq!(imported.header.duration, original.header.duration);
+	}
+
+	#[tokio::test]
+	async fn import_pls_follows_indices_and_reports_problems() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+
+		let pls = "[playlist]\n\
+			File7=root/Khemmis/Hunted/01 - Above The Water.mp3\n\
+			File2=root/Khemmis/Hunted/02 - Candlelight.mp3\n\
+			File4=root/Khemmis/Hunted/06 - Missing.mp3\n\
+			Title5\n\
+			NumberOfEntries=3\n";
+		let problems = ctx
+			.playlist_manager
+			.import_pls(TEST_PLAYLIST_NAME, TEST_USER, pls)
+			.await
+			.unwrap();
+		assert_eq!(problems.len(), 2);
+		assert!(matches!(problems[0], Error::PlaylistEntryMalformed(5)));
+		assert!(matches!(problems[1], Error::CouldNotMapToRealPath(_)));
+
+		let playlist = ctx
+			.playlist_manager
+			.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+			.await
+			.unwrap();
+		assert_eq!(
+			playlist.songs,
+			vec![
+				PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]),
+				PathBuf::from_iter([
+					TEST_MOUNT_NAME,
+					"Khemmis",
+					"Hunted",
+					"01 - Above The Water.mp3"
+				]),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn resolve_songs_ignores_case_and_diacritics() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+
+		let requested = vec![
+			PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]),
+			PathBuf::from_iter([TEST_MOUNT_NAME, "KHEMMIS", "hunted", "02 - candlelight.mp3"]),
+			PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "03 - Thrée Gates.mp3"]),
+			PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "06 - Missing.mp3"]),
+		];
+
+		let songs = ctx.playlist_manager.resolve_songs(requested).await;
+		assert_eq!(songs.len(), 4);
+		assert_eq!(
+			songs[0].as_ref().unwrap().virtual_path,
+			PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"])
+		);
+		assert_eq!(
+			songs[1].as_ref().unwrap().virtual_path,
+			PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"])
+		);
+		assert_eq!(
+			songs[2].as_ref().unwrap().virtual_path,
+			PathBuf::from_iter([TEST_MOUNT_NAME, "Khemmis", "Hunted", "03 - Three Gates.mp3"])
+		);
+		assert!(matches!(songs[3], Err(Error::CouldNotMapToRealPath(_))));
 	}
 }
@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, fmt::Write, path::PathBuf};
+
+use crate::app::Error;
+
+// One song of a PLS playlist
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Entry {
+	pub file: PathBuf,
+	pub title: Option<String>,
+	pub length: Option<i64>, // In seconds
+}
+
+#[derive(Default)]
+struct PartialEntry {
+	line: usize, // First line mentioning this entry
+	file: Option<PathBuf>,
+	title: Option<String>,
+	length: Option<i64>,
+}
+
+// Entries are listed in the order of their numeric index, which does not need to be
+// contiguous. Lines which cannot be understood are reported by line number, along with
+// entries which have a title or length but no file.
+pub fn parse(content: &str) -> (Vec<Entry>, Vec<Error>) {
+	let mut entries = BTreeMap::<u32, PartialEntry>::new();
+	let mut problems = Vec::new();
+
+	for (line_number, line) in content.lines().enumerate() {
+		let line_number = line_number + 1;
+		let line = line.trim();
+		if line.is_empty() || line.eq_ignore_ascii_case("[playlist]") {
+			continue;
+		}
+
+		let Some((key, value)) = line.split_once('=') else {
+			problems.push(Error::PlaylistEntryMalformed(line_number));
+			continue;
+		};
+		let key = key.trim().to_ascii_lowercase();
+		let value = value.trim();
+		if key == "numberofentries" || key == "version" {
+			continue;
+		}
+
+		let Some((field, index)) = split_index(&key) else {
+			problems.push(Error::PlaylistEntryMalformed(line_number));
+			continue;
+		};
+		let entry = entries.entry(index).or_insert_with(|| PartialEntry {
+			line: line_number,
+			..Default::default()
+		});
+		let is_valid = match field {
+			"file" if entry.file.is_none() && !value.is_empty() => {
+				entry.file = Some(PathBuf::from(value));
+				true
+			}
+			"title" if entry.title.is_none() => {
+				entry.title = Some(value.to_owned());
+				true
+			}
+			"length" if entry.length.is_none() => {
+				entry.length = value.parse().ok();
+				entry.length.is_some()
+			}
+			_ => false,
+		};
+		if !is_valid {
+			problems.push(Error::PlaylistEntryMalformed(line_number));
+		}
+	}
+
+	let mut songs = Vec::with_capacity(entries.len());
+	for entry in entries.into_values() {
+		match entry.file {
+			Some(file) => songs.push(Entry {
+				file,
+				title: entry.title,
+				length: entry.length,
+			}),
+			None => problems.push(Error::PlaylistEntryMalformed(entry.line)),
+		}
+	}
+
+	(songs, problems)
+}
+
+// Splits keys like `file12` into `file` and 12
+fn split_index(key: &str) -> Option<(&str, u32)> {
+	let digits = key.find(|c: char| c.is_ascii_digit())?;
+	let (field, index) = key.split_at(digits);
+	Some((field, index.parse().ok()?))
+}
+
+pub fn write(entries: &[Entry]) -> String {
+	let mut pls = String::from("[playlist]\n");
+	for (index, entry) in entries.iter().enumerate() {
+		let n = index + 1;
+		writeln!(pls, "File{n}={}", entry.file.to_string_lossy()).unwrap();
+		if let Some(title) = &entry.title {
+			writeln!(pls, "Title{n}={title}").unwrap();
+		}
+		if let Some(length) = entry.length {
+			writeln!(pls, "Length{n}={length}").unwrap();
+		}
+	}
+	writeln!(pls, "NumberOfEntries={}", entries.len()).unwrap();
+	writeln!(pls, "Version=2").unwrap();
+	pls
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn entry(file: &str) -> Entry {
+		Entry {
+			file: PathBuf::from(file),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn can_round_trip() {
+		let entries = vec![
+			Entry {
+				file: PathBuf::from("root/Khemmis/Hunted/01 - Above The Water.mp3"),
+				title: Some("Khemmis - Above The Water".to_owned()),
+				length: Some(429),
+			},
+			entry("root/Tobokegao/Picnic/07 - なぜ (Why).mp3"),
+		];
+		let (parsed, problems) = parse(&write(&entries));
+		assert!(problems.is_empty());
+		assert_eq!(parsed, entries);
+	}
+
+	#[test]
+	fn orders_entries_by_index() {
+		let content = "[playlist]\n\
+			File3=c.mp3\n\
+			File1=a.mp3\n\
+			Title1=A\n\
+			File10=d.mp3\n\
+			NumberOfEntries=3\n\
+			Version=2\n";
+		let (entries, problems) = parse(content);
+		assert!(problems.is_empty());
+		assert_eq!(
+			entries,
+			vec![
+				Entry {
+					title: Some("A".to_owned()),
+					..entry("a.mp3")
+				},
+				entry("c.mp3"),
+				entry("d.mp3"),
+			]
+		);
+	}
+
+	#[test]
+	fn reports_malformed_entries() {
+		let content = "[playlist]\n\
+			File1=a.mp3\n\
+			not an entry\n\
+			FileX=b.mp3\n\
+			File1=c.mp3\n\
+			Title2=No file\n\
+			Length3=long\n\
+			File3=d.mp3\n";
+		let (entries, problems) = parse(content);
+		assert_eq!(entries, vec![entry("a.mp3"), entry("d.mp3")]);
+		let lines = problems
+			.iter()
+			.map(|p| match p {
+				Error::PlaylistEntryMalformed(line) => *line,
+				_ => panic!("unexpected problem: {p}"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![3, 4, 5, 7, 6]);
+	}
+}
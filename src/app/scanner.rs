@@ -1,28 +1,48 @@
-use log::{error, info};
+use log::{error, info, warn};
 use notify::{RecommendedWatcher, Watcher};
-use notify_debouncer_full::{Debouncer, FileIdMap};
+use notify_debouncer_full::{DebounceEventResult, Debouncer, FileIdMap};
 use rayon::{Scope, ThreadPoolBuilder};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Sender, TryRecvError};
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::SystemTime;
 use std::{cmp::min, time::Duration};
 use tokio::sync::mpsc::unbounded_channel;
 use tokio::sync::{Notify, RwLock};
 use tokio::task::JoinSet;
 use tokio::time::Instant;
+use tracing::{field::Empty, Span};
 
 use crate::app::{config, formats, index, Error};
+use crate::utils::{get_audio_format, AudioFormat};
+
+mod genres;
+
+// How often the partial index of an initial scan is saved to disk, so that a crash
+// does not lose all progress.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+// Changes whenever the same file would be read into a different song, so that songs from
+// earlier scans are read again instead of reused
+const METADATA_VERSION: u32 = 6;
+// Audio formats which cannot be indexed. Unlike other unrecognized files (images, text
+// files, etc.), these are reported as skipped after each scan.
+const UNSUPPORTED_AUDIO_EXTENSIONS: &[&str] =
+	&["aac", "ac3", "dff", "dsf", "mka", "mp2", "tta", "wma", "wv"];
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Directory {
 	pub virtual_path: PathBuf,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Song {
 	pub real_path: PathBuf,
 	pub virtual_path: PathBuf,
@@ -32,16 +52,79 @@ pub struct Song {
 	pub artists: Vec<String>,
 	pub album_artists: Vec<String>,
 	pub year: Option<i64>,
+	pub year_inferred: bool, // Whether `year` comes from the file path rather than from tags
+	pub original_year: Option<i64>, // Falls back to `year` when the original release date is unknown
+	pub rating: Option<i64>,
 	pub album: Option<String>,
+	pub disc_subtitle: Option<String>,
+	pub grouping: Option<String>,
+	pub work: Option<String>,
+	pub movement: Option<String>,
+	pub movement_number: Option<i64>,
 	pub artwork: Option<PathBuf>,
 	pub duration: Option<i64>,
+	pub channels: Option<i64>,
+	pub bitrate: Option<i64>,
+	pub sample_rate: Option<i64>,
+	pub bit_depth: Option<i64>,
+	pub codec: Option<String>,
+	pub lossless: Option<bool>, // Unknown when neither the codec nor the file extension tell
+	pub encoder_delay: Option<i64>,
+	pub encoder_padding: Option<i64>,
+	pub file_size: Option<i64>,
 	pub lyricists: Vec<String>,
 	pub composers: Vec<String>,
 	pub genres: Vec<String>,
 	pub labels: Vec<String>,
+	pub conductors: Vec<String>,
+	pub ensembles: Vec<String>,
+	pub custom_fields: Vec<(String, String)>,
 	pub date_added: i64,
 }
 
+impl From<index::Song> for Song {
+	fn from(s: index::Song) -> Self {
+		Self {
+			real_path: s.real_path,
+			virtual_path: s.virtual_path,
+			track_number: s.track_number,
+			disc_number: s.disc_number,
+			title: s.title,
+			artists: s.artists,
+			album_artists: s.album_artists,
+			year: s.year,
+			year_inferred: s.year_inferred,
+			original_year: s.original_year,
+			rating: s.rating,
+			album: s.album,
+			disc_subtitle: s.disc_subtitle,
+			grouping: s.grouping,
+			work: s.work,
+			movement: s.movement,
+			movement_number: s.movement_number,
+			artwork: s.artwork,
+			duration: s.duration,
+			channels: s.channels,
+			bitrate: s.bitrate,
+			sample_rate: s.sample_rate,
+			bit_depth: s.bit_depth,
+			codec: s.codec,
+			lossless: s.lossless,
+			encoder_delay: s.encoder_delay,
+			encoder_padding: s.encoder_padding,
+			file_size: s.file_size,
+			lyricists: s.lyricists,
+			composers: s.composers,
+			genres: s.genres,
+			labels: s.labels,
+			conductors: s.conductors,
+			ensembles: s.ensembles,
+			custom_fields: s.custom_fields,
+			date_added: s.date_added,
+		}
+	}
+}
+
 #[derive(Clone, Default)]
 pub enum State {
 	#[default]
@@ -51,20 +134,110 @@ pub enum State {
 	UpToDate,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct Parameters {
-	artwork_regex: Option<Regex>,
+	artwork_regexes: Vec<Regex>,
 	mount_dirs: Vec<config::MountDir>,
+	normalization: index::Normalization,
+	path_indexing: index::PathIndexing,
+	bigram_threshold: index::BigramThreshold,
+	word_boundary_fields: Vec<index::TextField>,
+	watch_filesystem: bool,
+	audio_extensions: Option<Vec<String>>,
+	custom_fields: Vec<String>,
+	disabled_formats: Vec<AudioFormat>,
+	follow_symlinks: bool,
+	index_disabled_formats: bool,
+	infer_year_from_path: bool,
+	num_threads: Option<NonZeroUsize>,
+	various_artists_label: String,
 }
 
 impl PartialEq for Parameters {
 	fn eq(&self, other: &Self) -> bool {
-		self.artwork_regex.as_ref().map(|r| r.as_str())
-			== other.artwork_regex.as_ref().map(|r| r.as_str())
+		self.artwork_regexes
+			.iter()
+			.map(|r| r.as_str())
+			.eq(other.artwork_regexes.iter().map(|r| r.as_str()))
 			&& self.mount_dirs == other.mount_dirs
+			&& self.normalization == other.normalization
+			&& self.path_indexing == other.path_indexing
+			&& self.bigram_threshold == other.bigram_threshold
+			&& self.word_boundary_fields == other.word_boundary_fields
+			&& self.watch_filesystem == other.watch_filesystem
+			&& self.audio_extensions == other.audio_extensions
+			&& self.custom_fields == other.custom_fields
+			&& self.disabled_formats == other.disabled_formats
+			&& self.follow_symlinks == other.follow_symlinks
+			&& self.index_disabled_formats == other.index_disabled_formats
+			&& self.infer_year_from_path == other.infer_year_from_path
+			&& self.various_artists_label == other.various_artists_label
+		// Changing the number of threads alone does not warrant a new scan
 	}
 }
 
+impl Parameters {
+	// Identifies the settings which affect the content of scanned songs. Songs read
+	// with a different fingerprint cannot be reused and must be read again.
+	fn fingerprint(&self) -> u64 {
+		let mut hasher = DefaultHasher::new();
+		METADATA_VERSION.hash(&mut hasher);
+		self.custom_fields.hash(&mut hasher);
+		self.disabled_formats.hash(&mut hasher);
+		self.index_disabled_formats.hash(&mut hasher);
+		self.infer_year_from_path.hash(&mut hasher);
+		self.various_artists_label.hash(&mut hasher);
+		hasher.finish()
+	}
+
+	// Album art patterns for files under a virtual path, from most to least preferred.
+	// A pattern set on the enclosing mount replaces the global ones.
+	fn artwork_regexes_for(&self, virtual_path: &Path) -> Vec<Regex> {
+		let mount_pattern = self
+			.mount_dirs
+			.iter()
+			.find(|m| virtual_path.starts_with(&m.name))
+			.and_then(|m| m.album_art_pattern.as_deref());
+		let patterns = match mount_pattern {
+			Some(p) => vec![p],
+			None => self.artwork_regexes.iter().map(|r| r.as_str()).collect(),
+		};
+		patterns
+			.into_iter()
+			.filter_map(|p| RegexBuilder::new(p).case_insensitive(true).build().ok())
+			.collect()
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+	UnsupportedFormat,
+	DisabledFormat,
+	EmptyFile,
+	UnreadableFile(String),
+	UnreadableMetadata(String),
+}
+
+impl std::fmt::Display for SkipReason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SkipReason::UnsupportedFormat => write!(f, "Unsupported audio format"),
+			SkipReason::DisabledFormat => write!(f, "Audio format disabled in configuration"),
+			SkipReason::EmptyFile => write!(f, "File is empty"),
+			SkipReason::UnreadableFile(e) => write!(f, "Could not read file: {e}"),
+			SkipReason::UnreadableMetadata(e) => write!(f, "Could not read metadata: {e}"),
+		}
+	}
+}
+
+// Audio file which was left out of the index during the last scan
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedFile {
+	pub real_path: PathBuf,
+	pub virtual_path: PathBuf,
+	pub reason: SkipReason,
+}
+
 #[derive(Clone, Default)]
 pub struct Status {
 	pub state: State,
@@ -79,24 +252,39 @@ pub struct Scanner {
 	config_manager: config::Manager,
 	file_watcher: Arc<RwLock<Option<Debouncer<RecommendedWatcher, FileIdMap>>>>,
 	on_file_change: Arc<Notify>,
+	changed_directories: Arc<Mutex<HashSet<PathBuf>>>, // Real paths of directories with pending changes
+	scan_lock: Arc<tokio::sync::Mutex<()>>, // Prevents full scans and directory rescans from overlapping
 	pending_scan: Arc<Notify>,
+	full_scan_requested: Arc<AtomicBool>, // Next scan reads every file, instead of reusing unchanged songs
+	on_scan_start: Arc<Notify>,
+	on_scan_end: Arc<Notify>, // Only signaled by scans which completed successfully
 	status: Arc<RwLock<Status>>,
 	parameters: Arc<RwLock<Option<Parameters>>>,
+	skipped_files: Arc<RwLock<Vec<SkippedFile>>>,
+	report_file_path: PathBuf,
 }
 
 impl Scanner {
 	pub async fn new(
 		index_manager: index::Manager,
 		config_manager: config::Manager,
+		report_file_path: PathBuf,
 	) -> Result<Self, Error> {
 		let scanner = Self {
 			index_manager,
 			config_manager: config_manager.clone(),
 			file_watcher: Arc::default(),
 			on_file_change: Arc::default(),
+			changed_directories: Arc::default(),
+			scan_lock: Arc::default(),
 			pending_scan: Arc::new(Notify::new()),
+			full_scan_requested: Arc::default(),
+			on_scan_start: Arc::default(),
+			on_scan_end: Arc::default(),
 			status: Arc::new(RwLock::new(Status::default())),
 			parameters: Arc::default(),
+			skipped_files: Arc::default(),
+			report_file_path,
 		};
 
 		let abort_scan = Arc::new(Notify::new());
@@ -137,17 +325,66 @@ impl Scanner {
 			}
 		});
 
+		tokio::spawn({
+			let scanner = scanner.clone();
+			async move {
+				loop {
+					scanner.on_file_change.notified().await;
+					while tokio::time::timeout(
+						Duration::from_secs(2),
+						scanner.on_file_change.notified(),
+					)
+					.await
+					.is_ok()
+					{}
+					// Changes reported while these scans run accumulate and are handled
+					// together on the next iteration.
+					let directories =
+						std::mem::take(&mut *scanner.changed_directories.lock().unwrap());
+					let roots = minimal_roots(directories);
+					let virtual_paths = scanner.config_manager.map_to_virtual(&roots).await;
+					for (real_path, virtual_path) in roots.iter().zip(virtual_paths) {
+						let virtual_path = match virtual_path {
+							Ok(p) => p,
+							Err(e) => {
+								error!("Could not rescan `{}`: {e}", real_path.display());
+								continue;
+							}
+						};
+						if let Err(e) = scanner.rescan_directory(&virtual_path).await {
+							error!("Error while rescanning `{}`: {e}", virtual_path.display());
+						}
+					}
+				}
+			}
+		});
+
 		Ok(scanner)
 	}
 
 	async fn setup_file_watcher(
 		config_manager: &config::Manager,
 		on_file_changed: Arc<Notify>,
+		changed_directories: Arc<Mutex<HashSet<PathBuf>>>,
 	) -> Result<Debouncer<RecommendedWatcher, FileIdMap>, Error> {
-		let mut debouncer =
-			notify_debouncer_full::new_debouncer(Duration::from_millis(100), None, move |_| {
-				on_file_changed.notify_waiters();
-			})?;
+		let mut debouncer = notify_debouncer_full::new_debouncer(
+			Duration::from_millis(100),
+			None,
+			move |result: DebounceEventResult| {
+				let Ok(events) = result else {
+					return;
+				};
+				let mut changed_directories = changed_directories.lock().unwrap();
+				for event in events {
+					for path in &event.paths {
+						if let Some(parent) = path.parent() {
+							changed_directories.insert(parent.to_owned());
+						}
+					}
+				}
+				on_file_changed.notify_one();
+			},
+		)?;
 
 		let mount_dirs = config_manager.get_mounts().await;
 		for mount_dir in &mount_dirs {
@@ -163,26 +400,38 @@ impl Scanner {
 	}
 
 	async fn wait_for_change(&self) {
-		tokio::select! {
-			_ = async {
-				loop {
-					self.config_manager.on_config_change().await;
-					if *self.parameters.read().await == Some(self.read_parameters().await) {
-						continue;
-					}
-					break;
-				}
-			} => {},
-			_ = self.on_file_change.notified() => {},
+		loop {
+			self.config_manager.on_config_change().await;
+			if *self.parameters.read().await == Some(self.read_parameters().await) {
+				continue;
+			}
+			break;
 		}
 	}
 
 	async fn read_parameters(&self) -> Parameters {
-		let album_art_pattern = self.config_manager.get_index_album_art_pattern().await;
-		let artwork_regex = Regex::new(&format!("(?i){}", &album_art_pattern)).ok();
 		Parameters {
-			artwork_regex,
+			artwork_regexes: self.config_manager.get_index_album_art_patterns().await,
 			mount_dirs: self.config_manager.get_mounts().await,
+			normalization: self.config_manager.get_text_normalization().await,
+			path_indexing: self.config_manager.get_path_indexing().await,
+			bigram_threshold: self.config_manager.get_search_bigram_threshold().await,
+			word_boundary_fields: self.config_manager.get_search_word_boundary_fields().await,
+			watch_filesystem: self.config_manager.get_watch_filesystem().await,
+			audio_extensions: self.config_manager.get_audio_extensions().await,
+			custom_fields: self
+				.config_manager
+				.get_custom_fields()
+				.await
+				.iter()
+				.map(|f| f.to_lowercase())
+				.collect(),
+			disabled_formats: self.config_manager.get_disabled_formats().await,
+			follow_symlinks: self.config_manager.get_follow_symlinks().await,
+			index_disabled_formats: self.config_manager.get_index_disabled_formats().await,
+			infer_year_from_path: self.config_manager.get_infer_year_from_path().await,
+			num_threads: self.config_manager.get_scan_threads().await,
+			various_artists_label: self.config_manager.get_various_artists_label().await,
 		}
 	}
 
@@ -190,6 +439,40 @@ impl Scanner {
 		self.status.read().await.clone()
 	}
 
+	pub async fn get_skipped_files(&self) -> Vec<SkippedFile> {
+		self.skipped_files.read().await.clone()
+	}
+
+	// Replaces the skipped files found within `virtual_path` (or the entire collection
+	// when `None`), and writes them to the report file if enabled
+	async fn update_skipped_files(&self, virtual_path: Option<&Path>, found: Vec<SkippedFile>) {
+		let report = {
+			let mut skipped_files = self.skipped_files.write().await;
+			match virtual_path {
+				Some(p) => skipped_files.retain(|f| !f.virtual_path.starts_with(p)),
+				None => skipped_files.clear(),
+			}
+			skipped_files.extend(found);
+			skipped_files.sort_by(|a, b| a.virtual_path.cmp(&b.virtual_path));
+			if !skipped_files.is_empty() {
+				info!("{} audio files could not be indexed", skipped_files.len());
+			}
+			skipped_files
+				.iter()
+				.map(|f| format!("{}\t{}\n", f.real_path.display(), f.reason))
+				.collect::<String>()
+		};
+
+		if self.config_manager.get_write_scan_report().await {
+			if let Err(e) = tokio::fs::write(&self.report_file_path, report).await {
+				error!(
+					"Could not write scan report to `{}`: {e}",
+					self.report_file_path.display()
+				);
+			}
+		}
+	}
+
 	pub fn queue_scan(&self) {
 		self.pending_scan.notify_one();
 	}
@@ -198,7 +481,26 @@ impl Scanner {
 		self.pending_scan.notify_waiters();
 	}
 
+	// Unlike regular scans, the next scan reads every file again, even those which did
+	// not change since they were last read
+	pub fn try_trigger_full_scan(&self) {
+		self.full_scan_requested.store(true, Ordering::Relaxed);
+		self.try_trigger_scan();
+	}
+
+	pub async fn wait_for_scan_start(&self) {
+		self.on_scan_start.notified().await;
+	}
+
+	// Also returns immediately if a scan completed since the last call
+	pub async fn wait_for_scan_end(&self) {
+		self.on_scan_end.notified().await;
+	}
+
+	#[tracing::instrument(level = "debug", skip_all)]
 	pub async fn run_scan(&self) -> Result<(), Error> {
+		let _scan_lock = self.scan_lock.lock().await;
+		self.on_scan_start.notify_waiters();
 		info!("Beginning collection scan");
 
 		let start = Instant::now();
@@ -214,17 +516,42 @@ impl Scanner {
 
 		let new_parameters = self.read_parameters().await;
 		*self.parameters.write().await = Some(new_parameters.clone());
+		let normalization = new_parameters.normalization;
+		let path_indexing = new_parameters.path_indexing;
+		let bigram_threshold = new_parameters.bigram_threshold;
+		let word_boundary_fields = new_parameters.word_boundary_fields.clone();
+		let watch_filesystem = new_parameters.watch_filesystem;
+		let fingerprint = new_parameters.fingerprint();
+
+		// Songs from the previous index (or from a snapshot saved before a crash) are
+		// reused when their files did not change since they were read, unless a full
+		// scan was requested.
+		let full_scan = self.full_scan_requested.swap(false, Ordering::Relaxed);
+		let known_songs = match full_scan {
+			true => None,
+			false => self
+				.index_manager
+				.get_known_songs()
+				.await
+				.filter(|k| k.scan_fingerprint == fingerprint),
+		};
 
 		let (scan_directories_output, collection_directories_input) = channel();
 		let (scan_songs_output, collection_songs_input) = channel();
-		let scan = Scan::new(scan_directories_output, scan_songs_output, new_parameters);
+		let mut scan = Scan::new(scan_directories_output, scan_songs_output, new_parameters);
+		if let Some(known_songs) = known_songs {
+			scan = scan.with_known_songs(known_songs);
+		}
 
 		let mut scan_task_set = JoinSet::new();
 		let mut index_task_set = JoinSet::new();
 		let mut watch_task_set = JoinSet::<Result<(), Error>>::new();
 		let mut secondary_task_set = JoinSet::new();
 
-		scan_task_set.spawn_blocking(|| scan.run());
+		scan_task_set.spawn_blocking({
+			let span = Span::current();
+			move || span.in_scope(|| scan.run())
+		});
 
 		watch_task_set.spawn({
 			let scanner = self.clone();
@@ -232,10 +559,16 @@ impl Scanner {
 			async move {
 				let mut watcher = scanner.file_watcher.write().await;
 				*watcher = None; // Drops previous watcher
-				*watcher = Some(
-					Self::setup_file_watcher(&config_manager, scanner.on_file_change.clone())
+				if watch_filesystem {
+					*watcher = Some(
+						Self::setup_file_watcher(
+							&config_manager,
+							scanner.on_file_change.clone(),
+							scanner.changed_directories.clone(),
+						)
 						.await?,
-				);
+					);
+				}
 				Ok(())
 			}
 		});
@@ -246,14 +579,29 @@ impl Scanner {
 			let index_manager = self.index_manager.clone();
 			let partial_index_notify = partial_index_notify.clone();
 			let partial_index_mutex = partial_index_mutex.clone();
+			let word_boundary_fields = word_boundary_fields.clone();
 			async move {
+				let mut snapshot_time = Instant::now();
 				loop {
 					partial_index_notify.notified().await;
-					let mut partial_index = partial_index_mutex.clone().lock_owned().await;
-					let partial_index =
-						std::mem::replace(&mut *partial_index, index::Builder::new());
+					// Held until the partial index is published, so the end of the scan can wait for it
+					let mut guard = partial_index_mutex.clone().lock_owned().await;
+					let partial_index = std::mem::replace(
+						&mut *guard,
+						index::Builder::new(normalization)
+							.with_path_indexing(path_indexing)
+							.with_bigram_threshold(bigram_threshold)
+							.with_word_boundary_fields(word_boundary_fields.clone()),
+					);
 					let partial_index = partial_index.build();
 					let num_songs = partial_index.collection.num_songs();
+					if snapshot_time.elapsed() >= SNAPSHOT_INTERVAL {
+						match index_manager.persist_index(&partial_index).await {
+							Ok(()) => info!("Saved partial collection index ({num_songs} songs)"),
+							Err(e) => error!("Could not save partial collection index: {e}"),
+						}
+						snapshot_time = Instant::now();
+					}
 					index_manager.clone().replace_index(partial_index).await;
 					info!("Promoted partial collection index ({num_songs} songs)");
 				}
@@ -270,8 +618,14 @@ impl Scanner {
 			}
 		});
 
+		let index_span = tracing::debug_span!("index_songs", songs = Empty);
 		index_task_set.spawn_blocking(move || {
-			let mut index_builder = index::Builder::default();
+			let _index_span = index_span.enter();
+			let mut index_builder = index::Builder::new(normalization)
+				.with_path_indexing(path_indexing)
+				.with_bigram_threshold(bigram_threshold)
+				.with_word_boundary_fields(word_boundary_fields)
+				.with_fingerprint(fingerprint);
 			let mut num_songs_scanned = 0;
 
 			loop {
@@ -299,6 +653,7 @@ impl Scanner {
 				};
 
 				if exhausted_directories && exhausted_songs {
+					index_span.record("songs", num_songs_scanned);
 					break;
 				}
 
@@ -314,13 +669,18 @@ impl Scanner {
 			index_builder.build()
 		});
 
-		scan_task_set.join_next().await.unwrap()??;
+		let skipped_files = scan_task_set.join_next().await.unwrap()??;
 		watch_task_set.join_next().await.unwrap()??;
 		let index = index_task_set.join_next().await.unwrap()?;
-		secondary_task_set.abort_all();
+		{
+			// Wait for partial index promotions in progress, so they cannot overwrite the complete index
+			let _partial_index = partial_index_mutex.lock().await;
+			secondary_task_set.abort_all();
+		}
 
 		self.index_manager.persist_index(&index).await?;
 		self.index_manager.replace_index(index).await;
+		self.update_skipped_files(None, skipped_files).await;
 
 		{
 			let mut status = self.status.write().await;
@@ -333,6 +693,77 @@ impl Scanner {
 			start.elapsed().as_millis() as f32 / 1000.0
 		);
 
+		self.on_scan_end.notify_one();
+		Ok(())
+	}
+
+	// Re-reads a single directory of the collection (and its descendants). Songs
+	// elsewhere in the collection are copied over from the current index instead of
+	// being read from disk again, but the index itself is still rebuilt in full.
+	#[tracing::instrument(level = "debug", skip_all, fields(path = %virtual_path.as_ref().display()))]
+	pub async fn rescan_directory<P: AsRef<Path>>(&self, virtual_path: P) -> Result<(), Error> {
+		let _scan_lock = self.scan_lock.lock().await;
+		self.on_scan_start.notify_waiters();
+		let virtual_path = virtual_path.as_ref().to_owned();
+		info!("Beginning rescan of `{}`", virtual_path.display());
+
+		let start = Instant::now();
+		let real_path = self
+			.config_manager
+			.resolve_virtual_path(&virtual_path)
+			.await?;
+		let parameters = self.read_parameters().await;
+		let index_builder = self
+			.index_manager
+			.get_builder_excluding(virtual_path.clone())
+			.await;
+
+		{
+			let mut status = self.status.write().await;
+			status.last_start_time = Some(SystemTime::now());
+			status.state = State::InProgress;
+		}
+
+		let (index, skipped_files) = tokio::task::spawn_blocking({
+			let virtual_path = virtual_path.clone();
+			move || {
+				let (directories_output, directories_input) = channel();
+				let (songs_output, songs_input) = channel();
+				let scan = Scan::new(directories_output, songs_output, parameters)
+					.with_root(real_path, virtual_path);
+				let skipped_files = scan.run()?;
+
+				let mut index_builder = index_builder;
+				for directory in directories_input.iter() {
+					index_builder.add_directory(directory);
+				}
+				for song in songs_input.iter() {
+					index_builder.add_song(song);
+				}
+				Ok::<_, Error>((index_builder.build(), skipped_files))
+			}
+		})
+		.await??;
+
+		let num_songs = index.collection.num_songs() as u32;
+		self.index_manager.persist_index(&index).await?;
+		self.index_manager.replace_index(index).await;
+		self.update_skipped_files(Some(&virtual_path), skipped_files)
+			.await;
+
+		{
+			let mut status = self.status.write().await;
+			status.state = State::UpToDate;
+			status.last_end_time = Some(SystemTime::now());
+			status.num_songs_indexed = num_songs;
+		}
+
+		info!(
+			"Directory rescan took {} seconds",
+			start.elapsed().as_millis() as f32 / 1000.0
+		);
+
+		self.on_scan_end.notify_one();
 		Ok(())
 	}
 }
@@ -341,6 +772,9 @@ struct Scan {
 	directories_output: Sender<Directory>,
 	songs_output: Sender<Song>,
 	parameters: Parameters,
+	roots: Vec<(PathBuf, PathBuf)>, // Real and virtual paths of the directories to scan
+	known_songs: Option<Arc<index::KnownSongs>>,
+	skipped_files: Arc<Mutex<Vec<SkippedFile>>>,
 }
 
 impl Scan {
@@ -349,55 +783,395 @@ impl Scan {
 		songs_output: Sender<Song>,
 		parameters: Parameters,
 	) -> Self {
+		let roots = parameters
+			.mount_dirs
+			.iter()
+			.map(|m| (m.source.clone(), PathBuf::from(&m.name)))
+			.collect();
 		Self {
 			directories_output,
 			songs_output,
 			parameters,
+			roots,
+			known_songs: None,
+			skipped_files: Arc::default(),
 		}
 	}
 
-	pub fn run(self) -> Result<(), Error> {
+	pub fn with_root(mut self, real_path: PathBuf, virtual_path: PathBuf) -> Self {
+		self.roots = vec![(real_path, virtual_path)];
+		self
+	}
+
+	pub fn with_known_songs(mut self, known_songs: index::KnownSongs) -> Self {
+		self.known_songs = Some(Arc::new(known_songs));
+		self
+	}
+
+	// Returns the audio files which could not be indexed
+	#[tracing::instrument(name = "scan", level = "debug", skip_all, fields(roots = self.roots.len(), skipped = Empty))]
+	pub fn run(self) -> Result<Vec<SkippedFile>, Error> {
 		let key = "POLARIS_NUM_TRAVERSER_THREADS";
-		let num_threads = std::env::var_os(key)
-			.map(|v| v.to_string_lossy().to_string())
-			.and_then(|v| usize::from_str(&v).ok())
+		let num_threads = self
+			.parameters
+			.num_threads
+			.map(NonZeroUsize::get)
+			.or_else(|| {
+				std::env::var_os(key)
+					.map(|v| v.to_string_lossy().to_string())
+					.and_then(|v| usize::from_str(&v).ok())
+			})
 			.unwrap_or_else(|| min(num_cpus::get(), 8));
 		info!("Browsing collection using {} threads", num_threads);
 
 		let directories_output = self.directories_output.clone();
 		let songs_output = self.songs_output.clone();
-		let artwork_regex = self.parameters.artwork_regex.clone();
+		let traversal = Traversal {
+			artwork_regexes: Vec::new(),
+			audio_extensions: self.parameters.audio_extensions.clone(),
+			custom_fields: self.parameters.custom_fields.clone(),
+			disabled_formats: self.parameters.disabled_formats.clone(),
+			index_disabled_formats: self.parameters.index_disabled_formats,
+			infer_year_from_path: self.parameters.infer_year_from_path,
+			various_artists_label: self.parameters.various_artists_label.clone(),
+			directory_paths: self
+				.parameters
+				.follow_symlinks
+				.then(|| Arc::new(claim_directories(&self.roots))),
+			known_songs: self.known_songs.clone(),
+			skipped_files: self.skipped_files.clone(),
+		};
+		let roots = self
+			.roots
+			.into_iter()
+			.map(|(real_path, virtual_path)| {
+				let traversal = Traversal {
+					artwork_regexes: self.parameters.artwork_regexes_for(&virtual_path),
+					..traversal.clone()
+				};
+				(real_path, virtual_path, traversal)
+			})
+			.collect::<Vec<_>>();
 
+		let scan_span = Span::current();
 		let thread_pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
 		thread_pool.scope({
 			|scope| {
-				for mount in self.parameters.mount_dirs {
-					scope.spawn(|scope| {
+				for (real_path, virtual_path, traversal) in roots {
+					let directories_output = directories_output.clone();
+					let songs_output = songs_output.clone();
+					let span = scan_span.clone();
+					scope.spawn(move |scope| {
+						let _span = span.enter();
 						process_directory(
 							scope,
-							mount.source,
-							mount.name,
-							directories_output.clone(),
-							songs_output.clone(),
-							artwork_regex.clone(),
+							real_path,
+							virtual_path,
+							directories_output,
+							songs_output,
+							traversal,
 						);
 					});
 				}
 			}
 		});
 
-		Ok(())
+		let skipped_files = std::mem::take(&mut *self.skipped_files.lock().unwrap());
+		scan_span.record("skipped", skipped_files.len());
+		Ok(skipped_files)
 	}
 }
 
+// Finds a year such as `1997` or `[1997]` in the names of the directories containing
+// a song (nearest first), then in the name of the song file itself. The mount name is
+// not considered.
+fn infer_year(virtual_path: &Path) -> Option<i64> {
+	static YEAR_REGEX: LazyLock<Regex> =
+		LazyLock::new(|| Regex::new(r"(?:^|\D)((?:19|20)\d{2})(?:\D|$)").unwrap());
+
+	let mut names = virtual_path
+		.parent()?
+		.components()
+		.skip(1)
+		.map(|c| c.as_os_str())
+		.collect::<Vec<_>>();
+	names.reverse();
+	names.extend(virtual_path.file_stem());
+
+	names.into_iter().find_map(|name| {
+		YEAR_REGEX
+			.captures(&name.to_string_lossy())
+			.and_then(|c| c[1].parse::<i64>().ok())
+	})
+}
+
+// Tells whether a song uses lossless compression, from its codec when it could be read
+// or from its file extension otherwise. Extensions shared by lossless and lossy codecs
+// (eg. `m4a`, which can hold AAC or ALAC) are inconclusive.
+fn is_lossless(codec: Option<&str>, real_path: &Path) -> Option<bool> {
+	if let Some(codec) = codec.map(str::to_lowercase) {
+		match codec.as_str() {
+			"flac" | "alac" | "wavpack" | "ape" => return Some(true),
+			c if c.starts_with("pcm_") => return Some(true),
+			"mp1" | "mp2" | "mp3" | "aac" | "vorbis" | "opus" => return Some(false),
+			c if c.starts_with("adpcm_") => return Some(false),
+			_ => (),
+		}
+	}
+
+	let extension = real_path.extension()?.to_str()?.to_lowercase();
+	match extension.as_str() {
+		"flac" | "wav" | "aif" | "aiff" | "ape" | "wv" => Some(true),
+		"mp3" | "ogg" | "oga" | "opus" | "aac" | "mpc" => Some(false),
+		_ => None,
+	}
+}
+
+// MP4 durations read from tags come from the movie header, which some encoders (notably
+// for ALAC) fill in wrongly or not at all. The length of the audio track is trusted instead.
+fn song_duration(
+	real_path: &Path,
+	tag_duration: Option<u32>,
+	stream_duration: Option<u32>,
+) -> Option<u32> {
+	match get_audio_format(real_path) {
+		Some(AudioFormat::MP4) | Some(AudioFormat::M4B) => stream_duration.or(tag_duration),
+		_ => tag_duration.or(stream_duration),
+	}
+}
+
+// Trims tag values and collapses runs of whitespace into single spaces, so that values
+// differing only by stray spaces (eg. `" Metal "` and `"Metal"`) are indexed as one.
+fn tidy_whitespace(value: String) -> String {
+	let mut words = value.split_whitespace();
+	let mut tidy = String::with_capacity(value.len());
+	tidy.extend(words.next());
+	for word in words {
+		tidy.push(' ');
+		tidy.push_str(word);
+	}
+	tidy
+}
+
+fn tidy_tag(value: Option<String>) -> Option<String> {
+	value.map(tidy_whitespace).filter(|v| !v.is_empty())
+}
+
+fn tidy_tags(values: Vec<String>) -> Vec<String> {
+	values
+		.into_iter()
+		.map(tidy_whitespace)
+		.filter(|v| !v.is_empty())
+		.collect()
+}
+
+// Songs of an album without album artist tags are grouped by their artists. When an
+// album within a directory has songs by different artists, it is a compilation and
+// gets the various artists label as its album artist, so that it is listed once.
+// Songs reused from a previous scan may already carry the label.
+fn label_compilations(songs: &mut [Song], label: &str) {
+	let is_unattributed = |song: &Song| {
+		song.album_artists.is_empty()
+			|| (song.album_artists.len() == 1 && song.album_artists[0] == label)
+	};
+
+	let mut albums = HashMap::<&str, HashSet<&Vec<String>>>::new();
+	for song in songs.iter().filter(|s| is_unattributed(s)) {
+		if let Some(album) = &song.album {
+			albums.entry(album).or_default().insert(&song.artists);
+		}
+	}
+
+	let compilations = albums
+		.into_iter()
+		.filter(|(_, artists)| artists.len() > 1)
+		.map(|(album, _)| album.to_owned())
+		.collect::<HashSet<_>>();
+
+	for song in songs.iter_mut() {
+		if is_unattributed(song)
+			&& song
+				.album
+				.as_ref()
+				.is_some_and(|a| compilations.contains(a))
+		{
+			song.album_artists = vec![label.to_owned()];
+		}
+	}
+}
+
+// Cheap check based on file extension, so that metadata extraction is not attempted
+// on files which cannot contain audio (images, playlists, system files, etc.)
+fn is_unsupported_audio_file(path: &Path) -> bool {
+	path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+		UNSUPPORTED_AUDIO_EXTENSIONS
+			.iter()
+			.any(|u| u.eq_ignore_ascii_case(e))
+	})
+}
+
+fn is_audio_file(path: &Path, allowed_extensions: Option<&[String]>) -> bool {
+	if get_audio_format(path).is_none() {
+		return false;
+	}
+	let Some(allowed_extensions) = allowed_extensions else {
+		return true;
+	};
+	let extension = path
+		.extension()
+		.and_then(|e| e.to_str())
+		.unwrap_or_default();
+	allowed_extensions
+		.iter()
+		.any(|a| a.eq_ignore_ascii_case(extension))
+}
+
+// Catches partial downloads and files without read permission before they reach the
+// format readers, whose errors for such files are not very telling. Returns the file size.
+fn check_readable(path: &Path) -> Result<u64, SkipReason> {
+	let metadata = fs::metadata(path).map_err(|e| SkipReason::UnreadableFile(e.to_string()))?;
+	if metadata.len() == 0 {
+		return Err(SkipReason::EmptyFile);
+	}
+	fs::File::open(path).map_err(|e| SkipReason::UnreadableFile(e.to_string()))?;
+	Ok(metadata.len())
+}
+
+// Removes directories which are within other directories of the set
+fn minimal_roots(directories: HashSet<PathBuf>) -> Vec<PathBuf> {
+	let mut directories = directories.into_iter().collect::<Vec<_>>();
+	directories.sort();
+	let mut roots: Vec<PathBuf> = Vec::new();
+	for directory in directories {
+		if !roots.iter().any(|r| directory.starts_with(r)) {
+			roots.push(directory);
+		}
+	}
+	roots
+}
+
+// Settings and state shared by all directories visited during a scan
+#[derive(Clone)]
+struct Traversal {
+	// Album art patterns, from most to least preferred. Matched regardless of case
+	// because filesystem naming conventions vary (`Cover.JPG` vs `cover.jpg`).
+	artwork_regexes: Vec<Regex>,
+	audio_extensions: Option<Vec<String>>,
+	// Lowercase descriptions of the `TXXX` frames to index
+	custom_fields: Vec<String>,
+	// Formats whose files are never opened, and only indexed by path if `index_disabled_formats`
+	disabled_formats: Vec<AudioFormat>,
+	index_disabled_formats: bool,
+	infer_year_from_path: bool,
+	various_artists_label: String,
+	// Virtual path each directory is scanned under, by canonical path. Only tracked when
+	// following symlinks, which is the only way a directory can be reached more than once.
+	directory_paths: Option<Arc<HashMap<PathBuf, PathBuf>>>,
+	known_songs: Option<Arc<index::KnownSongs>>,
+	skipped_files: Arc<Mutex<Vec<SkippedFile>>>,
+}
+
+impl Traversal {
+	fn follows_symlinks(&self) -> bool {
+		self.directory_paths.is_some()
+	}
+
+	// Returns the previously read version of a song, unless its file was modified since
+	fn reuse_song(&self, real_path: &Path, virtual_path: &Path) -> Option<Song> {
+		let known_songs = self.known_songs.as_ref()?;
+		let song = known_songs.songs.get(real_path)?;
+		if song.virtual_path != virtual_path {
+			return None;
+		}
+		let modified = fs::metadata(real_path).ok()?.modified().ok()?;
+		if modified >= known_songs.scan_start_time {
+			return None;
+		}
+		let mut song = song.clone();
+		// Album art from adjacent files is looked up again, in case it changed
+		song.artwork = song.artwork.filter(|a| *a == song.virtual_path);
+		Some(song)
+	}
+
+	fn is_disabled(&self, real_path: &Path) -> bool {
+		get_audio_format(real_path).is_some_and(|f| self.disabled_formats.contains(&f))
+	}
+
+	fn skip(&self, real_path: &Path, virtual_path: &Path, reason: SkipReason) {
+		self.skipped_files.lock().unwrap().push(SkippedFile {
+			real_path: real_path.to_owned(),
+			virtual_path: virtual_path.to_owned(),
+			reason,
+		});
+	}
+
+	// Returns false if the directory is scanned under another virtual path
+	fn visit(&self, real_path: &Path, virtual_path: &Path) -> bool {
+		let Some(directory_paths) = &self.directory_paths else {
+			return true;
+		};
+		match fs::canonicalize(real_path) {
+			Ok(p) => directory_paths.get(&p).is_some_and(|v| v == virtual_path),
+			Err(e) => {
+				error!(
+					"Could not resolve canonical path for `{}`: {}",
+					real_path.display(),
+					e
+				);
+				false
+			}
+		}
+	}
+}
+
+// Decides which virtual path each directory is scanned under when following symlinks,
+// since a directory can then be reached from several places. Each directory goes to the
+// first path reaching it in a depth-first walk with entries sorted by name (ie. the
+// lexicographically smallest one, barring cycles), regardless of thread scheduling.
+fn claim_directories(roots: &[(PathBuf, PathBuf)]) -> HashMap<PathBuf, PathBuf> {
+	let mut claims = HashMap::new();
+	let mut stack = roots.iter().rev().cloned().collect::<Vec<_>>();
+	while let Some((real_path, virtual_path)) = stack.pop() {
+		let Ok(canonical_path) = fs::canonicalize(&real_path) else {
+			continue;
+		};
+		if claims.contains_key(&canonical_path) {
+			continue;
+		}
+		claims.insert(canonical_path, virtual_path.clone());
+
+		let Ok(read_dir) = fs::read_dir(&real_path) else {
+			continue;
+		};
+		let mut names = read_dir
+			.filter_map(Result::ok)
+			.filter(|e| fs::metadata(e.path()).is_ok_and(|m| m.is_dir()))
+			.map(|e| e.file_name())
+			.collect::<Vec<_>>();
+		names.sort();
+		stack.extend(
+			names
+				.into_iter()
+				.rev()
+				.map(|n| (real_path.join(&n), virtual_path.join(&n))),
+		);
+	}
+	claims
+}
+
+#[tracing::instrument(level = "trace", skip_all, fields(path = %virtual_path.as_ref().display(), files = Empty, songs = Empty))]
 fn process_directory<P: AsRef<Path>, Q: AsRef<Path>>(
 	scope: &Scope,
 	real_path: P,
 	virtual_path: Q,
 	directories_output: Sender<Directory>,
 	songs_output: Sender<Song>,
-	artwork_regex: Option<Regex>,
+	traversal: Traversal,
 ) {
+	if !traversal.visit(real_path.as_ref(), virtual_path.as_ref()) {
+		return;
+	}
+
 	let read_dir = match fs::read_dir(&real_path) {
 		Ok(read_dir) => read_dir,
 		Err(e) => {
@@ -412,6 +1186,7 @@ fn process_directory<P: AsRef<Path>, Q: AsRef<Path>>(
 
 	let mut songs = vec![];
 	let mut artwork_file = None;
+	let mut num_files = 0;
 
 	for entry in read_dir {
 		let entry = match entry {
@@ -425,8 +1200,16 @@ fn process_directory<P: AsRef<Path>, Q: AsRef<Path>>(
 				continue;
 			}
 		};
+		num_files += 1;
 
-		let is_dir = match entry.file_type().map(|f| f.is_dir()) {
+		let file_type = entry.file_type().and_then(|f| {
+			if f.is_symlink() && traversal.follows_symlinks() {
+				fs::metadata(entry.path()).map(|m| m.file_type())
+			} else {
+				Ok(f)
+			}
+		});
+		let is_dir = match file_type.map(|f| f.is_dir()) {
 			Ok(d) => d,
 			Err(e) => {
 				error!(
@@ -445,46 +1228,161 @@ fn process_directory<P: AsRef<Path>, Q: AsRef<Path>>(
 			scope.spawn({
 				let directories_output = directories_output.clone();
 				let songs_output = songs_output.clone();
-				let artwork_regex = artwork_regex.clone();
-				|scope| {
+				let traversal = traversal.clone();
+				let span = Span::current();
+				move |scope| {
+					let _span = span.enter();
 					process_directory(
 						scope,
 						entry_real_path,
 						entry_virtual_path,
 						directories_output,
 						songs_output,
-						artwork_regex,
+						traversal,
 					);
 				}
 			});
-		} else if let Some(metadata) = formats::read_metadata(&entry_real_path) {
+		} else if is_audio_file(&entry_real_path, traversal.audio_extensions.as_deref()) {
+			if traversal.is_disabled(&entry_real_path) {
+				if traversal.index_disabled_formats {
+					info!(
+						"Indexing `{}` by path only because its format is disabled",
+						entry_real_path.display()
+					);
+					songs.push(Song {
+						real_path: entry_real_path.clone(),
+						virtual_path: entry_virtual_path.clone(),
+						file_size: fs::metadata(&entry_real_path).ok().map(|m| m.len() as i64),
+						date_added: get_date_created(&entry_real_path).unwrap_or_default(),
+						..Default::default()
+					});
+				} else {
+					info!(
+						"Skipping `{}` because its format is disabled",
+						entry_real_path.display()
+					);
+					let reason = SkipReason::DisabledFormat;
+					traversal.skip(&entry_real_path, &entry_virtual_path, reason);
+				}
+				continue;
+			}
+			let file_size = match check_readable(&entry_real_path) {
+				Ok(s) => s,
+				Err(reason) => {
+					warn!("Skipping `{}`: {reason}", entry_real_path.display());
+					traversal.skip(&entry_real_path, &entry_virtual_path, reason);
+					continue;
+				}
+			};
+			if let Some(song) = traversal.reuse_song(&entry_real_path, &entry_virtual_path) {
+				songs.push(song);
+				continue;
+			}
+			let metadata = match formats::read_metadata(&entry_real_path) {
+				Ok(m) => m,
+				Err(e) => {
+					error!(
+						"Error while reading file metadata for `{}`: {e}",
+						entry_real_path.display()
+					);
+					let reason = SkipReason::UnreadableMetadata(e.to_string());
+					traversal.skip(&entry_real_path, &entry_virtual_path, reason);
+					continue;
+				}
+			};
+			let properties = formats::read_technical_properties(&entry_real_path);
+			let inferred_year = match metadata.year {
+				None if traversal.infer_year_from_path => infer_year(&entry_virtual_path),
+				_ => None,
+			};
 			songs.push(Song {
 				real_path: entry_real_path.clone(),
 				virtual_path: entry_virtual_path.clone(),
 				track_number: metadata.track_number.map(|n| n as i64),
 				disc_number: metadata.disc_number.map(|n| n as i64),
-				title: metadata.title,
-				artists: metadata.artists,
-				album_artists: metadata.album_artists,
-				year: metadata.year.map(|n| n as i64),
-				album: metadata.album,
+				title: tidy_tag(metadata.title),
+				artists: tidy_tags(metadata.artists),
+				album_artists: tidy_tags(metadata.album_artists),
+				year: metadata.year.map(|n| n as i64).or(inferred_year),
+				year_inferred: inferred_year.is_some(),
+				original_year: metadata.original_year.or(metadata.year).map(|n| n as i64),
+				rating: metadata.rating.map(|n| n as i64),
+				album: tidy_tag(metadata.album),
+				disc_subtitle: tidy_tag(metadata.disc_subtitle),
+				grouping: tidy_tag(metadata.grouping),
+				work: tidy_tag(metadata.work),
+				movement: tidy_tag(metadata.movement),
+				movement_number: metadata.movement_number.map(|n| n as i64),
 				artwork: metadata.has_artwork.then(|| entry_virtual_path.clone()),
-				duration: metadata.duration.map(|n| n as i64),
-				lyricists: metadata.lyricists,
-				composers: metadata.composers,
-				genres: metadata.genres,
-				labels: metadata.labels,
+				duration: song_duration(
+					&entry_real_path,
+					metadata.duration,
+					properties.as_ref().and_then(|p| p.duration),
+				)
+				.map(|n| n as i64),
+				channels: properties
+					.as_ref()
+					.and_then(|p| p.channels)
+					.map(|n| n as i64),
+				bitrate: properties
+					.as_ref()
+					.and_then(|p| p.bitrate)
+					.map(|n| n as i64),
+				sample_rate: properties
+					.as_ref()
+					.and_then(|p| p.sample_rate)
+					.map(|n| n as i64),
+				bit_depth: properties
+					.as_ref()
+					.and_then(|p| p.bit_depth)
+					.map(|n| n as i64),
+				lossless: is_lossless(
+					properties.as_ref().and_then(|p| p.codec.as_deref()),
+					&entry_real_path,
+				),
+				codec: properties.and_then(|p| p.codec),
+				encoder_delay: metadata.gapless.map(|g| g.encoder_delay as i64),
+				encoder_padding: metadata.gapless.map(|g| g.encoder_padding as i64),
+				file_size: Some(file_size as i64),
+				lyricists: tidy_tags(metadata.lyricists),
+				composers: tidy_tags(metadata.composers),
+				genres: tidy_tags(metadata.genres)
+					.into_iter()
+					.map(genres::normalize)
+					.collect(),
+				labels: tidy_tags(metadata.labels),
+				conductors: tidy_tags(metadata.conductors),
+				ensembles: tidy_tags(metadata.ensembles),
+				custom_fields: metadata
+					.custom_fields
+					.into_iter()
+					.map(|(key, value)| (key.to_lowercase(), tidy_whitespace(value)))
+					.filter(|(key, value)| {
+						traversal.custom_fields.contains(key) && !value.is_empty()
+					})
+					.collect(),
 				date_added: get_date_created(&entry_real_path).unwrap_or_default(),
 			});
-		} else if artwork_file.is_none()
-			&& artwork_regex
-				.as_ref()
-				.is_some_and(|r| r.is_match(name.to_str().unwrap_or_default()))
+		} else if is_unsupported_audio_file(&entry_real_path) {
+			let reason = SkipReason::UnsupportedFormat;
+			traversal.skip(&entry_real_path, &entry_virtual_path, reason);
+		} else if let Some(rank) = traversal
+			.artwork_regexes
+			.iter()
+			.position(|r| r.is_match(name.to_str().unwrap_or_default()))
 		{
-			artwork_file = Some(entry_virtual_path);
+			if !artwork_file.as_ref().is_some_and(|(r, _)| *r <= rank) {
+				artwork_file = Some((rank, entry_virtual_path));
+			}
 		}
 	}
 
+	Span::current().record("files", num_files);
+	Span::current().record("songs", songs.len());
+
+	label_compilations(&mut songs, &traversal.various_artists_label);
+
+	let artwork_file = artwork_file.map(|(_, path)| path);
 	for mut song in songs {
 		song.artwork = song.artwork.or_else(|| artwork_file.clone());
 		songs_output.send(song).ok();
@@ -521,11 +1419,36 @@ mod test {
 		let (directories_sender, directories_receiver) = channel();
 		let (songs_sender, songs_receiver) = channel();
 		let parameters = Parameters {
-			artwork_regex: None,
 			mount_dirs: vec![config::MountDir {
 				source: ["test-data", "small-collection"].iter().collect(),
 				name: "root".to_owned(),
+				..Default::default()
 			}],
+			..Default::default()
+		};
+
+		let scan = Scan::new(directories_sender, songs_sender, parameters);
+		scan.run().unwrap();
+
+		let directories = directories_receiver.iter().collect::<Vec<_>>();
+		assert_eq!(directories.len(), 6);
+
+		let songs = songs_receiver.iter().collect::<Vec<_>>();
+		assert_eq!(songs.len(), 13);
+	}
+
+	#[tokio::test]
+	async fn scan_can_run_on_single_thread() {
+		let (directories_sender, directories_receiver) = channel();
+		let (songs_sender, songs_receiver) = channel();
+		let parameters = Parameters {
+			mount_dirs: vec![config::MountDir {
+				source: ["test-data", "small-collection"].iter().collect(),
+				name: "root".to_owned(),
+				..Default::default()
+			}],
+			num_threads: NonZeroUsize::new(1),
+			..Default::default()
 		};
 
 		let scan = Scan::new(directories_sender, songs_sender, parameters);
@@ -543,11 +1466,12 @@ mod test {
 		let (directories_sender, _) = channel();
 		let (songs_sender, songs_receiver) = channel();
 		let parameters = Parameters {
-			artwork_regex: None,
 			mount_dirs: vec![config::MountDir {
 				source: ["test-data", "small-collection"].iter().collect(),
 				name: "root".to_owned(),
+				..Default::default()
 			}],
+			..Default::default()
 		};
 
 		let scan = Scan::new(directories_sender, songs_sender, parameters);
@@ -563,16 +1487,18 @@ mod test {
 	#[tokio::test]
 	async fn album_art_pattern_is_case_insensitive() {
 		let artwork_path = PathBuf::from_iter(["root", "Khemmis", "Hunted", "Folder.jpg"]);
-		let patterns = vec!["folder", "FOLDER"];
+		let patterns = vec!["folder", "FOLDER", "folder.JPG"];
 		for pattern in patterns.into_iter() {
 			let (directories_sender, _) = channel();
 			let (songs_sender, songs_receiver) = channel();
 			let parameters = Parameters {
-				artwork_regex: Some(Regex::new(pattern).unwrap()),
+				artwork_regexes: vec![Regex::new(pattern).unwrap()],
 				mount_dirs: vec![config::MountDir {
 					source: ["test-data", "small-collection"].iter().collect(),
 					name: "root".to_owned(),
+					..Default::default()
 				}],
+				..Default::default()
 			};
 
 			let scan = Scan::new(directories_sender, songs_sender, parameters);
@@ -580,12 +1506,638 @@ mod test {
 
 			let songs = songs_receiver.iter().collect::<Vec<_>>();
 
-			songs
+			assert!(songs
 				.iter()
-				.any(|s| s.artwork.as_ref() == Some(&artwork_path));
+				.any(|s| s.artwork.as_ref() == Some(&artwork_path)));
 		}
 	}
 
+	#[tokio::test]
+	async fn mounts_can_override_album_art_pattern() {
+		let (directories_sender, _) = channel();
+		let (songs_sender, songs_receiver) = channel();
+		let parameters = Parameters {
+			artwork_regexes: vec![Regex::new("Folder.jpg").unwrap()],
+			mount_dirs: vec![
+				config::MountDir {
+					source: ["test-data", "small-collection"].iter().collect(),
+					name: "global".to_owned(),
+					..Default::default()
+				},
+				config::MountDir {
+					source: ["test-data", "small-collection"].iter().collect(),
+					name: "custom".to_owned(),
+					album_art_pattern: Some("Folder.png".to_owned()),
+				},
+			],
+			..Default::default()
+		};
+
+		let scan = Scan::new(directories_sender, songs_sender, parameters);
+		scan.run().unwrap();
+
+		let artworks = songs_receiver
+			.iter()
+			.filter_map(|s| s.artwork)
+			.collect::<HashSet<_>>();
+		let jpg = |mount| PathBuf::from_iter([mount, "Khemmis", "Hunted", "Folder.jpg"]);
+		let png = |mount| PathBuf::from_iter([mount, "Tobokegao", "Picnic", "Folder.png"]);
+		assert!(artworks.contains(&jpg("global")));
+		assert!(!artworks.contains(&png("global")));
+		assert!(artworks.contains(&png("custom")));
+		assert!(!artworks.contains(&jpg("custom")));
+	}
+
+	#[tokio::test]
+	async fn album_art_patterns_are_tried_in_order() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		fs::copy(
+			PathBuf::from_iter(["test-data", "formats", "sample.mp3"]),
+			source.join("sample.mp3"),
+		)
+		.unwrap();
+		fs::write(source.join("Cover.jpg"), b"").unwrap();
+		fs::write(source.join("Folder.png"), b"").unwrap();
+
+		let scan_artwork = |patterns: &[&str]| {
+			let (directories_sender, _) = channel();
+			let (songs_sender, songs_receiver) = channel();
+			let parameters = Parameters {
+				artwork_regexes: patterns.iter().map(|p| Regex::new(p).unwrap()).collect(),
+				mount_dirs: vec![config::MountDir {
+					source: source.clone(),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				..Default::default()
+			};
+			Scan::new(directories_sender, songs_sender, parameters)
+				.run()
+				.unwrap();
+			let songs = songs_receiver.iter().collect::<Vec<_>>();
+			assert_eq!(songs.len(), 1);
+			songs[0].artwork.clone()
+		};
+
+		assert_eq!(
+			scan_artwork(&["Cover", "Folder"]),
+			Some(PathBuf::from_iter(["root", "Cover.jpg"]))
+		);
+		assert_eq!(
+			scan_artwork(&["Folder", "Cover"]),
+			Some(PathBuf::from_iter(["root", "Folder.png"]))
+		);
+		assert_eq!(
+			scan_artwork(&["Back", "Cover"]),
+			Some(PathBuf::from_iter(["root", "Cover.jpg"]))
+		);
+	}
+
+	#[tokio::test]
+	async fn scan_can_target_single_directory() {
+		let (directories_sender, directories_receiver) = channel();
+		let (songs_sender, songs_receiver) = channel();
+		let parameters = Parameters {
+			mount_dirs: vec![config::MountDir {
+				source: ["test-data", "small-collection"].iter().collect(),
+				name: "root".to_owned(),
+				..Default::default()
+			}],
+			..Default::default()
+		};
+
+		let scan = Scan::new(directories_sender, songs_sender, parameters).with_root(
+			["test-data", "small-collection", "Khemmis"]
+				.iter()
+				.collect(),
+			["root", "Khemmis"].iter().collect(),
+		);
+		scan.run().unwrap();
+
+		let directories = directories_receiver.iter().collect::<Vec<_>>();
+		assert_eq!(directories.len(), 2);
+
+		let songs = songs_receiver.iter().collect::<Vec<_>>();
+		assert_eq!(songs.len(), 5);
+		assert!(songs.iter().all(|s| s
+			.virtual_path
+			.starts_with(["root", "Khemmis"].iter().collect::<PathBuf>())));
+	}
+
+	#[tokio::test]
+	async fn rescan_directory_preserves_other_directories() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.mount("root", "test-data/small-collection")
+			.build()
+			.await;
+
+		ctx.scanner.run_scan().await.unwrap();
+		let all_songs = ctx
+			.index_manager
+			.flatten(PathBuf::from("root"))
+			.await
+			.unwrap();
+		assert_eq!(all_songs.len(), 13);
+
+		ctx.scanner
+			.rescan_directory(PathBuf::from_iter(["root", "Khemmis"]))
+			.await
+			.unwrap();
+
+		let songs = ctx
+			.index_manager
+			.flatten(PathBuf::from("root"))
+			.await
+			.unwrap();
+		assert_eq!(songs.len(), 13);
+		let songs = ctx
+			.index_manager
+			.flatten(PathBuf::from_iter(["root", "Tobokegao"]))
+			.await
+			.unwrap();
+		assert_eq!(songs.len(), 8);
+		let songs = ctx
+			.index_manager
+			.flatten(PathBuf::from_iter(["root", "Khemmis"]))
+			.await
+			.unwrap();
+		assert_eq!(songs.len(), 5);
+	}
+
+	#[tokio::test]
+	async fn scan_resumes_from_snapshot_after_crash() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.mount("root", "test-data/small-collection")
+			.build()
+			.await;
+
+		// Save a snapshot covering part of the collection, as an interrupted scan would
+		let parameters = ctx.scanner.read_parameters().await;
+		let mut builder = index::Builder::new(parameters.normalization)
+			.with_path_indexing(parameters.path_indexing)
+			.with_fingerprint(parameters.fingerprint());
+		let (directories_output, directories_input) = channel();
+		let (songs_output, songs_input) = channel();
+		Scan::new(directories_output, songs_output, parameters)
+			.with_root(
+				PathBuf::from_iter(["test-data", "small-collection", "Khemmis"]),
+				PathBuf::from_iter(["root", "Khemmis"]),
+			)
+			.run()
+			.unwrap();
+		for directory in directories_input.iter() {
+			builder.add_directory(directory);
+		}
+		for song in songs_input.iter() {
+			// Marks songs from the snapshot, to tell whether they were read again
+			builder.add_song(Song {
+				title: Some("From snapshot".to_owned()),
+				..song
+			});
+		}
+		let index_directory = ctx.test_directory.join("crashed");
+		index::Manager::new(&index_directory)
+			.await
+			.unwrap()
+			.persist_index(&builder.build())
+			.await
+			.unwrap();
+
+		// Restart from the snapshot
+		let index_manager = index::Manager::new(&index_directory).await.unwrap();
+		let scanner = Scanner::new(
+			index_manager.clone(),
+			ctx.config_manager.clone(),
+			index_directory.join("skipped_files.txt"),
+		)
+		.await
+		.unwrap();
+		let songs = index_manager.flatten(PathBuf::from("root")).await.unwrap();
+		assert_eq!(songs.len(), 5);
+
+		scanner.run_scan().await.unwrap();
+
+		let songs = index_manager.flatten(PathBuf::from("root")).await.unwrap();
+		assert_eq!(songs.len(), 13);
+		let songs = index_manager.get_songs(songs).await;
+		for song in songs.into_iter().map(Result::unwrap) {
+			let from_snapshot = song.title.as_deref() == Some("From snapshot");
+			assert_eq!(
+				from_snapshot,
+				song.virtual_path
+					.starts_with(PathBuf::from_iter(["root", "Khemmis"]))
+			);
+		}
+	}
+
+	#[test]
+	fn only_audio_files_are_extracted() {
+		let files = [
+			("song.mp3", true),
+			("song.FLAC", true),
+			("cover.jpg", false),
+			("notes.txt", false),
+			("playlist.m3u", false),
+			(".DS_Store", false),
+			("Thumbs.db", false),
+		];
+		for (name, is_audio) in files {
+			assert_eq!(is_audio_file(Path::new(name), None), is_audio, "{name}");
+		}
+
+		let allowed = vec!["flac".to_owned()];
+		assert!(is_audio_file(Path::new("song.FLAC"), Some(&allowed)));
+		assert!(!is_audio_file(Path::new("song.mp3"), Some(&allowed)));
+		assert!(!is_audio_file(Path::new("cover.jpg"), Some(&allowed)));
+	}
+
+	#[tokio::test]
+	async fn scan_skips_non_audio_files() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		for name in ["sample.mp3", "sample.flac"] {
+			fs::copy(
+				PathBuf::from_iter(["test-data", "formats", name]),
+				source.join(name),
+			)
+			.unwrap();
+		}
+		fs::copy("test-data/artwork/Folder.png", source.join("Folder.png")).unwrap();
+		fs::write(source.join("notes.txt"), "not a song").unwrap();
+
+		let scan_songs = |audio_extensions: Option<Vec<String>>| {
+			let (directories_sender, _) = channel();
+			let (songs_sender, songs_receiver) = channel();
+			let parameters = Parameters {
+				mount_dirs: vec![config::MountDir {
+					source: source.clone(),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				audio_extensions,
+				..Default::default()
+			};
+			Scan::new(directories_sender, songs_sender, parameters)
+				.run()
+				.unwrap();
+			let mut songs = songs_receiver
+				.iter()
+				.map(|s| s.virtual_path)
+				.collect::<Vec<_>>();
+			songs.sort();
+			songs
+		};
+
+		assert_eq!(
+			scan_songs(None),
+			vec![
+				PathBuf::from_iter(["root", "sample.flac"]),
+				PathBuf::from_iter(["root", "sample.mp3"]),
+			]
+		);
+		assert_eq!(
+			scan_songs(Some(vec!["flac".to_owned()])),
+			vec![PathBuf::from_iter(["root", "sample.flac"])]
+		);
+	}
+
+	#[test]
+	fn scan_honors_disabled_formats() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		for name in ["sample.mp3", "sample.flac"] {
+			fs::copy(
+				PathBuf::from_iter(["test-data", "formats", name]),
+				source.join(name),
+			)
+			.unwrap();
+		}
+
+		let scan = |index_disabled_formats: bool| {
+			let (directories_sender, _) = channel();
+			let (songs_sender, songs_receiver) = channel();
+			let parameters = Parameters {
+				mount_dirs: vec![config::MountDir {
+					source: source.clone(),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				disabled_formats: vec![AudioFormat::FLAC],
+				index_disabled_formats,
+				..Default::default()
+			};
+			let skipped_files = Scan::new(directories_sender, songs_sender, parameters)
+				.run()
+				.unwrap();
+			let mut songs = songs_receiver.iter().collect::<Vec<_>>();
+			songs.sort_by(|a, b| a.virtual_path.cmp(&b.virtual_path));
+			(songs, skipped_files)
+		};
+
+		let (songs, skipped_files) = scan(false);
+		assert_eq!(songs.len(), 1);
+		assert_eq!(
+			songs[0].virtual_path,
+			PathBuf::from_iter(["root", "sample.mp3"])
+		);
+		assert_eq!(skipped_files.len(), 1);
+		assert_eq!(skipped_files[0].reason, SkipReason::DisabledFormat);
+
+		let (songs, skipped_files) = scan(true);
+		assert!(skipped_files.is_empty());
+		assert_eq!(songs.len(), 2);
+		assert_eq!(
+			songs[0].virtual_path,
+			PathBuf::from_iter(["root", "sample.flac"])
+		);
+		assert_eq!(songs[0].title, None);
+		assert_eq!(songs[0].duration, None);
+		assert!(songs[1].title.is_some());
+	}
+
+	#[tokio::test]
+	async fn full_scan_does_not_reuse_songs() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+
+		let source = ctx.test_directory.join("source");
+		fs::create_dir_all(&source).unwrap();
+		fs::copy("test-data/formats/sample.mp3", source.join("sample.mp3")).unwrap();
+		ctx.config_manager
+			.set_mounts(vec![config::storage::MountDir {
+				source: source.clone(),
+				name: "root".to_owned(),
+				..Default::default()
+			}])
+			.await
+			.unwrap();
+		ctx.scanner.run_scan().await.unwrap();
+
+		// Pretend the song was previously read with different metadata
+		let known_songs = ctx.index_manager.get_known_songs().await.unwrap();
+		let mut builder = index::Builder::default().with_fingerprint(known_songs.scan_fingerprint);
+		for song in known_songs.songs.into_values() {
+			builder.add_song(Song {
+				title: Some("Stale".to_owned()),
+				..song
+			});
+		}
+		ctx.index_manager.replace_index(builder.build()).await;
+
+		let path = PathBuf::from_iter(["root", "sample.mp3"]);
+		let get_title = || async {
+			let songs = ctx.index_manager.get_songs(vec![path.clone()]).await;
+			songs[0].as_ref().unwrap().title.clone()
+		};
+
+		ctx.scanner.run_scan().await.unwrap();
+		assert_eq!(get_title().await.as_deref(), Some("Stale"));
+
+		ctx.scanner.try_trigger_full_scan();
+		ctx.scanner.run_scan().await.unwrap();
+		assert_ne!(get_title().await.as_deref(), Some("Stale"));
+	}
+
+	#[tokio::test]
+	async fn scan_reports_skipped_files() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+
+		let source = ctx.test_directory.join("source");
+		fs::create_dir_all(&source).unwrap();
+		fs::copy("test-data/formats/sample.mp3", source.join("sample.mp3")).unwrap();
+		fs::write(source.join("broken.flac"), "not a song").unwrap();
+		fs::write(source.join("song.wma"), "not supported").unwrap();
+		fs::write(source.join("notes.txt"), "not a song").unwrap();
+		ctx.config_manager
+			.apply_config(config::storage::Config {
+				write_scan_report: Some(true),
+				mount_dirs: vec![config::storage::MountDir {
+					source: source.clone(),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				..Default::default()
+			})
+			.await
+			.unwrap();
+
+		ctx.scanner.run_scan().await.unwrap();
+
+		let skipped_files = ctx.scanner.get_skipped_files().await;
+		assert_eq!(skipped_files.len(), 2);
+		assert_eq!(
+			skipped_files[0].virtual_path,
+			PathBuf::from_iter(["root", "broken.flac"])
+		);
+		assert!(matches!(
+			skipped_files[0].reason,
+			SkipReason::UnreadableMetadata(_)
+		));
+		assert_eq!(
+			skipped_files[1],
+			SkippedFile {
+				real_path: source.join("song.wma"),
+				virtual_path: PathBuf::from_iter(["root", "song.wma"]),
+				reason: SkipReason::UnsupportedFormat,
+			}
+		);
+
+		let report = fs::read_to_string(ctx.test_directory.join("skipped_files.txt")).unwrap();
+		assert_eq!(report.lines().count(), 2);
+		assert!(report.contains("song.wma\tUnsupported audio format"));
+	}
+
+	#[tokio::test]
+	async fn scan_skips_empty_files() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+
+		let source = ctx.test_directory.join("source");
+		fs::create_dir_all(&source).unwrap();
+		fs::copy("test-data/formats/sample.mp3", source.join("sample.mp3")).unwrap();
+		fs::write(source.join("partial.mp3"), "").unwrap();
+		fs::write(source.join("partial.flac"), "").unwrap();
+		ctx.config_manager
+			.set_mounts(vec![config::storage::MountDir {
+				source: source.clone(),
+				name: "root".to_owned(),
+				..Default::default()
+			}])
+			.await
+			.unwrap();
+
+		ctx.scanner.run_scan().await.unwrap();
+
+		let mut skipped_files = ctx.scanner.get_skipped_files().await;
+		skipped_files.sort_by(|a, b| a.virtual_path.cmp(&b.virtual_path));
+		assert_eq!(
+			skipped_files,
+			vec![
+				SkippedFile {
+					real_path: source.join("partial.flac"),
+					virtual_path: PathBuf::from_iter(["root", "partial.flac"]),
+					reason: SkipReason::EmptyFile,
+				},
+				SkippedFile {
+					real_path: source.join("partial.mp3"),
+					virtual_path: PathBuf::from_iter(["root", "partial.mp3"]),
+					reason: SkipReason::EmptyFile,
+				},
+			]
+		);
+		let songs = ctx
+			.index_manager
+			.flatten(PathBuf::from("root"))
+			.await
+			.unwrap();
+		assert_eq!(songs, vec![PathBuf::from_iter(["root", "sample.mp3"])]);
+	}
+
+	#[cfg(unix)]
+	fn scan_with_symlinks(source: &Path, follow_symlinks: bool) -> Vec<PathBuf> {
+		let (directories_sender, _) = channel();
+		let (songs_sender, songs_receiver) = channel();
+		let parameters = Parameters {
+			mount_dirs: vec![config::MountDir {
+				source: source.to_owned(),
+				name: "root".to_owned(),
+				..Default::default()
+			}],
+			follow_symlinks,
+			..Default::default()
+		};
+		Scan::new(directories_sender, songs_sender, parameters)
+			.run()
+			.unwrap();
+		let mut songs = songs_receiver
+			.iter()
+			.map(|s| s.virtual_path)
+			.collect::<Vec<_>>();
+		songs.sort();
+		songs
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn scan_can_follow_symlinked_directories() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		let target = fs::canonicalize(PathBuf::from_iter([
+			"test-data",
+			"small-collection",
+			"Khemmis",
+		]))
+		.unwrap();
+		std::os::unix::fs::symlink(target, source.join("Khemmis")).unwrap();
+
+		assert!(scan_with_symlinks(&source, false).is_empty());
+
+		let songs = scan_with_symlinks(&source, true);
+		assert!(!songs.is_empty());
+		assert!(songs
+			.iter()
+			.all(|s| s.starts_with(PathBuf::from_iter(["root", "Khemmis"]))));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn scan_survives_symlink_cycles() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		fs::copy(
+			PathBuf::from_iter(["test-data", "formats", "sample.mp3"]),
+			source.join("sample.mp3"),
+		)
+		.unwrap();
+		let source = fs::canonicalize(source).unwrap();
+		std::os::unix::fs::symlink(&source, source.join("loop")).unwrap();
+
+		assert_eq!(
+			scan_with_symlinks(&source, true),
+			vec![PathBuf::from_iter(["root", "sample.mp3"])]
+		);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn scan_keeps_smallest_path_to_symlinked_directories() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		let target = source.join("b");
+		fs::create_dir_all(&target).unwrap();
+		fs::copy(
+			PathBuf::from_iter(["test-data", "formats", "sample.mp3"]),
+			target.join("sample.mp3"),
+		)
+		.unwrap();
+		let target = fs::canonicalize(target).unwrap();
+		std::os::unix::fs::symlink(&target, source.join("a")).unwrap();
+		std::os::unix::fs::symlink(&target, source.join("c")).unwrap();
+
+		for _ in 0..10 {
+			assert_eq!(
+				scan_with_symlinks(&source, true),
+				vec![PathBuf::from_iter(["root", "a", "sample.mp3"])]
+			);
+		}
+	}
+
+	#[test]
+	fn minimal_roots_skips_nested_directories() {
+		let directories = HashSet::from([
+			PathBuf::from_iter(["music", "a", "b"]),
+			PathBuf::from_iter(["music", "a"]),
+			PathBuf::from_iter(["music", "c"]),
+			PathBuf::from_iter(["music", "ab"]),
+		]);
+		assert_eq!(
+			minimal_roots(directories),
+			vec![
+				PathBuf::from_iter(["music", "a"]),
+				PathBuf::from_iter(["music", "ab"]),
+				PathBuf::from_iter(["music", "c"]),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn watcher_picks_up_new_files() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		let source = ctx.test_directory.join("music");
+		fs::create_dir_all(&source).unwrap();
+
+		ctx.config_manager
+			.apply_config(config::storage::Config {
+				watch_filesystem: Some(true),
+				mount_dirs: vec![config::storage::MountDir {
+					source: source.clone(),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		ctx.scanner.run_scan().await.unwrap();
+
+		let song: PathBuf = [
+			"test-data",
+			"small-collection",
+			"Khemmis",
+			"Hunted",
+			"05 - Hunted.mp3",
+		]
+		.iter()
+		.collect();
+		fs::create_dir_all(source.join("Khemmis")).unwrap();
+		fs::copy(&song, source.join("Khemmis").join("05 - Hunted.mp3")).unwrap();
+
+		tokio::time::timeout(Duration::from_secs(10), async {
+			loop {
+				tokio::time::sleep(Duration::from_millis(100)).await;
+				let songs = ctx.index_manager.flatten(PathBuf::from("root")).await;
+				if songs.map(|s| s.len()).unwrap_or_default() == 1 {
+					break;
+				}
+			}
+		})
+		.await
+		.expect("New song was not indexed");
+	}
+
 	#[tokio::test]
 	async fn scanner_reacts_to_config_changes() {
 		let ctx = test::ContextBuilder::new(test_name!()).build().await;
@@ -596,6 +2148,7 @@ mod test {
 			.set_mounts(vec![config::storage::MountDir {
 				source: ["test-data", "small-collection"].iter().collect(),
 				name: "root".to_owned(),
+				..Default::default()
 			}])
 			.await
 			.unwrap();
@@ -611,4 +2164,165 @@ mod test {
 		.await
 		.expect("Index did not populate");
 	}
+
+	#[test]
+	fn tidies_tag_whitespace() {
+		assert_eq!(tidy_whitespace(" Metal ".to_owned()), "Metal");
+		assert_eq!(tidy_whitespace("Death\t  Metal".to_owned()), "Death Metal");
+		assert_eq!(tidy_whitespace("Metal".to_owned()), "Metal");
+		assert_eq!(tidy_tag(Some("   ".to_owned())), None);
+		assert_eq!(
+			tidy_tags(vec![" Metal".to_owned(), "".to_owned(), "Rock ".to_owned()]),
+			vec!["Metal".to_owned(), "Rock".to_owned()]
+		);
+	}
+
+	#[test]
+	fn can_infer_year_from_path() {
+		let year = |p: &[&str]| infer_year(&PathBuf::from_iter(p));
+		assert_eq!(
+			year(&["root", "Khemmis", "Hunted [2016]", "song.mp3"]),
+			Some(2016)
+		);
+		assert_eq!(
+			year(&["root", "1997 - Album", "CD1", "song.mp3"]),
+			Some(1997)
+		);
+		assert_eq!(year(&["root", "Album", "2001 - song.mp3"]), Some(2001));
+		assert_eq!(
+			year(&["root", "(1990) Album", "2001 - song.mp3"]),
+			Some(1990)
+		);
+		assert_eq!(year(&["root", "Album 12345", "song.mp3"]), None);
+		assert_eq!(year(&["1999", "Album", "song.mp3"]), None);
+	}
+
+	#[test]
+	fn can_tell_lossless_songs() {
+		let lossless = |codec: Option<&str>, file: &str| is_lossless(codec, Path::new(file));
+		assert_eq!(lossless(Some("flac"), "song.flac"), Some(true));
+		assert_eq!(lossless(Some("pcm_s16le"), "song.wav"), Some(true));
+		assert_eq!(lossless(Some("alac"), "song.m4a"), Some(true));
+		assert_eq!(lossless(Some("aac"), "song.m4a"), Some(false));
+		assert_eq!(lossless(Some("mp3"), "song.flac"), Some(false));
+		assert_eq!(lossless(None, "song.AIFF"), Some(true));
+		assert_eq!(lossless(None, "song.opus"), Some(false));
+		assert_eq!(lossless(None, "song.m4a"), None);
+		assert_eq!(lossless(Some("unknown"), "song"), None);
+	}
+
+	#[test]
+	fn mp4_durations_come_from_audio_track() {
+		let duration = |file: &str, tag, stream| song_duration(Path::new(file), tag, stream);
+		assert_eq!(duration("song.m4a", Some(0), Some(245)), Some(245));
+		assert_eq!(duration("song.m4a", Some(245), None), Some(245));
+		assert_eq!(duration("song.mp3", Some(245), Some(244)), Some(245));
+		assert_eq!(duration("song.mp3", None, Some(244)), Some(244));
+	}
+
+	#[tokio::test]
+	async fn scan_infers_missing_years_from_path() {
+		let source = crate::test::prepare_test_directory(test_name!());
+		let album = source.join("Khemmis").join("Hunted [1997]");
+		fs::create_dir_all(&album).unwrap();
+
+		let tagged = album.join("tagged.mp3");
+		fs::copy(
+			PathBuf::from_iter(["test-data", "formats", "sample.mp3"]),
+			&tagged,
+		)
+		.unwrap();
+
+		let untagged = album.join("untagged.mp3");
+		fs::copy(&tagged, &untagged).unwrap();
+		let mut tag = id3::Tag::new();
+		id3::TagLike::set_title(&mut tag, "Untagged");
+		tag.write_to_path(&untagged, id3::Version::Id3v24).unwrap();
+
+		let scan_years = |infer_year_from_path: bool| {
+			let (directories_sender, _) = channel();
+			let (songs_sender, songs_receiver) = channel();
+			let parameters = Parameters {
+				mount_dirs: vec![config::MountDir {
+					source: source.clone(),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				infer_year_from_path,
+				..Default::default()
+			};
+			Scan::new(directories_sender, songs_sender, parameters)
+				.run()
+				.unwrap();
+			let mut songs = songs_receiver
+				.iter()
+				.map(|s| (s.title.unwrap_or_default(), s.year, s.year_inferred))
+				.collect::<Vec<_>>();
+			songs.sort();
+			songs
+		};
+
+		assert_eq!(
+			scan_years(false),
+			vec![
+				("TEST TITLE".to_owned(), Some(2016), false),
+				("Untagged".to_owned(), None, false),
+			]
+		);
+		assert_eq!(
+			scan_years(true),
+			vec![
+				("TEST TITLE".to_owned(), Some(2016), false),
+				("Untagged".to_owned(), Some(1997), true),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn compilations_get_various_artists_label() {
+		let ctx = test::ContextBuilder::new(test_name!()).build().await;
+		let album = ctx.test_directory.join("music").join("Sampler");
+		fs::create_dir_all(&album).unwrap();
+
+		for (file_name, artist) in [("01.mp3", "Khemmis"), ("02.mp3", "Tobokegao")] {
+			let path = album.join(file_name);
+			fs::copy(
+				PathBuf::from_iter(["test-data", "formats", "sample.mp3"]),
+				&path,
+			)
+			.unwrap();
+			let mut tag = id3::Tag::new();
+			id3::TagLike::set_artist(&mut tag, artist);
+			id3::TagLike::set_album(&mut tag, "Sampler");
+			tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+		}
+
+		ctx.config_manager
+			.apply_config(config::storage::Config {
+				mount_dirs: vec![config::storage::MountDir {
+					source: ctx.test_directory.join("music"),
+					name: "root".to_owned(),
+					..Default::default()
+				}],
+				various_artists_label: Some("Verschiedene Interpreten".to_owned()),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		ctx.scanner.run_scan().await.unwrap();
+
+		let albums = ctx.index_manager.get_albums().await;
+		assert_eq!(albums.len(), 1);
+		assert_eq!(albums[0].name, "Sampler");
+		assert_eq!(
+			albums[0].artists,
+			vec!["Verschiedene Interpreten".to_owned()]
+		);
+
+		let artists = ctx
+			.index_manager
+			.get_artists("Verschiedene Interpreten".to_owned(), false)
+			.await;
+		assert_eq!(artists.len(), 2);
+	}
 }
@@ -0,0 +1,225 @@
+use std::{
+	path::PathBuf,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use log::{debug, error, warn};
+use serde::Serialize;
+use tokio::{sync::Semaphore, task::spawn_blocking};
+
+use crate::app::{config, index, Error};
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = if cfg!(test) {
+	Duration::from_millis(10)
+} else {
+	Duration::from_secs(2)
+};
+// Scrobbles beyond this many pending deliveries (including retries) are dropped
+const MAX_PENDING_DELIVERIES: usize = 32;
+const SECRET_HEADER: &str = "X-Polaris-Secret";
+
+// Payload sent to the scrobble webhook
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Scrobble {
+	pub username: String,
+	pub path: PathBuf,
+	pub title: Option<String>,
+	pub artists: Vec<String>,
+	pub album: Option<String>,
+	pub duration: Option<i64>,
+	pub timestamp: u64, // Seconds since the Unix epoch
+}
+
+impl Scrobble {
+	pub fn new(username: &str, song: &index::Song) -> Self {
+		Self {
+			username: username.to_owned(),
+			path: song.virtual_path.clone(),
+			title: song.title.clone(),
+			artists: song.artists.clone(),
+			album: song.album.clone(),
+			duration: song.duration,
+			timestamp: SystemTime::now()
+				.duration_since(SystemTime::UNIX_EPOCH)
+				.map(|d| d.as_secs())
+				.unwrap_or_default(),
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	config_manager: config::Manager,
+	delivery_permits: Arc<Semaphore>,
+}
+
+impl Manager {
+	pub fn new(config_manager: config::Manager) -> Self {
+		Self {
+			config_manager,
+			delivery_permits: Arc::new(Semaphore::new(MAX_PENDING_DELIVERIES)),
+		}
+	}
+
+	// Notifies the scrobble webhook in the background, so playback is never held up
+	// by a slow or unreachable server. Failed requests are retried with exponential backoff.
+	pub fn scrobble(&self, username: &str, song: &index::Song) {
+		let Ok(permit) = self.delivery_permits.clone().try_acquire_owned() else {
+			warn!("Dropping scrobble because {MAX_PENDING_DELIVERIES} deliveries are pending");
+			return;
+		};
+		let scrobble = Scrobble::new(username, song);
+		tokio::spawn({
+			let manager = self.clone();
+			async move {
+				manager.send(scrobble).await;
+				drop(permit);
+			}
+		});
+	}
+
+	async fn send(&self, scrobble: Scrobble) {
+		let Some(url) = self.config_manager.get_scrobble_url().await else {
+			debug!("Skipping scrobble because no webhook is configured");
+			return;
+		};
+		let secret = self.config_manager.get_scrobble_secret().await;
+
+		let body = match serde_json::to_string(&scrobble) {
+			Ok(b) => b,
+			Err(e) => {
+				error!("Could not serialize scrobble: {e}");
+				return;
+			}
+		};
+
+		let mut delay = INITIAL_RETRY_DELAY;
+		for attempt in 1..=MAX_ATTEMPTS {
+			match post(url.to_string(), secret.clone(), body.clone()).await {
+				Ok(()) => return,
+				Err(e) if attempt < MAX_ATTEMPTS => {
+					warn!("Scrobble attempt {attempt} failed, retrying in {delay:?}: {e}");
+					tokio::time::sleep(delay).await;
+					delay *= 2;
+				}
+				Err(e) => error!("Could not send scrobble after {MAX_ATTEMPTS} attempts: {e}"),
+			}
+		}
+	}
+}
+
+async fn post(url: String, secret: Option<String>, body: String) -> Result<(), Error> {
+	spawn_blocking(move || {
+		let mut request = ureq::post(&url).set("Content-Type", "application/json");
+		if let Some(secret) = &secret {
+			request = request.set(SECRET_HEADER, secret);
+		}
+		match request.send_string(&body) {
+			Ok(_) => Ok(()),
+			Err(ureq::Error::Status(code, _)) => Err(Error::ScrobbleQueryFailed(code)),
+			Err(ureq::Error::Transport(_)) => Err(Error::ScrobbleQueryTransport),
+		}
+	})
+	.await?
+}
+
+#[cfg(test)]
+mod test {
+	use crate::app::test;
+	use crate::test_name;
+
+	use super::*;
+
+	fn make_song() -> index::Song {
+		index::Song {
+			virtual_path: PathBuf::from_iter(["root", "Khemmis", "Hunted", "02 - Candlelight.mp3"]),
+			title: Some("Candlelight".to_owned()),
+			artists: vec!["Khemmis".to_owned()],
+			album: Some("Hunted".to_owned()),
+			duration: Some(300),
+			..Default::default()
+		}
+	}
+
+	async fn make_manager(test_name: String, webhook: &test::HttpListener) -> Manager {
+		let ctx = test::ContextBuilder::new(test_name).build().await;
+		ctx.config_manager
+			.apply_config(config::storage::Config {
+				scrobble_url: Some(webhook.url()),
+				scrobble_secret: Some("hunter2".to_owned()),
+				..Default::default()
+			})
+			.await
+			.unwrap();
+		Manager::new(ctx.config_manager)
+	}
+
+	#[test]
+	fn scrobble_describes_song() {
+		let song = make_song();
+
+		let scrobble = Scrobble::new("walter", &song);
+		assert_eq!(scrobble.username, "walter");
+		assert_eq!(scrobble.path, song.virtual_path);
+		assert_eq!(scrobble.title, song.title);
+		assert_eq!(scrobble.artists, song.artists);
+		assert_eq!(scrobble.album, song.album);
+		assert_eq!(scrobble.duration, Some(300));
+		assert!(scrobble.timestamp > 0);
+	}
+
+	#[tokio::test]
+	async fn scrobbles_are_delivered_to_webhook() {
+		let webhook = test::HttpListener::new();
+		let manager = make_manager(test_name!(), &webhook).await;
+		let scrobble = Scrobble::new("walter", &make_song());
+
+		manager.send(scrobble.clone()).await;
+
+		let requests = webhook.requests();
+		assert_eq!(requests.len(), 1);
+		assert!(requests[0].head.starts_with("POST "));
+		assert!(requests[0]
+			.head
+			.to_lowercase()
+			.contains("x-polaris-secret: hunter2"));
+		assert_eq!(requests[0].body, serde_json::to_string(&scrobble).unwrap());
+	}
+
+	#[tokio::test]
+	async fn failed_deliveries_are_retried() {
+		let webhook = test::HttpListener::new();
+		webhook.set_response(503, "");
+		let manager = make_manager(test_name!(), &webhook).await;
+
+		let start = std::time::Instant::now();
+		manager.send(Scrobble::new("walter", &make_song())).await;
+
+		assert_eq!(webhook.requests().len(), MAX_ATTEMPTS as usize);
+		// Delays double after each attempt: 1 + 2 + 4 + 8 times the initial delay
+		assert!(start.elapsed() >= INITIAL_RETRY_DELAY * 15);
+	}
+
+	#[tokio::test]
+	async fn scrobbles_are_dropped_when_too_many_are_pending() {
+		let webhook = test::HttpListener::new();
+		let manager = make_manager(test_name!(), &webhook).await;
+
+		let permits = manager
+			.delivery_permits
+			.clone()
+			.acquire_many_owned(MAX_PENDING_DELIVERIES as u32)
+			.await
+			.unwrap();
+		manager.scrobble("walter", &make_song());
+		tokio::time::sleep(Duration::from_millis(200)).await;
+		assert!(webhook.requests().is_empty());
+
+		drop(permits);
+		manager.scrobble("walter", &make_song());
+		tokio::time::sleep(Duration::from_millis(200)).await;
+		assert_eq!(webhook.requests().len(), 1);
+	}
+}
@@ -1,4 +1,7 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use crate::app::config::storage::*;
 use crate::app::{auth, config, index, ndb, playlist, scanner};
@@ -9,6 +12,7 @@ pub struct Context {
 	pub scanner: scanner::Scanner,
 	pub config_manager: config::Manager,
 	pub playlist_manager: playlist::Manager,
+	pub test_directory: PathBuf,
 }
 
 pub struct ContextBuilder {
@@ -38,6 +42,7 @@ impl ContextBuilder {
 		self.config.mount_dirs.push(MountDir {
 			name: name.to_owned(),
 			source: PathBuf::from(source),
+			..Default::default()
 		});
 		self
 	}
@@ -45,16 +50,24 @@ impl ContextBuilder {
 	pub async fn build(self) -> Context {
 		let config_path = self.test_directory.join("polaris.toml");
 
-		let auth_secret = auth::Secret::default();
-		let config_manager = config::Manager::new(&config_path, auth_secret)
+		let auth_keyring = auth::Keyring::default();
+		let config_manager = config::Manager::new(&config_path, auth_keyring)
 			.await
 			.unwrap();
 		let ndb_manager = ndb::Manager::new(&self.test_directory).unwrap();
 		let index_manager = index::Manager::new(&self.test_directory).await.unwrap();
-		let scanner = scanner::Scanner::new(index_manager.clone(), config_manager.clone())
-			.await
-			.unwrap();
-		let playlist_manager = playlist::Manager::new(ndb_manager.clone());
+		let scanner = scanner::Scanner::new(
+			index_manager.clone(),
+			config_manager.clone(),
+			self.test_directory.join("skipped_files.txt"),
+		)
+		.await
+		.unwrap();
+		let playlist_manager = playlist::Manager::new(
+			ndb_manager.clone(),
+			index_manager.clone(),
+			config_manager.clone(),
+		);
 
 		config_manager.apply_config(self.config).await.unwrap();
 
@@ -63,6 +76,87 @@ impl ContextBuilder {
 			scanner,
 			config_manager,
 			playlist_manager,
+			test_directory: self.test_directory,
 		}
 	}
 }
+
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+	pub head: String,
+	pub body: String,
+}
+
+// Minimal HTTP server standing in for third-party services. It records every request
+// and answers all of them with the same response.
+pub struct HttpListener {
+	port: u16,
+	requests: Arc<Mutex<Vec<HttpRequest>>>,
+	response: Arc<Mutex<(u16, String)>>,
+}
+
+impl HttpListener {
+	pub fn new() -> Self {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let response = Arc::new(Mutex::new((200, String::new())));
+
+		std::thread::spawn({
+			let requests = requests.clone();
+			let response = response.clone();
+			move || {
+				for stream in listener.incoming() {
+					let Ok(mut stream) = stream else {
+						continue;
+					};
+					let mut reader = BufReader::new(stream.try_clone().unwrap());
+					let mut head = String::new();
+					let mut content_length = 0;
+					loop {
+						let mut line = String::new();
+						if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+							break;
+						}
+						let lowercase = line.to_lowercase();
+						if let Some(length) = lowercase.strip_prefix("content-length:") {
+							content_length = length.trim().parse().unwrap_or(0);
+						}
+						head.push_str(&line);
+					}
+					let mut body = vec![0; content_length];
+					let _ = reader.read_exact(&mut body);
+					requests.lock().unwrap().push(HttpRequest {
+						head,
+						body: String::from_utf8_lossy(&body).into_owned(),
+					});
+
+					let (status, body) = response.lock().unwrap().clone();
+					let _ = write!(
+						stream,
+						"HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+						body.len()
+					);
+				}
+			}
+		});
+
+		Self {
+			port,
+			requests,
+			response,
+		}
+	}
+
+	pub fn url(&self) -> String {
+		format!("http://127.0.0.1:{}/", self.port)
+	}
+
+	pub fn set_response(&self, status: u16, body: &str) {
+		*self.response.lock().unwrap() = (status, body.to_owned());
+	}
+
+	pub fn requests(&self) -> Vec<HttpRequest> {
+		self.requests.lock().unwrap().clone()
+	}
+}
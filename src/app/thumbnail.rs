@@ -2,9 +2,11 @@ use std::cmp;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use image::codecs::jpeg::JpegEncoder;
 use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer};
+use tokio::sync::Semaphore;
 use tokio::task::spawn_blocking;
 
 use crate::app::Error;
@@ -15,6 +17,7 @@ pub struct Options {
 	pub max_dimension: Option<u32>,
 	pub resize_if_almost_square: bool,
 	pub pad_to_square: bool,
+	pub quality: u8, // JPEG quality, from 0 (smallest files) to 100 (best quality)
 }
 
 impl Default for Options {
@@ -23,10 +26,22 @@ impl Default for Options {
 			max_dimension: Some(400),
 			resize_if_almost_square: true,
 			pad_to_square: true,
+			quality: 80,
 		}
 	}
 }
 
+// JPEG qualities thumbnails can be requested with. Other values are rounded to the nearest
+// level, so that clients cannot fill the cache with many copies of the same thumbnail.
+const QUALITY_LEVELS: [u8; 4] = [25, 50, 80, 95];
+
+pub fn snap_quality(quality: u8) -> u8 {
+	QUALITY_LEVELS
+		.into_iter()
+		.min_by_key(|level| level.abs_diff(quality))
+		.unwrap()
+}
+
 #[derive(Clone)]
 pub struct Manager {
 	thumbnails_dir_path: PathBuf,
@@ -50,6 +65,53 @@ impl Manager {
 		}
 	}
 
+	pub async fn is_cached(&self, image_path: &Path, options: &Options) -> bool {
+		self.read_from_cache(image_path, options).await.is_some()
+	}
+
+	// Generates thumbnails for multiple images in parallel, one per CPU core at most.
+	// Results are in the same order as the requests, and a failure for one image does
+	// not affect the others.
+	pub async fn get_thumbnails(
+		&self,
+		requests: Vec<(PathBuf, Options)>,
+	) -> Vec<Result<PathBuf, Error>> {
+		let max_concurrent = std::thread::available_parallelism().map_or(1, |n| n.get());
+		let permits = Arc::new(Semaphore::new(max_concurrent));
+		let handles = requests
+			.into_iter()
+			.map(|(image_path, options)| {
+				let manager = self.clone();
+				let permits = permits.clone();
+				tokio::spawn(async move {
+					let _permit = permits.acquire().await.unwrap();
+					manager.get_thumbnail(&image_path, &options).await
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let mut results = Vec::with_capacity(handles.len());
+		for handle in handles {
+			results.push(handle.await.map_err(Error::from).and_then(|r| r));
+		}
+		results
+	}
+
+	// Returns the mime type and original bytes of the artwork embedded in an audio file,
+	// without any resizing or re-encoding. The mime type is sniffed from the image data
+	// because the one written in tags is free text.
+	pub async fn get_embedded_artwork(
+		&self,
+		audio_path: &Path,
+	) -> Result<(&'static str, Vec<u8>), Error> {
+		let audio_path = audio_path.to_owned();
+		let data = spawn_blocking(move || read_embedded_artwork(&audio_path)).await??;
+		let mime_type = image::guess_format(&data)
+			.map(|f| f.to_mime_type())
+			.unwrap_or("application/octet-stream");
+		Ok((mime_type, data))
+	}
+
 	fn get_thumbnail_path(&self, image_path: &Path, options: &Options) -> PathBuf {
 		let hash = Manager::hash(image_path, options);
 		let mut thumbnail_path = self.thumbnails_dir_path.clone();
@@ -88,10 +150,8 @@ impl Manager {
 
 		spawn_blocking({
 			let mut out_file = out_file.into_std().await;
-			move || {
-				let quality = 80;
-				thumbnail.write_with_encoder(JpegEncoder::new_with_quality(&mut out_file, quality))
-			}
+			let options = options.clone();
+			move || thumbnail.write_with_encoder(make_encoder(&mut out_file, &options))
 		})
 		.await?
 		.map_err(|e| Error::Image(image_path.to_owned(), e))?;
@@ -107,6 +167,10 @@ impl Manager {
 	}
 }
 
+fn make_encoder<W: std::io::Write>(writer: &mut W, options: &Options) -> JpegEncoder<&mut W> {
+	JpegEncoder::new_with_quality(writer, options.quality.clamp(1, 100))
+}
+
 fn generate_thumbnail(image_path: &Path, options: &Options) -> Result<DynamicImage, Error> {
 	let source_image = DynamicImage::ImageRgb8(read(image_path)?.into_rgb8());
 	let (source_width, source_height) = source_image.dimensions();
@@ -147,68 +211,79 @@ fn generate_thumbnail(image_path: &Path, options: &Options) -> Result<DynamicIma
 
 fn read(image_path: &Path) -> Result<DynamicImage, Error> {
 	match get_audio_format(image_path) {
-		Some(AudioFormat::AIFF) => read_aiff(image_path),
-		Some(AudioFormat::FLAC) => read_flac(image_path),
-		Some(AudioFormat::MP3) => read_mp3(image_path),
-		Some(AudioFormat::OGG) => read_vorbis(image_path),
-		Some(AudioFormat::OPUS) => read_opus(image_path),
-		Some(AudioFormat::WAVE) => read_wave(image_path),
-		Some(AudioFormat::APE) | Some(AudioFormat::MPC) => read_ape(image_path),
-		Some(AudioFormat::MP4) | Some(AudioFormat::M4B) => read_mp4(image_path),
+		Some(_) => {
+			let data = read_embedded_artwork(image_path)?;
+			image::load_from_memory(&data).map_err(|e| Error::Image(image_path.to_owned(), e))
+		}
 		None => image::open(image_path).map_err(|e| Error::Image(image_path.to_owned(), e)),
 	}
 }
 
-fn read_ape(_: &Path) -> Result<DynamicImage, Error> {
+// Returns the raw bytes of the picture embedded in an audio file.
+// When a file contains multiple pictures, the front cover is preferred.
+fn read_embedded_artwork(path: &Path) -> Result<Vec<u8>, Error> {
+	match get_audio_format(path) {
+		Some(AudioFormat::AIFF) => read_aiff(path),
+		Some(AudioFormat::FLAC) => read_flac(path),
+		Some(AudioFormat::MP3) => read_mp3(path),
+		Some(AudioFormat::OGG) => read_vorbis(path),
+		Some(AudioFormat::OPUS) => read_opus(path),
+		Some(AudioFormat::WAVE) => read_wave(path),
+		Some(AudioFormat::APE) | Some(AudioFormat::MPC) => read_ape(path),
+		Some(AudioFormat::MP4) | Some(AudioFormat::M4B) => read_mp4(path),
+		None => Err(Error::EmbeddedArtworkNotFound(path.to_owned())),
+	}
+}
+
+fn read_ape(_: &Path) -> Result<Vec<u8>, Error> {
 	Err(Error::UnsupportedFormat("ape"))
 }
 
-fn read_flac(path: &Path) -> Result<DynamicImage, Error> {
+fn read_flac(path: &Path) -> Result<Vec<u8>, Error> {
 	let tag =
 		metaflac::Tag::read_from_path(path).map_err(|e| Error::Metaflac(path.to_owned(), e))?;
-	if let Some(p) = tag.pictures().next() {
-		return image::load_from_memory(&p.data).map_err(|e| Error::Image(path.to_owned(), e));
-	}
-	Err(Error::EmbeddedArtworkNotFound(path.to_owned()))
+	tag.pictures()
+		.min_by_key(|p| p.picture_type != metaflac::block::PictureType::CoverFront)
+		.map(|p| p.data.clone())
+		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
 }
 
-fn read_mp3(path: &Path) -> Result<DynamicImage, Error> {
+fn read_mp3(path: &Path) -> Result<Vec<u8>, Error> {
 	let tag = id3::Tag::read_from_path(path).map_err(|e| Error::Id3(path.to_owned(), e))?;
 	read_id3(path, &tag)
 }
 
-fn read_aiff(path: &Path) -> Result<DynamicImage, Error> {
+fn read_aiff(path: &Path) -> Result<Vec<u8>, Error> {
 	let tag = id3::Tag::read_from_path(path).map_err(|e| Error::Id3(path.to_owned(), e))?;
 	read_id3(path, &tag)
 }
 
-fn read_wave(path: &Path) -> Result<DynamicImage, Error> {
+fn read_wave(path: &Path) -> Result<Vec<u8>, Error> {
 	let tag = id3::Tag::read_from_path(path).map_err(|e| Error::Id3(path.to_owned(), e))?;
 	read_id3(path, &tag)
 }
 
-fn read_id3(path: &Path, tag: &id3::Tag) -> Result<DynamicImage, Error> {
+fn read_id3(path: &Path, tag: &id3::Tag) -> Result<Vec<u8>, Error> {
 	tag.pictures()
-		.next()
+		.min_by_key(|p| p.picture_type != id3::frame::PictureType::CoverFront)
+		.map(|p| p.data.clone())
 		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
-		.and_then(|d| {
-			image::load_from_memory(&d.data).map_err(|e| Error::Image(path.to_owned(), e))
-		})
 }
 
-fn read_mp4(path: &Path) -> Result<DynamicImage, Error> {
+fn read_mp4(path: &Path) -> Result<Vec<u8>, Error> {
 	let tag =
 		mp4ameta::Tag::read_from_path(path).map_err(|e| Error::Mp4aMeta(path.to_owned(), e))?;
-	tag.artwork()
-		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
-		.and_then(|d| image::load_from_memory(d.data).map_err(|e| Error::Image(path.to_owned(), e)))
+	let artwork = tag
+		.artwork()
+		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))?;
+	Ok(artwork.data.to_vec())
 }
 
-fn read_vorbis(_: &Path) -> Result<DynamicImage, Error> {
+fn read_vorbis(_: &Path) -> Result<Vec<u8>, Error> {
 	Err(Error::UnsupportedFormat("vorbis"))
 }
 
-fn read_opus(_: &Path) -> Result<DynamicImage, Error> {
+fn read_opus(_: &Path) -> Result<Vec<u8>, Error> {
 	Err(Error::UnsupportedFormat("opus"))
 }
 
@@ -216,6 +291,70 @@ fn read_opus(_: &Path) -> Result<DynamicImage, Error> {
 mod test {
 
 	use super::*;
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	#[tokio::test]
+	async fn quality_affects_thumbnail() {
+		let manager = Manager::new(prepare_test_directory(test_name!()));
+		let image_path = Path::new("test-data/artwork/Folder.png");
+
+		let low_quality = Options {
+			quality: 10,
+			..Default::default()
+		};
+		let high_quality = Options {
+			quality: 100,
+			..Default::default()
+		};
+
+		let low = manager
+			.get_thumbnail(image_path, &low_quality)
+			.await
+			.unwrap();
+		let high = manager
+			.get_thumbnail(image_path, &high_quality)
+			.await
+			.unwrap();
+		assert_ne!(low, high);
+
+		let low_size = std::fs::metadata(&low).unwrap().len();
+		let high_size = std::fs::metadata(&high).unwrap().len();
+		assert!(low_size < high_size);
+	}
+
+	#[test]
+	fn quality_snaps_to_fixed_levels() {
+		assert_eq!(snap_quality(0), 25);
+		assert_eq!(snap_quality(79), 80);
+		assert_eq!(snap_quality(81), 80);
+		assert_eq!(snap_quality(100), 95);
+	}
+
+	#[tokio::test]
+	async fn can_generate_thumbnails_in_bulk() {
+		let manager = Manager::new(prepare_test_directory(test_name!()));
+		let requests = vec![
+			(
+				PathBuf::from("test-data/artwork/Folder.png"),
+				Options::default(),
+			),
+			(
+				PathBuf::from("test-data/artwork/sample.ape"),
+				Options::default(),
+			),
+			(
+				PathBuf::from("test-data/artwork/sample.mp3"),
+				Options::default(),
+			),
+		];
+
+		let results = manager.get_thumbnails(requests).await;
+		assert_eq!(results.len(), 3);
+		assert!(results[0].as_ref().unwrap().exists());
+		assert!(results[1].is_err());
+		assert!(results[2].as_ref().unwrap().exists());
+	}
 
 	#[test]
 	fn can_read_artwork_data() {
@@ -271,4 +410,33 @@ mod test {
 			.to_rgb8();
 		assert_eq!(wave_img, embedded_img);
 	}
+
+	#[tokio::test]
+	async fn can_read_raw_embedded_artwork() {
+		let manager = Manager::new(prepare_test_directory(test_name!()));
+		let embedded_img = image::open("test-data/artwork/Embedded.png")
+			.unwrap()
+			.to_rgb8();
+
+		for path in [
+			"test-data/artwork/sample.aif",
+			"test-data/artwork/sample.flac",
+			"test-data/artwork/sample.mp3",
+			"test-data/artwork/sample.m4a",
+			"test-data/artwork/sample.wav",
+		] {
+			let (mime_type, data) = manager.get_embedded_artwork(Path::new(path)).await.unwrap();
+			assert_eq!(mime_type, "image/png");
+			let img = image::load_from_memory(&data).unwrap().to_rgb8();
+			assert_eq!(img, embedded_img);
+		}
+	}
+
+	#[tokio::test]
+	async fn missing_embedded_artwork_is_reported() {
+		let manager = Manager::new(prepare_test_directory(test_name!()));
+		let path = Path::new("test-data/small-collection/Khemmis/Hunted/02 - Candlelight.mp3");
+		let result = manager.get_embedded_artwork(path).await;
+		assert!(matches!(result, Err(Error::EmbeddedArtworkNotFound(_))));
+	}
 }
@@ -0,0 +1,261 @@
+use std::{
+	hash::{DefaultHasher, Hash, Hasher},
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use tokio::task::spawn_blocking;
+
+use crate::app::{formats, Error};
+use crate::utils::AudioFormat;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Options {
+	pub format: AudioFormat,
+	pub bitrate: u32, // In kbps
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	transcodes_dir_path: PathBuf,
+}
+
+impl Manager {
+	pub fn new(transcodes_dir_path: PathBuf) -> Self {
+		Self {
+			transcodes_dir_path,
+		}
+	}
+
+	// Returns the location of a transcoded version of `real_path`. Cached outputs are
+	// keyed by virtual path, source modification time and transcode options, so editing
+	// the source file causes it to be transcoded again. The `transcode` function receives
+	// the source path and the path where its output should be written. It is skipped when
+	// the audio stream can be copied into the requested container as is.
+	pub async fn get_transcode<F>(
+		&self,
+		real_path: &Path,
+		virtual_path: &Path,
+		options: &Options,
+		transcode: F,
+	) -> Result<PathBuf, Error>
+	where
+		F: FnOnce(&Path, &Path) -> Result<(), Error> + Send + 'static,
+	{
+		let modified = tokio::fs::metadata(real_path)
+			.await
+			.and_then(|m| m.modified())
+			.map_err(|e| Error::Io(real_path.to_owned(), e))?;
+
+		let path = self.get_transcode_path(virtual_path, modified, options);
+		match tokio::fs::try_exists(&path).await.ok() {
+			Some(true) => Ok(path),
+			_ => {
+				self.read_from_source(real_path, path, options.clone(), transcode)
+					.await
+			}
+		}
+	}
+
+	fn get_transcode_path(
+		&self,
+		virtual_path: &Path,
+		modified: SystemTime,
+		options: &Options,
+	) -> PathBuf {
+		let hash = Manager::hash(virtual_path, modified, options);
+		let mut transcode_path = self.transcodes_dir_path.clone();
+		transcode_path.push(format!("{}.transcode", hash));
+		transcode_path
+	}
+
+	async fn read_from_source<F>(
+		&self,
+		real_path: &Path,
+		transcode_path: PathBuf,
+		options: Options,
+		transcode: F,
+	) -> Result<PathBuf, Error>
+	where
+		F: FnOnce(&Path, &Path) -> Result<(), Error> + Send + 'static,
+	{
+		tokio::fs::create_dir_all(&self.transcodes_dir_path)
+			.await
+			.map_err(|e| Error::Io(self.transcodes_dir_path.clone(), e))?;
+
+		// Output is moved into place once complete, so that interrupted transcodes
+		// never get served from the cache.
+		let partial_path = transcode_path.with_extension("partial");
+		spawn_blocking({
+			let real_path = real_path.to_owned();
+			let partial_path = partial_path.clone();
+			// Files which Symphonia cannot demux (eg. APE) are left to the transcoder
+			move || match remux(&real_path, &partial_path, &options) {
+				Err(Error::NotRemuxable(_, _) | Error::MediaProbeError(_)) => {
+					transcode(&real_path, &partial_path)
+				}
+				result => result,
+			}
+		})
+		.await??;
+
+		tokio::fs::rename(&partial_path, &transcode_path)
+			.await
+			.map_err(|e| Error::Io(transcode_path.clone(), e))?;
+
+		Ok(transcode_path)
+	}
+
+	fn hash(virtual_path: &Path, modified: SystemTime, options: &Options) -> u64 {
+		let mut hasher = DefaultHasher::new();
+		virtual_path.hash(&mut hasher);
+		modified.hash(&mut hasher);
+		options.hash(&mut hasher);
+		hasher.finish()
+	}
+}
+
+// Remuxing preserves the quality of the source, so lossy streams are only copied when
+// they do not exceed the requested bitrate.
+fn remux(source: &Path, destination: &Path, options: &Options) -> Result<(), Error> {
+	if options.format != AudioFormat::FLAC {
+		let bitrate = formats::read_technical_properties(source).and_then(|p| p.bitrate);
+		if !bitrate.is_some_and(|b| b <= options.bitrate) {
+			return Err(Error::NotRemuxable(source.to_owned(), options.format));
+		}
+	}
+	formats::remux(source, destination, options.format)
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+	use std::time::Duration;
+
+	use super::*;
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	struct Context {
+		manager: Manager,
+		source_path: PathBuf,
+		virtual_path: PathBuf,
+		num_transcodes: Arc<AtomicUsize>,
+	}
+
+	impl Context {
+		fn new(test_name: String) -> Self {
+			Self::with_source(
+				test_name,
+				[
+					"test-data",
+					"small-collection",
+					"Khemmis",
+					"Hunted",
+					"05 - Hunted.mp3",
+				]
+				.iter()
+				.collect(),
+			)
+		}
+
+		fn with_source(test_name: String, source: PathBuf) -> Self {
+			let test_directory = prepare_test_directory(test_name);
+			let source_path = test_directory
+				.join("source")
+				.with_extension(source.extension().unwrap());
+			std::fs::copy(source, &source_path).unwrap();
+			Self {
+				manager: Manager::new(test_directory.join("transcodes")),
+				source_path,
+				virtual_path: PathBuf::from_iter(["root", "source.mp3"]),
+				num_transcodes: Arc::default(),
+			}
+		}
+
+		async fn transcode(&self, options: &Options) -> PathBuf {
+			let num_transcodes = self.num_transcodes.clone();
+			self.manager
+				.get_transcode(
+					&self.source_path,
+					&self.virtual_path,
+					options,
+					move |source, destination| {
+						num_transcodes.fetch_add(1, Ordering::SeqCst);
+						std::fs::copy(source, destination)
+							.map_err(|e| Error::Io(destination.to_owned(), e))?;
+						Ok(())
+					},
+				)
+				.await
+				.unwrap()
+		}
+
+		fn num_transcodes(&self) -> usize {
+			self.num_transcodes.load(Ordering::SeqCst)
+		}
+	}
+
+	fn options(bitrate: u32) -> Options {
+		Options {
+			format: AudioFormat::OPUS,
+			bitrate,
+		}
+	}
+
+	#[tokio::test]
+	async fn serves_cached_transcode() {
+		let ctx = Context::new(test_name!());
+		let first = ctx.transcode(&options(128)).await;
+		let second = ctx.transcode(&options(128)).await;
+		assert_eq!(first, second);
+		assert!(second.exists());
+		assert_eq!(ctx.num_transcodes(), 1);
+	}
+
+	#[tokio::test]
+	async fn transcodes_on_cache_miss() {
+		let ctx = Context::new(test_name!());
+		let low = ctx.transcode(&options(64)).await;
+		let high = ctx.transcode(&options(320)).await;
+		assert_ne!(low, high);
+		assert!(low.exists());
+		assert!(high.exists());
+		assert_eq!(ctx.num_transcodes(), 2);
+	}
+
+	#[tokio::test]
+	async fn transcodes_again_when_source_changes() {
+		let ctx = Context::new(test_name!());
+		let before = ctx.transcode(&options(128)).await;
+
+		let modified = SystemTime::now() + Duration::from_secs(60);
+		std::fs::File::options()
+			.write(true)
+			.open(&ctx.source_path)
+			.unwrap()
+			.set_modified(modified)
+			.unwrap();
+
+		let after = ctx.transcode(&options(128)).await;
+		assert_ne!(before, after);
+		assert!(after.exists());
+		assert_eq!(ctx.num_transcodes(), 2);
+	}
+
+	#[tokio::test]
+	async fn remuxes_when_codec_matches() {
+		let ctx = Context::with_source(test_name!(), "test-data/formats/sample.flac".into());
+		let options = Options {
+			format: AudioFormat::FLAC,
+			bitrate: 128,
+		};
+		let path = ctx.transcode(&options).await;
+		assert!(path.exists());
+		assert_eq!(ctx.num_transcodes(), 0);
+		assert!(formats::decode_pcm(&path, formats::PcmOptions::default()).is_ok());
+	}
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashSet, time::Duration};
+
+use log::{error, info};
+
+use crate::app::{config, index, peaks, scanner, thumbnail};
+
+// Pause after creating each thumbnail, so that warm-up does not compete with user requests
+const THROTTLE_DELAY: Duration = Duration::from_millis(100);
+
+// Fills the peaks and thumbnail caches after each scan, so that browsing newly added
+// music does not wait on them.
+#[derive(Clone)]
+pub struct Manager {
+	config_manager: config::Manager,
+	index_manager: index::Manager,
+	peaks_manager: peaks::Manager,
+	scanner: scanner::Scanner,
+	thumbnail_manager: thumbnail::Manager,
+}
+
+impl Manager {
+	pub fn new(
+		config_manager: config::Manager,
+		index_manager: index::Manager,
+		peaks_manager: peaks::Manager,
+		scanner: scanner::Scanner,
+		thumbnail_manager: thumbnail::Manager,
+	) -> Self {
+		let manager = Self {
+			config_manager,
+			index_manager,
+			peaks_manager,
+			scanner,
+			thumbnail_manager,
+		};
+
+		tokio::spawn({
+			let manager = manager.clone();
+			async move {
+				loop {
+					manager.scanner.wait_for_scan_end().await;
+					if !manager.config_manager.get_warm_up_caches().await {
+						continue;
+					}
+					tokio::select! {
+						_ = manager.warm_up() => (),
+						_ = manager.scanner.wait_for_scan_start() => {
+							info!("Interrupted cache warm-up");
+						}
+					};
+				}
+			}
+		});
+
+		manager
+	}
+
+	// Songs and artwork which already have up-to-date cache entries are skipped. Peaks are
+	// computed a few songs at a time, within the configured concurrency limit. Thumbnails
+	// are created one at a time.
+	pub async fn warm_up(&self) {
+		info!("Beginning cache warm-up");
+		let song_files = self.index_manager.get_song_files().await;
+
+		let real_paths = song_files.iter().map(|(p, _)| p.clone()).collect();
+		self.peaks_manager.precompute(real_paths).await;
+
+		let thumbnail_options = thumbnail::Options::default();
+		let mut artworks = HashSet::new();
+		for (_, artwork) in song_files {
+			let Some(artwork) = artwork.filter(|a| artworks.insert(a.clone())) else {
+				continue;
+			};
+			let image_path = match self.config_manager.resolve_virtual_path(&artwork).await {
+				Ok(p) => p,
+				Err(e) => {
+					error!("Could not resolve `{}`: {e}", artwork.display());
+					continue;
+				}
+			};
+			if !self
+				.thumbnail_manager
+				.is_cached(&image_path, &thumbnail_options)
+				.await
+			{
+				if let Err(e) = self
+					.thumbnail_manager
+					.get_thumbnail(&image_path, &thumbnail_options)
+					.await
+				{
+					error!(
+						"Could not create thumbnail for `{}`: {e}",
+						image_path.display()
+					);
+				}
+				tokio::time::sleep(THROTTLE_DELAY).await;
+			}
+		}
+
+		info!("Completed cache warm-up");
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{num::NonZeroUsize, path::Path};
+
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	fn count_files(directory: &Path) -> usize {
+		std::fs::read_dir(directory).map_or(0, |d| d.count())
+	}
+
+	#[tokio::test]
+	async fn warm_up_fills_caches() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.mount("root", "test-data/small-collection")
+			.build()
+			.await;
+		ctx.scanner.run_scan().await.unwrap();
+
+		let peaks_dir_path = ctx.test_directory.join("peaks");
+		let thumbnails_dir_path = ctx.test_directory.join("thumbnails");
+		let manager = Manager::new(
+			ctx.config_manager.clone(),
+			ctx.index_manager.clone(),
+			peaks::Manager::new(peaks_dir_path.clone(), NonZeroUsize::MIN),
+			ctx.scanner.clone(),
+			thumbnail::Manager::new(thumbnails_dir_path.clone()),
+		);
+		manager.warm_up().await;
+
+		assert_eq!(count_files(&peaks_dir_path), 13);
+		assert!(count_files(&thumbnails_dir_path) >= 2);
+	}
+}
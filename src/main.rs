@@ -67,6 +67,8 @@ fn notify_ready() -> Result<(), Error> {
 	Ok(())
 }
 
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
 fn init_logging<T: AsRef<Path>>(
 	log_level: LevelFilter,
 	log_file_path: &Option<T>,
@@ -76,8 +78,10 @@ fn init_logging<T: AsRef<Path>>(
 		.add_filter_ignore_str("symphonia")
 		.build();
 
+	// Loggers accept everything, verbosity is controlled by the global max level instead so
+	// that it can change at runtime.
 	let mut loggers: Vec<Box<dyn SharedLogger>> = vec![TermLogger::new(
-		log_level,
+		LevelFilter::Trace,
 		log_config.clone(),
 		TerminalMode::Mixed,
 		ColorChoice::Auto,
@@ -89,7 +93,7 @@ fn init_logging<T: AsRef<Path>>(
 				.map_err(|e| Error::LogDirectoryCreationError(parent.to_owned(), e))?;
 		}
 		loggers.push(WriteLogger::new(
-			log_level,
+			LevelFilter::Trace,
 			log_config,
 			fs::File::create(path)
 				.map_err(|e| Error::LogFileCreationError(path.as_ref().to_owned(), e))?,
@@ -97,6 +101,7 @@ fn init_logging<T: AsRef<Path>>(
 	}
 
 	CombinedLogger::init(loggers).map_err(Error::LogInitialization)?;
+	log::set_max_level(log_level);
 
 	Ok(())
 }
@@ -118,8 +123,14 @@ fn main() -> Result<(), Error> {
 
 	let paths = paths::Paths::new(&cli_options);
 
+	if cli_options.check_config {
+		check_config(&paths.config_file_path)?;
+		println!("Configuration file {:#?} is valid", paths.config_file_path);
+		return Ok(());
+	}
+
 	// Logging
-	let log_level = cli_options.log_level.unwrap_or(LevelFilter::Info);
+	let log_level = cli_options.log_level.unwrap_or(DEFAULT_LOG_LEVEL);
 	init_logging(log_level, &paths.log_file_path)?;
 
 	// Fork
@@ -140,10 +151,31 @@ fn main() -> Result<(), Error> {
 	async_main(cli_options, paths)
 }
 
+#[tokio::main]
+async fn check_config(config_file_path: &Path) -> Result<(), Error> {
+	app::config::Manager::check_config(config_file_path).await?;
+	Ok(())
+}
+
 #[tokio::main]
 async fn async_main(cli_options: CLIOptions, paths: paths::Paths) -> Result<(), Error> {
 	// Create and run app
 	let app = app::App::new(cli_options.port.unwrap_or(5050), paths).await?;
+
+	// The command line log level takes precedence over the one from the config file
+	if cli_options.log_level.is_none() {
+		tokio::spawn({
+			let config_manager = app.config_manager.clone();
+			async move {
+				loop {
+					let log_level = config_manager.get_log_level().await;
+					log::set_max_level(log_level.unwrap_or(DEFAULT_LOG_LEVEL));
+					config_manager.on_config_change().await;
+				}
+			}
+		});
+	}
+
 	app.scanner.queue_scan();
 	app.ddns_manager.begin_periodic_updates();
 
@@ -3,6 +3,7 @@ use std::path::PathBuf;
 
 pub struct CLIOptions {
 	pub show_help: bool,
+	pub check_config: bool,
 	pub foreground: bool,
 	pub log_file_path: Option<PathBuf>,
 	#[cfg(unix)]
@@ -32,6 +33,7 @@ impl Manager {
 
 		Ok(CLIOptions {
 			show_help: matches.opt_present("h"),
+			check_config: matches.opt_present("check-config"),
 			#[cfg(unix)]
 			foreground: matches.opt_present("f"),
 			#[cfg(windows)]
@@ -88,6 +90,12 @@ fn get_options() -> getopts::Options {
 		"run polaris in the foreground instead of daemonizing",
 	);
 
+	options.optflag(
+		"",
+		"check-config",
+		"check that the configuration file is valid and exit",
+	);
+
 	options.optflag("h", "help", "print this help menu");
 	options
 }
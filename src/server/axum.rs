@@ -84,6 +84,18 @@ impl FromRef<App> for app::playlist::Manager {
 	}
 }
 
+impl FromRef<App> for app::scrobble::Manager {
+	fn from_ref(app: &App) -> Self {
+		app.scrobble_manager.clone()
+	}
+}
+
+impl FromRef<App> for app::transcode::Manager {
+	fn from_ref(app: &App) -> Self {
+		app.transcode_manager.clone()
+	}
+}
+
 impl FromRef<App> for app::thumbnail::Manager {
 	fn from_ref(app: &App) -> Self {
 		app.thumbnail_manager.clone()
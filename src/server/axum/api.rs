@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::{ops::RangeInclusive, path::PathBuf, time::Duration};
 
 use axum::{
+	body::Body,
 	extract::{DefaultBodyLimit, Path, Query, State},
+	http::{header, HeaderName},
 	response::{IntoResponse, Response},
 	routing::get,
 	Json,
@@ -9,19 +11,39 @@ use axum::{
 use axum_extra::headers::Range;
 use axum_extra::TypedHeader;
 use axum_range::{KnownSize, Ranged};
+use log::error;
 use regex::Regex;
+use tokio::{io::AsyncWriteExt, sync::Semaphore, task::spawn_blocking};
+use tokio_util::io::ReaderStream;
 use tower_http::{compression::CompressionLayer, CompressionLevel};
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 use crate::{
-	app::{auth, config, ddns, index, peaks, playlist, scanner, thumbnail, App},
+	app::{
+		self, auth, config, ddns, formats, index, peaks, playlist, scanner, scrobble, thumbnail,
+		transcode, App,
+	},
 	server::{
 		dto, error::APIError, APIMajorVersion, API_ARRAY_SEPARATOR, API_MAJOR_VERSION,
 		API_MINOR_VERSION,
 	},
 };
 
-use super::auth::{AdminRights, Auth};
+use super::auth::{AdminRights, Auth, ManageUsersRights, ScanRights, StreamRights, WriteRights};
+
+const PCM_FORMAT_HEADER: &str = "x-polaris-pcm-format";
+const TOTAL_COUNT_HEADER: &str = "x-polaris-total-count";
+const PCM_STREAM_BUFFER_SIZE: usize = 256 * 1024;
+const EXPORT_BUFFER_SIZE: usize = 64 * 1024;
+const PCM_SAMPLE_RATES: RangeInclusive<u32> = 8_000..=192_000;
+const MAX_CONCURRENT_PCM_STREAMS: usize = 8;
+const PEAKS_WINDOW_BUCKETS: RangeInclusive<usize> = 1..=10_000;
+const DEFAULT_PEAKS_WINDOW_BUCKETS: usize = 1_000;
+const ALBUM_PEAKS_BUCKETS: RangeInclusive<usize> = 1..=10_000;
+const DEFAULT_ALBUM_PEAKS_BUCKETS: usize = 1_000;
+
+// Each PCM stream occupies a blocking thread until decoding completes or the client goes away
+static PCM_STREAM_PERMITS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_PCM_STREAMS);
 
 pub fn router() -> OpenApiRouter<App> {
 	OpenApiRouter::new()
@@ -31,12 +53,17 @@ pub fn router() -> OpenApiRouter<App> {
 		.routes(routes!(get_settings, put_settings))
 		.routes(routes!(get_mount_dirs, put_mount_dirs))
 		.routes(routes!(post_trigger_index))
+		.routes(routes!(post_trigger_full_index))
+		.routes(routes!(post_trigger_index_rebuild))
 		.routes(routes!(get_index_status))
+		.routes(routes!(get_skipped_files))
+		.routes(routes!(get_export))
 		// User management
 		.routes(routes!(post_auth))
 		.routes(routes!(post_user))
 		.routes(routes!(delete_user, put_user))
 		.routes(routes!(get_users))
+		.routes(routes!(post_rotate_auth_secret))
 		// File browser
 		.routes(routes!(get_browse_root))
 		.routes(routes!(get_browse))
@@ -46,30 +73,54 @@ pub fn router() -> OpenApiRouter<App> {
 		.routes(routes!(get_albums))
 		.routes(routes!(get_recent_albums))
 		.routes(routes!(get_random_albums))
+		.routes(routes!(get_albums_from_years))
 		.routes(routes!(get_artists))
 		.routes(routes!(get_artist))
+		.routes(routes!(get_artist_collaborators))
 		.routes(routes!(get_album))
+		.routes(routes!(get_album_songs))
+		.routes(routes!(get_album_peaks))
+		.routes(routes!(get_similar_albums))
+		.routes(routes!(get_stats))
 		.routes(routes!(get_genres))
 		.routes(routes!(get_genre))
 		.routes(routes!(get_genre_albums))
 		.routes(routes!(get_genre_artists))
 		.routes(routes!(get_genre_songs))
+		.routes(routes!(get_field_values))
+		.routes(routes!(get_field_value_counts))
 		.route("/random", get(get_random_albums)) // Deprecated
 		.route("/recent", get(get_recent_albums)) // Deprecated
 		// Search
 		.routes(routes!(get_search))
+		.routes(routes!(get_explain_search))
+		.routes(routes!(get_search_facets))
+		.routes(routes!(get_validate_search))
+		.routes(routes!(get_parse_search))
 		// Playlist management
 		.routes(routes!(get_playlists))
 		.routes(routes!(put_playlist, get_playlist, delete_playlist))
+		.routes(routes!(get_playlist_collage))
+		.routes(routes!(put_playlist_pls, get_playlist_pls))
+		.routes(routes!(put_playlist_sharing))
+		.routes(routes!(post_playlist_share, delete_playlist_share))
+		.routes(routes!(get_shared_playlist))
+		.routes(routes!(post_playlist_song))
+		.routes(routes!(delete_playlist_song))
 		// Media
 		.routes(routes!(get_songs))
 		.routes(routes!(get_peaks))
+		.routes(routes!(post_scrobble))
 		.routes(routes!(get_thumbnail))
+		.routes(routes!(post_thumbnails))
+		.routes(routes!(get_artwork))
 		// Layers
 		.layer(CompressionLayer::new().quality(CompressionLevel::Fastest))
 		.layer(DefaultBodyLimit::max(10 * 1024 * 1024)) // 10MB
 		// Uncompressed
 		.routes(routes!(get_audio))
+		.routes(routes!(get_shared_playlist_audio))
+		.routes(routes!(get_pcm))
 }
 
 #[utoipa::path(
@@ -224,6 +275,24 @@ async fn put_mount_dirs(
 	Ok(())
 }
 
+#[utoipa::path(
+	post,
+	path = "/rotate_auth_secret",
+	tag = "User Management",
+	description = "Starts signing auth tokens with a new secret. Tokens issued before the rotation remain valid for a grace period of 7 days.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+)]
+async fn post_rotate_auth_secret(
+	_admin_rights: AdminRights,
+	State(app): State<App>,
+) -> Result<(), APIError> {
+	app.rotate_auth_secret().await?;
+	Ok(())
+}
+
 #[utoipa::path(
 	post,
 	path = "/auth",
@@ -250,6 +319,7 @@ async fn post_auth(
 		username: username.clone(),
 		token,
 		is_admin,
+		role: user.role().into(),
 	};
 
 	Ok(Json(authorization))
@@ -269,7 +339,7 @@ async fn post_auth(
 	),
 )]
 async fn get_users(
-	_admin_rights: AdminRights,
+	_manage_users_rights: ManageUsersRights,
 	State(config_manager): State<config::Manager>,
 ) -> Result<Json<Vec<dto::User>>, APIError> {
 	let users = config_manager.get_users().await;
@@ -294,13 +364,23 @@ async fn get_users(
 	)
 )]
 async fn post_user(
-	_admin_rights: AdminRights,
+	manage_users_rights: ManageUsersRights,
 	State(config_manager): State<config::Manager>,
 	Json(new_user): Json<dto::NewUser>,
 ) -> Result<(), APIError> {
+	let role = match new_user.role {
+		Some(role) => role.into(),
+		None if new_user.admin => auth::Role::Admin,
+		None => auth::Role::User,
+	};
+	manage_users_rights.check_grant(role.permissions())?;
+
 	config_manager
 		.create_user(&new_user.name, &new_user.password, new_user.admin)
 		.await?;
+	if let Some(role) = new_user.role {
+		config_manager.set_role(&new_user.name, role.into()).await?;
+	}
 	Ok(())
 }
 
@@ -321,14 +401,42 @@ async fn post_user(
 	)
 )]
 async fn put_user(
-	admin_rights: AdminRights,
+	manage_users_rights: ManageUsersRights,
 	State(config_manager): State<config::Manager>,
 	Path(name): Path<String>,
 	user_update: Json<dto::UserUpdate>,
 ) -> Result<(), APIError> {
-	if let Some(auth) = &admin_rights.get_auth() {
-		if auth.get_username() == name.as_str() && user_update.new_is_admin == Some(false) {
-			return Err(APIError::OwnAdminPrivilegeRemoval);
+	let user = config_manager.get_user(&name).await.ok();
+	if let Some(user) = &user {
+		manage_users_rights.check_manage(user)?;
+	}
+
+	let mut granted_permissions = match (user_update.new_role, user_update.new_is_admin) {
+		(Some(role), _) => auth::Role::from(role).permissions().collect(),
+		(None, Some(true)) => auth::Role::Admin.permissions().collect(),
+		(None, _) => Vec::new(),
+	};
+	if let Some(permissions) = &user_update.new_permissions {
+		granted_permissions.extend(permissions.iter().map(|p| auth::Permission::from(*p)));
+	}
+	manage_users_rights.check_grant(granted_permissions)?;
+
+	if let Some(auth) = &manage_users_rights.get_auth() {
+		if auth.get_username() == name.as_str() {
+			let is_admin = user.as_ref().is_some_and(|u| u.is_admin());
+			let removes_admin = match user_update.new_role {
+				Some(role) => role != dto::Role::Admin,
+				None => user_update.new_is_admin == Some(false),
+			};
+			if is_admin && removes_admin {
+				return Err(APIError::OwnAdminPrivilegeRemoval);
+			}
+			let changes_permissions = user_update.new_role.is_some()
+				|| user_update.new_is_admin.is_some()
+				|| user_update.new_permissions.is_some();
+			if changes_permissions {
+				return Err(APIError::OwnPermissionsChange);
+			}
 		}
 	}
 
@@ -336,10 +444,19 @@ async fn put_user(
 		config_manager.set_password(&name, password).await?;
 	}
 
-	if let Some(is_admin) = &user_update.new_is_admin {
+	if let Some(role) = user_update.new_role {
+		config_manager.set_role(&name, role.into()).await?;
+	} else if let Some(is_admin) = &user_update.new_is_admin {
 		config_manager.set_is_admin(&name, *is_admin).await?;
 	}
 
+	if let Some(permissions) = &user_update.new_permissions {
+		let permissions = permissions.iter().map(|p| (*p).into()).collect();
+		config_manager
+			.set_permissions(&name, Some(permissions))
+			.await?;
+	}
+
 	Ok(())
 }
 
@@ -359,15 +476,18 @@ async fn put_user(
 	)
 )]
 async fn delete_user(
-	admin_rights: AdminRights,
+	manage_users_rights: ManageUsersRights,
 	State(config_manager): State<config::Manager>,
 	Path(name): Path<String>,
 ) -> Result<(), APIError> {
-	if let Some(auth) = &admin_rights.get_auth() {
+	if let Some(auth) = &manage_users_rights.get_auth() {
 		if auth.get_username() == name.as_str() {
 			return Err(APIError::DeletingOwnAccount);
 		}
 	}
+	if let Ok(user) = config_manager.get_user(&name).await {
+		manage_users_rights.check_manage(&user)?;
+	}
 	config_manager.delete_user(&name).await?;
 	Ok(())
 }
@@ -383,13 +503,66 @@ async fn delete_user(
 	),
 )]
 async fn post_trigger_index(
-	_admin_rights: AdminRights,
+	_scan_rights: ScanRights,
 	State(scanner): State<scanner::Scanner>,
 ) -> Result<(), APIError> {
 	scanner.try_trigger_scan();
 	Ok(())
 }
 
+#[utoipa::path(
+	post,
+	path = "/trigger_full_index",
+	tag = "Configuration",
+	description = "Starts a scan which reads every music file again. Regular scans skip files which did not change since the previous scan, and reuse the metadata read back then.\n\nThe music collection will update after the scan is fully completed.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+)]
+async fn post_trigger_full_index(
+	_scan_rights: ScanRights,
+	State(scanner): State<scanner::Scanner>,
+) -> Result<(), APIError> {
+	scanner.try_trigger_full_scan();
+	Ok(())
+}
+
+#[utoipa::path(
+	post,
+	path = "/trigger_index_rebuild",
+	tag = "Configuration",
+	description = "Re-indexes the music collection with the current search settings, without reading any music file. Queries are served from the existing index until the new one is ready.\n\nThe rebuild is discarded if a scan completes while it is in progress.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+)]
+async fn post_trigger_index_rebuild(
+	_scan_rights: ScanRights,
+	State(config_manager): State<config::Manager>,
+	State(index_manager): State<index::Manager>,
+) -> Result<(), APIError> {
+	let normalization = config_manager.get_text_normalization().await;
+	let path_indexing = config_manager.get_path_indexing().await;
+	let bigram_threshold = config_manager.get_search_bigram_threshold().await;
+	let word_boundary_fields = config_manager.get_search_word_boundary_fields().await;
+	tokio::spawn(async move {
+		if let Err(e) = index_manager
+			.rebuild(
+				normalization,
+				path_indexing,
+				bigram_threshold,
+				word_boundary_fields,
+			)
+			.await
+		{
+			error!("Could not rebuild collection index: {e}");
+		}
+	});
+	Ok(())
+}
+
 #[utoipa::path(
 	get,
 	path = "/index_status",
@@ -410,6 +583,75 @@ async fn get_index_status(
 	Ok(Json(scanner.get_status().await.into()))
 }
 
+// Lets code running on a blocking thread write into an async stream
+struct BlockingWriter {
+	runtime: tokio::runtime::Handle,
+	writer: tokio::io::DuplexStream,
+}
+
+impl std::io::Write for BlockingWriter {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.runtime.block_on(self.writer.write(buf))
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		self.runtime.block_on(self.writer.flush())
+	}
+}
+
+#[utoipa::path(
+	get,
+	path = "/export",
+	tag = "Configuration",
+	description = "Downloads the metadata of every song in the collection, as a JSON array. The export is streamed while it is being written.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+)]
+async fn get_export(
+	_admin_rights: AdminRights,
+	State(index_manager): State<index::Manager>,
+) -> Response {
+	let (reader, writer) = tokio::io::duplex(EXPORT_BUFFER_SIZE);
+	let writer = std::io::BufWriter::new(BlockingWriter {
+		runtime: tokio::runtime::Handle::current(),
+		writer,
+	});
+	tokio::spawn(async move {
+		if let Err(e) = index_manager.export_json(writer).await {
+			error!("Could not export collection: {e}");
+		}
+	});
+
+	(
+		[(header::CONTENT_TYPE, "application/json")],
+		Body::from_stream(ReaderStream::new(reader)),
+	)
+		.into_response()
+}
+
+#[utoipa::path(
+	get,
+	path = "/skipped_files",
+	tag = "Configuration",
+	description = "Lists audio files which could not be indexed during the last scan, such as files in unsupported formats or with unreadable tags.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	responses(
+		(status = 200, body = Vec<dto::SkippedFile>),
+	)
+)]
+async fn get_skipped_files(
+	_admin_rights: AdminRights,
+	State(scanner): State<scanner::Scanner>,
+) -> Result<Json<Vec<dto::SkippedFile>>, APIError> {
+	let skipped_files = scanner.get_skipped_files().await;
+	Ok(Json(skipped_files.into_iter().map(|f| f.into()).collect()))
+}
+
 fn index_files_to_response(files: Vec<index::File>, api_version: APIMajorVersion) -> Response {
 	match api_version {
 		APIMajorVersion::V7 => Json(
@@ -429,6 +671,17 @@ fn index_files_to_response(files: Vec<index::File>, api_version: APIMajorVersion
 	}
 }
 
+fn index_page_to_response(page: index::Page, api_version: APIMajorVersion) -> Response {
+	(
+		[(
+			HeaderName::from_static(TOTAL_COUNT_HEADER),
+			page.num_files.to_string(),
+		)],
+		index_files_to_response(page.files, api_version),
+	)
+		.into_response()
+}
+
 const SONG_LIST_CAPACITY: usize = 200;
 
 async fn make_song_list(paths: Vec<PathBuf>, index_manager: &index::Manager) -> dto::SongList {
@@ -480,35 +733,41 @@ fn albums_to_response(albums: Vec<index::Album>, api_version: APIMajorVersion) -
 	get,
 	path = "/browse",
 	tag = "File Browser",
-	description = "Reads the content of the top-level directory in the music collection.",
+	description = "Reads the content of the top-level directory in the music collection. Large directories can be read one page at a time using `offset` and `count`, in which case the total number of entries is returned in the `X-Polaris-Total-Count` header.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
 	),
 	params(
-		("Accept-Version" = Option<i32>, Header, minimum = 7, maximum = 8)
+		("Accept-Version" = Option<i32>, Header, minimum = 7, maximum = 8),
+		dto::BrowseParameters
 	),
 	responses(
 		(status = 200, body = Vec<dto::BrowserEntry>),
 	)
 )]
 async fn get_browse_root(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	api_version: APIMajorVersion,
 	State(index_manager): State<index::Manager>,
+	Query(options): Query<dto::BrowseParameters>,
 ) -> Response {
-	let result = match index_manager.browse(PathBuf::new()).await {
+	let offset = options.offset.unwrap_or(0);
+	let result = match index_manager
+		.browse(PathBuf::new(), offset, options.count)
+		.await
+	{
 		Ok(r) => r,
 		Err(e) => return APIError::from(e).into_response(),
 	};
-	index_files_to_response(result, api_version)
+	index_page_to_response(result, api_version)
 }
 
 #[utoipa::path(
 	get,
 	path = "/browse/{*path}",
 	tag = "File Browser",
-	description = "Reads the content of a directory in the music collection.",
+	description = "Reads the content of a directory in the music collection. Large directories can be read one page at a time using `offset` and `count`, in which case the total number of entries is returned in the `X-Polaris-Total-Count` header.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
@@ -516,22 +775,25 @@ async fn get_browse_root(
 	params(
 		("Accept-Version" = Option<i32>, Header, minimum = 7, maximum = 8),
 		("path", allow_reserved, example = "my_music/classical/beethoven"),
+		dto::BrowseParameters
 	),
 	responses(
 		(status = 200, body = Vec<dto::BrowserEntry>),
 	)
 )]
 async fn get_browse(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	api_version: APIMajorVersion,
 	State(index_manager): State<index::Manager>,
 	Path(path): Path<PathBuf>,
+	Query(options): Query<dto::BrowseParameters>,
 ) -> Response {
-	let result = match index_manager.browse(path).await {
+	let offset = options.offset.unwrap_or(0);
+	let result = match index_manager.browse(path, offset, options.count).await {
 		Ok(r) => r,
 		Err(e) => return APIError::from(e).into_response(),
 	};
-	index_files_to_response(result, api_version)
+	index_page_to_response(result, api_version)
 }
 
 #[utoipa::path(
@@ -551,7 +813,7 @@ async fn get_browse(
 	)
 )]
 async fn get_flatten_root(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	api_version: APIMajorVersion,
 	State(index_manager): State<index::Manager>,
 ) -> Response {
@@ -581,7 +843,7 @@ async fn get_flatten_root(
 	)
 )]
 async fn get_flatten(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	api_version: APIMajorVersion,
 	State(index_manager): State<index::Manager>,
 	Path(path): Path<PathBuf>,
@@ -636,11 +898,14 @@ async fn get_albums(
 )]
 async fn get_artists(
 	_auth: Auth,
+	State(config_manager): State<config::Manager>,
 	State(index_manager): State<index::Manager>,
 ) -> Result<Json<Vec<dto::ArtistHeader>>, APIError> {
+	let various_artists_label = config_manager.get_various_artists_label().await;
+	let group_by_album_artist = config_manager.get_group_by_album_artist().await;
 	Ok(Json(
 		index_manager
-			.get_artists()
+			.get_artists(various_artists_label, group_by_album_artist)
 			.await
 			.into_iter()
 			.map(|a| a.into())
@@ -670,6 +935,29 @@ async fn get_artist(
 	Ok(Json(index_manager.get_artist(name).await?.into()))
 }
 
+#[utoipa::path(
+	get,
+	path = "/artist/{name}/collaborators",
+	tag = "Collection",
+	description = "Lists artists credited on the same songs as a given artist, from most to least frequent.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Claude Frank")),
+	responses(
+		(status = 200, body = Vec<dto::Collaborator>),
+	)
+)]
+async fn get_artist_collaborators(
+	_auth: Auth,
+	State(index_manager): State<index::Manager>,
+	Path(name): Path<String>,
+) -> Result<Json<Vec<dto::Collaborator>>, APIError> {
+	let collaborators = index_manager.get_collaborators(name).await?;
+	Ok(Json(collaborators.into_iter().map(|c| c.into()).collect()))
+}
+
 #[utoipa::path(
 	get,
 	path = "/album/{name}/by/{artists}",
@@ -682,6 +970,7 @@ async fn get_artist(
 	params(
 		("name", example = "The Piano Sonatas"),
 		("artists", example = "Claude Frank", description = "Artists the album is attributed to, separated by unicode \\u{000C} characters."),
+		dto::AlbumParameters
 	),
 	responses(
 		(status = 200, body = dto::Album),
@@ -691,12 +980,49 @@ async fn get_album(
 	_auth: Auth,
 	State(index_manager): State<index::Manager>,
 	Path((name, artists)): Path<(String, String)>,
+	Query(options): Query<dto::AlbumParameters>,
 ) -> Result<Json<dto::Album>, APIError> {
 	let artists = artists
 		.split(API_ARRAY_SEPARATOR)
 		.map(str::to_owned)
 		.collect::<Vec<_>>();
-	Ok(Json(index_manager.get_album(artists, name).await?.into()))
+	let album = index_manager.get_album(artists, name, options.year).await?;
+	Ok(Json(album.into()))
+}
+
+#[utoipa::path(
+	get,
+	path = "/album/{name}/by/{artists}/similar",
+	tag = "Collection",
+	description = "Returns albums similar to a given album, based on their genres, artists and release years. The most similar albums are listed first.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(
+		("name", example = "The Piano Sonatas"),
+		("artists", example = "Claude Frank", description = "Artists the album is attributed to, separated by unicode \\u{000C} characters."),
+		dto::SimilarAlbumsParameters
+	),
+	responses(
+		(status = 200, body = Vec<dto::AlbumHeader>),
+	)
+)]
+async fn get_similar_albums(
+	_auth: Auth,
+	State(index_manager): State<index::Manager>,
+	Path((name, artists)): Path<(String, String)>,
+	Query(options): Query<dto::SimilarAlbumsParameters>,
+) -> Result<Json<Vec<dto::AlbumHeader>>, APIError> {
+	let artists = artists
+		.split(API_ARRAY_SEPARATOR)
+		.map(str::to_owned)
+		.collect::<Vec<_>>();
+	let count = options.count.unwrap_or(20);
+	let albums = index_manager
+		.get_similar_albums(artists, name, options.year, count)
+		.await?;
+	Ok(Json(albums.into_iter().map(|a| a.into()).collect()))
 }
 
 #[utoipa::path(
@@ -802,6 +1128,50 @@ async fn get_recent_albums(
 	albums_to_response(albums, api_version)
 }
 
+#[utoipa::path(
+	get,
+	path = "/albums/years",
+	tag = "Collection",
+	description = "Returns the albums released within a range of years.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(dto::GetAlbumsFromYearsParameters),
+	responses(
+		(status = 200, body = Vec<dto::AlbumHeader>),
+	)
+)]
+async fn get_albums_from_years(
+	_auth: Auth,
+	State(index_manager): State<index::Manager>,
+	Query(options): Query<dto::GetAlbumsFromYearsParameters>,
+) -> Json<Vec<dto::AlbumHeader>> {
+	let years = options.from..=options.to.unwrap_or(options.from);
+	let albums = index_manager.get_albums_from_years(years).await;
+	Json(albums.into_iter().map(|a| a.into()).collect())
+}
+
+#[utoipa::path(
+	get,
+	path = "/stats",
+	tag = "Collection",
+	description = "Returns statistics about the whole music collection.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	responses(
+		(status = 200, body = dto::CollectionStats),
+	)
+)]
+async fn get_stats(
+	_auth: Auth,
+	State(index_manager): State<index::Manager>,
+) -> Json<dto::CollectionStats> {
+	Json(index_manager.stats().await.into())
+}
+
 #[utoipa::path(
 	get,
 	path = "/genres",
@@ -942,32 +1312,26 @@ async fn get_genre_songs(
 
 #[utoipa::path(
 	get,
-	path = "/search/{*query}",
+	path = "/album/{name}/songs",
 	tag = "Collection",
-	description = "Returns songs matching a search query. The query syntax is documented in the search section of the Polaris web UI.",
+	description = "Returns all songs of albums with the given name, regardless of which artists they are attributed to, in track order.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
 	),
-	params(
-		("Accept-Version" = Option<i32>, Header, minimum = 7, maximum = 8),
-		("query", allow_reserved, example = "sonata && moonlight"),
-	),
+	params(("name", example = "The Piano Sonatas")),
 	responses(
 		(status = 200, body = dto::SongList),
 	)
 )]
-async fn get_search(
+async fn get_album_songs(
 	_auth: Auth,
-	api_version: APIMajorVersion,
 	State(index_manager): State<index::Manager>,
-	Path(query): Path<String>,
-) -> Response {
-	let songs = match index_manager.search(query).await {
-		Ok(f) => f,
-		Err(e) => return APIError::from(e).into_response(),
-	};
-
+	Path(name): Path<String>,
+) -> Result<Json<dto::SongList>, APIError> {
+	let paths = index_manager.get_album_songs(name).await?;
+	let songs = index_manager.get_songs(paths).await;
+	let songs = songs.into_iter().filter_map(Result::ok).collect::<Vec<_>>();
 	let song_list = dto::SongList {
 		paths: songs.iter().map(|s| s.virtual_path.clone()).collect(),
 		first_songs: songs
@@ -976,70 +1340,343 @@ async fn get_search(
 			.map(|s| s.into())
 			.collect(),
 	};
-
-	match api_version {
-		APIMajorVersion::V7 => Json(
-			song_list
-				.paths
-				.iter()
-				.map(|p| dto::v7::CollectionFile::Song(p.into()))
-				.collect::<Vec<_>>(),
-		)
-		.into_response(),
-		APIMajorVersion::V8 => Json(song_list).into_response(),
-	}
+	Ok(Json(song_list))
 }
 
 #[utoipa::path(
 	get,
-	path = "/playlists",
-	tag = "Playlists",
-	description = "Lists playlists owned by the current user.",
+	path = "/album/{name}/by/{artists}/peaks",
+	tag = "Media",
+	description = "Returns a single waveform overview spanning all songs of an album, in track order.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
 	),
+	params(
+		("name", example = "The Piano Sonatas"),
+		("artists", example = "Claude Frank", description = "Artists the album is attributed to, separated by unicode \\u{000C} characters."),
+		dto::AlbumPeaksParameters
+	),
 	responses(
-		(status = 200, body = Vec<dto::PlaylistHeader>),
+		(status = 200, body = dto::AlbumPeaks),
 	)
 )]
-async fn get_playlists(
+async fn get_album_peaks(
 	auth: Auth,
-	State(playlist_manager): State<playlist::Manager>,
-) -> Result<Json<Vec<dto::PlaylistHeader>>, APIError> {
-	let playlists = playlist_manager.list_playlists(auth.get_username()).await?;
-	let playlists = playlists.into_iter().map(|p| p.into()).collect();
+	State(config_manager): State<config::Manager>,
+	State(index_manager): State<index::Manager>,
+	State(peaks_manager): State<peaks::Manager>,
+	Path((name, artists)): Path<(String, String)>,
+	Query(options): Query<dto::AlbumPeaksParameters>,
+) -> Result<Json<dto::AlbumPeaks>, APIError> {
+	// Guest paths do not apply, since albums can span directories outside of them
+	if !auth.has_permission(auth::Permission::Stream) {
+		return Err(APIError::PermissionRequired(auth::Permission::Stream));
+	}
 
-	Ok(Json(playlists))
+	let num_buckets = options.num_buckets.unwrap_or(DEFAULT_ALBUM_PEAKS_BUCKETS);
+	if !ALBUM_PEAKS_BUCKETS.contains(&num_buckets) {
+		return Err(APIError::InvalidPeaksBuckets(num_buckets));
+	}
+
+	let artists = artists
+		.split(API_ARRAY_SEPARATOR)
+		.map(str::to_owned)
+		.collect::<Vec<_>>();
+	let album = index_manager.get_album(artists, name, options.year).await?;
+	let mut audio_paths = Vec::new();
+	for song in album.songs {
+		let audio_path = config_manager
+			.resolve_virtual_path(&song.virtual_path)
+			.await?;
+		audio_paths.push(audio_path);
+	}
+
+	let peaks = peaks_manager
+		.get_album_peaks(&audio_paths, num_buckets)
+		.await?;
+	Ok(Json(peaks.into()))
 }
 
 #[utoipa::path(
-	put,
-	path = "/playlist/{name}",
-	tag = "Playlists",
-	description = "Creates or updates a playlist for the current user.",
+	get,
+	path = "/field_values/{field}",
+	tag = "Collection",
+	description = "Lists the distinct values of a text field across the collection in alphabetical order, eg. to populate filter dropdowns.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
 	),
-	params(("name", example = "Chill Jazz")),
-	request_body = dto::SavePlaylistInput,
+	params(
+		("field", example = "genre"),
+		dto::FieldValuesParameters,
+	),
+	responses(
+		(status = 200, body = Vec<String>),
+	)
 )]
-async fn put_playlist(
-	auth: Auth,
-	State(playlist_manager): State<playlist::Manager>,
+async fn get_field_values(
+	_auth: Auth,
 	State(index_manager): State<index::Manager>,
+	Path(field): Path<dto::TextField>,
+	Query(options): Query<dto::FieldValuesParameters>,
+) -> Json<Vec<String>> {
+	let offset = options.offset.unwrap_or(0);
+	let count = options.count.unwrap_or(usize::MAX);
+	Json(
+		index_manager
+			.get_field_values(field.into(), offset, count)
+			.await,
+	)
+}
+
+#[utoipa::path(
+	get,
+	path = "/field_value_counts/{field}",
+	tag = "Collection",
+	description = "Lists the distinct values of a text field across the collection, along with how many songs use each of them.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(
+		("field", example = "genre"),
+		dto::FieldValueCountsParameters,
+	),
+	responses(
+		(status = 200, body = Vec<dto::FieldValueCount>),
+	)
+)]
+async fn get_field_value_counts(
+	_auth: Auth,
+	State(index_manager): State<index::Manager>,
+	Path(field): Path<dto::TextField>,
+	Query(options): Query<dto::FieldValueCountsParameters>,
+) -> Json<Vec<dto::FieldValueCount>> {
+	let ordering = options.order_by.unwrap_or_default().into();
+	let values = index_manager
+		.get_distinct_values(field.into(), ordering)
+		.await;
+	Json(
+		values
+			.into_iter()
+			.map(|(value, num_songs)| dto::FieldValueCount { value, num_songs })
+			.collect(),
+	)
+}
+
+#[utoipa::path(
+	get,
+	path = "/search/{*query}",
+	tag = "Collection",
+	description = "Returns songs matching a search query. The query syntax is documented in the search section of the Polaris web UI.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(
+		("Accept-Version" = Option<i32>, Header, minimum = 7, maximum = 8),
+		("query", allow_reserved, example = "sonata && moonlight"),
+		dto::SearchParameters
+	),
+	responses(
+		(status = 200, body = dto::SearchResults),
+	)
+)]
+async fn get_search(
+	_auth: Auth,
+	api_version: APIMajorVersion,
+	State(config_manager): State<config::Manager>,
+	State(index_manager): State<index::Manager>,
+	Path(query): Path<String>,
+	Query(parameters): Query<dto::SearchParameters>,
+) -> Response {
+	let options = index::SearchOptions {
+		default_field: config_manager.get_search_default_field().await,
+		min_fuzzy_length: config_manager.get_search_min_length().await,
+		limits: config_manager.get_search_limits().await,
+		sort_order: parameters.sort_order(),
+	};
+	let songs = match index_manager.search(query, options).await {
+		Ok(f) => f,
+		Err(e) => return APIError::from(e).into_response(),
+	};
+
+	let exact_matches = songs
+		.iter()
+		.filter(|(_, kind)| *kind == index::MatchKind::Exact)
+		.map(|(s, _)| s.virtual_path.clone())
+		.collect();
+	let songs = songs.into_iter().map(|(s, _)| s).collect::<Vec<_>>();
+
+	let song_list = dto::SongList {
+		paths: songs.iter().map(|s| s.virtual_path.clone()).collect(),
+		first_songs: songs
+			.into_iter()
+			.take(SONG_LIST_CAPACITY)
+			.map(|s| s.into())
+			.collect(),
+	};
+
+	match api_version {
+		APIMajorVersion::V7 => Json(
+			song_list
+				.paths
+				.iter()
+				.map(|p| dto::v7::CollectionFile::Song(p.into()))
+				.collect::<Vec<_>>(),
+		)
+		.into_response(),
+		APIMajorVersion::V8 => Json(dto::SearchResults {
+			songs: song_list,
+			exact_matches,
+		})
+		.into_response(),
+	}
+}
+
+#[utoipa::path(
+	get,
+	path = "/explain_search/{*query}",
+	tag = "Collection",
+	description = "Lists the predicates of a search query in the order they would be evaluated, along with an estimate of how many songs each of them inspects. Useful to diagnose slow searches.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("query", allow_reserved, example = "sonata && moonlight")),
+	responses(
+		(status = 200, body = Vec<dto::PredicateEstimate>),
+	)
+)]
+async fn get_explain_search(
+	_auth: Auth,
+	State(config_manager): State<config::Manager>,
+	State(index_manager): State<index::Manager>,
+	Path(query): Path<String>,
+) -> Result<Json<Vec<dto::PredicateEstimate>>, APIError> {
+	let default_field = config_manager.get_search_default_field().await;
+	let estimates = index_manager.explain_search(query, default_field).await?;
+	Ok(Json(estimates.into_iter().map(|e| e.into()).collect()))
+}
+
+#[utoipa::path(
+	get,
+	path = "/search_facets/{*term}",
+	tag = "Collection",
+	description = "Counts how many songs match a fuzzy search term within each text field, so search results can be refined by field. A song matching in several fields is counted once per field. Fields without any match are omitted.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("term", allow_reserved, example = "dragon")),
+	responses(
+		(status = 200, body = Vec<dto::SearchFacet>),
+	)
+)]
+async fn get_search_facets(
+	_auth: Auth,
+	State(index_manager): State<index::Manager>,
+	Path(term): Path<String>,
+) -> Json<Vec<dto::SearchFacet>> {
+	let facets = index_manager.get_search_facets(term).await;
+	let facets = facets
+		.into_iter()
+		.filter(|(_, num_songs)| *num_songs > 0)
+		.map(|(field, num_songs)| dto::SearchFacet {
+			field: field.into(),
+			num_songs,
+		})
+		.collect();
+	Json(facets)
+}
+
+#[utoipa::path(
+	get,
+	path = "/validate_search/{*query}",
+	tag = "Collection",
+	description = "Checks the syntax of a search query without running it. Responds with an error when the query cannot be parsed.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("query", allow_reserved, example = "sonata && moonlight")),
+)]
+async fn get_validate_search(_auth: Auth, Path(query): Path<String>) -> Result<(), APIError> {
+	index::validate_query(&query)?;
+	Ok(())
+}
+
+#[utoipa::path(
+	get,
+	path = "/parse_search/{*query}",
+	tag = "Collection",
+	description = "Returns the syntax tree of a search query without running it, for tools which display or edit queries. Responds with an error when the query cannot be parsed.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("query", allow_reserved, example = "sonata && moonlight")),
+	responses(
+		(status = 200, body = dto::SearchExpr),
+	)
+)]
+async fn get_parse_search(
+	_auth: Auth,
+	Path(query): Path<String>,
+) -> Result<Json<dto::SearchExpr>, APIError> {
+	Ok(Json(index::parse_query(&query)?.into()))
+}
+
+#[utoipa::path(
+	get,
+	path = "/playlists",
+	tag = "Playlists",
+	description = "Lists playlists owned by the current user, followed by public playlists of other users.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	responses(
+		(status = 200, body = Vec<dto::PlaylistHeader>),
+	)
+)]
+async fn get_playlists(
+	auth: Auth,
+	State(playlist_manager): State<playlist::Manager>,
+) -> Result<Json<Vec<dto::PlaylistHeader>>, APIError> {
+	let playlists = playlist_manager.list_playlists(auth.get_username()).await?;
+	let playlists = playlists.into_iter().map(|p| p.into()).collect();
+
+	Ok(Json(playlists))
+}
+
+#[utoipa::path(
+	put,
+	path = "/playlist/{name}",
+	tag = "Playlists",
+	description = "Creates or updates a playlist for the current user.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	request_body = dto::SavePlaylistInput,
+)]
+async fn put_playlist(
+	write_rights: WriteRights,
+	State(playlist_manager): State<playlist::Manager>,
 	Path(name): Path<String>,
 	playlist: Json<dto::SavePlaylistInput>,
 ) -> Result<(), APIError> {
-	let songs = index_manager
-		.get_songs(playlist.tracks.clone())
+	let songs = playlist_manager
+		.resolve_songs(playlist.tracks.clone())
 		.await
 		.into_iter()
 		.filter_map(|s| s.ok())
 		.collect();
 	playlist_manager
-		.save_playlist(&name, auth.get_username(), songs)
+		.save_playlist(&name, write_rights.get_auth().get_username(), songs)
 		.await?;
 	Ok(())
 }
@@ -1048,7 +1685,7 @@ async fn put_playlist(
 	get,
 	path = "/playlist/{name}",
 	tag = "Playlists",
-	description = "Retrieves a playlist owned by the current user.",
+	description = "Retrieves a playlist owned by the current user, or a public playlist of another user.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
@@ -1056,6 +1693,7 @@ async fn put_playlist(
 	params(
 		("Accept-Version" = Option<i32>, Header, minimum = 7, maximum = 8),
 		("name", example = "Chill Jazz"),
+		dto::PlaylistParameters
 	),
 	responses(
 		(status = 200, body = dto::Playlist),
@@ -1067,9 +1705,12 @@ async fn get_playlist(
 	State(index_manager): State<index::Manager>,
 	State(playlist_manager): State<playlist::Manager>,
 	Path(name): Path<String>,
+	Query(options): Query<dto::PlaylistParameters>,
 ) -> Response {
+	let reader = auth.get_username();
+	let owner = options.owner.as_deref().unwrap_or(reader);
 	let playlist = match playlist_manager
-		.read_playlist(&name, auth.get_username())
+		.read_playlist_as(&name, owner, reader)
 		.await
 	{
 		Ok(s) => s,
@@ -1086,6 +1727,241 @@ async fn get_playlist(
 	}
 }
 
+#[utoipa::path(
+	put,
+	path = "/playlist/{name}/pls",
+	tag = "Playlists",
+	description = "Creates or updates a playlist for the current user from the content of a PLS file. Songs are listed in the order of their numeric index, and must be referred to by virtual path.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	request_body(content = String, content_type = "audio/x-scpls"),
+	responses(
+		(status = 200, body = dto::PlaylistImport),
+	)
+)]
+async fn put_playlist_pls(
+	write_rights: WriteRights,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+	content: String,
+) -> Result<Json<dto::PlaylistImport>, APIError> {
+	let problems = playlist_manager
+		.import_pls(&name, write_rights.get_auth().get_username(), &content)
+		.await?;
+	Ok(Json(dto::PlaylistImport {
+		problems: problems.into_iter().map(|p| p.to_string()).collect(),
+	}))
+}
+
+#[utoipa::path(
+	get,
+	path = "/playlist/{name}/pls",
+	tag = "Playlists",
+	description = "Exports a playlist owned by the current user as a PLS file.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	responses(
+		(status = 200, body = String, content_type = "audio/x-scpls"),
+	)
+)]
+async fn get_playlist_pls(
+	auth: Auth,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+) -> Result<impl IntoResponse, APIError> {
+	let pls = playlist_manager
+		.export_pls(&name, auth.get_username())
+		.await?;
+	Ok(([(header::CONTENT_TYPE, "audio/x-scpls")], pls))
+}
+
+#[utoipa::path(
+	get,
+	path = "/playlist/{name}/collage",
+	tag = "Playlists",
+	description = "Lists album arts from a playlist owned by the current user, suitable for displaying a 2x2 collage. Thumbnails can be retrieved with the `/thumbnail` endpoint.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	responses(
+		(status = 200, body = dto::PlaylistCollage),
+	)
+)]
+async fn get_playlist_collage(
+	auth: Auth,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+) -> Result<Json<dto::PlaylistCollage>, APIError> {
+	let artworks = playlist_manager
+		.get_collage(&name, auth.get_username())
+		.await?;
+	Ok(Json(dto::PlaylistCollage { artworks }))
+}
+
+#[utoipa::path(
+	put,
+	path = "/playlist/{name}/sharing",
+	tag = "Playlists",
+	description = "Makes a playlist owned by the current user readable by other users, or hands it over to another user.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	request_body = dto::PlaylistSharingInput,
+)]
+async fn put_playlist_sharing(
+	write_rights: WriteRights,
+	State(config_manager): State<config::Manager>,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+	sharing: Json<dto::PlaylistSharingInput>,
+) -> Result<(), APIError> {
+	let owner = write_rights.get_auth().get_username();
+	if let Some(public) = sharing.public {
+		playlist_manager
+			.set_playlist_public(&name, owner, public)
+			.await?;
+	}
+	if let Some(new_owner) = &sharing.owner {
+		config_manager.get_user(new_owner).await?;
+		playlist_manager
+			.set_playlist_owner(&name, owner, new_owner)
+			.await?;
+	}
+	Ok(())
+}
+
+#[utoipa::path(
+	post,
+	path = "/playlist/{name}/share",
+	tag = "Playlists",
+	description = "Creates a link to share a playlist owned by the current user with anyone, including people without an account. The returned token grants read-only access to this playlist and to streaming its songs, but not to the rest of the collection.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	responses(
+		(status = 200, body = dto::PlaylistShare),
+	)
+)]
+async fn post_playlist_share(
+	write_rights: WriteRights,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+) -> Result<Json<dto::PlaylistShare>, APIError> {
+	let auth::Token(token) = playlist_manager
+		.share_playlist(&name, write_rights.get_auth().get_username())
+		.await?;
+	Ok(Json(dto::PlaylistShare { token }))
+}
+
+#[utoipa::path(
+	delete,
+	path = "/playlist/{name}/share",
+	tag = "Playlists",
+	description = "Invalidates all links sharing a playlist owned by the current user.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+)]
+async fn delete_playlist_share(
+	write_rights: WriteRights,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+) -> Result<(), APIError> {
+	playlist_manager
+		.revoke_playlist_share(&name, write_rights.get_auth().get_username())
+		.await?;
+	Ok(())
+}
+
+#[utoipa::path(
+	get,
+	path = "/shared_playlist/{token}",
+	tag = "Playlists",
+	description = "Retrieves the playlist a share token grants access to. No other authentication is required.",
+	params(("token", example = "XCWOGzhfvB8wnHhEdhAV0fTm2iVkW5Yk")),
+	responses(
+		(status = 200, body = dto::Playlist),
+	)
+)]
+async fn get_shared_playlist(
+	State(index_manager): State<index::Manager>,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(token): Path<String>,
+) -> Result<Json<dto::Playlist>, APIError> {
+	let playlist = playlist_manager
+		.read_shared_playlist(&auth::Token(token))
+		.await?;
+	Ok(Json(dto::Playlist {
+		header: playlist.header.into(),
+		songs: make_song_list(playlist.songs, &index_manager).await,
+	}))
+}
+
+#[utoipa::path(
+	post,
+	path = "/playlist/{name}/songs",
+	tag = "Playlists",
+	description = "Inserts a song in a playlist owned by the current user, without replacing its other songs.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz")),
+	request_body = dto::AddPlaylistSongInput,
+)]
+async fn post_playlist_song(
+	write_rights: WriteRights,
+	State(playlist_manager): State<playlist::Manager>,
+	Path(name): Path<String>,
+	song: Json<dto::AddPlaylistSongInput>,
+) -> Result<(), APIError> {
+	playlist_manager
+		.add_song(
+			&name,
+			write_rights.get_auth().get_username(),
+			song.path.clone(),
+			song.position,
+		)
+		.await?;
+	Ok(())
+}
+
+#[utoipa::path(
+	delete,
+	path = "/playlist/{name}/songs/{index}",
+	tag = "Playlists",
+	description = "Removes the song at the given position from a playlist owned by the current user.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("name", example = "Chill Jazz"), ("index", example = 0)),
+)]
+async fn delete_playlist_song(
+	write_rights: WriteRights,
+	State(playlist_manager): State<playlist::Manager>,
+	Path((name, index)): Path<(String, usize)>,
+) -> Result<(), APIError> {
+	playlist_manager
+		.remove_song_at(&name, write_rights.get_auth().get_username(), index)
+		.await?;
+	Ok(())
+}
+
 #[utoipa::path(
 	delete,
 	path = "/playlist/{name}",
@@ -1098,12 +1974,12 @@ async fn get_playlist(
 	params(("name", example = "Chill Jazz")),
 )]
 async fn delete_playlist(
-	auth: Auth,
+	write_rights: WriteRights,
 	State(playlist_manager): State<playlist::Manager>,
 	Path(name): Path<String>,
 ) -> Result<(), APIError> {
 	playlist_manager
-		.delete_playlist(&name, auth.get_username())
+		.delete_playlist(&name, write_rights.get_auth().get_username())
 		.await?;
 	Ok(())
 }
@@ -1112,67 +1988,255 @@ async fn delete_playlist(
 	get,
 	path = "/audio/{*path}",
 	tag = "Media",
-	description = "Serves a music file.\n\nThis endpoint supports HTTP range requests to facilitate streaming.",
+	description = "Serves a music file.\n\nWhen `format` is set, the audio stream is copied into that container without being re-encoded, and the result is cached. Streams which cannot be copied as is (eg. a lossy stream above `max_bitrate`) are rejected.\n\nThis endpoint supports HTTP range requests to facilitate streaming.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
 	),
-	params(("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3")),
+	params(
+		("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3"),
+		dto::AudioParameters,
+	),
 	responses(
 		(status = 206, body = [u8]),
 		(status = 200, body = [u8]),
+		(status = 406, description = "The audio stream cannot be served in the requested format"),
 	)
 )]
 async fn get_audio(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	State(config_manager): State<config::Manager>,
+	State(transcode_manager): State<transcode::Manager>,
 	Path(path): Path<PathBuf>,
+	Query(options): Query<dto::AudioParameters>,
 	range: Option<TypedHeader<Range>>,
 ) -> Result<impl IntoResponse, APIError> {
-	let audio_path = config_manager.resolve_virtual_path(&path).await?;
-
-	let Ok(file) = tokio::fs::File::open(audio_path).await else {
-		return Err(APIError::AudioFileIOError);
-	};
+	let mut audio_path = config_manager.resolve_virtual_path(&path).await?;
+	if let Some(options) = options.transcode_options() {
+		// Polaris does not bundle an encoder, so only streams which can be remuxed are served
+		let format = options.format;
+		audio_path = transcode_manager
+			.get_transcode(&audio_path, &path, &options, move |source, _| {
+				Err(app::Error::NotRemuxable(source.to_owned(), format))
+			})
+			.await?;
+	}
+	serve_file(&audio_path, range)
+		.await
+		.map_err(|_| APIError::AudioFileIOError)
+}
 
-	let Ok(body) = KnownSize::file(file).await else {
-		return Err(APIError::AudioFileIOError);
-	};
+#[utoipa::path(
+	get,
+	path = "/shared_playlist/{token}/audio/{*path}",
+	tag = "Media",
+	description = "Serves a music file which belongs to the playlist a share token grants access to. No other authentication is required.\n\nThis endpoint supports HTTP range requests to facilitate streaming.",
+	params(
+		("token", example = "XCWOGzhfvB8wnHhEdhAV0fTm2iVkW5Yk"),
+		("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3"),
+	),
+	responses(
+		(status = 206, body = [u8]),
+		(status = 200, body = [u8]),
+	)
+)]
+async fn get_shared_playlist_audio(
+	State(config_manager): State<config::Manager>,
+	State(playlist_manager): State<playlist::Manager>,
+	Path((token, path)): Path<(String, PathBuf)>,
+	range: Option<TypedHeader<Range>>,
+) -> Result<impl IntoResponse, APIError> {
+	let playlist = playlist_manager
+		.read_shared_playlist(&auth::Token(token))
+		.await?;
+	if !playlist.songs.contains(&path) {
+		return Err(APIError::SongNotFound);
+	}
+	let audio_path = config_manager.resolve_virtual_path(&path).await?;
+	serve_file(&audio_path, range)
+		.await
+		.map_err(|_| APIError::AudioFileIOError)
+}
 
+// Streams a file from disk, honoring the `Range` header so clients can seek
+async fn serve_file(
+	path: &std::path::Path,
+	range: Option<TypedHeader<Range>>,
+) -> Result<Ranged<KnownSize<tokio::fs::File>>, std::io::Error> {
+	let file = tokio::fs::File::open(path).await?;
+	let body = KnownSize::file(file).await?;
 	let range = range.map(|TypedHeader(r)| r);
 	Ok(Ranged::new(range, body))
 }
 
+#[utoipa::path(
+	get,
+	path = "/pcm/{*path}",
+	tag = "Media",
+	description = "Decodes an audio file into raw interleaved little-endian PCM samples, streamed as decoding progresses. The output format is described by the `X-Polaris-PCM-Format` header (eg. `f32; rate=44100; channels=2`).",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(
+		("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3"),
+		dto::PcmOptions
+	),
+	responses(
+		(status = 200, body = [u8]),
+		(status = 400, description = "Sample rate outside of 8000-192000"),
+		(status = 503, description = "Too many PCM streams in progress"),
+	)
+)]
+async fn get_pcm(
+	_stream_rights: StreamRights,
+	State(config_manager): State<config::Manager>,
+	Path(path): Path<PathBuf>,
+	Query(options): Query<dto::PcmOptions>,
+) -> Result<Response, APIError> {
+	if let Some(sample_rate) = options.sample_rate {
+		if !PCM_SAMPLE_RATES.contains(&sample_rate) {
+			return Err(APIError::InvalidPcmSampleRate(sample_rate));
+		}
+	}
+
+	let permit = PCM_STREAM_PERMITS
+		.try_acquire()
+		.map_err(|_| APIError::TooManyPcmStreams)?;
+
+	let audio_path = config_manager.resolve_virtual_path(&path).await?;
+	let options = formats::PcmOptions::from(options);
+
+	let mut decoder = spawn_blocking({
+		let audio_path = audio_path.clone();
+		move || formats::decode_pcm(&audio_path, options)
+	})
+	.await
+	.map_err(app::Error::from)??;
+
+	let format = decoder.format();
+	let sample_format = match format.sample_format {
+		formats::SampleFormat::F32 => "f32",
+		formats::SampleFormat::I16 => "i16",
+	};
+	let format_header = format!(
+		"{sample_format}; rate={}; channels={}",
+		format.sample_rate, format.num_channels
+	);
+
+	// Decoding happens on a blocking thread and stops as soon as the client goes away
+	let (reader, mut writer) = tokio::io::duplex(PCM_STREAM_BUFFER_SIZE);
+	let runtime = tokio::runtime::Handle::current();
+	spawn_blocking(move || {
+		let _permit = permit;
+		loop {
+			match decoder.next_chunk() {
+				Ok(Some(chunk)) => {
+					if runtime.block_on(writer.write_all(&chunk)).is_err() {
+						break;
+					}
+				}
+				Ok(None) => break,
+				Err(e) => {
+					error!("Could not decode {}: {e}", audio_path.display());
+					break;
+				}
+			}
+		}
+	});
+
+	Ok((
+		[
+			(header::CONTENT_TYPE, "application/octet-stream".to_owned()),
+			(HeaderName::from_static(PCM_FORMAT_HEADER), format_header),
+		],
+		Body::from_stream(ReaderStream::new(reader)),
+	)
+		.into_response())
+}
+
 #[utoipa::path(
 	get,
 	path = "/peaks/{*path}",
 	tag = "Media",
-	description = "Returns loudness values regularly sampled throughout the specified song.",
+	description = "Returns loudness values regularly sampled throughout the specified song. When `end_ms` is set, values are computed for that time window only, split into `num_buckets` min/max pairs.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
 	),
-	params(("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3")),
+	params(
+		("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3"),
+		dto::PeaksParameters,
+	),
 	responses(
 		(status = 200, body = [u8]),
+		(status = 400, description = "Invalid time window or number of buckets"),
 	)
 )]
 async fn get_peaks(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	State(config_manager): State<config::Manager>,
 	State(peaks_manager): State<peaks::Manager>,
 	Path(path): Path<PathBuf>,
+	Query(options): Query<dto::PeaksParameters>,
 ) -> Result<dto::Peaks, APIError> {
 	let audio_path = config_manager.resolve_virtual_path(&path).await?;
-	let peaks = peaks_manager.get_peaks(&audio_path).await?;
+
+	let Some(end_ms) = options.end_ms else {
+		if options.start_ms.is_some() || options.num_buckets.is_some() {
+			return Err(APIError::InvalidPeaksWindow);
+		}
+		let peaks = peaks_manager.get_peaks(&audio_path).await?;
+		return Ok(peaks.interleaved);
+	};
+
+	let start_ms = options.start_ms.unwrap_or_default();
+	let num_buckets = options.num_buckets.unwrap_or(DEFAULT_PEAKS_WINDOW_BUCKETS);
+	if end_ms <= start_ms || !PEAKS_WINDOW_BUCKETS.contains(&num_buckets) {
+		return Err(APIError::InvalidPeaksWindow);
+	}
+
+	let peaks = peaks_manager
+		.get_peaks_window(
+			&audio_path,
+			Duration::from_millis(start_ms),
+			Duration::from_millis(end_ms),
+			num_buckets,
+		)
+		.await?;
 	Ok(peaks.interleaved)
 }
 
+#[utoipa::path(
+	post,
+	path = "/scrobble/{*path}",
+	tag = "Media",
+	description = "Reports that the current user played the specified song. When a scrobble webhook is configured, it is notified in the background.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3")),
+)]
+async fn post_scrobble(
+	auth: Auth,
+	State(index_manager): State<index::Manager>,
+	State(scrobble_manager): State<scrobble::Manager>,
+	Path(path): Path<PathBuf>,
+) -> Result<(), APIError> {
+	let Some(song) = index_manager.get_songs(vec![path]).await.pop() else {
+		return Err(APIError::SongNotFound);
+	};
+	let song = song?;
+	scrobble_manager.scrobble(auth.get_username(), &song);
+	Ok(())
+}
+
 #[utoipa::path(
 	get,
 	path = "/thumbnail/{*path}",
 	tag = "Media",
-	description = "Serves an image file. Valid paths can be obtained from the `.artwork` field of `Song`, `Album` and `AlbumHeader` models.\n\nThis endpoint supports HTTP range requests to facilitate streaming.",
+	description = "Serves a resized version of an image file. Valid paths can be obtained from the `.artwork` field of `Song`, `Album` and `AlbumHeader` models. Other image files within the music collection (eg. booklet scans) can also be requested by their virtual path.\n\nThis endpoint supports HTTP range requests to facilitate streaming.",
 	security(
 		("auth_token" = []),
 		("auth_query_param" = []),
@@ -1187,7 +2251,7 @@ async fn get_peaks(
 	)
 )]
 async fn get_thumbnail(
-	_auth: Auth,
+	_stream_rights: StreamRights,
 	State(config_manager): State<config::Manager>,
 	State(thumbnails_manager): State<thumbnail::Manager>,
 	Path(path): Path<PathBuf>,
@@ -1201,14 +2265,84 @@ async fn get_thumbnail(
 		.get_thumbnail(&image_path, &options)
 		.await?;
 
-	let Ok(file) = tokio::fs::File::open(thumbnail_path).await else {
-		return Err(APIError::ThumbnailFileIOError);
-	};
+	serve_file(&thumbnail_path, range)
+		.await
+		.map_err(|_| APIError::ThumbnailFileIOError)
+}
 
-	let Ok(body) = KnownSize::file(file).await else {
-		return Err(APIError::ThumbnailFileIOError);
-	};
+#[utoipa::path(
+	post,
+	path = "/thumbnails",
+	tag = "Media",
+	description = "Generates thumbnails for many images at once, eg. before displaying a grid of albums. Thumbnails are generated in parallel and can then be served quickly by the `/thumbnail` endpoint using the same options.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	request_body = dto::GenerateThumbnailsInput,
+	responses(
+		(status = 200, body = dto::GenerateThumbnailsOutput),
+	)
+)]
+async fn post_thumbnails(
+	_stream_rights: StreamRights,
+	State(config_manager): State<config::Manager>,
+	State(thumbnails_manager): State<thumbnail::Manager>,
+	Json(input): Json<dto::GenerateThumbnailsInput>,
+) -> Json<dto::GenerateThumbnailsOutput> {
+	let options = input.options();
+	let mut output = dto::GenerateThumbnailsOutput::default();
+
+	let mut requests = Vec::new();
+	let mut requested_paths = Vec::new();
+	for path in input.paths {
+		match config_manager.resolve_virtual_path(&path).await {
+			Ok(image_path) => {
+				requests.push((image_path, options.clone()));
+				requested_paths.push(path);
+			}
+			Err(_) => output.not_found.push(path),
+		}
+	}
 
-	let range = range.map(|TypedHeader(r)| r);
-	Ok(Ranged::new(range, body))
+	let results = thumbnails_manager.get_thumbnails(requests).await;
+	for (path, result) in requested_paths.into_iter().zip(results) {
+		match result {
+			Ok(_) => output.generated.push(path),
+			Err(_) => output.not_found.push(path),
+		}
+	}
+
+	Json(output)
+}
+
+#[utoipa::path(
+	get,
+	path = "/artwork/{*path}",
+	tag = "Media",
+	description = "Serves the original artwork embedded in an audio file, without resizing or re-encoding it. When the file contains multiple pictures, the front cover is preferred.",
+	security(
+		("auth_token" = []),
+		("auth_query_param" = []),
+	),
+	params(("path", allow_reserved, example = "my_music/beethoven/moonlight_sonata.mp3")),
+	responses(
+		(status = 200, body = [u8]),
+	)
+)]
+async fn get_artwork(
+	_stream_rights: StreamRights,
+	State(config_manager): State<config::Manager>,
+	State(thumbnails_manager): State<thumbnail::Manager>,
+	Path(path): Path<PathBuf>,
+) -> Result<impl IntoResponse, APIError> {
+	let audio_path = config_manager.resolve_virtual_path(&path).await?;
+	let (mime_type, data) = thumbnails_manager.get_embedded_artwork(&audio_path).await?;
+	Ok((
+		[
+			(header::CONTENT_TYPE, mime_type),
+			(header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+		],
+		data,
+	))
 }
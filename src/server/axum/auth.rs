@@ -1,4 +1,6 @@
-use axum::extract::{FromRef, FromRequestParts, Query};
+use std::path::PathBuf;
+
+use axum::extract::{FromRef, FromRequestParts, Path, Query};
 use headers::authorization::{Bearer, Credentials};
 use http::request::Parts;
 
@@ -10,12 +12,17 @@ use crate::{
 #[derive(Debug)]
 pub struct Auth {
 	username: String,
+	permissions: Vec<auth::Permission>,
 }
 
 impl Auth {
 	pub fn get_username(&self) -> &String {
 		&self.username
 	}
+
+	pub fn has_permission(&self, permission: auth::Permission) -> bool {
+		self.permissions.contains(&permission)
+	}
 }
 
 impl<S> FromRequestParts<S> for Auth
@@ -45,25 +52,82 @@ where
 		let authorization = config_manager
 			.authenticate(&auth::Token(token), auth::Scope::PolarisAuth)
 			.await?;
+		let permissions = config_manager.get_permissions(&authorization).await;
 
 		Ok(Auth {
 			username: authorization.username,
+			permissions,
 		})
 	}
 }
 
+// Grants access to users allowed to change server settings, which admins always are
+#[derive(Debug)]
+pub struct AdminRights;
+
+impl<S> FromRequestParts<S> for AdminRights
+where
+	config::Manager: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = APIError;
+
+	async fn from_request_parts(parts: &mut Parts, app: &S) -> Result<Self, Self::Rejection> {
+		let config_manager = config::Manager::from_ref(app);
+
+		let user_count = config_manager.get_users().await.len();
+		if user_count == 0 {
+			return Ok(AdminRights);
+		}
+
+		let auth = Auth::from_request_parts(parts, app).await?;
+		if auth.has_permission(auth::Permission::ManageSettings) {
+			Ok(AdminRights)
+		} else {
+			Err(APIError::AdminPermissionRequired)
+		}
+	}
+}
+
+// Same as `AdminRights`, for users allowed to manage other users without being admins
 #[derive(Debug)]
-pub struct AdminRights {
+pub struct ManageUsersRights {
 	auth: Option<Auth>,
 }
 
-impl AdminRights {
+impl ManageUsersRights {
 	pub fn get_auth(&self) -> &Option<Auth> {
 		&self.auth
 	}
+
+	// Permissions can only be handed out by users who have them (or by anyone, before the
+	// first user is created). Since admins have every permission, only admins can create
+	// other admins.
+	pub fn check_grant<I>(&self, permissions: I) -> Result<(), APIError>
+	where
+		I: IntoIterator<Item = auth::Permission>,
+	{
+		let Some(auth) = &self.auth else {
+			return Ok(());
+		};
+		match permissions.into_iter().find(|p| !auth.has_permission(*p)) {
+			Some(permission) => Err(APIError::PermissionRequired(permission)),
+			None => Ok(()),
+		}
+	}
+
+	// Accounts with permissions the caller does not have, like admins, cannot be modified
+	// or deleted by them
+	pub fn check_manage(&self, user: &config::User) -> Result<(), APIError> {
+		self.check_grant(
+			auth::Permission::ALL
+				.into_iter()
+				.filter(|p| user.has_permission(*p)),
+		)
+	}
 }
 
-impl<S> FromRequestParts<S> for AdminRights
+impl<S> FromRequestParts<S> for ManageUsersRights
 where
 	config::Manager: FromRef<S>,
 	S: Send + Sync,
@@ -75,14 +139,115 @@ where
 
 		let user_count = config_manager.get_users().await.len();
 		if user_count == 0 {
-			return Ok(AdminRights { auth: None });
+			return Ok(ManageUsersRights { auth: None });
 		}
 
 		let auth = Auth::from_request_parts(parts, app).await?;
-		if config_manager.get_user(&auth.username).await?.is_admin() {
-			Ok(AdminRights { auth: Some(auth) })
+		if auth.has_permission(auth::Permission::ManageUsers) {
+			Ok(ManageUsersRights { auth: Some(auth) })
 		} else {
-			Err(APIError::AdminPermissionRequired)
+			Err(APIError::PermissionRequired(auth::Permission::ManageUsers))
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct ScanRights;
+
+impl<S> FromRequestParts<S> for ScanRights
+where
+	config::Manager: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = APIError;
+
+	async fn from_request_parts(parts: &mut Parts, app: &S) -> Result<Self, Self::Rejection> {
+		let auth = Auth::from_request_parts(parts, app).await?;
+		if auth.has_permission(auth::Permission::Scan) {
+			Ok(ScanRights)
+		} else {
+			Err(APIError::PermissionRequired(auth::Permission::Scan))
+		}
+	}
+}
+
+// Grants access to logged in users, and to guests for paths listed in the `guest_paths` setting
+#[derive(Debug)]
+pub struct ReadRights;
+
+impl<S> FromRequestParts<S> for ReadRights
+where
+	config::Manager: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = APIError;
+
+	async fn from_request_parts(parts: &mut Parts, app: &S) -> Result<Self, Self::Rejection> {
+		match Auth::from_request_parts(parts, app).await {
+			Err(APIError::AuthenticationRequired) => (),
+			auth => return auth.map(|_| ReadRights),
+		};
+
+		let config_manager = config::Manager::from_ref(app);
+		let Ok(Path(path)) = Path::<PathBuf>::from_request_parts(parts, app).await else {
+			return Err(APIError::AuthenticationRequired);
+		};
+
+		if config_manager.is_guest_path(&path).await {
+			Ok(ReadRights)
+		} else {
+			Err(APIError::AuthenticationRequired)
+		}
+	}
+}
+
+// Same as `ReadRights`, but logged in users also need the permission to stream
+#[derive(Debug)]
+pub struct StreamRights;
+
+impl<S> FromRequestParts<S> for StreamRights
+where
+	config::Manager: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = APIError;
+
+	async fn from_request_parts(parts: &mut Parts, app: &S) -> Result<Self, Self::Rejection> {
+		match Auth::from_request_parts(parts, app).await {
+			Ok(auth) if auth.has_permission(auth::Permission::Stream) => Ok(StreamRights),
+			Ok(_) => Err(APIError::PermissionRequired(auth::Permission::Stream)),
+			Err(APIError::AuthenticationRequired) => ReadRights::from_request_parts(parts, app)
+				.await
+				.map(|_| StreamRights),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct WriteRights {
+	auth: Auth,
+}
+
+impl WriteRights {
+	pub fn get_auth(&self) -> &Auth {
+		&self.auth
+	}
+}
+
+impl<S> FromRequestParts<S> for WriteRights
+where
+	config::Manager: FromRef<S>,
+	S: Send + Sync,
+{
+	type Rejection = APIError;
+
+	async fn from_request_parts(parts: &mut Parts, app: &S) -> Result<Self, Self::Rejection> {
+		let auth = Auth::from_request_parts(parts, app).await?;
+		if auth.has_permission(auth::Permission::EditPlaylists) {
+			Ok(WriteRights { auth })
+		} else {
+			Err(APIError::WritePermissionRequired)
 		}
 	}
 }
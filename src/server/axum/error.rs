@@ -12,7 +12,10 @@ impl IntoResponse for APIError {
 			APIError::UnsupportedAPIVersion => StatusCode::NOT_ACCEPTABLE,
 			APIError::AuthorizationTokenEncoding => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::AdminPermissionRequired => StatusCode::FORBIDDEN,
+			APIError::WritePermissionRequired => StatusCode::FORBIDDEN,
+			APIError::PermissionRequired(_) => StatusCode::FORBIDDEN,
 			APIError::AudioFileIOError => StatusCode::NOT_FOUND,
+			APIError::AudioNotRemuxable(_) => StatusCode::NOT_ACCEPTABLE,
 			APIError::AuthenticationRequired => StatusCode::UNAUTHORIZED,
 			APIError::BrancaTokenEncoding => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::DdnsUpdateQueryFailed(s) => {
@@ -21,6 +24,7 @@ impl IntoResponse for APIError {
 			APIError::NativeDatabase(_) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::DeletingOwnAccount => StatusCode::CONFLICT,
 			APIError::DirectoryNotFound(_) => StatusCode::NOT_FOUND,
+			APIError::DuplicatePlaylist => StatusCode::CONFLICT,
 			APIError::DuplicateUsername => StatusCode::CONFLICT,
 			APIError::ArtistNotFound => StatusCode::NOT_FOUND,
 			APIError::AlbumNotFound => StatusCode::NOT_FOUND,
@@ -33,11 +37,21 @@ impl IntoResponse for APIError {
 			APIError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::InvalidAlbumArtPattern => StatusCode::BAD_REQUEST,
 			APIError::InvalidDDNSURL => StatusCode::BAD_REQUEST,
+			APIError::InvalidScrobbleURL => StatusCode::BAD_REQUEST,
+			APIError::InvalidPeaksBuckets(_) => StatusCode::BAD_REQUEST,
+			APIError::InvalidPeaksWindow => StatusCode::BAD_REQUEST,
+			APIError::InvalidPcmSampleRate(_) => StatusCode::BAD_REQUEST,
 			APIError::Io(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::OwnAdminPrivilegeRemoval => StatusCode::CONFLICT,
+			APIError::OwnPermissionsChange => StatusCode::CONFLICT,
 			APIError::PasswordHashing => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::PlaylistNotFound => StatusCode::NOT_FOUND,
-			APIError::SearchQueryParseError => StatusCode::BAD_REQUEST,
+			APIError::PlaylistPositionOutOfBounds(_) => StatusCode::BAD_REQUEST,
+			APIError::PlaylistEntryMalformed(_) => StatusCode::BAD_REQUEST,
+			APIError::SearchQueryParseError(_) => StatusCode::BAD_REQUEST,
+			APIError::SearchQueryTooShort(_) => StatusCode::BAD_REQUEST,
+			APIError::SearchQueryTimeout => StatusCode::BAD_REQUEST,
+			APIError::TooManyPcmStreams => StatusCode::SERVICE_UNAVAILABLE,
 			APIError::ThumbnailFlacDecoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::ThumbnailFileIOError => StatusCode::NOT_FOUND,
 			APIError::ThumbnailId3Decoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
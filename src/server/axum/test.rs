@@ -18,8 +18,11 @@ pub struct AxumTestService {
 pub type ServiceType = AxumTestService;
 
 impl TestService for AxumTestService {
-	async fn new(test_name: &str) -> Self {
+	async fn new_with_config(test_name: &str, config: &str) -> Self {
 		let output_dir = prepare_test_directory(test_name);
+		if !config.is_empty() {
+			std::fs::write(output_dir.join("polaris.toml"), config).unwrap();
+		}
 
 		let paths = Paths {
 			cache_dir_path: ["test-output", test_name].iter().collect(),
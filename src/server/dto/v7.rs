@@ -106,6 +106,7 @@ impl From<MountDir> for config::storage::MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			album_art_pattern: None,
 		}
 	}
 }
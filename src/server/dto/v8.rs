@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 
-use crate::app::{config, index, peaks, playlist, scanner, thumbnail};
-use std::{collections::HashMap, convert::From, path::PathBuf, time::UNIX_EPOCH};
+use crate::app::{auth, config, formats, index, peaks, playlist, scanner, thumbnail, transcode};
+use crate::utils::AudioFormat;
+use std::{collections::HashMap, convert::From, num::NonZeroU32, path::PathBuf, time::UNIX_EPOCH};
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, ToSchema)]
 pub struct Version {
@@ -36,6 +37,69 @@ pub struct Authorization {
 	pub token: String,
 	#[schema(examples(true, false))]
 	pub is_admin: bool,
+	pub role: Role,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Role {
+	/// Can browse and stream music, but cannot make changes such as saving playlists
+	ReadOnly,
+	User,
+	Admin,
+}
+
+impl From<auth::Role> for Role {
+	fn from(role: auth::Role) -> Self {
+		match role {
+			auth::Role::ReadOnly => Self::ReadOnly,
+			auth::Role::User => Self::User,
+			auth::Role::Admin => Self::Admin,
+		}
+	}
+}
+
+impl From<Role> for auth::Role {
+	fn from(role: Role) -> Self {
+		match role {
+			Role::ReadOnly => Self::ReadOnly,
+			Role::User => Self::User,
+			Role::Admin => Self::Admin,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Permission {
+	/// Mount directories, DDNS and other server settings
+	ManageSettings,
+	ManageUsers,
+	Scan,
+	EditPlaylists,
+	Stream,
+}
+
+impl From<auth::Permission> for Permission {
+	fn from(permission: auth::Permission) -> Self {
+		match permission {
+			auth::Permission::ManageSettings => Self::ManageSettings,
+			auth::Permission::ManageUsers => Self::ManageUsers,
+			auth::Permission::Scan => Self::Scan,
+			auth::Permission::EditPlaylists => Self::EditPlaylists,
+			auth::Permission::Stream => Self::Stream,
+		}
+	}
+}
+
+impl From<Permission> for auth::Permission {
+	fn from(permission: Permission) -> Self {
+		match permission {
+			Permission::ManageSettings => Self::ManageSettings,
+			Permission::ManageUsers => Self::ManageUsers,
+			Permission::Scan => Self::Scan,
+			Permission::EditPlaylists => Self::EditPlaylists,
+			Permission::Stream => Self::Stream,
+		}
+	}
 }
 
 #[derive(Clone, Serialize, Deserialize, ToSchema)]
@@ -51,6 +115,9 @@ pub struct ThumbnailOptions {
 	pub size: Option<ThumbnailSize>,
 	#[schema(examples(true, false))]
 	pub pad: Option<bool>,
+	/// Rounded to the nearest of 25, 50, 80 and 95
+	#[schema(minimum = 0, maximum = 100, examples(80))]
+	pub quality: Option<u8>,
 }
 
 impl From<ThumbnailOptions> for thumbnail::Options {
@@ -58,10 +125,44 @@ impl From<ThumbnailOptions> for thumbnail::Options {
 		let mut options = thumbnail::Options::default();
 		options.max_dimension = dto.size.map_or(options.max_dimension, Into::into);
 		options.pad_to_square = dto.pad.unwrap_or(options.pad_to_square);
+		options.quality = dto.quality.map_or(options.quality, thumbnail::snap_quality);
 		options
 	}
 }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GenerateThumbnailsInput {
+	#[schema(value_type = Vec<String>, examples(json!(["my_music/destiny/folder.jpg", "my_music/sos/folder.jpg"])))]
+	pub paths: Vec<PathBuf>,
+	pub size: Option<ThumbnailSize>,
+	#[schema(examples(true, false))]
+	pub pad: Option<bool>,
+	/// Rounded to the nearest of 25, 50, 80 and 95
+	#[schema(minimum = 0, maximum = 100, examples(80))]
+	pub quality: Option<u8>,
+}
+
+impl GenerateThumbnailsInput {
+	pub fn options(&self) -> thumbnail::Options {
+		ThumbnailOptions {
+			size: self.size,
+			pad: self.pad,
+			quality: self.quality,
+		}
+		.into()
+	}
+}
+
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct GenerateThumbnailsOutput {
+	/// Path to requested images whose thumbnail is ready to be served
+	#[schema(value_type = Vec<String>, examples(json!(["my_music/destiny/folder.jpg"])))]
+	pub generated: Vec<PathBuf>,
+	/// Path to requested images that could not be found or read
+	#[schema(value_type = Vec<String>, examples(json!(["my_music/sos/folder.jpg"])))]
+	pub not_found: Vec<PathBuf>,
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 #[schema(example = "small")]
@@ -84,8 +185,114 @@ impl Into<Option<u32>> for ThumbnailSize {
 	}
 }
 
+#[derive(Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "f32")]
+pub enum SampleFormat {
+	#[default]
+	F32,
+	I16,
+}
+
+impl From<SampleFormat> for formats::SampleFormat {
+	fn from(s: SampleFormat) -> Self {
+		match s {
+			SampleFormat::F32 => Self::F32,
+			SampleFormat::I16 => Self::I16,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "flac")]
+pub enum AudioContainer {
+	Flac,
+	Mp3,
+}
+
+impl From<AudioContainer> for AudioFormat {
+	fn from(c: AudioContainer) -> Self {
+		match c {
+			AudioContainer::Flac => Self::FLAC,
+			AudioContainer::Mp3 => Self::MP3,
+		}
+	}
+}
+
+#[derive(Default, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct AudioParameters {
+	/// Container to serve the audio stream in. Defaults to the original file
+	pub format: Option<AudioContainer>,
+	/// Highest acceptable bitrate in kbps when serving lossy streams in another container
+	#[schema(examples(128, 320))]
+	pub max_bitrate: Option<u32>,
+}
+
+impl AudioParameters {
+	pub fn transcode_options(&self) -> Option<transcode::Options> {
+		self.format.map(|format| transcode::Options {
+			format: format.into(),
+			bitrate: self.max_bitrate.unwrap_or(u32::MAX),
+		})
+	}
+}
+
+#[derive(Default, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct PcmOptions {
+	/// Between 8000 and 192000. Defaults to the sample rate of the audio file
+	#[schema(examples(44100, 48000), minimum = 8000, maximum = 192000)]
+	pub sample_rate: Option<u32>,
+	pub sample_format: Option<SampleFormat>,
+}
+
+impl From<PcmOptions> for formats::PcmOptions {
+	fn from(dto: PcmOptions) -> Self {
+		Self {
+			sample_rate: dto.sample_rate.and_then(NonZeroU32::new),
+			sample_format: dto.sample_format.unwrap_or_default().into(),
+		}
+	}
+}
+
 pub type Peaks = Vec<u8>;
 
+#[derive(Default, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct PeaksParameters {
+	/// Start of the time window to compute peaks for, in milliseconds. Defaults to 0
+	pub start_ms: Option<u64>,
+	/// End of the time window to compute peaks for, in milliseconds. When set, only this window of the song is decoded
+	pub end_ms: Option<u64>,
+	/// Number of min/max pairs computed for the time window, between 1 and 10000. Defaults to 1000
+	pub num_buckets: Option<usize>,
+}
+
+#[derive(Default, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct AlbumPeaksParameters {
+	/// Release year, to tell apart albums which share a name and artists. The earliest matching album is used when omitted.
+	#[schema(examples(1975))]
+	pub year: Option<i64>,
+	/// Number of min/max pairs spanning the whole album, between 1 and 10000. Defaults to 1000
+	pub num_buckets: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AlbumPeaks {
+	/// Min/max pairs of normalized loudness values, from the first track to the last
+	pub interleaved: Vec<u8>,
+	/// Index of the first min/max pair belonging to each track
+	pub track_offsets: Vec<usize>,
+}
+
+impl From<peaks::AlbumPeaks> for AlbumPeaks {
+	fn from(p: peaks::AlbumPeaks) -> Self {
+		Self {
+			interleaved: p.interleaved,
+			track_offsets: p.track_offsets,
+		}
+	}
+}
+
 impl From<peaks::Peaks> for Peaks {
 	fn from(p: peaks::Peaks) -> Self {
 		p.interleaved
@@ -96,6 +303,10 @@ impl From<peaks::Peaks> for Peaks {
 pub struct PlaylistHeader {
 	#[schema(examples("Hotel Lounge Jazz", "Chill Beats 🏝️"))]
 	pub name: String,
+	#[schema(examples("alice"))]
+	pub owner: String,
+	/// Whether other users can read (but not edit) this playlist
+	pub public: bool,
 	#[schema(examples(json!({ "Jazz": 2, "Classical": 11 })))]
 	pub num_songs_by_genre: HashMap<String, u32>,
 	#[schema(examples(2309))]
@@ -107,6 +318,8 @@ impl From<playlist::PlaylistHeader> for PlaylistHeader {
 	fn from(header: playlist::PlaylistHeader) -> Self {
 		Self {
 			name: header.name.to_string(),
+			owner: header.owner,
+			public: header.public,
 			num_songs_by_genre: header.num_songs_by_genre,
 			duration: header.duration.as_secs(),
 		}
@@ -120,25 +333,94 @@ pub struct Playlist {
 	pub songs: SongList,
 }
 
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PlaylistCollage {
+	/// Up to four distinct album arts from the songs of the playlist. Empty when none of the songs have artwork, in which case a placeholder should be displayed.
+	#[schema(value_type = Vec<String>, examples(json!(["my_music/destiny/folder.jpg", "my_music/sos/folder.jpg"])))]
+	pub artworks: Vec<PathBuf>,
+}
+
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct AlbumParameters {
+	/// Release year, to tell apart albums which share a name and artists. The earliest matching album is returned when omitted.
+	#[schema(examples(1975))]
+	pub year: Option<i64>,
+}
+
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct SimilarAlbumsParameters {
+	/// Release year, to tell apart albums which share a name and artists. Albums similar to the earliest matching album are returned when omitted.
+	#[schema(examples(1975))]
+	pub year: Option<i64>,
+	#[schema(examples(10, 50))]
+	pub count: Option<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct PlaylistParameters {
+	/// Owner of the playlist, when reading a public playlist of another user
+	#[schema(examples("alice"))]
+	pub owner: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlaylistSharingInput {
+	/// User who should own the playlist from now on
+	#[schema(examples("bob"))]
+	pub owner: Option<String>,
+	/// Whether other users can read (but not edit) the playlist
+	pub public: Option<bool>,
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct AddPlaylistSongInput {
+	#[schema(value_type = String, examples("my_music/destiny.mp3"))]
+	pub path: PathBuf,
+	/// Index at which the song should be inserted. Songs are appended when this is omitted.
+	pub position: Option<usize>,
+}
+
 #[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct SavePlaylistInput {
 	#[schema(value_type = Vec<String>, examples(json!(["my_music/destiny.mp3", "my_music/dancing_all_night.mp3"])))]
 	pub tracks: Vec<PathBuf>,
 }
 
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlaylistShare {
+	/// Grants read-only access to the playlist and its songs, until sharing is revoked
+	#[schema(examples("XCWOGzhfvB8wnHhEdhAV0fTm2iVkW5Yk"))]
+	pub token: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlaylistImport {
+	/// Entries of the playlist file which were left out, because they could not be read or did not match a song in the collection
+	#[schema(examples(json!(["Playlist entry on line 4 is malformed"])))]
+	pub problems: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct User {
 	#[schema(examples("alice"))]
 	pub name: String,
 	#[schema(examples(true, false))]
 	pub is_admin: bool,
+	pub role: Role,
+	pub permissions: Vec<Permission>,
 }
 
 impl From<config::User> for User {
 	fn from(u: config::User) -> Self {
 		Self {
+			is_admin: u.is_admin(),
+			role: u.role().into(),
+			permissions: auth::Permission::ALL
+				.into_iter()
+				.filter(|p| u.has_permission(*p))
+				.map(|p| p.into())
+				.collect(),
 			name: u.name,
-			is_admin: u.admin == Some(true),
 		}
 	}
 }
@@ -151,6 +433,9 @@ pub struct NewUser {
 	pub password: String,
 	#[schema(examples(true, false))]
 	pub admin: bool,
+	/// Takes precedence over `admin` when set
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub role: Option<Role>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -159,6 +444,12 @@ pub struct UserUpdate {
 	pub new_password: Option<String>,
 	#[schema(examples(true, false))]
 	pub new_is_admin: Option<bool>,
+	/// Takes precedence over `new_is_admin` when set
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub new_role: Option<Role>,
+	/// Replaces the permissions granted by the user's role. Has no effect on admins.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub new_permissions: Option<Vec<Permission>>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, ToSchema)]
@@ -167,6 +458,10 @@ pub struct MountDir {
 	pub source: PathBuf,
 	#[schema(examples("my_music", "root"))]
 	pub name: String,
+	/// Replaces the global album art patterns for this mount
+	#[schema(examples("Cover.(jpeg|jpg|png)"))]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub album_art_pattern: Option<String>,
 }
 
 impl From<MountDir> for config::storage::MountDir {
@@ -174,6 +469,7 @@ impl From<MountDir> for config::storage::MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			album_art_pattern: m.album_art_pattern,
 		}
 	}
 }
@@ -183,6 +479,7 @@ impl From<config::MountDir> for MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			album_art_pattern: m.album_art_pattern,
 		}
 	}
 }
@@ -249,6 +546,60 @@ impl From<scanner::Status> for IndexStatus {
 	}
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CollectionStats {
+	#[schema(examples(289))]
+	pub num_songs: usize,
+	#[schema(examples(24))]
+	pub num_albums: usize,
+	#[schema(examples(31))]
+	pub num_artists: usize,
+	#[schema(examples(9))]
+	pub num_genres: usize,
+	/// Combined duration of all songs, in seconds
+	#[schema(examples(71280))]
+	pub total_duration: i64,
+	/// Combined size of all song files, in bytes
+	#[schema(examples(2147483648_i64))]
+	pub total_file_size: i64,
+	#[schema(examples(1736929992))]
+	pub last_scan_time: Option<u64>,
+}
+
+impl From<index::Stats> for CollectionStats {
+	fn from(s: index::Stats) -> Self {
+		Self {
+			num_songs: s.num_songs,
+			num_albums: s.num_albums,
+			num_artists: s.num_artists,
+			num_genres: s.num_genres,
+			total_duration: s.total_duration,
+			total_file_size: s.total_file_size,
+			last_scan_time: s
+				.last_scan_time
+				.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+				.map(|d| d.as_millis() as u64),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SkippedFile {
+	#[schema(value_type = String, examples("my_music/destiny.wma"))]
+	pub path: PathBuf,
+	#[schema(examples("Unsupported audio format"))]
+	pub reason: String,
+}
+
+impl From<scanner::SkippedFile> for SkippedFile {
+	fn from(f: scanner::SkippedFile) -> Self {
+		Self {
+			path: f.virtual_path,
+			reason: f.reason.to_string(),
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct Song {
 	#[schema(value_type = String, examples("my_music/destiny.mp3"))]
@@ -271,9 +622,33 @@ pub struct Song {
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	#[schema(examples(2018))]
 	pub year: Option<i64>,
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	/// Whether `year` was inferred from the file path rather than read from tags
+	pub year_inferred: bool,
+	/// Number of stars, from 1 to 5
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples(4))]
+	pub rating: Option<i64>,
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	#[schema(examples("Swing Tunes"))]
 	pub album: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples("Live in Vienna"))]
+	pub disc_subtitle: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples("Favorites"))]
+	pub grouping: Option<String>,
+	/// Composition this song is a movement of, for songs tagged with a work
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples("Symphony No. 9"))]
+	pub work: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples("Ode to Joy"))]
+	pub movement: Option<String>,
+	/// Position of `movement` within `work`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples(4))]
+	pub movement_number: Option<i64>,
 	#[schema(value_type = Option<String>)]
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	#[schema(examples("/my_music/destiny.jpg"))]
@@ -282,6 +657,24 @@ pub struct Song {
 	/// Duration in seconds
 	#[schema(examples(192))]
 	pub duration: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples("flac", "mp3"))]
+	pub codec: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	/// Average bitrate in kbps
+	#[schema(examples(320))]
+	pub bitrate: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	/// Sample rate in Hz
+	#[schema(examples(44100))]
+	pub sample_rate: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[schema(examples(2))]
+	pub channels: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	/// Bits per sample, only known for lossless codecs
+	#[schema(examples(16, 24))]
+	pub bit_depth: Option<i64>,
 	#[serde(default, skip_serializing_if = "Vec::is_empty")]
 	#[schema(examples(json!(["John Writer", "Isabel Editor"])))]
 	pub lyricists: Vec<String>,
@@ -294,6 +687,20 @@ pub struct Song {
 	#[serde(default, skip_serializing_if = "Vec::is_empty")]
 	#[schema(examples(json!(["Ninja Tuna"])))]
 	pub labels: Vec<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	#[schema(examples(json!(["Herbert von Karajan"])))]
+	pub conductors: Vec<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	#[schema(examples(json!(["Berliner Philharmoniker"])))]
+	pub ensembles: Vec<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	/// Number of samples to skip at the start of the song for gapless playback
+	#[schema(examples(576))]
+	pub encoder_delay: Option<i64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	/// Number of samples to skip at the end of the song for gapless playback
+	#[schema(examples(1152))]
+	pub encoder_padding: Option<i64>,
 }
 
 impl From<index::Song> for Song {
@@ -306,13 +713,29 @@ impl From<index::Song> for Song {
 			artists: s.artists,
 			album_artists: s.album_artists,
 			year: s.year,
+			year_inferred: s.year_inferred,
+			rating: s.rating,
 			album: s.album,
+			disc_subtitle: s.disc_subtitle,
+			grouping: s.grouping,
+			work: s.work,
+			movement: s.movement,
+			movement_number: s.movement_number,
 			artwork: s.artwork,
 			duration: s.duration,
+			codec: s.codec,
+			bitrate: s.bitrate,
+			sample_rate: s.sample_rate,
+			channels: s.channels,
+			bit_depth: s.bit_depth,
 			lyricists: s.lyricists,
 			composers: s.composers,
 			genres: s.genres,
 			labels: s.labels,
+			conductors: s.conductors,
+			ensembles: s.ensembles,
+			encoder_delay: s.encoder_delay,
+			encoder_padding: s.encoder_padding,
 		}
 	}
 }
@@ -325,6 +748,359 @@ pub struct SongList {
 	pub first_songs: Vec<Song>,
 }
 
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SearchResults {
+	#[serde(flatten)]
+	pub songs: SongList,
+	/// Songs listed in `.paths` which matched the query exactly, rather than fuzzily
+	#[schema(value_type = Vec<String>, examples(json!(["my_music/destiny.mp3"])))]
+	pub exact_matches: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "year")]
+pub enum SortField {
+	Title,
+	Artist,
+	Album,
+	Year,
+	Duration,
+}
+
+impl From<SortField> for index::SortField {
+	fn from(f: SortField) -> Self {
+		match f {
+			SortField::Title => Self::Title,
+			SortField::Artist => Self::Artist,
+			SortField::Album => Self::Album,
+			SortField::Year => Self::Year,
+			SortField::Duration => Self::Duration,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "descending")]
+pub enum SortDirection {
+	#[default]
+	Ascending,
+	Descending,
+}
+
+impl From<SortDirection> for index::SortDirection {
+	fn from(d: SortDirection) -> Self {
+		match d {
+			SortDirection::Ascending => Self::Ascending,
+			SortDirection::Descending => Self::Descending,
+		}
+	}
+}
+
+#[derive(Default, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct SearchParameters {
+	/// Defaults to relevance. Songs without a value for this field are listed last. Values are compared as tagged, sort tags (eg. `ARTISTSORT` or `TSOP`) are not supported.
+	pub sort_by: Option<SortField>,
+	pub sort_direction: Option<SortDirection>,
+}
+
+impl SearchParameters {
+	pub fn sort_order(&self) -> Option<index::SortOrder> {
+		self.sort_by.map(|field| index::SortOrder {
+			field: field.into(),
+			direction: self.sort_direction.unwrap_or_default().into(),
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "album_artist")]
+pub enum TextField {
+	Album,
+	AlbumArtist,
+	Artist,
+	Codec,
+	Composer,
+	Conductor,
+	Ensemble,
+	Extension,
+	Genre,
+	Grouping,
+	Label,
+	Lyricist,
+	Path,
+	Title,
+	Work,
+}
+
+impl From<TextField> for index::TextField {
+	fn from(f: TextField) -> Self {
+		match f {
+			TextField::Album => Self::Album,
+			TextField::AlbumArtist => Self::AlbumArtist,
+			TextField::Artist => Self::Artist,
+			TextField::Codec => Self::Codec,
+			TextField::Composer => Self::Composer,
+			TextField::Conductor => Self::Conductor,
+			TextField::Ensemble => Self::Ensemble,
+			TextField::Extension => Self::Extension,
+			TextField::Genre => Self::Genre,
+			TextField::Grouping => Self::Grouping,
+			TextField::Label => Self::Label,
+			TextField::Lyricist => Self::Lyricist,
+			TextField::Path => Self::Path,
+			TextField::Title => Self::Title,
+			TextField::Work => Self::Work,
+		}
+	}
+}
+
+impl From<index::TextField> for TextField {
+	fn from(f: index::TextField) -> Self {
+		match f {
+			index::TextField::Album => Self::Album,
+			index::TextField::AlbumArtist => Self::AlbumArtist,
+			index::TextField::Artist => Self::Artist,
+			index::TextField::Codec => Self::Codec,
+			index::TextField::Composer => Self::Composer,
+			index::TextField::Conductor => Self::Conductor,
+			index::TextField::Ensemble => Self::Ensemble,
+			index::TextField::Extension => Self::Extension,
+			index::TextField::Genre => Self::Genre,
+			index::TextField::Grouping => Self::Grouping,
+			index::TextField::Label => Self::Label,
+			index::TextField::Lyricist => Self::Lyricist,
+			index::TextField::Path => Self::Path,
+			index::TextField::Title => Self::Title,
+			index::TextField::Work => Self::Work,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SearchFacet {
+	pub field: TextField,
+	/// Number of songs whose value for this field matches the search term
+	#[schema(examples(12))]
+	pub num_songs: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "year")]
+pub enum NumberField {
+	Bitrate,
+	Channels,
+	DateAdded,
+	DiscNumber,
+	OriginalYear,
+	Rating,
+	TrackNumber,
+	Year,
+}
+
+impl From<index::NumberField> for NumberField {
+	fn from(f: index::NumberField) -> Self {
+		match f {
+			index::NumberField::Bitrate => Self::Bitrate,
+			index::NumberField::Channels => Self::Channels,
+			index::NumberField::DateAdded => Self::DateAdded,
+			index::NumberField::DiscNumber => Self::DiscNumber,
+			index::NumberField::OriginalYear => Self::OriginalYear,
+			index::NumberField::Rating => Self::Rating,
+			index::NumberField::TrackNumber => Self::TrackNumber,
+			index::NumberField::Year => Self::Year,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "artwork")]
+pub enum BoolField {
+	Artwork,
+	Lossless,
+	Lossy,
+}
+
+impl From<index::BoolField> for BoolField {
+	fn from(f: index::BoolField) -> Self {
+		match f {
+			index::BoolField::Artwork => Self::Artwork,
+			index::BoolField::Lossless => Self::Lossless,
+			index::BoolField::Lossy => Self::Lossy,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "like")]
+pub enum TextOp {
+	Eq,
+	NotEq,
+	Like,
+}
+
+impl From<index::TextOp> for TextOp {
+	fn from(o: index::TextOp) -> Self {
+		match o {
+			index::TextOp::Eq => Self::Eq,
+			index::TextOp::NotEq => Self::NotEq,
+			index::TextOp::Like => Self::Like,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "greater_or_eq")]
+pub enum NumberOp {
+	Eq,
+	Greater,
+	GreaterOrEq,
+	Less,
+	LessOrEq,
+}
+
+impl From<index::NumberOp> for NumberOp {
+	fn from(o: index::NumberOp) -> Self {
+		match o {
+			index::NumberOp::Eq => Self::Eq,
+			index::NumberOp::Greater => Self::Greater,
+			index::NumberOp::GreaterOrEq => Self::GreaterOrEq,
+			index::NumberOp::Less => Self::Less,
+			index::NumberOp::LessOrEq => Self::LessOrEq,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "and")]
+pub enum BoolOp {
+	And,
+	Or,
+	Not,
+}
+
+impl From<index::BoolOp> for BoolOp {
+	fn from(o: index::BoolOp) -> Self {
+		match o {
+			index::BoolOp::And => Self::And,
+			index::BoolOp::Or => Self::Or,
+			index::BoolOp::Not => Self::Not,
+		}
+	}
+}
+
+/// Node of a parsed search query
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchExpr {
+	/// Bare search term, matched against most text fields
+	Fuzzy { value: String },
+	/// Bare number, matched against most number fields as well as text fields
+	FuzzyNumber { value: i32 },
+	/// Bare search term restricted to some fields with a `@field,field:` prefix
+	FuzzyIn {
+		fields: Vec<TextField>,
+		value: String,
+	},
+	TextComparison {
+		field: TextField,
+		op: TextOp,
+		value: String,
+	},
+	TextIn {
+		field: TextField,
+		values: Vec<String>,
+	},
+	/// Comparison against a custom `TXXX` field
+	CustomComparison {
+		key: String,
+		op: TextOp,
+		value: String,
+	},
+	NumberComparison {
+		field: NumberField,
+		op: NumberOp,
+		value: i64,
+	},
+	/// `value` is false for `!has:` queries
+	Has { field: BoolField, value: bool },
+	Combined {
+		#[schema(no_recursion)]
+		left: Box<SearchExpr>,
+		op: BoolOp,
+		#[schema(no_recursion)]
+		right: Box<SearchExpr>,
+	},
+}
+
+impl From<index::Expr> for SearchExpr {
+	fn from(e: index::Expr) -> Self {
+		match e {
+			index::Expr::Fuzzy(index::Literal::Text(value)) => Self::Fuzzy { value },
+			index::Expr::Fuzzy(index::Literal::Number(value)) => Self::FuzzyNumber { value },
+			index::Expr::FuzzyIn(fields, literal) => Self::FuzzyIn {
+				fields: fields.into_iter().map(|f| f.into()).collect(),
+				value: match literal {
+					index::Literal::Text(s) => s,
+					index::Literal::Number(n) => n.to_string(),
+				},
+			},
+			index::Expr::TextCmp(field, op, value) => Self::TextComparison {
+				field: field.into(),
+				op: op.into(),
+				value,
+			},
+			index::Expr::TextIn(field, values) => Self::TextIn {
+				field: field.into(),
+				values,
+			},
+			index::Expr::CustomCmp(key, op, value) => Self::CustomComparison {
+				key,
+				op: op.into(),
+				value,
+			},
+			index::Expr::NumberCmp(field, op, value) => Self::NumberComparison {
+				field: field.into(),
+				op: op.into(),
+				value,
+			},
+			index::Expr::Has(field, value) => Self::Has {
+				field: field.into(),
+				value,
+			},
+			index::Expr::Combined(left, op, right) => Self::Combined {
+				left: Box::new((*left).into()),
+				op: op.into(),
+				right: Box::new((*right).into()),
+			},
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PredicateEstimate {
+	pub predicate: SearchExpr,
+	/// Upper bound on the number of songs inspected to evaluate this predicate
+	#[schema(examples(12))]
+	pub num_candidates: usize,
+}
+
+impl From<index::PredicateEstimate> for PredicateEstimate {
+	fn from(e: index::PredicateEstimate) -> Self {
+		Self {
+			predicate: e.predicate.into(),
+			num_candidates: e.num_candidates,
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct BrowserEntry {
 	#[schema(value_type = String, examples("my_music/stratovarius/destiny"))]
@@ -440,6 +1216,24 @@ impl From<index::ArtistHeader> for ArtistHeader {
 	}
 }
 
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Collaborator {
+	#[serde(flatten)]
+	pub header: ArtistHeader,
+	/// Number of songs credited to both artists
+	#[schema(examples(3))]
+	pub num_shared_songs: u32,
+}
+
+impl From<index::Collaborator> for Collaborator {
+	fn from(c: index::Collaborator) -> Self {
+		Self {
+			header: c.header.into(),
+			num_shared_songs: c.num_shared_songs,
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct Artist {
 	#[serde(flatten)]
@@ -554,6 +1348,64 @@ pub struct GetRandomAlbumsParameters {
 	pub count: Option<usize>,
 }
 
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct BrowseParameters {
+	#[schema(examples(0, 100))]
+	pub offset: Option<usize>,
+	#[schema(examples(100, 1000))]
+	pub count: Option<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct FieldValuesParameters {
+	#[schema(examples(0, 100))]
+	pub offset: Option<usize>,
+	/// Defaults to all remaining values
+	#[schema(examples(100, 1000))]
+	pub count: Option<usize>,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(example = "song_count")]
+pub enum ValueOrdering {
+	#[default]
+	Alphabetical,
+	SongCount,
+}
+
+impl From<ValueOrdering> for index::ValueOrdering {
+	fn from(o: ValueOrdering) -> Self {
+		match o {
+			ValueOrdering::Alphabetical => Self::Alphabetical,
+			ValueOrdering::SongCount => Self::SongCount,
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct FieldValueCountsParameters {
+	/// Defaults to alphabetical. When ordering by song count, most frequent values come first.
+	pub order_by: Option<ValueOrdering>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldValueCount {
+	#[schema(examples("Heavy Metal"))]
+	pub value: String,
+	#[schema(examples(42))]
+	pub num_songs: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
+pub struct GetAlbumsFromYearsParameters {
+	#[schema(examples(1990, 2016))]
+	pub from: i64,
+	/// Inclusive. Defaults to `from`, which lists albums from a single year
+	#[schema(examples(1999, 2016))]
+	pub to: Option<i64>,
+}
+
 #[derive(Clone, Serialize, Deserialize, IntoParams, ToSchema)]
 pub struct GetRecentAlbumsParameters {
 	#[schema(examples(0, 100))]
@@ -15,8 +15,14 @@ pub enum APIError {
 	AuthorizationTokenEncoding,
 	#[error("Administrator permission is required")]
 	AdminPermissionRequired,
+	#[error("Write permission is required")]
+	WritePermissionRequired,
+	#[error("{0:?} permission is required")]
+	PermissionRequired(app::auth::Permission),
 	#[error("Audio file could not be opened")]
 	AudioFileIOError,
+	#[error("Audio stream cannot be served as {0:?} without re-encoding it")]
+	AudioNotRemuxable(crate::utils::AudioFormat),
 	#[error("Authentication is required")]
 	AuthenticationRequired,
 	#[error("Could not encode Branca token")]
@@ -37,10 +43,20 @@ pub enum APIError {
 	DdnsUpdateQueryFailed(u16),
 	#[error("Cannot delete your own account")]
 	DeletingOwnAccount,
+	#[error("Playlist already exists")]
+	DuplicatePlaylist,
 	#[error("Username already exists")]
 	DuplicateUsername,
 	#[error("EmbeddedArtworkNotFound")]
 	EmbeddedArtworkNotFound,
+	#[error("Number of peaks buckets `{0}` is outside of the supported range")]
+	InvalidPeaksBuckets(usize),
+	#[error("Peaks time window or number of buckets is invalid")]
+	InvalidPeaksWindow,
+	#[error("PCM sample rate `{0}` is outside of the supported range")]
+	InvalidPcmSampleRate(u32),
+	#[error("Too many PCM streams in progress")]
+	TooManyPcmStreams,
 	#[error("EmptyUsername")]
 	EmptyUsername,
 	#[error("EmptyPassword")]
@@ -53,16 +69,28 @@ pub enum APIError {
 	InvalidAlbumArtPattern,
 	#[error("Could not parse DDNS update URL")]
 	InvalidDDNSURL,
+	#[error("Could not parse scrobble webhook URL")]
+	InvalidScrobbleURL,
 	#[error("File I/O error for `{0}`:\n\n{1}")]
 	Io(PathBuf, std::io::Error),
 	#[error("Cannot remove your own admin privilege")]
 	OwnAdminPrivilegeRemoval,
+	#[error("Cannot change your own permissions")]
+	OwnPermissionsChange,
 	#[error("Could not hash password")]
 	PasswordHashing,
 	#[error("Playlist not found")]
 	PlaylistNotFound,
-	#[error("Could not parse search query")]
-	SearchQueryParseError,
+	#[error("Position {0} is out of bounds for this playlist")]
+	PlaylistPositionOutOfBounds(usize),
+	#[error("Playlist entry on line {0} is malformed")]
+	PlaylistEntryMalformed(usize),
+	#[error("Could not parse search query at character {0}")]
+	SearchQueryParseError(usize),
+	#[error("Search terms must be at least {0} characters long")]
+	SearchQueryTooShort(usize),
+	#[error("Search query is too expensive to evaluate")]
+	SearchQueryTimeout,
 	#[error("Could not decode thumbnail from flac file `{0}`:\n\n{1}")]
 	ThumbnailFlacDecoding(PathBuf, metaflac::Error),
 	#[error("Thumbnail file could not be opened")]
@@ -105,11 +133,13 @@ impl From<app::Error> for APIError {
 			app::Error::Image(p, e) => APIError::ThumbnailImageDecoding(p, e),
 			app::Error::UnsupportedFormat(f) => APIError::UnsupportedThumbnailFormat(f),
 
+			app::Error::NotRemuxable(_, f) => APIError::AudioNotRemuxable(f),
 			app::Error::MediaEmpty(p) => APIError::AudioEmpty(p),
 			app::Error::MediaDecodeError(e) => APIError::AudioDecoding(e),
 			app::Error::MediaDecoderError(e) => APIError::AudioDecoding(e),
 			app::Error::MediaPacketError(e) => APIError::AudioDecoding(e),
 			app::Error::MediaProbeError(e) => APIError::AudioDecoding(e),
+			app::Error::MediaSeekError(e) => APIError::AudioDecoding(e),
 
 			app::Error::PeaksSerialization(_) => APIError::Internal,
 			app::Error::PeaksDeserialization(_) => APIError::Internal,
@@ -119,17 +149,26 @@ impl From<app::Error> for APIError {
 
 			app::Error::UpdateQueryFailed(s) => APIError::DdnsUpdateQueryFailed(s),
 			app::Error::UpdateQueryTransport => APIError::DdnsUpdateQueryFailed(0),
+			app::Error::ScrobbleQueryFailed(_) => APIError::Internal,
+			app::Error::ScrobbleQueryTransport => APIError::Internal,
 
 			app::Error::AuthenticationSecretNotFound => APIError::Internal,
 			app::Error::AuthenticationSecretInvalid => APIError::Internal,
+			app::Error::AuthenticationSecretLengthInvalid(_, _) => APIError::Internal,
+			app::Error::AuthenticationKeyringInvalid(_) => APIError::Internal,
 			app::Error::MiscSettingsNotFound => APIError::Internal,
 			app::Error::DDNSUpdateURLInvalid => APIError::InvalidDDNSURL,
+			app::Error::DDNSIPLookupURLInvalid => APIError::InvalidDDNSURL,
+			app::Error::ScrobbleURLInvalid => APIError::InvalidScrobbleURL,
+			app::Error::LogLevelInvalid(_) => APIError::Internal,
 			app::Error::IndexAlbumArtPatternInvalid => APIError::InvalidAlbumArtPattern,
+			app::Error::MountAlbumArtPatternInvalid(_) => APIError::InvalidAlbumArtPattern,
 
 			app::Error::ConfigDeserialization(_) => APIError::Internal,
 			app::Error::ConfigSerialization(_) => APIError::Internal,
 			app::Error::IndexDeserializationError => APIError::Internal,
 			app::Error::IndexSerializationError => APIError::Internal,
+			app::Error::IndexExportError(_) => APIError::Internal,
 
 			app::Error::CouldNotMapToRealPath(_) => APIError::VFSPathNotFound,
 			app::Error::CouldNotMapToVirtualPath(_) => APIError::Internal,
@@ -140,7 +179,12 @@ impl From<app::Error> for APIError {
 			app::Error::GenreNotFound => APIError::GenreNotFound,
 			app::Error::SongNotFound => APIError::SongNotFound,
 			app::Error::PlaylistNotFound => APIError::PlaylistNotFound,
-			app::Error::SearchQueryParseError => APIError::SearchQueryParseError,
+			app::Error::PlaylistPositionOutOfBounds(p) => APIError::PlaylistPositionOutOfBounds(p),
+			app::Error::PlaylistEntryMalformed(l) => APIError::PlaylistEntryMalformed(l),
+			app::Error::DuplicatePlaylist => APIError::DuplicatePlaylist,
+			app::Error::SearchQueryParseError(p) => APIError::SearchQueryParseError(p),
+			app::Error::SearchQueryTooShort(n) => APIError::SearchQueryTooShort(n),
+			app::Error::SearchQueryTimeout => APIError::SearchQueryTimeout,
 			app::Error::EmbeddedArtworkNotFound(_) => APIError::EmbeddedArtworkNotFound,
 
 			app::Error::DuplicateUsername => APIError::DuplicateUsername,
@@ -29,7 +29,12 @@ use protocol::V8;
 pub use crate::server::axum::test::ServiceType;
 
 pub trait TestService {
-	async fn new(test_name: &str) -> Self;
+	async fn new(test_name: &str) -> Self {
+		Self::new_with_config(test_name, "").await
+	}
+
+	// Starts the service with `config` as the initial content of its config file
+	async fn new_with_config(test_name: &str, config: &str) -> Self;
 
 	async fn execute_request<T: Serialize + Clone + 'static>(
 		&mut self,
@@ -68,6 +73,7 @@ pub trait TestService {
 			self.fetch(&protocol::put_mount_dirs(vec![dto::MountDir {
 				name: TEST_MOUNT_NAME.into(),
 				source: TEST_MOUNT_SOURCE.into(),
+				album_art_pattern: None,
 			}]))
 			.await
 			.status(),
@@ -79,6 +85,7 @@ pub trait TestService {
 				name: TEST_USERNAME_ADMIN.into(),
 				password: TEST_PASSWORD_ADMIN.into(),
 				admin: true,
+				role: None,
 			}))
 			.await
 			.status(),
@@ -92,6 +99,7 @@ pub trait TestService {
 				name: TEST_USERNAME.into(),
 				password: TEST_PASSWORD.into(),
 				admin: false,
+				role: None,
 			}))
 			.await
 			.status(),
@@ -111,3 +111,47 @@ async fn authentication_via_query_param_golden_path() {
 	let response = service.fetch(&request).await;
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn rotate_auth_secret_requires_admin() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login().await;
+
+	let request = protocol::rotate_auth_secret();
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn rotate_auth_secret_keeps_existing_tokens_valid() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+
+	let login = protocol::login(TEST_USERNAME_ADMIN, TEST_PASSWORD_ADMIN);
+
+	let old_authorization = service
+		.fetch_json::<_, dto::Authorization>(&login)
+		.await
+		.into_body();
+	let old_token = old_authorization.token.clone();
+	service.set_authorization(Some(old_authorization));
+
+	let request = protocol::rotate_auth_secret();
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let new_authorization = service
+		.fetch_json::<_, dto::Authorization>(&login)
+		.await
+		.into_body();
+	assert_ne!(new_authorization.token, old_token);
+
+	let request = protocol::random::<V8>();
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.set_authorization(Some(new_authorization));
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+}
@@ -14,6 +14,27 @@ async fn browse_requires_auth() {
 	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[tokio::test]
+async fn browse_requires_stream_permission() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_permissions: Some(vec![dto::Permission::EditPlaylists]),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.login().await;
+	let request = protocol::browse::<V8>(&PathBuf::new());
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
 #[tokio::test]
 async fn browse_root() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -49,6 +70,27 @@ async fn browse_directory() {
 	assert_eq!(entries.len(), 5);
 }
 
+#[tokio::test]
+async fn browse_directory_by_page() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::browse_page(&path, 1, 2);
+	let response = service
+		.fetch_json::<_, Vec<dto::BrowserEntry>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 2);
+	assert_eq!(
+		response.headers().get("x-polaris-total-count").unwrap(),
+		"5"
+	);
+}
+
 #[tokio::test]
 async fn browse_missing_directory() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -98,6 +98,66 @@ async fn recent_golden_path() {
 	assert_eq!(entries.len(), 3);
 }
 
+#[tokio::test]
+async fn albums_from_years_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	let request = protocol::albums_from_years(2016, None);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn albums_from_years_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::albums_from_years(2016, None);
+	let response = service
+		.fetch_json::<_, Vec<dto::AlbumHeader>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 3);
+
+	let request = protocol::albums_from_years(1990, Some(1999));
+	let response = service
+		.fetch_json::<_, Vec<dto::AlbumHeader>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.body().is_empty());
+}
+
+#[tokio::test]
+async fn stats_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	let request = protocol::stats();
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn stats_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::stats();
+	let response = service
+		.fetch_json::<_, dto::CollectionStats>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let stats = response.body();
+	assert_eq!(stats.num_songs, 13);
+	assert_eq!(stats.num_albums, 3);
+	assert_eq!(stats.num_genres, 4);
+	assert!(stats.total_file_size > 0);
+	assert!(stats.last_scan_time.is_some());
+}
+
 #[tokio::test]
 async fn recent_with_trailing_slash() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -159,6 +219,107 @@ async fn genres_golden_path() {
 	assert_eq!(entries.len(), 4);
 }
 
+#[tokio::test]
+async fn album_songs_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	let request = protocol::album_songs("Hunted");
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn album_songs_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::album_songs("Hunted");
+	let response = service.fetch_json::<_, dto::SongList>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let song_list = response.body();
+	assert_eq!(song_list.paths.len(), 5);
+	let mut sorted_paths = song_list.paths.clone();
+	sorted_paths.sort();
+	assert_eq!(song_list.paths, sorted_paths);
+}
+
+#[tokio::test]
+async fn album_songs_bad_name_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::album_songs("Not an album");
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn field_values_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	let request = protocol::field_values("genre", None, None);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn field_values_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::field_values("genre", None, None);
+	let response = service.fetch_json::<_, Vec<String>>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		*response.body(),
+		vec!["Chiptune", "Doom Metal", "Electronic", "Metal"]
+	);
+
+	let request = protocol::field_values("genre", Some(1), Some(2));
+	let response = service.fetch_json::<_, Vec<String>>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(*response.body(), vec!["Doom Metal", "Electronic"]);
+}
+
+#[tokio::test]
+async fn field_value_counts_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::field_value_counts("artist", None);
+	let response = service
+		.fetch_json::<_, Vec<dto::FieldValueCount>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let values = response.body();
+	assert_eq!(values[0].value, "Khemmis");
+	assert_eq!(values[0].num_songs, 5);
+
+	let request = protocol::field_value_counts("genre", Some("song_count"));
+	let response = service
+		.fetch_json::<_, Vec<dto::FieldValueCount>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let counts = response
+		.body()
+		.iter()
+		.map(|v| v.num_songs)
+		.collect::<Vec<_>>();
+	let mut sorted_counts = counts.clone();
+	sorted_counts.sort_by(|a, b| b.cmp(a));
+	assert_eq!(counts, sorted_counts);
+}
+
 #[tokio::test]
 async fn genre_requires_auth() {
 	let mut service = ServiceType::new(&test_name!()).await;
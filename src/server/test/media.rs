@@ -52,6 +52,35 @@ async fn audio_requires_auth() {
 	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[tokio::test]
+async fn audio_guest_paths_cannot_be_escaped() {
+	let config = format!(r#"guest_paths = ["{TEST_MOUNT_NAME}/Khemmis"]"#);
+	let mut service = ServiceType::new_with_config(&test_name!(), &config).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.logout().await;
+
+	let guest_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let response = service.fetch(&protocol::audio(&guest_path)).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let escaped_path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"..",
+		"Tobokegao",
+		"Picnic",
+		"07 - なぜ (Why).mp3",
+	]
+	.iter()
+	.collect();
+	let response = service.fetch(&protocol::audio(&escaped_path)).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
 #[tokio::test]
 async fn audio_golden_path() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -74,6 +103,33 @@ async fn audio_golden_path() {
 	);
 }
 
+#[tokio::test]
+async fn audio_can_be_remuxed() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::audio_in_format(&path, "mp3", None);
+	let response = service.fetch_bytes(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(!response.body().is_empty());
+	assert!(response.body().len() < 24_142); // Tags are not copied
+
+	let request = protocol::audio_in_format(&path, "mp3", Some(1));
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+
+	let request = protocol::audio_in_format(&path, "flac", None);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
 #[tokio::test]
 async fn audio_does_not_encode_content() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -131,6 +187,34 @@ async fn audio_partial_content() {
 	);
 }
 
+#[tokio::test]
+async fn audio_partial_content_until_end() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let mut request = protocol::audio(&path);
+	let headers = request.headers_mut();
+	headers.append(
+		header::RANGE,
+		HeaderValue::from_str("bytes=24042-").unwrap(),
+	);
+
+	let response = service.fetch_bytes(&request).await;
+	assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+	assert_eq!(response.body().len(), 100);
+	assert_eq!(
+		response.headers().get(header::CONTENT_RANGE).unwrap(),
+		"bytes 24042-24141/24142"
+	);
+}
+
 #[tokio::test]
 async fn audio_bad_path_returns_not_found() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -144,6 +228,105 @@ async fn audio_bad_path_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn scrobble_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::scrobble(&path);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn scrobble_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::scrobble(&path);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn scrobble_bad_path_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login().await;
+
+	let path: PathBuf = ["not_my_collection", "song.mp3"].iter().collect();
+
+	let request = protocol::scrobble(&path);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn pcm_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::pcm(&path, None);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn pcm_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::pcm(&path, Some(8000));
+	let response = service.fetch_bytes(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let format = response.headers().get("x-polaris-pcm-format").unwrap();
+	let format = format.to_str().unwrap();
+	assert!(format.starts_with("i16; rate=8000; channels="));
+	let num_channels: usize = format.rsplit('=').next().unwrap().parse().unwrap();
+	assert!(response.body().len() > 0);
+	assert_eq!(response.body().len() % (2 * num_channels), 0);
+}
+
+#[tokio::test]
+async fn pcm_rejects_unsupported_sample_rates() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	for sample_rate in [0, 7_999, 192_001, 4_000_000_000] {
+		let request = protocol::pcm(&path, Some(sample_rate));
+		let response = service.fetch(&request).await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
 #[tokio::test]
 async fn peaks_requires_auth() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -176,6 +359,97 @@ async fn peaks_golden_path() {
 	assert!(response.body().len() > 0);
 }
 
+#[tokio::test]
+async fn album_peaks_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::album_peaks("Hunted", &["Khemmis"], Some(100));
+	let response = service.fetch_json::<_, dto::AlbumPeaks>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let peaks = response.body();
+	assert!(peaks.interleaved.len() <= 2 * 100);
+	assert_eq!(peaks.track_offsets.len(), 5);
+	assert!(peaks.track_offsets.is_sorted());
+}
+
+#[tokio::test]
+async fn album_peaks_ignores_guest_paths() {
+	let config = format!(r#"guest_paths = ["{TEST_MOUNT_NAME}"]"#);
+	let mut service = ServiceType::new_with_config(&test_name!(), &config).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.logout().await;
+
+	for name in [TEST_MOUNT_NAME, "Hunted"] {
+		let request = protocol::album_peaks(name, &["Khemmis"], Some(100));
+		let response = service.fetch(&request).await;
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+}
+
+#[tokio::test]
+async fn album_peaks_rejects_invalid_bucket_count() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	for num_buckets in [0, 10_001] {
+		let request = protocol::album_peaks("Hunted", &["Khemmis"], Some(num_buckets));
+		let response = service.fetch(&request).await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
+#[tokio::test]
+async fn peaks_window_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::peaks_window(&path, Some(100), Some(600), Some(50));
+	let response = service.fetch_bytes(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 2 * 50);
+}
+
+#[tokio::test]
+async fn peaks_window_rejects_invalid_parameters() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	for (start_ms, end_ms, num_buckets) in [
+		(Some(500), Some(500), None),
+		(Some(600), Some(100), None),
+		(None, Some(100), Some(0)),
+		(None, Some(100), Some(10_001)),
+		(Some(100), None, None),
+	] {
+		let request = protocol::peaks_window(&path, start_ms, end_ms, num_buckets);
+		let response = service.fetch(&request).await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
 #[tokio::test]
 async fn peaks_bad_path_returns_not_found() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -223,6 +497,41 @@ async fn thumbnail_golden_path() {
 	assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn thumbnails_can_be_generated_in_bulk() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let valid_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+	let missing_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Missing.jpg"]
+		.iter()
+		.collect();
+	let unmapped_path: PathBuf = ["not_my_collection", "Folder.jpg"].iter().collect();
+
+	let request = protocol::generate_thumbnails(dto::GenerateThumbnailsInput {
+		paths: vec![
+			valid_path.clone(),
+			missing_path.clone(),
+			unmapped_path.clone(),
+		],
+		size: Some(ThumbnailSize::Tiny),
+		pad: None,
+		quality: None,
+	});
+	let response = service
+		.fetch_json::<_, dto::GenerateThumbnailsOutput>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let output = response.body();
+	assert_eq!(output.generated, vec![valid_path]);
+	assert_eq!(output.not_found, vec![unmapped_path, missing_path]);
+}
+
 #[tokio::test]
 async fn thumbnail_bad_path_returns_not_found() {
 	let mut service = ServiceType::new(&test_name!()).await;
@@ -238,6 +547,34 @@ async fn thumbnail_bad_path_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn artwork_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::artwork(&path);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn artwork_missing_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login().await;
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::artwork(&path);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn thumbnail_size_default() {
 	thumbnail_size(&test_name!(), None, None, 400).await;
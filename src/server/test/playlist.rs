@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use http::StatusCode;
 
@@ -143,3 +143,116 @@ async fn delete_playlist_golden_path() {
 	let response = service.fetch(&request).await;
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn read_only_user_cannot_save_playlist() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+
+	let request = protocol::create_user(dto::NewUser {
+		name: "Walter".into(),
+		password: "secret".into(),
+		admin: false,
+		role: Some(dto::Role::ReadOnly),
+	});
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.login_internal("Walter", "secret").await;
+
+	let request = protocol::playlists();
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let my_playlist = dto::SavePlaylistInput { tracks: Vec::new() };
+	let request = protocol::save_playlist(TEST_PLAYLIST_NAME, my_playlist);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+	let request = protocol::delete_playlist(TEST_PLAYLIST_NAME);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn share_playlist_golden_path() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let shared_song: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let other_song: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"01 - Above The Water.mp3",
+	]
+	.iter()
+	.collect();
+
+	let my_playlist = dto::SavePlaylistInput {
+		tracks: vec![shared_song.clone()],
+	};
+	let request = protocol::save_playlist(TEST_PLAYLIST_NAME, my_playlist);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::share_playlist(TEST_PLAYLIST_NAME);
+	let response = service.fetch_json::<_, dto::PlaylistShare>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let token = response.body().token.clone();
+
+	service.logout().await;
+
+	let request = protocol::read_shared_playlist(&token);
+	let response = service.fetch_json::<_, dto::Playlist>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().songs.paths, vec![shared_song.clone()]);
+
+	let request = protocol::shared_playlist_audio(&token, &shared_song);
+	let response = service.fetch_bytes(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::shared_playlist_audio(&token, &other_song);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+	// The token cannot be used to access the rest of the collection
+	service.set_authorization(Some(dto::Authorization {
+		username: TEST_USERNAME.to_owned(),
+		token: token.clone(),
+		is_admin: false,
+		role: dto::Role::ReadOnly,
+	}));
+	let request = protocol::audio(&other_song);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	service.logout().await;
+
+	service.login().await;
+	let request = protocol::revoke_playlist_share(TEST_PLAYLIST_NAME);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	service.logout().await;
+
+	let request = protocol::read_shared_playlist(&token);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let request = protocol::shared_playlist_audio(&token, &shared_song);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn share_playlist_requires_auth() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	let request = protocol::share_playlist(TEST_PLAYLIST_NAME);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -4,6 +4,7 @@ use std::path::Path;
 
 use crate::server::dto;
 use crate::server::dto::ThumbnailSize;
+use crate::server::API_ARRAY_SEPARATOR;
 
 pub trait ProtocolVersion {
 	fn header_value() -> i32;
@@ -124,6 +125,22 @@ pub fn delete_user(username: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn rotate_auth_secret() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/rotate_auth_secret")
+		.body(())
+		.unwrap()
+}
+
+pub fn export() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/export")
+		.body(())
+		.unwrap()
+}
+
 pub fn trigger_index() -> Request<()> {
 	Request::builder()
 		.method(Method::POST)
@@ -132,6 +149,22 @@ pub fn trigger_index() -> Request<()> {
 		.unwrap()
 }
 
+pub fn trigger_full_index() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/trigger_full_index")
+		.body(())
+		.unwrap()
+}
+
+pub fn trigger_index_rebuild() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/trigger_index_rebuild")
+		.body(())
+		.unwrap()
+}
+
 pub fn browse<VERSION: ProtocolVersion>(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/browse/{}", url_encode(path.as_ref()));
@@ -143,6 +176,20 @@ pub fn browse<VERSION: ProtocolVersion>(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn browse_page(path: &Path, offset: usize, count: usize) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/browse/{}?offset={offset}&count={count}",
+		url_encode(path.as_ref())
+	);
+	Request::builder()
+		.header("Accept-Version", V8::header_value())
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn flatten<VERSION: ProtocolVersion>(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/flatten/{}", url_encode(path.as_ref()));
@@ -203,6 +250,63 @@ pub fn genre_songs<VERSION: ProtocolVersion>(genre: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn album_songs(name: &str) -> Request<()> {
+	let endpoint = format!("/api/album/{}/songs", url_encode(name));
+	Request::builder()
+		.method(Method::GET)
+		.uri(endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn album_peaks(name: &str, artists: &[&str], num_buckets: Option<usize>) -> Request<()> {
+	let mut endpoint = format!(
+		"/api/album/{}/by/{}/peaks",
+		url_encode(name),
+		url_encode(&artists.join(API_ARRAY_SEPARATOR))
+	);
+	if let Some(num_buckets) = num_buckets {
+		endpoint.push_str(&format!("?num_buckets={num_buckets}"));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn field_values(field: &str, offset: Option<usize>, count: Option<usize>) -> Request<()> {
+	let mut endpoint = format!("/api/field_values/{field}");
+	let mut parameters = Vec::new();
+	if let Some(offset) = offset {
+		parameters.push(format!("offset={offset}"));
+	}
+	if let Some(count) = count {
+		parameters.push(format!("count={count}"));
+	}
+	if !parameters.is_empty() {
+		endpoint.push('?');
+		endpoint.push_str(&parameters.join("&"));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn field_value_counts(field: &str, order_by: Option<&str>) -> Request<()> {
+	let mut endpoint = format!("/api/field_value_counts/{field}");
+	if let Some(order_by) = order_by {
+		endpoint.push_str(&format!("?order_by={order_by}"));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn random<VERSION: ProtocolVersion>() -> Request<()> {
 	Request::builder()
 		.header("Accept-Version", VERSION::header_value())
@@ -221,6 +325,26 @@ pub fn recent<VERSION: ProtocolVersion>() -> Request<()> {
 		.unwrap()
 }
 
+pub fn stats() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/stats")
+		.body(())
+		.unwrap()
+}
+
+pub fn albums_from_years(from: i64, to: Option<i64>) -> Request<()> {
+	let mut endpoint = format!("/api/albums/years?from={from}");
+	if let Some(to) = to {
+		endpoint.push_str(&format!("&to={to}"));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn search<VERSION: ProtocolVersion>(query: &str) -> Request<()> {
 	let endpoint = format!("/api/search/{}", url_encode(query));
 	Request::builder()
@@ -231,6 +355,33 @@ pub fn search<VERSION: ProtocolVersion>(query: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn explain_search(query: &str) -> Request<()> {
+	let endpoint = format!("/api/explain_search/{}", url_encode(query));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn search_facets(term: &str) -> Request<()> {
+	let endpoint = format!("/api/search_facets/{}", url_encode(term));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn parse_search(query: &str) -> Request<()> {
+	let endpoint = format!("/api/parse_search/{}", url_encode(query));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn songs(songs: dto::GetSongsBulkInput) -> Request<dto::GetSongsBulkInput> {
 	Request::builder()
 		.method(Method::POST)
@@ -239,6 +390,16 @@ pub fn songs(songs: dto::GetSongsBulkInput) -> Request<dto::GetSongsBulkInput> {
 		.unwrap()
 }
 
+pub fn generate_thumbnails(
+	input: dto::GenerateThumbnailsInput,
+) -> Request<dto::GenerateThumbnailsInput> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/thumbnails")
+		.body(input)
+		.unwrap()
+}
+
 pub fn audio(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/audio/{}", url_encode(path.as_ref()));
@@ -249,6 +410,19 @@ pub fn audio(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn audio_in_format(path: &Path, format: &str, max_bitrate: Option<u32>) -> Request<()> {
+	let path = path.to_string_lossy();
+	let mut endpoint = format!("/api/audio/{}?format={format}", url_encode(path.as_ref()));
+	if let Some(max_bitrate) = max_bitrate {
+		endpoint.push_str(&format!("&max_bitrate={max_bitrate}"));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn peaks(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/peaks/{}", url_encode(path.as_ref()));
@@ -259,6 +433,58 @@ pub fn peaks(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn pcm(path: &Path, sample_rate: Option<u32>) -> Request<()> {
+	let path = path.to_string_lossy();
+	let mut endpoint = format!("/api/pcm/{}", url_encode(path.as_ref()));
+	if let Some(rate) = sample_rate {
+		endpoint.push_str(&format!("?sample_rate={rate}&sample_format=i16"));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn peaks_window(
+	path: &Path,
+	start_ms: Option<u64>,
+	end_ms: Option<u64>,
+	num_buckets: Option<usize>,
+) -> Request<()> {
+	let path = path.to_string_lossy();
+	let mut parameters = Vec::new();
+	if let Some(start_ms) = start_ms {
+		parameters.push(format!("start_ms={start_ms}"));
+	}
+	if let Some(end_ms) = end_ms {
+		parameters.push(format!("end_ms={end_ms}"));
+	}
+	if let Some(num_buckets) = num_buckets {
+		parameters.push(format!("num_buckets={num_buckets}"));
+	}
+	let endpoint = format!(
+		"/api/peaks/{}?{}",
+		url_encode(path.as_ref()),
+		parameters.join("&")
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn scrobble(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/scrobble/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn thumbnail(path: &Path, size: Option<ThumbnailSize>, pad: Option<bool>) -> Request<()> {
 	let path = path.to_string_lossy();
 	let mut params = String::new();
@@ -293,6 +519,16 @@ pub fn thumbnail(path: &Path, size: Option<ThumbnailSize>, pad: Option<bool>) ->
 		.unwrap()
 }
 
+pub fn artwork(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/artwork/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn playlists() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -332,6 +568,47 @@ pub fn delete_playlist(name: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn share_playlist(name: &str) -> Request<()> {
+	let endpoint = format!("/api/playlist/{}/share", url_encode(name));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn revoke_playlist_share(name: &str) -> Request<()> {
+	let endpoint = format!("/api/playlist/{}/share", url_encode(name));
+	Request::builder()
+		.method(Method::DELETE)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn read_shared_playlist(token: &str) -> Request<()> {
+	let endpoint = format!("/api/shared_playlist/{}", url_encode(token));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn shared_playlist_audio(token: &str, path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/shared_playlist/{}/audio/{}",
+		url_encode(token),
+		url_encode(path.as_ref())
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 fn url_encode(input: &str) -> String {
 	percent_encode(input.as_bytes(), NON_ALPHANUMERIC).to_string()
 }
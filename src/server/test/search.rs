@@ -31,8 +31,8 @@ async fn search_with_query() {
 	service.login().await;
 
 	let request = protocol::search::<V8>("door");
-	let response = service.fetch_json::<_, dto::SongList>(&request).await;
-	let songs = response.body();
+	let response = service.fetch_json::<_, dto::SearchResults>(&request).await;
+	let results = response.body();
 
 	let path: PathBuf = [
 		TEST_MOUNT_NAME,
@@ -42,7 +42,27 @@ async fn search_with_query() {
 	]
 	.iter()
 	.collect();
-	assert_eq!(songs.paths, vec![path]);
+	assert_eq!(results.songs.paths, vec![path]);
+	assert!(results.exact_matches.is_empty());
+}
+
+#[tokio::test]
+async fn search_results_include_technical_details() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::search::<V8>("door");
+	let response = service.fetch_json::<_, dto::SearchResults>(&request).await;
+	let song = &response.body().songs.first_songs[0];
+
+	assert_eq!(song.codec.as_deref(), Some("mp3"));
+	assert!(song.bitrate.is_some());
+	assert!(song.sample_rate.is_some());
+	assert!(song.channels.is_some());
+	assert_eq!(song.bit_depth, None);
 }
 
 #[tokio::test]
@@ -76,3 +96,82 @@ async fn search_with_query_v7() {
 		})]
 	);
 }
+
+#[tokio::test]
+async fn explain_search_lists_predicates() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::explain_search("door && artist = khemmis");
+	let response = service
+		.fetch_json::<_, Vec<dto::PredicateEstimate>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 2);
+	assert!(response.body().iter().any(|e| e.predicate
+		== dto::SearchExpr::TextComparison {
+			field: dto::TextField::Artist,
+			op: dto::TextOp::Eq,
+			value: "khemmis".to_owned(),
+		}));
+}
+
+#[tokio::test]
+async fn search_facets_count_matches_per_field() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login_admin().await;
+	service.index().await;
+	service.login().await;
+
+	let request = protocol::search_facets("hunted");
+	let response = service
+		.fetch_json::<_, Vec<dto::SearchFacet>>(&request)
+		.await;
+	assert_eq!(response.status(), StatusCode::OK);
+	let facets = response.body();
+
+	let album = facets
+		.iter()
+		.find(|f| f.field == dto::TextField::Album)
+		.unwrap();
+	assert_eq!(album.num_songs, 5);
+	let title = facets
+		.iter()
+		.find(|f| f.field == dto::TextField::Title)
+		.unwrap();
+	assert_eq!(title.num_songs, 1);
+	assert!(facets.iter().all(|f| f.num_songs > 0));
+}
+
+#[tokio::test]
+async fn parse_search_returns_syntax_tree() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+	service.login().await;
+
+	let request = protocol::parse_search("door && artist = khemmis");
+	let response = service.fetch_json::<_, serde_json::Value>(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.body(),
+		&serde_json::json!({
+			"type": "combined",
+			"left": { "type": "fuzzy", "value": "door" },
+			"op": "and",
+			"right": {
+				"type": "text_comparison",
+				"field": "artist",
+				"op": "eq",
+				"value": "khemmis",
+			},
+		})
+	);
+
+	let request = protocol::parse_search("door &&");
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -37,6 +37,7 @@ async fn create_user_requires_admin() {
 		name: "Walter".into(),
 		password: "secret".into(),
 		admin: false,
+		role: None,
 	});
 
 	let response = service.fetch(&request).await;
@@ -57,6 +58,7 @@ async fn create_user_golden_path() {
 		name: "Walter".into(),
 		password: "secret".into(),
 		admin: false,
+		role: None,
 	};
 	let request = protocol::create_user(new_user);
 	let response = service.fetch(&request).await;
@@ -105,6 +107,110 @@ async fn update_user_cannot_unadmin_self() {
 	assert_eq!(response.status(), StatusCode::CONFLICT);
 }
 
+#[tokio::test]
+async fn user_management_permission_can_be_granted() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+
+	service.login_admin().await;
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_permissions: Some(vec![dto::Permission::ManageUsers]),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.login().await;
+	let response = service.fetch(&protocol::list_users()).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_role: Some(dto::Role::Admin),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn user_managers_cannot_modify_or_delete_admins() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+
+	service.login_admin().await;
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_permissions: Some(vec![dto::Permission::ManageUsers]),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.login().await;
+	let request = protocol::update_user(
+		TEST_USERNAME_ADMIN,
+		dto::UserUpdate {
+			new_password: Some("hijacked".into()),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+	let request = protocol::delete_user(TEST_USERNAME_ADMIN);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+	// Password and account are unchanged
+	service.login_admin().await;
+}
+
+#[tokio::test]
+async fn update_user_cannot_change_own_permissions() {
+	let mut service = ServiceType::new(&test_name!()).await;
+	service.complete_initial_setup().await;
+
+	service.login_admin().await;
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_permissions: Some(vec![dto::Permission::ManageUsers, dto::Permission::Stream]),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.login().await;
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_permissions: Some(vec![dto::Permission::ManageUsers]),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+
+	let request = protocol::update_user(
+		TEST_USERNAME,
+		dto::UserUpdate {
+			new_role: Some(dto::Role::ReadOnly),
+			..Default::default()
+		},
+	);
+	let response = service.fetch(&request).await;
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
 #[tokio::test]
 async fn delete_user_requires_admin() {
 	let mut service = ServiceType::new(&test_name!()).await;
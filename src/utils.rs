@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use serde::{Deserialize, Serialize};
+
 #[macro_export]
 macro_rules! match_ignore_case {
     (match $v:ident {
@@ -13,7 +15,8 @@ macro_rules! match_ignore_case {
 pub use crate::match_ignore_case;
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AudioFormat {
 	AIFF,
 	APE,